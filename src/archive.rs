@@ -0,0 +1,310 @@
+//! The `.slpfx` prefix archive: a zstd-compressed tar holding `manifest.json` and `SHA256SUMS`
+//! followed by the prefix under `prefix/`. The manifest records the wine build, environment and
+//! tweaks the prefix was set up with, and the file count and size used to check an extracted
+//! archive. `SHA256SUMS` lists the hash of every file, in `sha256sum` format, to check their
+//! contents; archives made before it was added are checked by count and size only.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use regex::Regex;
+
+use crate::fsutil::{hash_tree, sha256_files};
+use crate::output::*;
+
+/// File extension of prefix archives
+pub const ARCHIVE_EXTENSION: &str = "slpfx";
+
+// Bumped when the archive layout changes incompatibly
+const ARCHIVE_FORMAT: u64 = 1;
+
+// Archive member listing the SHA-256 of the prefix's files
+const CHECKSUMS: &str = "SHA256SUMS";
+
+/// What a prefix archive contains and how the prefix was set up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveManifest {
+    pub format: u64,
+    /// Launcher ID, e.g. `battlenet`
+    pub launcher: String,
+    /// Wine build that last updated the prefix
+    pub runner: String,
+    /// Environment the launcher expects, e.g. `WINEDLLOVERRIDES`
+    pub env: Vec<(String, String)>,
+    /// Tweaks applied to the prefix, e.g. `font-smoothing`
+    pub tweaks: Vec<String>,
+    /// Regular files and their total size in bytes under `prefix/`
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl ArchiveManifest {
+    /// A manifest for `prefix`, counting its files.
+    pub fn for_prefix(prefix: &Path, launcher: &str, runner: &str, env: Vec<(String, String)>, tweaks: Vec<String>)
+    -> Result<ArchiveManifest, String> {
+        let (files, bytes) = count_files(prefix)
+        .map_err(|e| format!("Failed to read {}: {}", prefix.display(), e))?;
+        Ok(ArchiveManifest {
+            format: ARCHIVE_FORMAT,
+            launcher: launcher.to_string(),
+            runner: runner.to_string(),
+            env,
+            tweaks,
+            files,
+            bytes,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let env: Vec<String> = self.env.iter()
+        .map(|(name, value)| format!("\"{}\": \"{}\"", json_escape(name), json_escape(value)))
+        .collect();
+        let tweaks: Vec<String> = self.tweaks.iter()
+        .map(|tweak| format!("\"{}\"", json_escape(tweak)))
+        .collect();
+        format!(
+            "{{\n  \"format\": {},\n  \"launcher\": \"{}\",\n  \"runner\": \"{}\",\n  \"env\": {{{}}},\n  \"tweaks\": [{}],\n  \"files\": {},\n  \"bytes\": {}\n}}\n",
+            self.format, json_escape(&self.launcher), json_escape(&self.runner),
+            env.join(", "), tweaks.join(", "), self.files, self.bytes
+        )
+    }
+
+    /// Parse a manifest written by `to_json`.
+    pub fn from_json(json: &str) -> Result<ArchiveManifest, String> {
+        let string = r#""((?:[^"\\]|\\.)*)""#;
+        let string_re = Regex::new(string).unwrap();
+        let field = |name: &str| -> Option<String> {
+            Regex::new(&format!(r#""{}"\s*:\s*{}"#, name, string)).unwrap()
+            .captures(json)
+            .map(|caps| json_unescape(&caps[1]))
+        };
+        let number = |name: &str| -> Option<u64> {
+            Regex::new(&format!(r#""{}"\s*:\s*(\d+)"#, name)).unwrap()
+            .captures(json)
+            .and_then(|caps| caps[1].parse().ok())
+        };
+
+        let env = Regex::new(r#""env"\s*:\s*\{([^}]*)\}"#).unwrap()
+        .captures(json)
+        .map(|caps| {
+            let pair_re = Regex::new(&format!(r#"{}\s*:\s*{}"#, string, string)).unwrap();
+            pair_re.captures_iter(&caps[1])
+            .map(|pair| (json_unescape(&pair[1]), json_unescape(&pair[2])))
+            .collect()
+        })
+        .unwrap_or_default();
+        let tweaks = Regex::new(r#""tweaks"\s*:\s*\[([^\]]*)\]"#).unwrap()
+        .captures(json)
+        .map(|caps| string_re.captures_iter(&caps[1]).map(|tweak| json_unescape(&tweak[1])).collect())
+        .unwrap_or_default();
+
+        let invalid = |what: &str| format!("Invalid archive manifest: missing {}", what);
+        Ok(ArchiveManifest {
+            format: number("format").ok_or_else(|| invalid("format"))?,
+            launcher: field("launcher").ok_or_else(|| invalid("launcher"))?,
+            runner: field("runner").unwrap_or_default(),
+            env,
+            tweaks,
+            files: number("files").ok_or_else(|| invalid("files"))?,
+            bytes: number("bytes").ok_or_else(|| invalid("bytes"))?,
+        })
+    }
+}
+
+/// Stream `prefix` and its manifest into a zstd-compressed archive at `output`.
+pub fn create_archive(prefix: &Path, manifest: &ArchiveManifest, output: &Path) -> Result<(), String> {
+    let prefix_parent = prefix.parent().ok_or("Prefix has no parent directory")?;
+    let prefix_name = prefix.file_name().ok_or("Prefix has no directory name")?;
+
+    // Staged in its own directory so tar can store it at the archive root
+    let staging = std::env::temp_dir().join(format!("slpfx-{}", std::process::id()));
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+    fs::write(staging.join("manifest.json"), manifest.to_json())
+    .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    println!("{}Hashing {} files...{}", COLOR_BLUE, manifest.files, COLOR_RESET);
+    let checksums: String = hash_tree(prefix)?.iter()
+    .map(|(file, hash)| format!("{}  prefix/{}\n", hash, file.display()))
+    .collect();
+    fs::write(staging.join(CHECKSUMS), checksums)
+    .map_err(|e| format!("Failed to write checksums: {}", e))?;
+
+    println!("{}Creating {}...{}", COLOR_BLUE, output.display(), COLOR_RESET);
+    emit_progress("archive", None, &format!("Creating {}", output.display()));
+
+    let mut tar = Command::new("tar")
+    .arg("-cf").arg("-")
+    .arg("-C").arg(&staging).arg("manifest.json").arg(CHECKSUMS)
+    .arg("-C").arg(prefix_parent)
+    // Rename the prefix directory to `prefix/`, leaving symlink and hardlink targets alone
+    .arg(format!("--transform=s,^{}\\(/\\|$\\),prefix\\1,SH", sed_escape(&prefix_name.to_string_lossy())))
+    .arg(prefix_name)
+    .stdout(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Failed to execute tar: {}", e))?;
+    let tar_stdout = tar.stdout.take().ok_or("Failed to read tar output")?;
+
+    let zstd_status = Command::new("zstd")
+    .args(["-q", "-f", "-T0", "--check", "-o"])
+    .arg(output)
+    .stdin(tar_stdout)
+    .status()
+    .map_err(|e| format!("Failed to execute zstd: {}", e))?;
+    let tar_status = tar.wait().map_err(|e| format!("Failed to wait for tar: {}", e))?;
+    let _ = fs::remove_dir_all(&staging);
+
+    if !tar_status.success() || !zstd_status.success() {
+        let _ = fs::remove_file(output);
+        return Err(format!("Failed to create archive {}", output.display()));
+    }
+
+    println!("{}Archive written: {}{}", COLOR_GREEN, output.display(), COLOR_RESET);
+    Ok(())
+}
+
+/// Read just the manifest of an archive.
+pub fn read_manifest(archive: &Path) -> Result<ArchiveManifest, String> {
+    let json = read_member(archive, "manifest.json")?;
+    if json.is_empty() {
+        return Err(format!("{} is not a prefix archive (no manifest.json)", archive.display()));
+    }
+    ArchiveManifest::from_json(&json)
+}
+
+/// Extract an archive's prefix to `destination`, checking the zstd checksums and that the
+/// extracted files match the manifest. Nothing is left behind if a check fails.
+pub fn extract_archive(archive: &Path, destination: &Path) -> Result<ArchiveManifest, String> {
+    let manifest = read_manifest(archive)?;
+    if manifest.format > ARCHIVE_FORMAT {
+        return Err(format!("{} uses archive format {}; this version supports up to {}.",
+                           archive.display(), manifest.format, ARCHIVE_FORMAT));
+    }
+    if destination.exists() {
+        return Err(format!("{} already exists.", destination.display()));
+    }
+
+    let parent = destination.parent().ok_or("Destination has no parent directory")?;
+    let staging = parent.join(format!(".slpfx-extract-{}", std::process::id()));
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+
+    println!("{}Extracting {}...{}", COLOR_BLUE, archive.display(), COLOR_RESET);
+    emit_progress("archive", None, &format!("Extracting {}", archive.display()));
+
+    let result = (|| {
+        let mut zstd = decompress(archive)?;
+        let zstd_stdout = zstd.stdout.take().ok_or("Failed to read zstd output")?;
+        let tar_status = Command::new("tar")
+        .arg("-xf").arg("-")
+        .arg("-C").arg(&staging)
+        .arg("prefix")
+        .stdin(zstd_stdout)
+        .status()
+        .map_err(|e| format!("Failed to execute tar: {}", e))?;
+        let zstd_status = zstd.wait().map_err(|e| format!("Failed to wait for zstd: {}", e))?;
+        if !zstd_status.success() {
+            return Err(format!("{} is corrupt (zstd checksum mismatch).", archive.display()));
+        }
+        if !tar_status.success() {
+            return Err(format!("Failed to extract {}", archive.display()));
+        }
+
+        let extracted = staging.join("prefix");
+        let (files, bytes) = count_files(&extracted)
+        .map_err(|e| format!("Failed to read extracted prefix: {}", e))?;
+        if files != manifest.files || bytes != manifest.bytes {
+            return Err(format!("{} is incomplete: expected {} files ({} bytes), found {} ({} bytes).",
+                               archive.display(), manifest.files, manifest.bytes, files, bytes));
+        }
+
+        // The hashes of the file contents, in archives that have them
+        let checksums = read_member(archive, CHECKSUMS)?;
+        if !checksums.is_empty() {
+            println!("{}Checking {} files...{}", COLOR_BLUE, files, COLOR_RESET);
+            let expected: Vec<(&str, PathBuf)> = checksums.lines()
+            .filter_map(|line| line.split_once("  "))
+            .map(|(hash, file)| (hash, staging.join(file)))
+            .collect();
+            let paths: Vec<PathBuf> = expected.iter().map(|(_, path)| path.clone()).collect();
+            let changed = expected.iter().zip(sha256_files(&paths))
+            .filter(|((hash, _), actual)| actual.as_deref() != Some(*hash))
+            .count();
+            if changed > 0 {
+                return Err(format!("{} is corrupt: {} files do not match their checksums.", archive.display(), changed));
+            }
+        }
+
+        fs::rename(&extracted, destination)
+        .map_err(|e| format!("Failed to move prefix into place: {}", e))
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result?;
+
+    println!("{}Prefix restored to {}{}", COLOR_GREEN, destination.display(), COLOR_RESET);
+    Ok(manifest)
+}
+
+// Read a small member of an archive, e.g. `manifest.json`; empty if it has none
+fn read_member(archive: &Path, member: &str) -> Result<String, String> {
+    let mut zstd = decompress(archive)?;
+    let zstd_stdout = zstd.stdout.take().ok_or("Failed to read zstd output")?;
+
+    let mut tar = Command::new("tar")
+    .args(["-xOf", "-", member])
+    .stdin(zstd_stdout)
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to execute tar: {}", e))?;
+
+    let mut content = String::new();
+    if let Some(mut stdout) = tar.stdout.take() {
+        stdout.read_to_string(&mut content).map_err(|e| format!("Failed to read {}: {}", member, e))?;
+    }
+    let _ = tar.wait();
+    let _ = zstd.wait();
+    Ok(content)
+}
+
+// Start `zstd -dc` on the archive with its output piped
+fn decompress(archive: &Path) -> Result<std::process::Child, String> {
+    Command::new("zstd")
+    .args(["-q", "-d", "-c"])
+    .arg(archive)
+    .stdout(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("Failed to execute zstd: {}", e))
+}
+
+// Count regular files and their total size, without following symlinks
+fn count_files(dir: &Path) -> io::Result<(u64, u64)> {
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut pending: Vec<PathBuf> = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files += 1;
+                bytes += entry.metadata()?.len();
+            }
+        }
+    }
+    Ok((files, bytes))
+}
+
+// Escape a literal for a sed basic regular expression delimited by commas
+fn sed_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '.' | '*' | '[' | ']' | '^' | '$' | ',') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,69 @@
+//! Host audio stack detection and the matching wine audio settings.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Sound server running on the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioStack {
+    PipeWire,
+    PulseAudio,
+    Alsa,
+}
+
+/// Wine audio driver and DirectSound buffer sizes for a prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioSettings {
+    /// Value of `HKCU\Software\Wine\Drivers\Audio`, e.g. `pulse` or `alsa`.
+    pub driver: &'static str,
+    /// DirectSound hardware emulation buffer length in bytes. Larger values stop crackling at the cost of latency.
+    pub buffer_length: u32,
+    /// Number of buffers DirectSound queues ahead.
+    pub queue_max: u32,
+}
+
+impl AudioStack {
+    /// Detect the host sound server from running processes and the PulseAudio socket.
+    pub fn detect() -> AudioStack {
+        if process_running("pipewire-pulse") || process_running("pipewire") {
+            return AudioStack::PipeWire;
+        }
+        let pulse_socket = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("pulse/native"));
+        if process_running("pulseaudio") || pulse_socket.map(|p| p.exists()).unwrap_or(false) {
+            return AudioStack::PulseAudio;
+        }
+        AudioStack::Alsa
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioStack::PipeWire => "PipeWire",
+            AudioStack::PulseAudio => "PulseAudio",
+            AudioStack::Alsa => "ALSA",
+        }
+    }
+
+    /// Settings that avoid crackling on this stack.
+    /// PipeWire's PulseAudio emulation needs larger buffers than native PulseAudio.
+    pub fn default_settings(&self) -> AudioSettings {
+        match self {
+            AudioStack::PipeWire => AudioSettings { driver: "pulse", buffer_length: 1024, queue_max: 16 },
+            AudioStack::PulseAudio => AudioSettings { driver: "pulse", buffer_length: 512, queue_max: 10 },
+            AudioStack::Alsa => AudioSettings { driver: "alsa", buffer_length: 512, queue_max: 10 },
+        }
+    }
+}
+
+// Whether any process with the given command name is running
+fn process_running(name: &str) -> bool {
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    entries.flatten().any(|entry| {
+        fs::read_to_string(entry.path().join("comm"))
+        .map(|comm| comm.trim() == name)
+        .unwrap_or(false)
+    })
+}
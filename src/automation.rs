@@ -0,0 +1,172 @@
+//! AutoHotkey answer scripts that click through installers without a silent mode.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use crate::download::download_file;
+use crate::launchers::LauncherInfo;
+use crate::output::*;
+use crate::policy::{sha256_file, HashAllowlist};
+use crate::wine::WineArgs;
+
+// AutoHotkey v1.1 portable build used to click through installers without a silent mode
+const AUTOHOTKEY_URL: &str = "https://www.autohotkey.com/download/1.1/AutoHotkey_1.1.37.02.zip";
+
+/// ID the AutoHotkey archive's hash is listed under in the allowlist and the manifest, as
+/// `<sha256> autohotkey`
+pub const AUTOHOTKEY_ALLOWLIST_ID: &str = "autohotkey";
+
+// Executable in the archive, and its name in drive_c
+const AUTOHOTKEY_EXE: &str = "AutoHotkeyU64.exe";
+
+/// Directory holding user-provided AutoHotkey answer scripts
+pub fn automation_config_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join(".config/game-launcher-installer/automation")
+}
+
+/// Resolve the answer script for a launcher, preferring a user override over the built-in one
+pub fn find_automation_script(home_dir: &Path, launcher: &LauncherInfo) -> Option<String> {
+    let override_path = automation_config_dir(home_dir).join(format!("{}.ahk", launcher.id));
+    if let Ok(script) = fs::read_to_string(&override_path) {
+        println!("{}Using automation script from {}{}", COLOR_BLUE, override_path.display(), COLOR_RESET);
+        return Some(script);
+    }
+
+    launcher.automation_script.map(str::to_string)
+}
+
+/// Download and unpack AutoHotkey, returning the path to its executable. The archive is kept
+/// and has to be on the allowlist (see `AUTOHOTKEY_ALLOWLIST_ID`) every time it is used.
+pub fn ensure_autohotkey(home_dir: &Path, allowlist: &HashAllowlist) -> Result<PathBuf, String> {
+    let ahk_dir = home_dir.join(".autohotkey");
+    let ahk_exe = ahk_dir.join(AUTOHOTKEY_EXE);
+    let archive = ahk_dir.join("AutoHotkey.zip");
+
+    if !archive.exists() {
+        let _ = fs::remove_file(&ahk_exe);
+        download_file(AUTOHOTKEY_URL, &archive)?;
+    }
+    let sha256 = sha256_file(&archive)?;
+    if !allowlist.contains(&sha256, AUTOHOTKEY_ALLOWLIST_ID) {
+        let _ = fs::remove_file(&archive);
+        let _ = fs::remove_file(&ahk_exe);
+        return Err(format!(
+            "The AutoHotkey download from {} (SHA-256 {}) is not on the allowlist. Compare the hash with the one autohotkey.com publishes and, if it matches, add `{} {}` to {} or run `refresh-manifest`.",
+            AUTOHOTKEY_URL, sha256, sha256, AUTOHOTKEY_ALLOWLIST_ID, HashAllowlist::path(home_dir).display()
+        ));
+    }
+    if ahk_exe.exists() {
+        return Ok(ahk_exe);
+    }
+
+    let status = Command::new("unzip")
+    .arg("-o")
+    .arg("-q")
+    .arg(&archive)
+    .arg("-d")
+    .arg(&ahk_dir)
+    .status()
+    .map_err(|e| format!("Failed to execute unzip: {}", e))?;
+
+    if !status.success() || !ahk_exe.exists() {
+        return Err("Failed to extract AutoHotkey.".to_string());
+    }
+
+    Ok(ahk_exe)
+}
+
+/// Start the launcher's answer script inside the prefix, returning the running AutoHotkey process
+pub fn start_installer_automation(wine_path: &str, wine_prefix: &Path, home_dir: &Path, launcher: &LauncherInfo, allowlist: &HashAllowlist)
+-> Result<Option<Child>, String> {
+    let script = match find_automation_script(home_dir, launcher) {
+        Some(script) => script,
+        None => return Ok(None),
+    };
+
+    // AutoHotkey and the script go inside drive_c, so both are reachable when the prefix has
+    // no Z: drive
+    let drive_c = wine_prefix.join("drive_c");
+    fs::copy(ensure_autohotkey(home_dir, allowlist)?, drive_c.join(AUTOHOTKEY_EXE))
+    .map_err(|e| format!("Failed to copy AutoHotkey into the prefix: {}", e))?;
+    let script_name = format!("{}.ahk", launcher.id);
+    fs::write(drive_c.join(&script_name), script)
+    .map_err(|e| format!("Failed to write automation script: {}", e))?;
+
+    println!("{}Starting installer automation for {}...{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+
+    let child = Command::new(wine_path)
+    .env("WINEPREFIX", wine_prefix)
+    .env("WINEDEBUG", "-all")
+    .arg(format!("C:\\{}", AUTOHOTKEY_EXE))
+    .args(WineArgs::new().arg(&format!("C:\\{}", script_name)).build())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to start AutoHotkey: {}", e))?;
+
+    Ok(Some(child))
+}
+
+/// Stop the answer script once the installer is done
+pub fn stop_installer_automation(automation: Option<Child>) {
+    if let Some(mut child) = automation {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launchers::LauncherRegistry;
+
+    #[test]
+    fn scripts_come_from_the_registry_unless_overridden() {
+        let home_dir = std::env::temp_dir().join(format!("gli-automation-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home_dir);
+        let registry = LauncherRegistry::builtin();
+        let battlenet = registry.get("battlenet").unwrap();
+        let ea_app = registry.get("ea-app").unwrap();
+
+        assert!(find_automation_script(&home_dir, battlenet).unwrap().contains("WinWait, Battle.net Setup"));
+        assert_eq!(find_automation_script(&home_dir, ea_app), None);
+
+        fs::create_dir_all(automation_config_dir(&home_dir)).unwrap();
+        fs::write(automation_config_dir(&home_dir).join("ea-app.ahk"), "ExitApp\n").unwrap();
+        assert_eq!(find_automation_script(&home_dir, ea_app).as_deref(), Some("ExitApp\n"));
+        fs::remove_dir_all(&home_dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_unlisted_autohotkey() {
+        let home_dir = std::env::temp_dir().join(format!("gli-autohotkey-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home_dir);
+        // A kept archive is checked again before it is used
+        fs::create_dir_all(home_dir.join(".autohotkey")).unwrap();
+        fs::write(home_dir.join(".autohotkey/AutoHotkey.zip"), "not the real archive").unwrap();
+        fs::write(home_dir.join(".autohotkey").join(AUTOHOTKEY_EXE), "MZ").unwrap();
+
+        let error = ensure_autohotkey(&home_dir, &HashAllowlist::default()).unwrap_err();
+        assert!(error.contains("is not on the allowlist"), "{}", error);
+        assert!(!home_dir.join(".autohotkey/AutoHotkey.zip").exists());
+        assert!(!home_dir.join(".autohotkey").join(AUTOHOTKEY_EXE).exists());
+        fs::remove_dir_all(&home_dir).unwrap();
+    }
+
+    #[test]
+    fn uses_listed_autohotkey() {
+        let home_dir = std::env::temp_dir().join(format!("gli-autohotkey-listed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home_dir);
+        let archive = home_dir.join(".autohotkey/AutoHotkey.zip");
+        fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        fs::write(&archive, "stand-in archive").unwrap();
+        fs::write(home_dir.join(".autohotkey").join(AUTOHOTKEY_EXE), "MZ").unwrap();
+        let allowlist_path = home_dir.join("allowlist");
+        HashAllowlist::add(&allowlist_path, &sha256_file(&archive).unwrap(), AUTOHOTKEY_ALLOWLIST_ID).unwrap();
+
+        let exe = ensure_autohotkey(&home_dir, &HashAllowlist::load(&allowlist_path).unwrap()).unwrap();
+        assert_eq!(exe, home_dir.join(".autohotkey").join(AUTOHOTKEY_EXE));
+        fs::remove_dir_all(&home_dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! The Battle.net client's own settings file, `%APPDATA%\Battle.net\Battle.net.config`,
+//! and what its Update Agent leaves in a prefix.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::dlloverrides::DllOverrides;
+use crate::fsutil::write_atomic;
+use crate::launchers::LauncherRegistry;
+use crate::output::*;
+use crate::prefix::PrefixManager;
+
+// wine's location API never answers, and the login page waits for it forever
+const LOGIN_DLL_OVERRIDES: &[(&str, &str)] = &[("locationapi", "disabled")];
+
+// WinHTTP TLS versions for the login servers: TLS 1.1, 1.2 and 1.3
+const WINHTTP_SECURE_PROTOCOLS: &str = "0x2a00";
+
+// WinHttpSettings for a direct connection without a proxy, as written by `netsh winhttp reset proxy`
+const WINHTTP_DIRECT_SETTINGS: &str = "1800000000000000010000000000000000000000";
+
+// Names the embedded browser's cache has had in %LOCALAPPDATA%\Battle.net
+const BROWSER_CACHE_DIRS: &[&str] = &["BrowserCache", "BrowserCaches"];
+
+// Processes of an install in progress, lower case as `PrefixManager::running_processes` lists them
+const INSTALLER_PROCESSES: &[&str] = &["agent.exe", "battle.net-setup.exe", "battle.net.exe"];
+
+// Where the Update Agent keeps its state, relative to drive_c. A copy half written by an earlier
+// install makes the installer exit right away without an error.
+const AGENT_STATE_DIRS: &[&str] = &["ProgramData/Battle.net/Agent", "ProgramData/Battle.net/Setup"];
+
+/// What an earlier, unfinished Battle.net install left in a prefix.
+pub struct LeftoverAgent {
+    /// Installer processes still running in the prefix
+    pub processes: Vec<String>,
+    /// Update Agent state directories that exist although the client isn't installed
+    pub state_dirs: Vec<PathBuf>,
+}
+
+/// Look for Agent processes, and for Update Agent state without an installed client, left by
+/// an earlier install. The state of a complete install also lists its games, so it is kept.
+pub fn find_leftover_agent(prefix: &PrefixManager) -> Option<LeftoverAgent> {
+    let processes: Vec<String> = prefix.running_processes().into_iter()
+    .filter(|process| INSTALLER_PROCESSES.contains(&process.as_str()))
+    .collect();
+
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get("battlenet").expect("Battle.net is a built-in launcher");
+    let installed = launcher.find_install_dir(prefix.path()).is_some_and(|dir| dir.join(launcher.exe_name).is_file());
+    let state_dirs: Vec<PathBuf> = if installed {
+        Vec::new()
+    } else {
+        AGENT_STATE_DIRS.iter().map(|dir| prefix.path().join("drive_c").join(dir)).filter(|dir| dir.exists()).collect()
+    };
+
+    if processes.is_empty() && state_dirs.is_empty() {
+        None
+    } else {
+        Some(LeftoverAgent { processes, state_dirs })
+    }
+}
+
+/// Stop the leftover processes and remove the leftover Update Agent state, so the next
+/// install starts from a clean state.
+pub fn reset_agent(prefix: &PrefixManager, leftover: &LeftoverAgent) -> Result<(), String> {
+    if !leftover.processes.is_empty() {
+        prefix.kill_wineserver();
+    }
+    for dir in &leftover.state_dirs {
+        fs::remove_dir_all(dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+        println!("{}Cleared {}.{}", COLOR_GREEN, dir.display(), COLOR_RESET);
+    }
+    Ok(())
+}
+
+/// Where the client keeps its settings in a prefix
+pub fn client_config_path(prefix: &PrefixManager) -> Option<PathBuf> {
+    Some(prefix.user_dir()?.join("AppData/Roaming/Battle.net/Battle.net.config"))
+}
+
+/// Make `install_path` (a Windows path such as `D:/`) the default location for game installs.
+/// Other settings in the file are kept; a missing file is created.
+pub fn set_default_install_path(config_path: &Path, install_path: &str) -> Result<(), String> {
+    let content = fs::read_to_string(config_path).unwrap_or_else(|_| "{}".to_string());
+    let value = format!("\"{}\"", json_escape(install_path));
+
+    let existing = Regex::new(r#""DefaultInstallPath"\s*:\s*"(?:[^"\\]|\\.)*""#).unwrap();
+    let updated = if existing.is_match(&content) {
+        existing.replace(&content, format!("\"DefaultInstallPath\": {}", value).as_str()).to_string()
+    } else {
+        // Add the key to the innermost of Client.Install that already exists
+        let install = Regex::new(r#""Install"\s*:\s*\{"#).unwrap();
+        let client = Regex::new(r#""Client"\s*:\s*\{"#).unwrap();
+        if let Some(m) = install.find(&content) {
+            insert_member(&content, m.end(), &format!("\"DefaultInstallPath\": {}", value))
+        } else if let Some(m) = client.find(&content) {
+            insert_member(&content, m.end(), &format!("\"Install\": {{ \"DefaultInstallPath\": {} }}", value))
+        } else {
+            let root = content.find('{').ok_or_else(|| format!("{} is not a JSON object.", config_path.display()))?;
+            insert_member(&content, root + 1, &format!("\"Client\": {{ \"Install\": {{ \"DefaultInstallPath\": {} }} }}", value))
+        }
+    };
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_atomic(config_path, updated).map_err(|e| format!("Failed to write {}: {}", config_path.display(), e))
+}
+
+/// Set a string setting directly under `Client`, e.g. `HardwareAcceleration`. Other settings
+/// in the file are kept; a missing file is created.
+pub fn set_client_setting(config_path: &Path, name: &str, value: &str) -> Result<(), String> {
+    let content = fs::read_to_string(config_path).unwrap_or_else(|_| "{}".to_string());
+    let member = format!("\"{}\": \"{}\"", json_escape(name), json_escape(value));
+
+    let existing = Regex::new(&format!(r#""{}"\s*:\s*"(?:[^"\\]|\\.)*""#, regex::escape(name))).unwrap();
+    let updated = if existing.is_match(&content) {
+        existing.replace(&content, regex::NoExpand(&member)).to_string()
+    } else if let Some(m) = Regex::new(r#""Client"\s*:\s*\{"#).unwrap().find(&content) {
+        insert_member(&content, m.end(), &member)
+    } else {
+        let root = content.find('{').ok_or_else(|| format!("{} is not a JSON object.", config_path.display()))?;
+        insert_member(&content, root + 1, &format!("\"Client\": {{ {} }}", member))
+    };
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_atomic(config_path, updated).map_err(|e| format!("Failed to write {}: {}", config_path.display(), e))
+}
+
+/// Apply the known fixes for a blank, frozen or endlessly reloading login window in one step:
+/// disable the DLLs that break the embedded browser (also added to `overrides`), let WinHTTP use
+/// current TLS versions without a proxy, turn off the client's hardware acceleration and clear
+/// the browser cache. Battle.net must not be running.
+pub fn fix_login(prefix: &PrefixManager, overrides: &mut DllOverrides) -> Result<(), String> {
+    for (dll, mode) in LOGIN_DLL_OVERRIDES {
+        overrides.set(dll, mode)?;
+    }
+    prefix.apply_dll_overrides(overrides, &[])?;
+    println!("{}DLL overrides set: {}{}", COLOR_GREEN, overrides.to_env(), COLOR_RESET);
+
+    for key in [
+        "HKLM\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings\\WinHttp",
+        "HKLM\\Software\\Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\Internet Settings\\WinHttp",
+    ] {
+        prefix.set_registry_value(key, "DefaultSecureProtocols", "REG_DWORD", WINHTTP_SECURE_PROTOCOLS)?;
+    }
+    prefix.set_registry_value(
+        "HKLM\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings\\Connections",
+        "WinHttpSettings", "REG_BINARY", WINHTTP_DIRECT_SETTINGS,
+    )?;
+    println!("{}WinHTTP set to TLS 1.1-1.3 with a direct connection.{}", COLOR_GREEN, COLOR_RESET);
+
+    let config_path = client_config_path(prefix).ok_or("The prefix has no Windows user profile yet.")?;
+    set_client_setting(&config_path, "HardwareAcceleration", "false")?;
+    println!("{}Hardware acceleration turned off in {}.{}", COLOR_GREEN, config_path.display(), COLOR_RESET);
+
+    let local_dir = prefix.user_dir().ok_or("The prefix has no Windows user profile yet.")?.join("AppData/Local/Battle.net");
+    for cache in BROWSER_CACHE_DIRS.iter().map(|name| local_dir.join(name)).filter(|dir| dir.exists()) {
+        fs::remove_dir_all(&cache).map_err(|e| format!("Failed to remove {}: {}", cache.display(), e))?;
+        println!("{}Cleared {}.{}", COLOR_GREEN, cache.display(), COLOR_RESET);
+    }
+    Ok(())
+}
+
+// Insert `member` right after an object's opening brace, adding a comma if the object isn't empty
+pub(crate) fn insert_member(content: &str, after_brace: usize, member: &str) -> String {
+    let rest = &content[after_brace..];
+    let separator = if rest.trim_start().starts_with('}') { "" } else { "," };
+    format!("{}\n    {}{}{}", &content[..after_brace], member, separator, rest)
+}
@@ -0,0 +1,111 @@
+//! Btrfs subvolumes for launcher directories and the snapshots taken before they change.
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::output::*;
+
+// Inode number btrfs gives the root directory of every subvolume
+const SUBVOLUME_ROOT_INODE: u64 = 256;
+
+/// Whether `path`, or the nearest existing directory above it, is on btrfs.
+pub fn is_btrfs(path: &Path) -> bool {
+    let existing = match path.ancestors().find(|ancestor| ancestor.exists()) {
+        Some(existing) => existing,
+        None => return false,
+    };
+    Command::new("stat")
+    .args(["-f", "-c", "%T"])
+    .arg(existing)
+    .output()
+    .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "btrfs")
+    .unwrap_or(false)
+}
+
+/// Whether `path` is the root of a btrfs subvolume.
+pub fn is_subvolume(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.is_dir() && m.ino() == SUBVOLUME_ROOT_INODE).unwrap_or(false) && is_btrfs(path)
+}
+
+/// Create `path` as a new subvolume.
+pub fn create_subvolume(path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    run_btrfs(&["subvolume", "create"], &[path])?;
+    println!("{}Created btrfs subvolume {}{}", COLOR_GREEN, path.display(), COLOR_RESET);
+    Ok(())
+}
+
+/// Take a read-only snapshot of `subvolume` in `snapshots_dir`, named after the current time.
+pub fn snapshot(subvolume: &Path, snapshots_dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(snapshots_dir)
+    .map_err(|e| format!("Failed to create {}: {}", snapshots_dir.display(), e))?;
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let target = snapshots_dir.join(timestamp.to_string());
+    run_btrfs(&["subvolume", "snapshot", "-r"], &[subvolume, &target])?;
+    println!("{}Snapshot of {} saved to {}{}", COLOR_GREEN, subvolume.display(), target.display(), COLOR_RESET);
+    Ok(target)
+}
+
+/// Snapshots in `snapshots_dir`, oldest first.
+pub fn list_snapshots(snapshots_dir: &Path) -> Vec<PathBuf> {
+    let mut snapshots: Vec<(u64, PathBuf)> = match fs::read_dir(snapshots_dir) {
+        Ok(entries) => entries.flatten()
+        .filter_map(|entry| {
+            let timestamp = entry.file_name().to_str()?.parse().ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect(),
+        Err(_) => Vec::new(),
+    };
+    snapshots.sort();
+    snapshots.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Replace `subvolume` with a writable copy of its latest snapshot. The current state is
+/// kept next to the snapshots as `<timestamp>-pre-rollback`, so the rollback can be undone.
+pub fn rollback(subvolume: &Path, snapshots_dir: &Path) -> Result<PathBuf, String> {
+    let latest = list_snapshots(snapshots_dir).pop()
+    .ok_or_else(|| format!("No snapshots found in {}", snapshots_dir.display()))?;
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let set_aside = snapshots_dir.join(format!("{}-pre-rollback", timestamp));
+    fs::rename(subvolume, &set_aside)
+    .map_err(|e| format!("Failed to move {} aside: {}", subvolume.display(), e))?;
+
+    if let Err(e) = run_btrfs(&["subvolume", "snapshot"], &[&latest, subvolume]) {
+        // Put the original back so the launcher keeps working
+        let _ = fs::rename(&set_aside, subvolume);
+        return Err(e);
+    }
+
+    println!("{}Restored {} from {}. The previous state is in {}.{}",
+             COLOR_GREEN, subvolume.display(), latest.display(), set_aside.display(), COLOR_RESET);
+    Ok(latest)
+}
+
+/// Delete a subvolume or snapshot, read-only ones included.
+pub fn delete_subvolume(path: &Path) -> Result<(), String> {
+    run_btrfs(&["subvolume", "delete"], &[path])
+}
+
+// Run `btrfs <args> <paths>` and turn a failure into an error message
+fn run_btrfs(args: &[&str], paths: &[&Path]) -> Result<(), String> {
+    let status = Command::new("btrfs")
+    .args(args)
+    .args(paths)
+    .stdout(Stdio::null())
+    .status()
+    .map_err(|e| format!("Failed to execute btrfs: {}", e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("btrfs {} failed with exit code: {}", args.join(" "), status))
+    }
+}
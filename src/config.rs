@@ -0,0 +1,640 @@
+//! User settings and the on-disk layout of managed launchers.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::download::MAX_DOWNLOAD_CONNECTIONS;
+use crate::fsutil::write_atomic;
+use crate::installer::{DEFAULT_HYGIENE, HYGIENE_SETS};
+use crate::launchers::LauncherRegistry;
+use crate::manifest::DEFAULT_MANIFEST_URL;
+use crate::output::{is_sgr, Theme, ThemeColor};
+use crate::policy::InstallerPolicy;
+use crate::steam::SteamIntegrationLevel;
+use crate::template::DEFAULT_TEMPLATE_URL;
+use crate::wayland::WaylandPreference;
+use crate::wine::Runner;
+
+// Minutes without activity before a silent installer counts as hung
+const DEFAULT_HANG_TIMEOUT: u64 = 10;
+
+/// Paths the installer works with: the user's home and the Games root that holds
+/// `<root>/<Launcher>/{prefix,drive,cache}` for each launcher.
+pub struct AppPaths {
+    pub home_dir: PathBuf,
+    pub games_root: PathBuf,
+}
+
+impl AppPaths {
+    /// Each launcher gets its own directory under the Games root
+    pub fn launcher_dir(&self, launcher: &str) -> PathBuf {
+        self.games_root.join(launcher)
+    }
+
+    /// Wine prefix the launcher's installer runs in
+    pub fn prefix_dir(&self, launcher: &str) -> PathBuf {
+        self.launcher_dir(launcher).join("prefix")
+    }
+
+    /// Default destination for the launcher's files
+    pub fn drive_dir(&self, launcher: &str) -> PathBuf {
+        self.launcher_dir(launcher).join("drive")
+    }
+
+    /// Downloaded installers
+    pub fn cache_dir(&self, launcher: &str) -> PathBuf {
+        self.launcher_dir(launcher).join("cache")
+    }
+
+    /// What was last installed for the launcher, see `LauncherState`
+    pub fn state_path(&self, launcher: &str) -> PathBuf {
+        self.launcher_dir(launcher).join("state")
+    }
+
+    /// DLL overrides applied to the launcher's prefix and installer
+    pub fn dll_overrides_path(&self, launcher: &str) -> PathBuf {
+        self.launcher_dir(launcher).join("dlloverrides")
+    }
+
+    /// Btrfs snapshots of the launcher directory, kept outside it so they aren't snapshotted themselves
+    pub fn snapshots_dir(&self, launcher: &str) -> PathBuf {
+        self.games_root.join(".snapshots").join(launcher)
+    }
+
+    /// Sandboxed launch script generated by the `wrapper` command
+    pub fn launch_script_path(&self, launcher: &str) -> PathBuf {
+        self.launcher_dir(launcher).join("launch.sh")
+    }
+}
+
+// Settings the config file accepts (key, JSON Schema type fragment, description).
+// `pin.<launcher>`, `compatdata.<launcher>`, `color.<role>` and `env.` keys are handled separately.
+const CONFIG_KEYS: &[(&str, &str, &str)] = &[
+    ("games_root", r#""type": "string", "minLength": 1"#,
+     "Directory that holds <root>/<Launcher>/{prefix,drive,cache}"),
+    ("installer_policy", r#""enum": ["strict", "normal", "off"]"#,
+     "What to do with installers whose hash isn't on the allowlist"),
+    ("manifest_url", r#""type": "string", "pattern": "^https?://""#,
+     "Where refresh-manifest downloads the signed manifest from"),
+    ("template_url", r#""type": "string", "pattern": "^https?://""#,
+     "Where `template download` fetches pre-baked prefix templates from"),
+    ("signing_key", r#""type": "string", "minLength": 1"#,
+     "PEM file with the ed25519 public key the manifest and template checksums must be signed with, instead of the project's"),
+    ("runner", r#""enum": ["wine", "umu", "fex", "box64"]"#,
+     "Run launchers with system wine, Proton through umu-run, or on ARM64 x86-64 wine under FEX-Emu or Box64"),
+    ("x86_wine", r#""type": "string", "minLength": 1"#,
+     "x86-64 wine binary run by the fex and box64 runners (default: wine from PATH)"),
+    ("steam_integration", r#""enum": ["shortcut", "instructions", "none"]"#,
+     "Add a Steam shortcut after installing, print how to add one, or do neither"),
+    ("logging", r#""enum": ["local", "off"]"#,
+     "Keep a local log of progress events. Nothing is ever sent anywhere"),
+    ("favorites", r#""type": "string""#,
+     "Comma-separated launcher IDs listed first in the menu"),
+    ("steam_collection", r#""type": "string", "minLength": 1"#,
+     "Steam collection the shortcuts this tool adds are put in, e.g. Launchers (default: none)"),
+    ("steam_libraries", r#""type": "string""#,
+     "Comma-separated Steam library directories libraryfolders.vdf doesn't list, e.g. on external drives"),
+    ("shared_root", r#""type": "string", "minLength": 1"#,
+     "System-wide directory `share` publishes launcher files to, e.g. /opt/game-launchers"),
+    ("shared_group", r#""type": "string", "minLength": 1"#,
+     "Group allowed to update the shared launcher files (default: users)"),
+    ("wayland", r#""enum": ["auto", "xwayland"]"#,
+     "Under Wayland, use wine's Wayland driver in launch scripts when the runner has one, or always Xwayland"),
+    ("installer_hang_timeout", r#""type": "integer", "minimum": 0"#,
+     "Minutes a silent installer may show no CPU, disk or prefix activity before it counts as hung; 0 waits forever (default: 10)"),
+    ("download_connections", r#""type": "integer", "minimum": 1, "maximum": 16"#,
+     "Connections large installers are downloaded over at once, each fetching its own part of the file (default: 1)"),
+    ("installer_hygiene", r#""type": "string", "pattern": "^(off|[a-z-]+( *, *[a-z-]+)*)$""#,
+     "Comma-separated sets of variables that keep overlays out of installers: mangohud, amd-switchable, vkbasalt, obs, implicit-layers; or off (default: mangohud, amd-switchable)"),
+    ("registry_backups", r#""enum": ["on", "off"]"#,
+     "Export registry keys before tweaks change them, for `restore-registry` (default: on)"),
+    ("theme", r#""enum": ["default", "colorblind", "none"]"#,
+     "Terminal color palette. NO_COLOR in the environment also turns colors off"),
+];
+
+/// A problem found in the config file, e.g. `line 3: installer_polcy: unknown key`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub line: usize,
+    pub key: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "line {}: {}: {}", self.line, self.key, self.message)
+        }
+    }
+}
+
+/// User settings, stored as `key = value` lines in the config file.
+pub struct Config {
+    pub games_root: PathBuf,
+    /// Installer URL or local file per launcher ID, from `pin.<launcher> = <url>` lines.
+    /// A pinned launcher is installed from it and skipped by `update`.
+    pub installer_pins: Vec<(String, String)>,
+    /// What to do with installers whose hash isn't on the allowlist (`installer_policy`)
+    pub installer_policy: InstallerPolicy,
+    /// Where `refresh-manifest` downloads the signed manifest from (`manifest_url`)
+    pub manifest_url: String,
+    /// Where `template download` fetches pre-baked templates from (`template_url`)
+    pub template_url: String,
+    /// Public key manifests and template checksums are verified against instead of the
+    /// project's (`signing_key`)
+    pub signing_key: Option<PathBuf>,
+    pub runner: Runner,
+    /// x86-64 `wine` the FEX and Box64 runners start (`x86_wine`)
+    pub x86_wine: Option<PathBuf>,
+    pub steam_integration: SteamIntegrationLevel,
+    /// Collection (tag) new Steam shortcuts are put in (`steam_collection = Launchers`)
+    pub steam_collection: Option<String>,
+    /// Write progress events to `log_path` unless `--progress-events` is given (`logging = local`)
+    pub local_log: bool,
+    /// Launcher IDs listed at the top of the menu (`favorites = battlenet, hoyoplay`)
+    pub favorites: Vec<String>,
+    /// Steam libraries not in libraryfolders.vdf, e.g. on external drives (`steam_libraries = /mnt/games/SteamLibrary`)
+    pub steam_libraries: Vec<PathBuf>,
+    /// System-wide directory for shared launcher files (`shared_root`), see `share`
+    pub shared_root: Option<PathBuf>,
+    /// Group that owns the shared launcher files (`shared_group`)
+    pub shared_group: String,
+    /// Whether launch scripts may use wine's Wayland driver (`wayland`)
+    pub wayland: WaylandPreference,
+    /// Minutes without activity before a silent installer counts as hung, 0 for never (`installer_hang_timeout`)
+    pub installer_hang_timeout: u64,
+    /// Connections an installer is downloaded over at once (`download_connections`)
+    pub download_connections: u32,
+    /// Compatdata directory per launcher ID for its Steam shortcut, from `compatdata.<launcher> = <dir>`
+    /// lines. Set with `STEAM_COMPAT_DATA_PATH` in the shortcut's launch options.
+    pub compat_data_paths: Vec<(String, PathBuf)>,
+    /// Names of the `HYGIENE_SETS` installers run with (`installer_hygiene`); empty for off
+    pub installer_hygiene: Vec<String>,
+    /// Variables installers run with, from `env.<NAME> = <value>` lines for every launcher and
+    /// `env.<launcher>.<NAME> = <value>` lines for one, keyed without the `env.`. An empty
+    /// value removes the variable.
+    pub env_overrides: Vec<(String, String)>,
+    /// Export registry keys to a backup before a tweak changes them (`registry_backups`)
+    pub registry_backups: bool,
+    /// Built-in color theme (`theme`), see `Theme::named`
+    pub theme_name: String,
+    /// SGR parameters per color role from `color.<role> = 1;36` lines, applied over the theme
+    pub color_overrides: Vec<(String, String)>,
+}
+
+impl Config {
+    pub fn path(home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/game-launcher-installer/config")
+    }
+
+    /// Load settings, falling back to defaults for anything missing or unreadable
+    pub fn load(home_dir: &Path) -> Config {
+        let mut config = Config {
+            games_root: home_dir.join("Games"),
+            installer_pins: Vec::new(),
+            installer_policy: InstallerPolicy::Normal,
+            manifest_url: DEFAULT_MANIFEST_URL.to_string(),
+            template_url: DEFAULT_TEMPLATE_URL.to_string(),
+            signing_key: None,
+            runner: Runner::SystemWine,
+            x86_wine: None,
+            steam_integration: SteamIntegrationLevel::Instructions,
+            steam_collection: None,
+            local_log: false,
+            favorites: Vec::new(),
+            steam_libraries: Vec::new(),
+            shared_root: None,
+            shared_group: "users".to_string(),
+            wayland: WaylandPreference::Auto,
+            installer_hang_timeout: DEFAULT_HANG_TIMEOUT,
+            download_connections: 1,
+            compat_data_paths: Vec::new(),
+            installer_hygiene: DEFAULT_HYGIENE.iter().map(|name| name.to_string()).collect(),
+            env_overrides: Vec::new(),
+            registry_backups: true,
+            theme_name: "default".to_string(),
+            color_overrides: Vec::new(),
+        };
+
+        let content = match fs::read_to_string(Config::path(home_dir)) {
+            Ok(content) => content,
+            Err(_) => return config,
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                match key.trim() {
+                    "games_root" if !value.is_empty() => config.games_root = PathBuf::from(value),
+                    "manifest_url" if !value.is_empty() => config.manifest_url = value.to_string(),
+                    "template_url" if !value.is_empty() => config.template_url = value.to_string(),
+                    "x86_wine" if !value.is_empty() => config.x86_wine = Some(PathBuf::from(value)),
+                    "signing_key" if !value.is_empty() => config.signing_key = Some(PathBuf::from(value)),
+                    "shared_root" if !value.is_empty() => config.shared_root = Some(PathBuf::from(value)),
+                    "shared_group" if !value.is_empty() => config.shared_group = value.to_string(),
+                    "steam_collection" if !value.is_empty() => config.steam_collection = Some(value.to_string()),
+                    "installer_policy" => {
+                        if let Some(policy) = InstallerPolicy::parse(value) {
+                            config.installer_policy = policy;
+                        }
+                    },
+                    "runner" => {
+                        if let Some(runner) = Runner::parse(value) {
+                            config.runner = runner;
+                        }
+                    },
+                    "steam_integration" => {
+                        if let Some(level) = SteamIntegrationLevel::parse(value) {
+                            config.steam_integration = level;
+                        }
+                    },
+                    "logging" => config.local_log = value == "local",
+                    "registry_backups" => config.registry_backups = value != "off",
+                    "wayland" => {
+                        if let Some(preference) = WaylandPreference::parse(value) {
+                            config.wayland = preference;
+                        }
+                    },
+                    "installer_hang_timeout" => {
+                        if let Ok(minutes) = value.parse::<u64>() {
+                            config.installer_hang_timeout = minutes;
+                        }
+                    },
+                    "installer_hygiene" if value == "off" => config.installer_hygiene.clear(),
+                    "installer_hygiene" => {
+                        config.installer_hygiene = value.split(',')
+                        .map(str::trim)
+                        .filter(|name| HYGIENE_SETS.iter().any(|set| set.name == *name))
+                        .map(str::to_string)
+                        .collect();
+                    },
+                    "download_connections" => {
+                        if let Ok(connections) = value.parse::<u32>() {
+                            config.download_connections = connections.clamp(1, MAX_DOWNLOAD_CONNECTIONS);
+                        }
+                    },
+                    "favorites" => {
+                        config.favorites = value.split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    },
+                    "steam_libraries" => {
+                        config.steam_libraries = value.split(',')
+                        .map(str::trim)
+                        .filter(|path| !path.is_empty())
+                        .map(PathBuf::from)
+                        .collect();
+                    },
+                    "theme" if Theme::named(value).is_some() => config.theme_name = value.to_string(),
+                    key if key.starts_with("color.") && is_sgr(value) => {
+                        config.color_overrides.push((key["color.".len()..].to_string(), value.to_string()));
+                    },
+                    key if key.starts_with("compatdata.") && !value.is_empty() => {
+                        config.compat_data_paths.push((key["compatdata.".len()..].to_string(), PathBuf::from(value)));
+                    },
+                    key if key.starts_with("pin.") && !value.is_empty() => {
+                        config.installer_pins.push((key["pin.".len()..].to_string(), value.to_string()));
+                    },
+                    key if key.starts_with("env.") && key.rsplit('.').next().is_some_and(is_env_name) => {
+                        config.env_overrides.push((key["env.".len()..].to_string(), value.to_string()));
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        config
+    }
+
+    pub fn save(&self, home_dir: &Path) -> Result<(), String> {
+        let path = Config::path(home_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let mut content = format!(
+            "games_root = {}\ninstaller_policy = {}\nrunner = {}\nsteam_integration = {}\nlogging = {}\n",
+            self.games_root.display(), self.installer_policy.name(), self.runner.name(),
+            self.steam_integration.name(), if self.local_log { "local" } else { "off" }
+        );
+        if let Some(x86_wine) = &self.x86_wine {
+            content.push_str(&format!("x86_wine = {}\n", x86_wine.display()));
+        }
+        if let Some(signing_key) = &self.signing_key {
+            content.push_str(&format!("signing_key = {}\n", signing_key.display()));
+        }
+        if let Some(shared_root) = &self.shared_root {
+            content.push_str(&format!("shared_root = {}\n", shared_root.display()));
+        }
+        if let Some(collection) = &self.steam_collection {
+            content.push_str(&format!("steam_collection = {}\n", collection));
+        }
+        if self.shared_group != "users" {
+            content.push_str(&format!("shared_group = {}\n", self.shared_group));
+        }
+        if self.wayland != WaylandPreference::Auto {
+            content.push_str(&format!("wayland = {}\n", self.wayland.name()));
+        }
+        if self.installer_hang_timeout != DEFAULT_HANG_TIMEOUT {
+            content.push_str(&format!("installer_hang_timeout = {}\n", self.installer_hang_timeout));
+        }
+        if self.installer_hygiene != DEFAULT_HYGIENE {
+            let value = if self.installer_hygiene.is_empty() { "off".to_string() } else { self.installer_hygiene.join(", ") };
+            content.push_str(&format!("installer_hygiene = {}\n", value));
+        }
+        if self.download_connections != 1 {
+            content.push_str(&format!("download_connections = {}\n", self.download_connections));
+        }
+        if !self.registry_backups {
+            content.push_str("registry_backups = off\n");
+        }
+        if self.theme_name != "default" {
+            content.push_str(&format!("theme = {}\n", self.theme_name));
+        }
+        for (role, sgr) in &self.color_overrides {
+            content.push_str(&format!("color.{} = {}\n", role, sgr));
+        }
+        if !self.favorites.is_empty() {
+            content.push_str(&format!("favorites = {}\n", self.favorites.join(", ")));
+        }
+        if !self.steam_libraries.is_empty() {
+            let libraries: Vec<String> = self.steam_libraries.iter().map(|library| library.display().to_string()).collect();
+            content.push_str(&format!("steam_libraries = {}\n", libraries.join(", ")));
+        }
+        if self.manifest_url != DEFAULT_MANIFEST_URL {
+            content.push_str(&format!("manifest_url = {}\n", self.manifest_url));
+        }
+        if self.template_url != DEFAULT_TEMPLATE_URL {
+            content.push_str(&format!("template_url = {}\n", self.template_url));
+        }
+        for (launcher, source) in &self.installer_pins {
+            content.push_str(&format!("pin.{} = {}\n", launcher, source));
+        }
+        for (launcher, dir) in &self.compat_data_paths {
+            content.push_str(&format!("compatdata.{} = {}\n", launcher, dir.display()));
+        }
+        for (key, value) in &self.env_overrides {
+            content.push_str(&format!("env.{} = {}\n", key, value));
+        }
+        write_atomic(&path, content).map_err(|e| format!("Failed to write config file: {}", e))
+    }
+
+    /// Whether the config file exists yet. Without one, the interactive menu starts with the setup wizard.
+    pub fn exists(home_dir: &Path) -> bool {
+        Config::path(home_dir).exists()
+    }
+
+    /// Local log of progress events, used with `logging = local`
+    pub fn log_path(home_dir: &Path) -> PathBuf {
+        home_dir.join(".local/state/game-launcher-installer/progress.log")
+    }
+
+    /// Validate the config file. A missing file has no issues.
+    pub fn check(home_dir: &Path) -> Result<Vec<ConfigIssue>, String> {
+        let path = Config::path(home_dir);
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(Config::validate(&content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Find everything `load` would ignore or fall back to a default for.
+    pub fn validate(content: &str) -> Vec<ConfigIssue> {
+        let registry = LauncherRegistry::builtin();
+        let mut issues = Vec::new();
+        let mut seen: Vec<&str> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut issue = |key: &str, message: String| {
+                issues.push(ConfigIssue { line: index + 1, key: key.to_string(), message });
+            };
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    issue("", format!("expected `key = value`, found `{}`", line));
+                    continue;
+                },
+            };
+            if seen.contains(&key) {
+                issue(key, "duplicate key".to_string());
+            }
+            seen.push(key);
+
+            match key {
+                "games_root" if value.is_empty() => issue(key, "must not be empty".to_string()),
+                "games_root" if !Path::new(value).is_absolute() => issue(key, "must be an absolute path".to_string()),
+                "x86_wine" | "shared_root" | "signing_key" if !Path::new(value).is_absolute() => issue(key, "must be an absolute path".to_string()),
+                "shared_group" | "steam_collection" if value.is_empty() => issue(key, "must not be empty".to_string()),
+                "installer_policy" if InstallerPolicy::parse(value).is_none() => {
+                    issue(key, format!("unknown policy `{}`, expected strict, normal or off", value));
+                },
+                "manifest_url" | "template_url" if !value.starts_with("https://") && !value.starts_with("http://") => {
+                    issue(key, "must be an http(s) URL".to_string());
+                },
+                "runner" if Runner::parse(value).is_none() => {
+                    issue(key, format!("unknown runner `{}`, expected wine, umu, fex or box64", value));
+                },
+                "steam_integration" if SteamIntegrationLevel::parse(value).is_none() => {
+                    issue(key, format!("unknown level `{}`, expected shortcut, instructions or none", value));
+                },
+                "logging" if value != "local" && value != "off" => {
+                    issue(key, format!("unknown value `{}`, expected local or off", value));
+                },
+                "registry_backups" if value != "on" && value != "off" => {
+                    issue(key, format!("unknown value `{}`, expected on or off", value));
+                },
+                "wayland" if WaylandPreference::parse(value).is_none() => {
+                    issue(key, format!("unknown value `{}`, expected auto or xwayland", value));
+                },
+                "installer_hang_timeout" if value.parse::<u64>().is_err() => {
+                    issue(key, format!("`{}` is not a number of minutes", value));
+                },
+                "download_connections" if !value.parse::<u32>().is_ok_and(|connections| (1..=MAX_DOWNLOAD_CONNECTIONS).contains(&connections)) => {
+                    issue(key, format!("`{}` is not a number from 1 to {}", value, MAX_DOWNLOAD_CONNECTIONS));
+                },
+                "installer_hygiene" if value != "off" => {
+                    for name in value.split(',').map(str::trim).filter(|name| !HYGIENE_SETS.iter().any(|set| set.name == *name)) {
+                        let sets: Vec<&str> = HYGIENE_SETS.iter().map(|set| set.name).collect();
+                        issue(key, format!("unknown set `{}`, expected off or some of {}", name, sets.join(", ")));
+                    }
+                },
+                "theme" if Theme::named(value).is_none() => {
+                    issue(key, format!("unknown theme `{}`, expected {}", value, Theme::NAMES.join(", ")));
+                },
+                key if key.starts_with("color.") => {
+                    let role = &key["color.".len()..];
+                    if ThemeColor::parse(role).is_none() {
+                        let roles: Vec<&str> = ThemeColor::ROLES.iter().map(|(name, _)| *name).collect();
+                        issue(key, format!("unknown color role `{}`, expected {}", role, roles.join(", ")));
+                    } else if !is_sgr(value) {
+                        issue(key, format!("`{}` is not an ANSI color code such as 1;36 or 38;5;208", value));
+                    }
+                },
+                "favorites" => {
+                    for id in value.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+                        if registry.get(id).is_none() {
+                            issue(key, format!("unknown launcher `{}`", id));
+                        }
+                    }
+                },
+                "steam_libraries" => {
+                    for library in value.split(',').map(str::trim).filter(|path| !path.is_empty()) {
+                        if !Path::new(library).is_absolute() {
+                            issue(key, format!("`{}` must be an absolute path", library));
+                        }
+                    }
+                },
+                "games_root" | "installer_policy" | "manifest_url" | "template_url" | "signing_key" | "runner" | "x86_wine" | "shared_root" | "shared_group" | "steam_integration" | "steam_collection" | "logging" | "registry_backups" | "wayland" | "installer_hang_timeout" | "installer_hygiene" | "download_connections" | "theme" => {},
+                key if key.starts_with("compatdata.") => {
+                    let launcher = &key["compatdata.".len()..];
+                    if registry.get(launcher).is_none() {
+                        issue(key, format!("unknown launcher `{}`", launcher));
+                    } else if !Path::new(value).is_absolute() {
+                        issue(key, "must be an absolute path".to_string());
+                    }
+                },
+                key if key.starts_with("env.") => {
+                    let (launcher, name) = match key["env.".len()..].split_once('.') {
+                        Some((launcher, name)) => (Some(launcher), name),
+                        None => (None, &key["env.".len()..]),
+                    };
+                    if let Some(launcher) = launcher.filter(|launcher| registry.get(launcher).is_none()) {
+                        issue(key, format!("unknown launcher `{}`", launcher));
+                    } else if !is_env_name(name) {
+                        issue(key, format!("`{}` is not a valid environment variable name", name));
+                    }
+                },
+                key if key.starts_with("pin.") => {
+                    let launcher = &key["pin.".len()..];
+                    if registry.get(launcher).is_none() {
+                        issue(key, format!("unknown launcher `{}`", launcher));
+                    } else if value.is_empty() {
+                        issue(key, "must be an installer URL or file".to_string());
+                    }
+                },
+                _ => issue(key, "unknown key".to_string()),
+            }
+        }
+        issues
+    }
+
+    /// JSON Schema describing the config file's keys, as if it were a JSON object.
+    pub fn json_schema() -> String {
+        let properties: Vec<String> = CONFIG_KEYS.iter()
+        .map(|(key, schema, description)| format!("    \"{}\": {{ {}, \"description\": \"{}\" }}", key, schema, description))
+        .collect();
+        let launchers: Vec<&str> = LauncherRegistry::builtin().all().iter().map(|launcher| launcher.id).collect();
+
+        format!(
+            "{{\n  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n  \"title\": \"game-launcher-installer config\",\n  \"description\": \"~/.config/game-launcher-installer/config, written as `key = value` lines\",\n  \"type\": \"object\",\n  \"properties\": {{\n{}\n  }},\n  \"patternProperties\": {{\n    \"^color\\\\.(success|warning|error|info)$\": {{ \"type\": \"string\", \"pattern\": \"^[0-9]{{1,3}}(;[0-9]{{1,3}})*$\", \"description\": \"ANSI SGR parameters for one color role, e.g. 1;36\" }},\n    \"^pin\\\\.({})$\": {{ \"type\": \"string\", \"minLength\": 1, \"description\": \"Installer URL or file the launcher is pinned to\" }},\n    \"^compatdata\\\\.({})$\": {{ \"type\": \"string\", \"minLength\": 1, \"description\": \"Compatdata directory the launcher's Steam shortcut uses instead of Steam's\" }},\n    \"^env\\\\.(({})\\\\.)?[A-Za-z_][A-Za-z0-9_]*$\": {{ \"type\": \"string\", \"description\": \"Environment variable installers run with, for every launcher or one; empty removes it\" }}\n  }},\n  \"additionalProperties\": false\n}}\n",
+            properties.join(",\n"),
+            launchers.join("|"),
+            launchers.join("|"),
+            launchers.join("|")
+        )
+    }
+
+    /// The `env.` variables for a launcher, the ones for every launcher first so the launcher's
+    /// own win. Keyed by variable name; an empty value removes the variable.
+    pub fn env_for(&self, launcher: &str) -> Vec<(String, String)> {
+        let everyone = self.env_overrides.iter().filter(|(key, _)| !key.contains('.')).cloned();
+        let own = self.env_overrides.iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix(launcher)?.strip_prefix('.')?.to_string(), value.clone())));
+        everyone.chain(own).collect()
+    }
+
+    /// The color theme with the `color.<role>` overrides applied.
+    pub fn theme(&self) -> Theme {
+        let mut theme = Theme::named(&self.theme_name).unwrap_or_default();
+        for (role, sgr) in &self.color_overrides {
+            if let Some(color) = ThemeColor::parse(role) {
+                theme.set(color, sgr);
+            }
+        }
+        theme
+    }
+
+    /// How long a silent installer may show no activity before it counts as hung, if ever.
+    pub fn hang_timeout(&self) -> Option<Duration> {
+        match self.installer_hang_timeout {
+            0 => None,
+            minutes => Some(Duration::from_secs(minutes * 60)),
+        }
+    }
+
+    /// The installer a launcher is pinned to, if any.
+    /// The compatdata directory set for a launcher's Steam shortcut, if any.
+    pub fn compat_data_path(&self, launcher: &str) -> Option<&Path> {
+        self.compat_data_paths.iter().find(|(id, _)| id == launcher).map(|(_, dir)| dir.as_path())
+    }
+
+    /// Set or, with `None`, clear the compatdata directory of a launcher's Steam shortcut.
+    pub fn set_compat_data_path(&mut self, launcher: &str, dir: Option<&Path>) {
+        self.compat_data_paths.retain(|(id, _)| id != launcher);
+        if let Some(dir) = dir {
+            self.compat_data_paths.push((launcher.to_string(), dir.to_path_buf()));
+        }
+    }
+
+    pub fn pinned_installer(&self, launcher: &str) -> Option<&str> {
+        self.installer_pins.iter()
+        .find(|(id, _)| id == launcher)
+        .map(|(_, source)| source.as_str())
+    }
+
+    /// Pin a launcher to an installer URL or file, or unpin it with None.
+    pub fn set_pinned_installer(&mut self, launcher: &str, source: Option<&str>) {
+        self.installer_pins.retain(|(id, _)| id != launcher);
+        if let Some(source) = source {
+            self.installer_pins.push((launcher.to_string(), source.to_string()));
+        }
+    }
+}
+
+/// Turn a typed or pasted path into a PathBuf, returning None for empty input.
+/// Handles surrounding quotes and backslash-escaped spaces from terminal drag-and-drop,
+/// and expands a leading ~. Non-ASCII characters are kept as typed.
+pub fn parse_path_input(input: &str, home_dir: &Path) -> Option<PathBuf> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let unquoted = if trimmed.len() >= 2
+        && ((trimmed.starts_with('"') && trimmed.ends_with('"'))
+            || (trimmed.starts_with('\'') && trimmed.ends_with('\'')))
+    {
+        trimmed[1..trimmed.len() - 1].to_string()
+    } else {
+        trimmed.replace("\\ ", " ")
+    };
+
+    if unquoted == "~" {
+        Some(home_dir.to_path_buf())
+    } else if let Some(rest) = unquoted.strip_prefix("~/") {
+        Some(home_dir.join(rest))
+    } else {
+        Some(PathBuf::from(unquoted))
+    }
+}
+
+// Whether `name` can be an environment variable set from a shell: letters, digits and
+// underscores, not starting with a digit
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
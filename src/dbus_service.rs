@@ -0,0 +1,250 @@
+// Session D-Bus service for desktop frontends (`--service`). It offers the operations of the
+// command line a frontend needs: listing the launchers, installing one and applying its
+// post-setup tweaks. Like the web interface, each operation runs the installer binary as a
+// child process that accepts every default; its --progress-events stream is sent out as
+// Progress signals, followed by Finished once the child exits. One operation runs at a time.
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use zbus::object_server::SignalEmitter;
+use zbus::{fdo, interface, Connection};
+
+use game_launcher_installer::output::parse_progress_event;
+use game_launcher_installer::{AppPaths, Config, LauncherRegistry};
+
+pub const BUS_NAME: &str = "io.github.SulfurNitride.GameLauncherInstaller";
+const OBJECT_PATH: &str = "/io/github/SulfurNitride/GameLauncherInstaller";
+
+// How often a running operation's progress log is checked for new events
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+struct Service {
+    home_dir: PathBuf,
+    events_path: PathBuf,
+    // Name of the operation in progress, if any
+    running: Arc<Mutex<Option<String>>>,
+}
+
+#[interface(name = "io.github.SulfurNitride.GameLauncherInstaller1")]
+impl Service {
+    /// Every launcher as (ID, name, whether its prefix exists)
+    fn list_launchers(&self) -> Vec<(String, String, bool)> {
+        let config = Config::load(&self.home_dir);
+        let app_paths = AppPaths { games_root: config.games_root.clone(), home_dir: self.home_dir.clone() };
+        LauncherRegistry::builtin().all().iter()
+        .map(|launcher| {
+            let installed = app_paths.prefix_dir(launcher.dir_name).join("system.reg").exists();
+            (launcher.id.to_string(), launcher.name.to_string(), installed)
+        })
+        .collect()
+    }
+
+    /// Install a launcher, taking the default answer at every prompt
+    fn install(&self, #[zbus(connection)] connection: &Connection, id: &str) -> fdo::Result<()> {
+        self.start(connection, "install", id, &["--launcher", id])
+    }
+
+    /// Apply the post-setup tweaks and DLL overrides recorded for an installed launcher
+    fn post_setup(&self, #[zbus(connection)] connection: &Connection, id: &str) -> fdo::Result<()> {
+        self.start(connection, "post-setup", id, &["reapply", id])
+    }
+
+    /// Whether an operation is running. Read it on startup; Finished says when it ends.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn busy(&self) -> bool {
+        self.running.lock().map(|running| running.is_some()).unwrap_or(false)
+    }
+
+    /// A progress event of the running operation; `percent` is -1 when it is not known
+    #[zbus(signal)]
+    async fn progress(emitter: &SignalEmitter<'_>, stage: &str, percent: i32, message: &str) -> zbus::Result<()>;
+
+    /// The operation started with `id` has ended
+    #[zbus(signal)]
+    async fn finished(emitter: &SignalEmitter<'_>, operation: &str, id: &str, success: bool) -> zbus::Result<()>;
+}
+
+impl Service {
+    fn start(&self, connection: &Connection, operation: &str, id: &str, args: &[&str]) -> fdo::Result<()> {
+        let mut running = self.running.lock().map_err(|_| fdo::Error::Failed("Service state is poisoned.".to_string()))?;
+        check_operation(id, running.as_deref())?;
+
+        let child = spawn_operation(&self.events_path, args).map_err(fdo::Error::Failed)?;
+        println!("Started {} of {}.", operation, id);
+        *running = Some(format!("{} {}", operation, id));
+
+        let watch = Watch {
+            connection: connection.clone(),
+            events_path: self.events_path.clone(),
+            running: Arc::clone(&self.running),
+            operation: operation.to_string(),
+            id: id.to_string(),
+        };
+        thread::spawn(move || watch.run(child));
+        Ok(())
+    }
+}
+
+// Forwards the progress of one operation until its child process exits
+struct Watch {
+    connection: Connection,
+    events_path: PathBuf,
+    running: Arc<Mutex<Option<String>>>,
+    operation: String,
+    id: String,
+}
+
+impl Watch {
+    fn run(self, mut child: Child) {
+        let emitter = match SignalEmitter::new(&self.connection, OBJECT_PATH) {
+            Ok(emitter) => emitter,
+            Err(e) => {
+                println!("Failed to send signals: {}", e);
+                return;
+            },
+        };
+        let mut offset = 0;
+        let status = loop {
+            let exited = child.try_wait();
+            // Events written before the exit are still sent
+            for line in read_new_lines(&self.events_path, &mut offset) {
+                if let Some((stage, percent, message)) = parse_progress_event(&line) {
+                    let percent = percent.map(i32::from).unwrap_or(-1);
+                    let _ = zbus::block_on(Service::progress(&emitter, &stage, percent, &message));
+                }
+            }
+            match exited {
+                Ok(Some(status)) => break status.success(),
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(_) => break false,
+            }
+        };
+
+        if let Ok(mut running) = self.running.lock() {
+            *running = None;
+        }
+        println!("Finished {} of {}: {}.", self.operation, self.id, if status { "succeeded" } else { "failed" });
+        let _ = zbus::block_on(Service::finished(&emitter, &self.operation, &self.id, status));
+    }
+}
+
+// Serve the D-Bus API on the session bus until the process is stopped
+pub fn serve(home_dir: &Path) -> Result<(), String> {
+    let events_dir = home_dir.join(".cache/game-launcher-installer");
+    fs::create_dir_all(&events_dir)
+    .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let service = Service {
+        home_dir: home_dir.to_path_buf(),
+        events_path: events_dir.join("service-progress.jsonl"),
+        running: Arc::new(Mutex::new(None)),
+    };
+    let _connection = zbus::blocking::connection::Builder::session()
+    .and_then(|builder| builder.name(BUS_NAME))
+    .and_then(|builder| builder.serve_at(OBJECT_PATH, service))
+    .and_then(|builder| builder.build())
+    .map_err(|e| format!("Failed to register {} on the session bus: {}", BUS_NAME, e))?;
+
+    println!("D-Bus service running as {} at {} (Ctrl+C to stop)", BUS_NAME, OBJECT_PATH);
+    // The connection answers calls on its own thread
+    loop {
+        thread::park();
+    }
+}
+
+// Whether an operation on launcher `id` can start while `running` is in progress
+fn check_operation(id: &str, running: Option<&str>) -> fdo::Result<()> {
+    if LauncherRegistry::builtin().get(id).is_none() {
+        return Err(fdo::Error::InvalidArgs(format!("Unknown launcher: {}", id)));
+    }
+    match running {
+        Some(current) => Err(fdo::Error::Failed(format!("Another operation is running: {}", current))),
+        None => Ok(()),
+    }
+}
+
+// Start an operation in a child installer process with an empty progress log
+fn spawn_operation(events_path: &Path, args: &[&str]) -> Result<Child, String> {
+    fs::write(events_path, "")
+    .map_err(|e| format!("Failed to reset progress log: {}", e))?;
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate installer binary: {}", e))?;
+    // With no stdin every prompt reads an empty (default) answer
+    Command::new(exe)
+    .arg("--progress-events")
+    .arg(events_path)
+    .args(args)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to start installer: {}", e))
+}
+
+// Complete lines added to the file since `offset`, which is moved past them. A line still
+// being written is left for the next call.
+fn read_new_lines(path: &Path, offset: &mut u64) -> Vec<String> {
+    let mut added = Vec::new();
+    if let Ok(mut file) = fs::File::open(path) {
+        if file.seek(SeekFrom::Start(*offset)).is_ok() {
+            let _ = file.read_to_end(&mut added);
+        }
+    }
+    let complete = match added.iter().rposition(|&byte| byte == b'\n') {
+        Some(end) => end + 1,
+        None => return Vec::new(),
+    };
+    *offset += complete as u64;
+    String::from_utf8_lossy(&added[..complete]).lines().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_only_complete_lines() {
+        let path = std::env::temp_dir().join(format!("gli-service-events-{}", std::process::id()));
+        fs::write(&path, "{\"stage\":\"download\",\"percent\":10,\"message\":\"a\"}\n{\"stage\":\"dow").unwrap();
+        let mut offset = 0;
+        assert_eq!(read_new_lines(&path, &mut offset), vec!["{\"stage\":\"download\",\"percent\":10,\"message\":\"a\"}"]);
+        assert_eq!(read_new_lines(&path, &mut offset), Vec::<String>::new());
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, b"nload\",\"percent\":20,\"message\":\"b\"}\n").unwrap();
+        let lines = read_new_lines(&path, &mut offset);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(parse_progress_event(&lines[0]), Some(("download".to_string(), Some(20), "b".to_string())));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read_new_lines(&path, &mut offset), Vec::<String>::new());
+    }
+
+    #[test]
+    fn refuses_unknown_launchers_and_overlapping_operations() {
+        assert_eq!(check_operation("nope", None), Err(fdo::Error::InvalidArgs("Unknown launcher: nope".to_string())));
+        assert_eq!(check_operation("battlenet", Some("install hoyoplay")),
+                   Err(fdo::Error::Failed("Another operation is running: install hoyoplay".to_string())));
+        assert_eq!(check_operation("battlenet", None), Ok(()));
+    }
+
+    #[test]
+    fn lists_launchers_and_busy_state() {
+        let service = Service {
+            home_dir: std::env::temp_dir().join(format!("gli-service-home-{}", std::process::id())),
+            events_path: PathBuf::from("/nonexistent/events.jsonl"),
+            running: Arc::new(Mutex::new(None)),
+        };
+        let launchers = service.list_launchers();
+        assert_eq!(launchers.len(), LauncherRegistry::builtin().all().len());
+        assert!(launchers.contains(&("battlenet".to_string(), "Battle.net".to_string(), false)));
+        assert!(!service.busy());
+        *service.running.lock().unwrap() = Some("install battlenet".to_string());
+        assert!(service.busy());
+    }
+}
@@ -0,0 +1,221 @@
+//! Reflinking identical files across managed prefixes, so fonts, DXVK DLLs and redistributable
+//! payloads installed into every launcher's prefix are stored only once. A reflink shares the
+//! data until one copy is written to, so a launcher or wine patching a DLL in place changes
+//! only its own prefix; filesystems without copy-on-write (ext4) refuse it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::policy::sha256_file;
+
+// Files that rarely change once written. Registry hives, configs and saves change all the
+// time and are left alone.
+const LINKABLE_EXTENSIONS: &[&str] = &["dll", "exe", "sys", "drv", "ttf", "ttc", "otf", "fon", "cab", "msi", "msu"];
+
+// Smaller files save too little to be worth hashing
+const MIN_SIZE: u64 = 16 * 1024;
+
+/// Files with the same content on one filesystem that are not hardlinked to each other.
+#[derive(Clone, Debug)]
+pub struct DuplicateSet {
+    /// The copy the others are cloned from
+    pub original: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+    pub size: u64,
+    /// SHA-256 of the content the files had when they were found
+    pub hash: String,
+}
+
+impl DuplicateSet {
+    /// Bytes freed by cloning the duplicates from the original.
+    pub fn savings(&self) -> u64 {
+        self.size * self.duplicates.len() as u64
+    }
+}
+
+/// Find identical files in `prefixes`. Only files on the same filesystem can share their
+/// data, and files that are hardlinked to each other count once.
+pub fn find_duplicates(prefixes: &[PathBuf]) -> Vec<DuplicateSet> {
+    // (device, size) -> one path per inode
+    let mut by_size: HashMap<(u64, u64), HashMap<u64, PathBuf>> = HashMap::new();
+    for prefix in prefixes {
+        collect_files(&prefix.join("drive_c"), &mut by_size);
+    }
+
+    let mut sets = Vec::new();
+    for ((_, size), inodes) in by_size {
+        if inodes.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in inodes.into_values() {
+            if let Ok(hash) = sha256_file(&path) {
+                by_hash.entry(hash).or_default().push(path);
+            }
+        }
+        for (hash, mut paths) in by_hash {
+            if paths.len() < 2 {
+                continue;
+            }
+            paths.sort();
+            let original = paths.remove(0);
+            sets.push(DuplicateSet { original, duplicates: paths, size, hash });
+        }
+    }
+    sets.sort_by(|a, b| b.savings().cmp(&a.savings()).then_with(|| a.original.cmp(&b.original)));
+    sets
+}
+
+/// Replace the duplicates with reflinks of their original, returning how many files were
+/// replaced and the bytes freed. Files are hashed again first, and any that changed since
+/// they were found are skipped. Each file is swapped in one rename, so an interrupted run
+/// leaves every file readable.
+pub fn link_duplicates(sets: &[DuplicateSet]) -> Result<(usize, u64), String> {
+    let mut linked = 0;
+    let mut saved = 0;
+    for set in sets {
+        if sha256_file(&set.original).ok().as_ref() != Some(&set.hash) {
+            continue;
+        }
+        for duplicate in &set.duplicates {
+            if sha256_file(duplicate).ok().as_ref() != Some(&set.hash) {
+                continue;
+            }
+            let name = duplicate.file_name().unwrap_or_default().to_string_lossy();
+            let temp = duplicate.with_file_name(format!(".{}.dedupe", name));
+            let _ = fs::remove_file(&temp);
+            if let Err(e) = reflink(&set.original, &temp) {
+                let _ = fs::remove_file(&temp);
+                return Err(format!("Failed to clone {} to {}: {}", set.original.display(), duplicate.display(), e));
+            }
+            if let Err(e) = fs::rename(&temp, duplicate) {
+                let _ = fs::remove_file(&temp);
+                return Err(format!("Failed to replace {}: {}", duplicate.display(), e));
+            }
+            linked += 1;
+            saved += set.size;
+        }
+    }
+    Ok((linked, saved))
+}
+
+// Copy `source` to `target` as a reflink, which fails on filesystems without copy-on-write
+// rather than making a full copy
+fn reflink(source: &Path, target: &Path) -> Result<(), String> {
+    let output = Command::new("cp")
+    .args(["--reflink=always", "--preserve=mode,timestamps"])
+    .arg(source)
+    .arg(target)
+    .output()
+    .map_err(|e| format!("Failed to execute cp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("{} The filesystem has to support reflinks, like btrfs and XFS do.",
+                           String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// Record the files worth sharing under `dir`. Symlinks are not followed, since the prefix's
+// dosdevices and `C:\Linux Root` link out of it.
+fn collect_files(dir: &Path, by_size: &mut HashMap<(u64, u64), HashMap<u64, PathBuf>>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            collect_files(&path, by_size);
+            continue;
+        }
+        let linkable = path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| LINKABLE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+        if metadata.is_file() && linkable && metadata.len() >= MIN_SIZE {
+            by_size.entry((metadata.dev(), metadata.len())).or_default().entry(metadata.ino()).or_insert(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_prefixes(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gli-dedupe-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn write(path: &Path, content: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn finds_identical_files_once_per_inode() {
+        let dir = temp_prefixes("find");
+        let dll = vec![7u8; MIN_SIZE as usize];
+        write(&dir.join("a/drive_c/windows/system32/d3d11.dll"), &dll);
+        write(&dir.join("b/drive_c/windows/system32/d3d11.dll"), &dll);
+        fs::hard_link(dir.join("b/drive_c/windows/system32/d3d11.dll"), dir.join("b/drive_c/d3d11.dll")).unwrap();
+        write(&dir.join("b/drive_c/users/steamuser/save.dat"), &dll);
+        write(&dir.join("b/drive_c/small.dll"), b"small");
+        write(&dir.join("a/drive_c/small.dll"), b"small");
+
+        let sets = find_duplicates(&[dir.join("a"), dir.join("b")]);
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].original, dir.join("a/drive_c/windows/system32/d3d11.dll"));
+        assert_eq!(sets[0].duplicates.len(), 1);
+        assert_eq!(sets[0].savings(), MIN_SIZE);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn skips_files_changed_since_they_were_found() {
+        let dir = temp_prefixes("changed");
+        let dll = vec![7u8; MIN_SIZE as usize];
+        let original = dir.join("a/drive_c/font.ttf");
+        let duplicate = dir.join("b/drive_c/font.ttf");
+        write(&original, &dll);
+        write(&duplicate, &dll);
+        let sets = find_duplicates(&[dir.join("a"), dir.join("b")]);
+
+        write(&duplicate, &vec![8u8; MIN_SIZE as usize]);
+        assert_eq!(link_duplicates(&sets), Ok((0, 0)));
+        assert_eq!(fs::read(&duplicate).unwrap(), vec![8u8; MIN_SIZE as usize]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn clones_or_leaves_duplicates_untouched() {
+        let dir = temp_prefixes("clone");
+        let dll = vec![7u8; MIN_SIZE as usize];
+        let original = dir.join("a/drive_c/vcredist.msi");
+        let duplicate = dir.join("b/drive_c/vcredist.msi");
+        write(&original, &dll);
+        write(&duplicate, &dll);
+        let sets = find_duplicates(&[dir.join("a"), dir.join("b")]);
+
+        // Without reflinks (ext4, tmpfs) nothing is changed, never hardlinked
+        match link_duplicates(&sets) {
+            Ok(result) => assert_eq!(result, (1, MIN_SIZE)),
+            Err(e) => assert!(e.contains("reflinks"), "{}", e),
+        }
+        assert_eq!(fs::read(&duplicate).unwrap(), dll);
+        assert_eq!(fs::metadata(&duplicate).unwrap().nlink(), 1);
+        assert!(!dir.join("b/drive_c/.vcredist.msi.dedupe").exists());
+
+        // Writing one copy leaves the other as it was
+        fs::write(&duplicate, b"patched").unwrap();
+        assert_eq!(fs::read(&original).unwrap(), dll);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
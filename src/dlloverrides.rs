@@ -0,0 +1,106 @@
+//! Per-launcher wine DLL overrides, kept in a file next to the prefix and mirrored into its registry.
+
+use std::fs;
+use std::path::Path;
+
+use crate::fsutil::write_atomic;
+
+// Installers run with the .NET and browser components disabled unless the user changes it
+const DEFAULT_OVERRIDES: &[(&str, &str)] = &[("mscoree", ""), ("mshtml", "")];
+
+/// DLL name to load-order mode, in registry form: `native`, `builtin`,
+/// `native,builtin`, `builtin,native` or empty for disabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DllOverrides {
+    entries: Vec<(String, String)>,
+}
+
+impl Default for DllOverrides {
+    fn default() -> DllOverrides {
+        DllOverrides {
+            entries: DEFAULT_OVERRIDES.iter()
+            .map(|(dll, mode)| (dll.to_string(), mode.to_string()))
+            .collect(),
+        }
+    }
+}
+
+impl DllOverrides {
+    /// Overrides with the given entries instead of the defaults, in registry form.
+    pub fn from_entries(entries: &[(&str, &str)]) -> DllOverrides {
+        DllOverrides {
+            entries: entries.iter().map(|(dll, mode)| (dll.to_string(), mode.to_string())).collect(),
+        }
+    }
+
+    /// Load overrides from `path`, one `dll = mode` per line. A missing file gives the defaults.
+    pub fn load(path: &Path) -> Result<DllOverrides, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(DllOverrides::default()),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+
+        let entries = contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(dll, mode)| (dll.trim().to_string(), mode.trim().to_string()))
+        .collect();
+        Ok(DllOverrides { entries })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let contents: String = self.entries.iter()
+        .map(|(dll, mode)| format!("{} = {}", dll, mode).trim_end().to_string() + "\n")
+        .collect();
+        write_atomic(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    /// Add or replace an override. `mode` accepts the registry words, `n`/`b` shorthands or `disabled`.
+    pub fn set(&mut self, dll: &str, mode: &str) -> Result<(), String> {
+        let mode = normalize_mode(mode)?;
+        let dll = dll.trim().to_lowercase();
+        match self.entries.iter_mut().find(|(name, _)| *name == dll) {
+            Some(entry) => entry.1 = mode.to_string(),
+            None => self.entries.push((dll, mode.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Remove an override, returning whether it existed.
+    pub fn remove(&mut self, dll: &str) -> bool {
+        let dll = dll.trim().to_lowercase();
+        let before = self.entries.len();
+        self.entries.retain(|(name, _)| *name != dll);
+        self.entries.len() != before
+    }
+
+    /// The overrides as a `WINEDLLOVERRIDES` value, e.g. `mscoree=;mshtml=;d3d11=n,b`.
+    pub fn to_env(&self) -> String {
+        self.entries.iter()
+        .map(|(dll, mode)| format!("{}={}", dll, mode.replace("native", "n").replace("builtin", "b")))
+        .collect::<Vec<_>>()
+        .join(";")
+    }
+}
+
+// Map user input to the registry spelling of a load-order mode
+fn normalize_mode(mode: &str) -> Result<&'static str, String> {
+    match mode.trim().to_lowercase().as_str() {
+        "" | "disabled" | "d" => Ok(""),
+        "n" | "native" => Ok("native"),
+        "b" | "builtin" => Ok("builtin"),
+        "n,b" | "native,builtin" => Ok("native,builtin"),
+        "b,n" | "builtin,native" => Ok("builtin,native"),
+        other => Err(format!("Unknown DLL override mode: {} (use native, builtin, native,builtin, builtin,native or disabled)", other)),
+    }
+}
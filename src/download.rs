@@ -0,0 +1,616 @@
+//! Downloading installers.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+
+use crate::fsutil::{format_size, write_atomic};
+use crate::journal;
+use crate::output::*;
+
+/// Most connections a segmented download may use.
+pub const MAX_DOWNLOAD_CONNECTIONS: u32 = 16;
+
+// Segments are at least this big; smaller files are downloaded over fewer connections
+const MIN_SEGMENT_SIZE: u64 = 8 * 1024 * 1024;
+// A segment whose connection drops is resumed where it stopped this many times
+const SEGMENT_ATTEMPTS: u32 = 3;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Ask the server for a file's size with a HEAD request, following redirects.
+/// Returns None if the server doesn't send a Content-Length.
+pub fn remote_file_size(url: &str) -> Option<u64> {
+    let response = Client::new().head(url).send().ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.headers()
+    .get(reqwest::header::CONTENT_LENGTH)?
+    .to_str().ok()?
+    .parse().ok()
+}
+
+/// Identify the file currently served at `url` by its ETag, Last-Modified date or size,
+/// so a change can be detected without downloading it.
+pub fn remote_file_identity(url: &str) -> Option<String> {
+    let response = Client::new().head(url).send().ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let headers = response.headers();
+    [reqwest::header::ETAG, reqwest::header::LAST_MODIFIED, reqwest::header::CONTENT_LENGTH]
+    .iter()
+    .find_map(|name| headers.get(name).and_then(|value| value.to_str().ok()))
+    .map(|value| format!("{} {}", response.url(), value))
+}
+
+/// Download a file. A copy already at `destination` is reused if the server says it has not
+/// changed since it was downloaded, and downloaded again if it has or the copy is incomplete.
+pub fn download_file(url: &str, destination: &Path) -> Result<(), String> {
+    if reuse_cached(url, destination) {
+        return Ok(());
+    }
+    download_fresh(url, destination)
+}
+
+// Download `url` to `destination` over one connection, replacing whatever is there
+fn download_fresh(url: &str, destination: &Path) -> Result<(), String> {
+    // Create parent directories if they don't exist
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    println!("{}Downloading file from {}...{}", COLOR_BLUE, url, COLOR_RESET);
+    emit_progress("download", None, &format!("Downloading {}", url));
+    journal::record("download", &[("url", url), ("path", &destination.to_string_lossy())]);
+
+    // Written under a temporary name, so an interrupted download is never taken for a finished one
+    let part = part_path(destination);
+    match download_whole(url, &part) {
+        Ok(headers) => finish_download(&part, destination, headers),
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+            Err(e)
+        },
+    }
+}
+
+// Download `url` to `path` with curl, wget or reqwest, returning what the server said about
+// the file it sent
+fn download_whole(url: &str, path: &Path) -> Result<CachedHeaders, String> {
+    // Try to use wget or curl if available
+    if Command::new("which").arg("curl").status().is_ok() {
+        // --fail, so an error page is not saved as the file. The headers go to stdout; the
+        // progress meter stays on stderr.
+        let output = Command::new("curl")
+        .arg("-L")
+        .arg("--fail")
+        .arg("--dump-header")
+        .arg("-")
+        .arg("-o")
+        .arg(path)
+        .arg(url)
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|child| child.wait_with_output())
+        .map_err(|e| format!("Failed to execute curl: {}", e))?;
+
+        if output.status.success() {
+            Ok(CachedHeaders::parse(&String::from_utf8_lossy(&output.stdout)))
+        } else {
+            Err(format!("curl failed with exit code: {}", output.status))
+        }
+    } else if Command::new("which").arg("wget").status().is_ok() {
+        // --server-response prints the headers among the progress on stderr, which is passed on
+        let mut child = Command::new("wget")
+        .arg("--server-response")
+        .arg("-O")
+        .arg(path)
+        .arg(url)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute wget: {}", e))?;
+        let mut messages = Vec::new();
+        if let Some(mut stderr) = child.stderr.take() {
+            let mut buffer = [0; 4096];
+            while let Ok(read @ 1..) = stderr.read(&mut buffer) {
+                let _ = io::stderr().write_all(&buffer[..read]);
+                messages.extend_from_slice(&buffer[..read]);
+            }
+        }
+        let status = child.wait()
+        .map_err(|e| format!("Failed to execute wget: {}", e))?;
+
+        if status.success() {
+            Ok(CachedHeaders::parse(&String::from_utf8_lossy(&messages)))
+        } else {
+            Err(format!("wget failed with exit code: {}", status))
+        }
+    } else {
+        // Fallback to using reqwest
+        let client = Client::new();
+        let response = client.get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download file: {}", e))?;
+
+        let headers = CachedHeaders::from_response(&response);
+
+        let mut file = fs::File::create(path)
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+        let content = response.bytes()
+        .map_err(|e| format!("Failed to read response bytes: {}", e))?;
+
+        file.write_all(&content)
+        .map_err(|e| format!("Failed to write to file: {}", e))?;
+        Ok(headers)
+    }
+}
+
+// Move a finished download into place and remember what the server said about it when it sent
+// it, so a version published since isn't recorded for the file on disk
+fn finish_download(part: &Path, destination: &Path, mut headers: CachedHeaders) -> Result<(), String> {
+    fs::rename(part, destination).map_err(|e| format!("Failed to move {} to {}: {}", part.display(), destination.display(), e))?;
+    // The size on disk, not the one reported, so a copy truncated later is noticed
+    headers.size = fs::metadata(destination).ok().map(|metadata| metadata.len());
+    if let Err(e) = headers.save(&headers_path(destination)) {
+        println!("{}Warning: Could not record the download's headers: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+    println!("{}Download complete!{}", COLOR_GREEN, COLOR_RESET);
+    Ok(())
+}
+
+// What the server said about a downloaded file, kept next to it as `.<name>.headers` to make
+// the next check a conditional request
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct CachedHeaders {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    size: Option<u64>,
+}
+
+impl CachedHeaders {
+    fn load(path: &Path) -> CachedHeaders {
+        let mut headers = CachedHeaders::default();
+        for line in fs::read_to_string(path).unwrap_or_default().lines() {
+            if let Some((key, value)) = line.split_once(" = ") {
+                match key {
+                    "etag" => headers.etag = Some(value.to_string()),
+                    "last_modified" => headers.last_modified = Some(value.to_string()),
+                    "size" => headers.size = value.parse().ok(),
+                    _ => {},
+                }
+            }
+        }
+        headers
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut content = String::new();
+        if let Some(etag) = &self.etag {
+            content.push_str(&format!("etag = {}\n", etag));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            content.push_str(&format!("last_modified = {}\n", last_modified));
+        }
+        if let Some(size) = self.size {
+            content.push_str(&format!("size = {}\n", size));
+        }
+        write_atomic(path, content)
+    }
+
+    // The headers of the last response in `text`, as `curl --dump-header` writes them or
+    // `wget --server-response` prints them: after any redirects, the one the file came from
+    fn parse(text: &str) -> CachedHeaders {
+        let mut headers = CachedHeaders::default();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with("HTTP/") {
+                headers = CachedHeaders::default();
+            }
+            let Some((name, value)) = line.split_once(':') else { continue };
+            let value = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            match name.to_ascii_lowercase().as_str() {
+                "etag" => headers.etag = value,
+                "last-modified" => headers.last_modified = value,
+                "content-length" => headers.size = value.and_then(|size| size.parse().ok()),
+                _ => {},
+            }
+        }
+        headers
+    }
+
+    // HEAD `url`, conditional on `known`'s validators; the status and the headers the server sent
+    fn fetch(client: &Client, url: &str, known: &CachedHeaders) -> Option<(StatusCode, CachedHeaders)> {
+        let mut request = client.head(url);
+        if let Some(etag) = &known.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &known.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request.send().ok()?;
+        Some((response.status(), CachedHeaders::from_response(&response)))
+    }
+
+    fn from_response(response: &reqwest::blocking::Response) -> CachedHeaders {
+        let header = |name| response.headers().get(name).and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok()).map(str::to_string);
+        CachedHeaders {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            size: header(reqwest::header::CONTENT_LENGTH).and_then(|size| size.parse().ok()),
+        }
+    }
+
+    // The validator for an If-Range header. It needs a strong ETag, so a weak one falls back to
+    // the modification date.
+    fn if_range(&self) -> Option<&str> {
+        self.etag.as_deref().filter(|etag| !etag.starts_with("W/"))
+        .or(self.last_modified.as_deref())
+    }
+}
+
+fn headers_path(destination: &Path) -> PathBuf {
+    let name = destination.file_name().unwrap_or_default().to_string_lossy();
+    destination.with_file_name(format!(".{}.headers", name))
+}
+
+// Whether the copy at `destination` can be used instead of downloading `url` again. A copy that
+// can't be checked, e.g. when offline, is used as it is.
+fn reuse_cached(url: &str, destination: &Path) -> bool {
+    let size = match fs::metadata(destination) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return false,
+    };
+    let known = CachedHeaders::load(&headers_path(destination));
+    let changed = if known.size.is_some_and(|known_size| known_size != size) {
+        Some("the copy on disk is incomplete")
+    } else {
+        match CachedHeaders::fetch(&Client::new(), url, &known) {
+            Some((StatusCode::NOT_MODIFIED, _)) => None,
+            Some((status, remote)) if status.is_success() => {
+                let validated = (remote.etag.is_some() && remote.etag == known.etag)
+                    || (remote.etag.is_none() && remote.last_modified.is_some() && remote.last_modified == known.last_modified);
+                if validated {
+                    // The server ignored the conditional request but sent the same version
+                    None
+                } else if known.etag.is_some() || known.last_modified.is_some() {
+                    Some("the server has a newer version")
+                } else if remote.size.is_some_and(|remote_size| remote_size != size) {
+                    // Downloaded before versions were recorded; the size is all there is to compare
+                    Some("its size differs from the server's")
+                } else if remote.size.is_none() && remote.etag.is_none() && remote.last_modified.is_none() {
+                    println!("{}The server does not say which version it has; using {} as it is.{}", COLOR_YELLOW, destination.display(), COLOR_RESET);
+                    return true;
+                } else {
+                    let _ = CachedHeaders { size: Some(size), ..remote }.save(&headers_path(destination));
+                    None
+                }
+            },
+            _ => {
+                println!("{}Could not check whether {} changed on the server; using it as it is.{}", COLOR_YELLOW, destination.display(), COLOR_RESET);
+                return true;
+            },
+        }
+    };
+
+    match changed {
+        None => {
+            println!("{}{} is up to date. Skipping download.{}", COLOR_YELLOW, destination.display(), COLOR_RESET);
+            true
+        },
+        Some(reason) => {
+            println!("{}Downloading {} again: {}.{}", COLOR_BLUE, destination.display(), reason, COLOR_RESET);
+            false
+        },
+    }
+}
+
+/// Download a file over up to `connections` connections at once, each fetching its own byte
+/// range straight into its place in the file. A copy already at `destination` is checked as by
+/// `download_file`. One connection is used for files too small to split and servers that don't
+/// answer range requests.
+pub fn download_file_segmented(url: &str, destination: &Path, connections: u32) -> Result<(), String> {
+    if connections <= 1 {
+        return download_file(url, destination);
+    }
+    if reuse_cached(url, destination) {
+        return Ok(());
+    }
+    let client = Client::builder()
+    .timeout(None)
+    .build()
+    .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let (final_url, size, headers) = match range_support(&client, url) {
+        Some(found) => found,
+        None => {
+            println!("{}The server does not support ranged downloads; using one connection.{}", COLOR_YELLOW, COLOR_RESET);
+            return download_fresh(url, destination);
+        },
+    };
+    let segments = segment_count(size, connections);
+    if segments <= 1 {
+        return download_fresh(url, destination);
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    println!("{}Downloading file from {} over {} connections...{}", COLOR_BLUE, url, segments, COLOR_RESET);
+    emit_progress("download", None, &format!("Downloading {}", url));
+    journal::record("download", &[("url", url), ("path", &destination.to_string_lossy()), ("connections", &segments.to_string())]);
+
+    // Written under a temporary name, so an interrupted download is never taken for a finished one
+    let part = part_path(destination);
+    let result = File::create(&part)
+    .and_then(|file| file.set_len(size).map(|_| file))
+    .map_err(|e| format!("Failed to create {}: {}", part.display(), e))
+    .and_then(|file| fetch_segments(&client, &final_url, &file, size, segments, headers.if_range()));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&part);
+        return Err(e);
+    }
+    finish_download(&part, destination, headers)
+}
+
+// The URL ranges are fetched from once redirects are followed, the file's size and the
+// version the server sent, if it answers a range request for the first byte
+fn range_support(client: &Client, url: &str) -> Option<(String, u64, CachedHeaders)> {
+    let response = client.get(url).header(reqwest::header::RANGE, "bytes=0-0").send().ok()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    // `Content-Range: bytes 0-0/<size>`
+    let size = response.headers()
+    .get(reqwest::header::CONTENT_RANGE)?
+    .to_str().ok()?
+    .rsplit_once('/')?.1
+    .parse().ok()?;
+    Some((response.url().to_string(), size, CachedHeaders::from_response(&response)))
+}
+
+// How many connections a file of `size` bytes is fetched over, at most `connections`
+fn segment_count(size: u64, connections: u32) -> u64 {
+    u64::from(connections.min(MAX_DOWNLOAD_CONNECTIONS)).min(size / MIN_SEGMENT_SIZE)
+}
+
+// The byte ranges `start..end` each of `segments` connections fetches, covering the file
+fn segment_ranges(size: u64, segments: u64) -> Vec<(u64, u64)> {
+    (0..segments).map(|segment| (size * segment / segments, size * (segment + 1) / segments)).collect()
+}
+
+fn part_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    destination.with_file_name(name)
+}
+
+// Fetch `segments` equal ranges of the file in parallel, showing the combined progress.
+// `if_range` is the version the ranges have to come from.
+fn fetch_segments(client: &Client, url: &str, file: &File, size: u64, segments: u64, if_range: Option<&str>) -> Result<(), String> {
+    let downloaded = AtomicU64::new(0);
+    thread::scope(|scope| {
+        let handles: Vec<_> = segment_ranges(size, segments).into_iter().map(|(start, end)| {
+            let downloaded = &downloaded;
+            scope.spawn(move || fetch_range(client, url, file, start, end, if_range, downloaded))
+        })
+        .collect();
+
+        let mut reported = None;
+        while !handles.iter().all(|handle| handle.is_finished()) {
+            let done = downloaded.load(Ordering::Relaxed);
+            let percent = (done * 100 / size) as u8;
+            print!("\r  {} of {} ({}%)", format_size(done), format_size(size), percent);
+            io::stdout().flush().unwrap();
+            if reported != Some(percent / 10) {
+                reported = Some(percent / 10);
+                emit_progress("download", Some(percent), &format!("Downloaded {} of {}", format_size(done), format_size(size)));
+            }
+            thread::sleep(PROGRESS_INTERVAL);
+        }
+        println!("\r  {} of {}      ", format_size(downloaded.load(Ordering::Relaxed)), format_size(size));
+
+        handles.into_iter()
+        .try_for_each(|handle| handle.join().unwrap_or_else(|_| Err("A download connection crashed.".to_string())))
+    })
+}
+
+// Fetch bytes `start..end` into the same place in `file`, resuming where a dropped connection
+// stopped. With `if_range` the server sends the whole file instead if it has another version
+// by now, which fails the download rather than mixing bytes of two versions.
+fn fetch_range(client: &Client, url: &str, file: &File, start: u64, end: u64, if_range: Option<&str>, downloaded: &AtomicU64) -> Result<(), String> {
+    let mut offset = start;
+    let mut error = String::new();
+    for _ in 0..SEGMENT_ATTEMPTS {
+        let mut request = client.get(url)
+        .header(reqwest::header::RANGE, format!("bytes={}-{}", offset, end - 1));
+        if let Some(if_range) = if_range {
+            request = request.header(reqwest::header::IF_RANGE, if_range);
+        }
+        let mut response = match request.send() {
+            Ok(response) if response.status() == StatusCode::PARTIAL_CONTENT => response,
+            Ok(response) if response.status() == StatusCode::OK => {
+                return Err("The server sent the whole file instead of a part of it; it changed since the download started.".to_string());
+            },
+            Ok(response) => return Err(format!("The server answered a range request with {}.", response.status())),
+            Err(e) => {
+                error = e.to_string();
+                continue;
+            },
+        };
+
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match response.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => {
+                    let read = read.min((end - offset) as usize);
+                    file.write_all_at(&buffer[..read], offset).map_err(|e| format!("Failed to write to file: {}", e))?;
+                    offset += read as u64;
+                    downloaded.fetch_add(read as u64, Ordering::Relaxed);
+                },
+                Err(e) => {
+                    error = e.to_string();
+                    break;
+                },
+            }
+        }
+        if offset >= end {
+            return Ok(());
+        }
+    }
+    Err(format!("Failed to download bytes {}-{}: {}", offset, end - 1,
+                if error.is_empty() { "the connection kept closing early" } else { error.as_str() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // Serve `body` with ETag `etag` on `connections` connections, honouring Range and If-Range.
+    // The first response is cut off after `cut` bytes. Returns the URL and, once the
+    // connections are served, the Range headers that came in.
+    fn serve(body: Vec<u8>, etag: &'static str, connections: usize, cut: Option<usize>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}/installer.exe", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut ranges = Vec::new();
+            for (n, stream) in listener.incoming().take(connections).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut byte = [0; 1];
+                while !request.ends_with(b"\r\n\r\n") {
+                    stream.read_exact(&mut byte).unwrap();
+                    request.push(byte[0]);
+                }
+                let request = String::from_utf8(request).unwrap();
+                let header = |name: &str| request.lines()
+                .find_map(|line| line.split_once(": ").filter(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.to_string()));
+                let range = header("range").unwrap_or_default();
+                ranges.push(range.clone());
+
+                let requested = range.strip_prefix("bytes=").and_then(|range| range.split_once('-'))
+                .map(|(start, end)| start.parse::<usize>().unwrap()..end.parse::<usize>().unwrap() + 1);
+                let (head, part) = match requested {
+                    Some(part) if header("if-range").is_none_or(|if_range| if_range == etag) => {
+                        (format!("HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n", part.start, part.end - 1, body.len()), part)
+                    },
+                    _ => ("HTTP/1.1 200 OK\r\n".to_string(), 0..body.len()),
+                };
+                let data = &body[part.clone()];
+                let data = match cut {
+                    Some(cut) if n == 0 => &data[..cut],
+                    _ => data,
+                };
+                let _ = write!(stream, "{}ETag: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", head, etag, part.len());
+                let _ = stream.write_all(data);
+            }
+            ranges
+        });
+        (url, server)
+    }
+
+    fn client() -> Client {
+        Client::builder().no_proxy().build().unwrap()
+    }
+
+    #[test]
+    fn segments_cover_the_file() {
+        assert_eq!(segment_count(100 * 1024 * 1024, 4), 4);
+        // Segments are at least MIN_SEGMENT_SIZE, and there are never more than MAX_DOWNLOAD_CONNECTIONS
+        assert_eq!(segment_count(20 * 1024 * 1024, 8), 2);
+        assert_eq!(segment_count(MIN_SEGMENT_SIZE - 1, 8), 0);
+        assert_eq!(segment_count(u64::MAX, 64), u64::from(MAX_DOWNLOAD_CONNECTIONS));
+
+        assert_eq!(segment_ranges(10, 3), vec![(0, 3), (3, 6), (6, 10)]);
+        for (size, segments) in [(MIN_SEGMENT_SIZE * 3 + 7, 3), (1_000_003, 16)] {
+            let ranges = segment_ranges(size, segments);
+            assert_eq!(ranges.len() as u64, segments);
+            assert_eq!(ranges[0].0, 0);
+            assert_eq!(ranges.last().unwrap().1, size);
+            assert!(ranges.windows(2).all(|pair| pair[0].1 == pair[1].0));
+        }
+    }
+
+    #[test]
+    fn dropped_segment_resumes_where_it_stopped() {
+        let body: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+        let (url, server) = serve(body.clone(), "\"v1\"", 2, Some(30_000));
+        let path = std::env::temp_dir().join(format!("gli-fetch-range-{}", std::process::id()));
+        let file = File::create(&path).unwrap();
+        file.set_len(100_000).unwrap();
+        let downloaded = AtomicU64::new(0);
+
+        fetch_range(&client(), &url, &file, 10_000, 90_000, Some("\"v1\""), &downloaded).unwrap();
+        assert_eq!(server.join().unwrap(), vec!["bytes=10000-89999", "bytes=40000-89999"]);
+        assert_eq!(downloaded.load(Ordering::Relaxed), 80_000);
+        assert_eq!(fs::read(&path).unwrap()[10_000..90_000], body[10_000..90_000]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn segment_of_a_changed_file_fails() {
+        let (url, server) = serve(vec![7; 1000], "\"v2\"", 1, None);
+        let path = std::env::temp_dir().join(format!("gli-fetch-changed-{}", std::process::id()));
+        let file = File::create(&path).unwrap();
+        file.set_len(1000).unwrap();
+
+        let error = fetch_range(&client(), &url, &file, 0, 500, Some("\"v1\""), &AtomicU64::new(0)).unwrap_err();
+        assert!(error.contains("changed since the download started"), "{}", error);
+        server.join().unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn headers_come_from_the_response_after_redirects() {
+        let curl = "HTTP/1.1 302 Found\r\nLocation: https://cdn.example.com/setup.exe\r\nETag: \"redirect\"\r\n\r\n\
+                    HTTP/2 200\r\netag: \"v7\"\r\nlast-modified: Tue, 01 Sep 2026 10:00:00 GMT\r\ncontent-length: 1234\r\n\r\n";
+        assert_eq!(CachedHeaders::parse(curl), CachedHeaders {
+            etag: Some("\"v7\"".to_string()),
+            last_modified: Some("Tue, 01 Sep 2026 10:00:00 GMT".to_string()),
+            size: Some(1234),
+        });
+
+        let wget = "--2026-10-16 12:00:00--  https://example.com/setup.exe\n\
+                    Resolving example.com... 192.0.2.1\n\
+                    HTTP request sent, awaiting response... \n  HTTP/1.1 301 Moved Permanently\n  Last-Modified: Mon, 01 Jan 2024 00:00:00 GMT\n\
+                    Location: https://cdn.example.com/setup.exe [following]\n\
+                    HTTP request sent, awaiting response... \n  HTTP/1.1 200 OK\n  ETag: W/\"v8\"\n  Content-Length: 99\n\
+                    Length: 99 [application/octet-stream]\n";
+        assert_eq!(CachedHeaders::parse(wget), CachedHeaders { etag: Some("W/\"v8\"".to_string()), last_modified: None, size: Some(99) });
+    }
+
+    #[test]
+    fn finished_download_records_the_headers_it_was_sent_with() {
+        let dir = std::env::temp_dir().join(format!("gli-finish-download-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let destination = dir.join("setup.exe");
+        fs::write(part_path(&destination), "installer").unwrap();
+
+        let sent = CachedHeaders { etag: Some("\"v7\"".to_string()), last_modified: None, size: Some(1) };
+        finish_download(&part_path(&destination), &destination, sent).unwrap();
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "installer");
+        assert_eq!(CachedHeaders::load(&headers_path(&destination)),
+                   CachedHeaders { etag: Some("\"v7\"".to_string()), last_modified: None, size: Some(9) });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn if_range_needs_a_strong_validator() {
+        let headers = CachedHeaders { etag: Some("\"abc\"".to_string()), last_modified: Some("Tue, 01 Sep 2026 10:00:00 GMT".to_string()), size: None };
+        assert_eq!(headers.if_range(), Some("\"abc\""));
+        let weak = CachedHeaders { etag: Some("W/\"abc\"".to_string()), ..headers.clone() };
+        assert_eq!(weak.if_range(), Some("Tue, 01 Sep 2026 10:00:00 GMT"));
+        assert_eq!(CachedHeaders { etag: Some("W/\"abc\"".to_string()), ..CachedHeaders::default() }.if_range(), None);
+    }
+}
@@ -0,0 +1,480 @@
+//! Filesystem helpers for relocating launcher files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use rayon::prelude::*;
+
+// Files hashed by one `sha256sum` run; starting one per file would dominate on small files
+const HASH_BATCH: usize = 64;
+
+/// Recursively copy a directory
+pub fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<()> {
+    if !dst.exists() {
+        fs::create_dir_all(dst)?;
+    }
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        if file_type.is_dir() {
+            copy_dir_recursive(&src_path, &dst_path)?;
+        } else {
+            fs::copy(&src_path, &dst_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A file or directory that could not be copied.
+#[derive(Debug)]
+pub struct CopyFailure {
+    pub src: PathBuf,
+    pub dst: PathBuf,
+    pub error: io::Error,
+}
+
+impl CopyFailure {
+    /// What went wrong, in the terms a user can act on.
+    pub fn reason(&self) -> String {
+        match self.error.raw_os_error() {
+            Some(ENAMETOOLONG) => "path too long for the destination".to_string(),
+            Some(EINVAL) | Some(EILSEQ) => "name not allowed on the destination filesystem".to_string(),
+            _ if self.error.kind() == io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+            _ => self.error.to_string(),
+        }
+    }
+}
+
+// errno values behind the failures `reason` explains
+const EINVAL: i32 = 22;
+const ENAMETOOLONG: i32 = 36;
+const EILSEQ: i32 = 84;
+
+/// Outcome of a copy that keeps going past files it can't copy.
+#[derive(Debug, Default)]
+pub struct CopyReport {
+    pub copied: usize,
+    /// Files a delta copy left alone because the destination already had them
+    pub unchanged: usize,
+    pub failures: Vec<CopyFailure>,
+}
+
+impl CopyReport {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Try the failed files again, keeping the ones that still fail.
+    pub fn retry(&mut self) {
+        for failure in std::mem::take(&mut self.failures) {
+            copy_path_into(&failure.src, &failure.dst, self);
+        }
+    }
+}
+
+/// Recursively copy a directory like [`copy_dir_recursive`], but copy everything that can be
+/// copied and collect the files that failed instead of stopping at the first error.
+/// Modification times are kept, so a later delta copy can tell which files changed.
+pub fn copy_dir_with_report(src: &Path, dst: &Path) -> CopyReport {
+    let mut report = CopyReport::default();
+    copy_path_into(src, dst, &mut report);
+    report
+}
+
+/// How a delta copy decides that a file at the destination is the same as the source's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaCheck {
+    /// Same size and modification time, like rsync does by default
+    SizeAndTime,
+    /// Same size and SHA-256, for when files are rewritten unchanged (e.g. by an installer)
+    Hash,
+}
+
+/// Copy like [`copy_dir_with_report`], but only the files that are missing at the destination
+/// or differ from it, so refreshing a multi-gigabyte install after an update only copies what
+/// the update changed. Files only at the destination are left alone.
+pub fn copy_changed_with_report(src: &Path, dst: &Path, check: DeltaCheck) -> CopyReport {
+    let mut report = CopyReport::default();
+    let mut to_copy = Vec::new();
+    let mut to_hash = Vec::new();
+    collect_changed(src, dst, check, &mut report, &mut to_copy, &mut to_hash);
+
+    let (sources, copies): (Vec<PathBuf>, Vec<PathBuf>) = to_hash.iter().cloned().unzip();
+    let (source_hashes, copy_hashes) = rayon::join(|| sha256_files(&sources), || sha256_files(&copies));
+    for ((src, dst), (source_hash, copy_hash)) in to_hash.into_iter().zip(source_hashes.into_iter().zip(copy_hashes)) {
+        if source_hash.is_some() && source_hash == copy_hash {
+            report.unchanged += 1;
+        } else {
+            to_copy.push((src, dst));
+        }
+    }
+
+    for (src, dst) in to_copy {
+        copy_path_into(&src, &dst, &mut report);
+    }
+    report
+}
+
+// Sort the files under `src` into unchanged ones, ones to copy and ones to compare by hash
+fn collect_changed(src: &Path, dst: &Path, check: DeltaCheck, report: &mut CopyReport,
+                   to_copy: &mut Vec<(PathBuf, PathBuf)>, to_hash: &mut Vec<(PathBuf, PathBuf)>) {
+    if !fs::symlink_metadata(src).is_ok_and(|metadata| metadata.is_dir()) {
+        // Symlinks count as the file they point to, as the copy follows them
+        let same_size = match (fs::metadata(src), fs::metadata(dst)) {
+            (Ok(source), Ok(copy)) if copy.is_file() && copy.len() == source.len() => Some((source, copy)),
+            _ => None,
+        };
+        match (same_size, check) {
+            (Some((source, copy)), DeltaCheck::SizeAndTime) if source.modified().ok() == copy.modified().ok() => report.unchanged += 1,
+            (Some(_), DeltaCheck::Hash) => to_hash.push((src.to_path_buf(), dst.to_path_buf())),
+            _ => to_copy.push((src.to_path_buf(), dst.to_path_buf())),
+        }
+        return;
+    }
+
+    if let Err(error) = fs::create_dir_all(dst) {
+        report.failures.push(CopyFailure { src: src.to_path_buf(), dst: dst.to_path_buf(), error });
+        return;
+    }
+    match fs::read_dir(src) {
+        Ok(entries) => {
+            for entry in entries {
+                match entry {
+                    Ok(entry) => collect_changed(&entry.path(), &dst.join(entry.file_name()), check, report, to_copy, to_hash),
+                    Err(error) => report.failures.push(CopyFailure { src: src.to_path_buf(), dst: dst.to_path_buf(), error }),
+                }
+            }
+        },
+        Err(error) => report.failures.push(CopyFailure { src: src.to_path_buf(), dst: dst.to_path_buf(), error }),
+    }
+}
+
+fn copy_path_into(src: &Path, dst: &Path, report: &mut CopyReport) {
+    let fail = |report: &mut CopyReport, error| {
+        report.failures.push(CopyFailure { src: src.to_path_buf(), dst: dst.to_path_buf(), error });
+    };
+    // Symlinks are copied as the file they point to, like `copy_dir_recursive` does
+    let metadata = match fs::symlink_metadata(src) {
+        Ok(metadata) => metadata,
+        Err(e) => return fail(report, e),
+    };
+    if !metadata.is_dir() {
+        match copy_file_keeping_time(src, dst) {
+            Ok(()) => report.copied += 1,
+            Err(e) => fail(report, e),
+        }
+        return;
+    }
+
+    if let Err(e) = fs::create_dir_all(dst) {
+        return fail(report, e);
+    }
+    let entries = match fs::read_dir(src) {
+        Ok(entries) => entries,
+        Err(e) => return fail(report, e),
+    };
+    for entry in entries {
+        match entry {
+            Ok(entry) => copy_path_into(&entry.path(), &dst.join(entry.file_name()), report),
+            Err(e) => fail(report, e),
+        }
+    }
+}
+
+/// How much of a copied tree arrived: the files and bytes under the source, and those of them
+/// missing from the destination or with a different size there.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CopyVerification {
+    pub files: usize,
+    pub bytes: u64,
+    pub missing_files: usize,
+    pub missing_bytes: u64,
+}
+
+impl CopyVerification {
+    pub fn is_complete(&self) -> bool {
+        self.missing_files == 0
+    }
+}
+
+// Copy a file and give the copy the source's modification time
+fn copy_file_keeping_time(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::copy(src, dst)?;
+    let modified = fs::metadata(src)?.modified()?;
+    // The owner may set the times through a read-only handle, so read-only files work too
+    fs::File::open(dst)?.set_modified(modified)
+}
+
+/// Compare a copy made with [`copy_dir_with_report`] against its source, file by file: by size,
+/// then by SHA-256 for the files whose sizes match. Symlinks count as the file they point to,
+/// as the copy follows them.
+pub fn verify_copy(src: &Path, dst: &Path) -> CopyVerification {
+    let mut verification = CopyVerification::default();
+    let mut same_size = Vec::new();
+    verify_path(src, dst, &mut verification, &mut same_size);
+
+    let (sources, copies): (Vec<PathBuf>, Vec<PathBuf>) = same_size.iter().map(|(src, dst, _)| (src.clone(), dst.clone())).unzip();
+    let (source_hashes, copy_hashes) = rayon::join(|| sha256_files(&sources), || sha256_files(&copies));
+    for ((_, _, len), (source_hash, copy_hash)) in same_size.iter().zip(source_hashes.iter().zip(&copy_hashes)) {
+        if source_hash.is_none() || source_hash != copy_hash {
+            verification.missing_files += 1;
+            verification.missing_bytes += len;
+        }
+    }
+    verification
+}
+
+// Count the files under `src`, noting the ones whose copy has the same size for hashing
+fn verify_path(src: &Path, dst: &Path, verification: &mut CopyVerification, same_size: &mut Vec<(PathBuf, PathBuf, u64)>) {
+    if fs::symlink_metadata(src).is_ok_and(|metadata| metadata.is_dir()) {
+        for entry in fs::read_dir(src).into_iter().flatten().flatten() {
+            verify_path(&entry.path(), &dst.join(entry.file_name()), verification, same_size);
+        }
+        return;
+    }
+    let len = fs::metadata(src).map(|metadata| metadata.len()).unwrap_or(0);
+    verification.files += 1;
+    verification.bytes += len;
+    if fs::metadata(dst).is_ok_and(|metadata| metadata.is_file() && metadata.len() == len) {
+        same_size.push((src.to_path_buf(), dst.to_path_buf(), len));
+    } else {
+        verification.missing_files += 1;
+        verification.missing_bytes += len;
+    }
+}
+
+/// SHA-256 of each file as lowercase hex, in the same order, or None for a file that could not
+/// be read. Batches of files are hashed by `sha256sum` runs on all cores at once.
+pub fn sha256_files(files: &[PathBuf]) -> Vec<Option<String>> {
+    files.par_chunks(HASH_BATCH).flat_map_iter(|batch| {
+        // `-z` separates the lines with NUL and leaves file names unescaped
+        let output = Command::new("sha256sum")
+        .arg("-z")
+        .arg("--")
+        .args(batch)
+        .stderr(Stdio::null())
+        .output();
+        let mut hashes: HashMap<&[u8], String> = HashMap::new();
+        if let Ok(output) = &output {
+            for line in output.stdout.split(|b| *b == 0) {
+                if line.len() > 66 && &line[64..66] == b"  " {
+                    hashes.insert(&line[66..], String::from_utf8_lossy(&line[..64]).to_lowercase());
+                }
+            }
+        }
+        batch.iter().map(|file| hashes.get(file.as_os_str().as_bytes()).cloned()).collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+/// The regular files under `dir` and their SHA-256, as paths relative to `dir` in sorted
+/// order. Symlinks are not followed.
+pub fn hash_tree(dir: &Path) -> Result<Vec<(PathBuf, String)>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current).map_err(|e| format!("Failed to read {}: {}", current.display(), e))?;
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => pending.push(entry.path()),
+                Ok(file_type) if file_type.is_file() => files.push(entry.path()),
+                _ => {},
+            }
+        }
+    }
+    files.sort();
+
+    let hashes = sha256_files(&files);
+    files.into_iter().zip(hashes).map(|(file, hash)| {
+        let hash = hash.ok_or_else(|| format!("Failed to hash {}", file.display()))?;
+        let relative = file.strip_prefix(dir).map(Path::to_path_buf).unwrap_or(file);
+        Ok((relative, hash))
+    })
+    .collect()
+}
+
+/// Replace the file at `path` with `contents` so that a crash or power loss leaves either the
+/// old file or the new one, never half of it: the contents are written to a temporary file next
+/// to it, synced to disk and renamed over it. The file keeps its permissions, and a symlink is
+/// followed so the file it points to is replaced instead of the link.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result?;
+
+    // The rename is only durable once the directory entry is on disk too
+    if let Some(parent) = path.parent() {
+        fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Move a file or directory, falling back to copy + delete across filesystems
+pub fn move_path(src: &Path, dst: &Path) -> io::Result<()> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+
+    if src.is_dir() {
+        copy_dir_recursive(src, dst)?;
+        fs::remove_dir_all(src)
+    } else {
+        fs::copy(src, dst)?;
+        fs::remove_file(src)
+    }
+}
+
+/// Total size in bytes of the files under `path`. Symlinks are not followed.
+pub fn dir_size(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+    .map(|entries| entries.flatten().map(|entry| dir_size(&entry.path())).sum())
+    .unwrap_or(0)
+}
+
+/// Free space in bytes on the filesystem that holds `path`, or would hold it once created
+pub fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    let output = Command::new("df")
+    .arg("-Pk")
+    .arg(existing)
+    .output()
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Second line: filesystem, 1K-blocks, used, available, capacity, mount point
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kb: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb * 1024)
+}
+
+/// Format a byte count for display, e.g. `1.4 GiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    // A source tree and a full copy of it, with times kept
+    fn copied_tree(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("gli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (src, dst) = (dir.join("src"), dir.join("dst"));
+        fs::create_dir_all(src.join("data")).unwrap();
+        fs::write(src.join("launcher.exe"), "client v1").unwrap();
+        fs::write(src.join("data/pack.bin"), "pack").unwrap();
+        assert!(copy_dir_with_report(&src, &dst).is_complete());
+        (dir, src, dst)
+    }
+
+    #[test]
+    fn delta_copy_by_size_and_time_copies_only_changed_files() {
+        let (dir, src, dst) = copied_tree("delta-time");
+        fs::write(src.join("launcher.exe"), "client v2").unwrap();
+        set_modified(&src.join("launcher.exe"), SystemTime::now() + Duration::from_secs(60));
+        fs::write(src.join("data/new.bin"), "new").unwrap();
+        fs::write(dst.join("user.cfg"), "kept").unwrap();
+
+        let report = copy_changed_with_report(&src, &dst, DeltaCheck::SizeAndTime);
+        assert!(report.is_complete());
+        assert_eq!((report.copied, report.unchanged), (2, 1));
+        assert_eq!(fs::read_to_string(dst.join("launcher.exe")).unwrap(), "client v2");
+        assert_eq!(fs::read_to_string(dst.join("data/new.bin")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dst.join("user.cfg")).unwrap(), "kept");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn delta_copy_by_hash_skips_files_rewritten_unchanged() {
+        let (dir, src, dst) = copied_tree("delta-hash");
+        // Rewritten by the installer with the same contents, and changed without a size change
+        set_modified(&src.join("data/pack.bin"), SystemTime::now() + Duration::from_secs(60));
+        fs::write(src.join("launcher.exe"), "client v2").unwrap();
+        set_modified(&src.join("launcher.exe"), fs::metadata(dst.join("launcher.exe")).unwrap().modified().unwrap());
+
+        let report = copy_changed_with_report(&src, &dst, DeltaCheck::Hash);
+        assert!(report.is_complete());
+        assert_eq!((report.copied, report.unchanged), (1, 1));
+        assert_eq!(fs::read_to_string(dst.join("launcher.exe")).unwrap(), "client v2");
+
+        // Going by size and time, the same-size change with the old time is missed
+        fs::write(src.join("launcher.exe"), "client v3").unwrap();
+        set_modified(&src.join("launcher.exe"), fs::metadata(dst.join("launcher.exe")).unwrap().modified().unwrap());
+        let report = copy_changed_with_report(&src, &dst, DeltaCheck::SizeAndTime);
+        assert_eq!(report.copied, 1);
+        assert_eq!(fs::read_to_string(dst.join("launcher.exe")).unwrap(), "client v2");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn collect_changed_sorts_files_by_check() {
+        let (dir, src, dst) = copied_tree("collect-changed");
+        fs::write(src.join("launcher.exe"), "client v10").unwrap();
+        fs::write(src.join("data/new.bin"), "new").unwrap();
+
+        for (check, copy, hash, unchanged) in [(DeltaCheck::SizeAndTime, 2, 0, 1), (DeltaCheck::Hash, 2, 1, 0)] {
+            let mut report = CopyReport::default();
+            let (mut to_copy, mut to_hash) = (Vec::new(), Vec::new());
+            collect_changed(&src, &dst, check, &mut report, &mut to_copy, &mut to_hash);
+            to_copy.sort();
+            assert_eq!(to_copy.len(), copy);
+            assert_eq!(to_copy[0], (src.join("data/new.bin"), dst.join("data/new.bin")));
+            assert_eq!(to_hash.len(), hash);
+            assert_eq!(report.unchanged, unchanged);
+            assert_eq!(report.copied, 0);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
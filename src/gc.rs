@@ -0,0 +1,127 @@
+//! Finding and removing leftovers of launchers that are no longer installed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppPaths;
+use crate::launchers::LauncherRegistry;
+use crate::links::{link_path, LINUX_ROOT};
+use crate::steam::{self, SteamIntegration};
+
+/// Something left behind that no launcher uses any more.
+#[derive(Clone, Debug)]
+pub enum Orphan {
+    /// Downloaded installers of a launcher whose prefix is gone
+    InstallerCache(PathBuf),
+    /// A prefix under the Games root that doesn't belong to any known launcher
+    Prefix(PathBuf),
+    /// A non-Steam shortcut whose executable no longer exists
+    SteamShortcut { file: PathBuf, app_name: String, exe: String },
+    /// A `C:\Linux Root` symlink whose target is gone
+    DeadLink(PathBuf),
+}
+
+impl Orphan {
+    pub fn describe(&self) -> String {
+        match self {
+            Orphan::InstallerCache(path) => format!("Installer cache of an uninstalled launcher: {}", path.display()),
+            Orphan::Prefix(path) => format!("Prefix with no registered launcher: {}", path.display()),
+            Orphan::SteamShortcut { app_name, exe, .. } => format!("Steam shortcut '{}' to a deleted file: {}", app_name, exe),
+            Orphan::DeadLink(path) => format!("Dead symlink: {}", path.display()),
+        }
+    }
+}
+
+/// Look for orphans under the Games root and, if Steam was found, in its prefixes and shortcuts.
+pub fn find_orphans(app_paths: &AppPaths, registry: &LauncherRegistry, steam: Option<&SteamIntegration>) -> Vec<Orphan> {
+    let mut orphans = Vec::new();
+    let mut prefixes = Vec::new();
+
+    for launcher in registry.all() {
+        let cache = app_paths.cache_dir(launcher.dir_name);
+        let prefix = app_paths.prefix_dir(launcher.dir_name);
+        if !prefix.exists() && dir_has_entries(&cache) {
+            orphans.push(Orphan::InstallerCache(cache));
+        }
+        prefixes.push(prefix);
+    }
+
+    if let Ok(entries) = fs::read_dir(&app_paths.games_root) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Skip hidden directories such as .snapshots
+            if name.starts_with('.') || registry.all().iter().any(|launcher| launcher.dir_name == name) {
+                continue;
+            }
+            let prefix = entry.path().join("prefix");
+            if prefix.join("system.reg").exists() {
+                orphans.push(Orphan::Prefix(prefix));
+            }
+        }
+    }
+
+    if let Some(steam) = steam {
+        for file in steam.shortcut_files() {
+            for shortcut in steam::read_shortcuts(&file).unwrap_or_default() {
+                if shortcut.exe.to_lowercase().ends_with(".exe") && !Path::new(&shortcut.exe).exists() {
+                    orphans.push(Orphan::SteamShortcut { file: file.clone(), app_name: shortcut.app_name, exe: shortcut.exe });
+                }
+            }
+        }
+
+        for library in steam.libraries() {
+            if let Ok(entries) = fs::read_dir(library.join("steamapps/compatdata")) {
+                prefixes.extend(entries.flatten().map(|entry| entry.path().join("pfx")));
+            }
+        }
+    }
+
+    for prefix in prefixes {
+        let link = link_path(&prefix, LINUX_ROOT);
+        let is_symlink = fs::symlink_metadata(&link).map(|meta| meta.file_type().is_symlink()).unwrap_or(false);
+        if is_symlink && fs::metadata(&link).is_err() {
+            orphans.push(Orphan::DeadLink(link));
+        }
+    }
+
+    orphans
+}
+
+/// Remove the given orphans, returning how many were removed. Steam shortcuts are
+/// skipped while Steam is running since it would write them back on exit.
+pub fn remove_orphans(orphans: &[Orphan]) -> Result<usize, String> {
+    let mut removed = 0;
+    let mut shortcuts: HashMap<&Path, Vec<&str>> = HashMap::new();
+
+    for orphan in orphans {
+        match orphan {
+            Orphan::InstallerCache(path) | Orphan::Prefix(path) => {
+                fs::remove_dir_all(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                removed += 1;
+            },
+            Orphan::DeadLink(path) => {
+                fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                removed += 1;
+            },
+            Orphan::SteamShortcut { file, exe, .. } => {
+                shortcuts.entry(file.as_path()).or_default().push(exe.as_str());
+            },
+        }
+    }
+
+    if !shortcuts.is_empty() {
+        if steam::steam_running() {
+            return Err(format!("Removed {} items, but Steam is running. Close Steam and run gc again to remove the shortcuts.", removed));
+        }
+        for (file, exes) in shortcuts {
+            removed += steam::remove_shortcuts(file, |shortcut| exes.contains(&shortcut.exe.as_str()))?;
+        }
+    }
+
+    Ok(removed)
+}
+
+fn dir_has_entries(path: &Path) -> bool {
+    fs::read_dir(path).map(|mut entries| entries.next().is_some()).unwrap_or(false)
+}
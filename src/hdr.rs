@@ -0,0 +1,94 @@
+//! HDR for games started from Steam: detecting sessions that can show it and the launch
+//! options that turn it on.
+
+use std::process::Command;
+
+use regex::Regex;
+
+use crate::wayland::is_wayland_session;
+use crate::wine::find_in_path;
+
+// What `with_hdr` adds in front of %command%, so `without_hdr` can take it out again
+const DXVK_HDR: &str = "DXVK_HDR=1";
+const HDR_WSI: &str = "ENABLE_HDR_WSI=1";
+const GAMESCOPE_HDR: &str = "gamescope -f --hdr-enabled --";
+
+/// A desktop session that can present HDR output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HdrSession {
+    /// A gamescope session such as Steam's gaming mode, which handles HDR itself
+    Gamescope,
+    /// KDE Plasma 6 on Wayland with HDR turned on for a screen. Games are run in a nested
+    /// gamescope if it is installed, otherwise through the Vulkan HDR layer.
+    Plasma { gamescope: bool },
+}
+
+impl HdrSession {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            HdrSession::Gamescope => "gamescope session",
+            HdrSession::Plasma { gamescope: true } => "KDE Plasma with HDR enabled, games run in gamescope",
+            HdrSession::Plasma { gamescope: false } => "KDE Plasma with HDR enabled, games use the Vulkan HDR layer",
+        }
+    }
+
+    // Environment and wrapper placed right before %command%
+    fn launch_prefix(&self) -> String {
+        match self {
+            HdrSession::Gamescope => DXVK_HDR.to_string(),
+            HdrSession::Plasma { gamescope: true } => format!("{} {}", DXVK_HDR, GAMESCOPE_HDR),
+            HdrSession::Plasma { gamescope: false } => format!("{} {}", DXVK_HDR, HDR_WSI),
+        }
+    }
+}
+
+/// Find out whether the current session can show HDR.
+pub fn detect_hdr_session() -> Option<HdrSession> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    if std::env::var_os("GAMESCOPE_WAYLAND_DISPLAY").is_some() || desktop.eq_ignore_ascii_case("gamescope") {
+        return Some(HdrSession::Gamescope);
+    }
+
+    // Plasma supports HDR from 6.0, and only on Wayland
+    let plasma_6 = std::env::var("KDE_SESSION_VERSION").ok().and_then(|version| version.parse::<u32>().ok()).unwrap_or(0) >= 6;
+    if !desktop.split(':').any(|name| name == "KDE") || !plasma_6 || !is_wayland_session() {
+        return None;
+    }
+    let output = Command::new("kscreen-doctor").arg("-o").output().ok()?;
+    let ansi = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    let outputs = ansi.replace_all(&String::from_utf8_lossy(&output.stdout), "").to_string();
+    if !outputs.lines().any(|line| line.trim() == "HDR: enabled") {
+        return None;
+    }
+    Some(HdrSession::Plasma { gamescope: find_in_path("gamescope").is_some() })
+}
+
+/// Whether Steam launch options already have HDR turned on.
+pub fn has_hdr(launch_options: &str) -> bool {
+    launch_options.split_whitespace().any(|word| word == DXVK_HDR)
+}
+
+/// Steam launch options with the HDR environment (and gamescope, if used) added in front of
+/// `%command%`. Options without `%command%` are arguments to the game and are kept after it.
+pub fn with_hdr(launch_options: &str, session: HdrSession) -> String {
+    let launch_options = without_hdr(launch_options);
+    let (env, command) = match launch_options.split_once("%command%") {
+        Some((env, rest)) => (env.trim().to_string(), format!("%command%{}", rest)),
+        None if launch_options.trim().is_empty() => (String::new(), "%command%".to_string()),
+        None => (String::new(), format!("%command% {}", launch_options.trim())),
+    };
+    [env, session.launch_prefix(), command].iter()
+    .filter(|part| !part.is_empty())
+    .cloned()
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+/// Steam launch options with everything `with_hdr` added removed again.
+pub fn without_hdr(launch_options: &str) -> String {
+    let mut launch_options = launch_options.to_string();
+    for part in [GAMESCOPE_HDR, HDR_WSI, DXVK_HDR] {
+        launch_options = launch_options.replace(&format!("{} ", part), "");
+    }
+    launch_options
+}
@@ -0,0 +1,106 @@
+//! Checks run by `healthcheck` that find what a system upgrade, a moved directory or a full
+//! disk has broken in the installed launchers.
+
+use std::fs;
+use std::path::Path;
+
+use crate::config::AppPaths;
+use crate::fsutil::{available_space, format_size};
+use crate::launchers::LauncherRegistry;
+use crate::links::{self, LinkStatus};
+use crate::prefix::PrefixManager;
+use crate::state::LauncherState;
+use crate::steam::SteamIntegration;
+
+/// Less free space than this under the Games root is reported, since launcher updates fail
+/// half-way when the disk fills up.
+pub const LOW_DISK_SPACE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Something `check_health` found broken.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthIssue {
+    /// What is affected, e.g. a launcher's name or a link's path
+    pub subject: String,
+    pub problem: String,
+}
+
+impl HealthIssue {
+    fn new(subject: impl Into<String>, problem: impl Into<String>) -> HealthIssue {
+        HealthIssue { subject: subject.into(), problem: problem.into() }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{}: {}", self.subject, self.problem)
+    }
+}
+
+/// Check the prefixes of the installed launchers, the links this tool made, the targets of
+/// Steam's non-Steam shortcuts (if Steam was found) and the free space under the Games root.
+pub fn check_health(app_paths: &AppPaths, registry: &LauncherRegistry, steam: Option<&SteamIntegration>) -> Vec<HealthIssue> {
+    let mut issues = Vec::new();
+    let mut prefixes = Vec::new();
+
+    for launcher in registry.all() {
+        if !app_paths.launcher_dir(launcher.dir_name).exists() {
+            continue;
+        }
+        let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+        prefixes.push(prefix_path.clone());
+        issues.extend(check_prefix(&prefix_path).into_iter().map(|problem| HealthIssue::new(launcher.name, problem)));
+        if let Some(arch) = PrefixManager::new(&prefix_path).arch().filter(|arch| *arch != launcher.arch) {
+            issues.push(HealthIssue::new(launcher.name, format!("its prefix is {} ({}), but it needs a {} one; reinstall it to get a fresh prefix",
+                                                                arch.describe(), arch.name(), launcher.arch.describe())));
+        }
+
+        let state = LauncherState::load(&app_paths.state_path(launcher.dir_name));
+        if let Some(runner) = state.runner.filter(|runner| !Path::new(runner).exists()) {
+            issues.push(HealthIssue::new(launcher.name, format!("the runner it was migrated to, {}, is gone", runner)));
+        }
+    }
+
+    for entry in links::audit(&app_paths.home_dir, &prefixes) {
+        if entry.status == LinkStatus::Broken {
+            issues.push(HealthIssue::new(entry.link.display().to_string(), format!("links to {}, which is gone", entry.target.display())));
+        }
+    }
+
+    if let Some(steam) = steam {
+        for shortcut in steam.shortcuts() {
+            if shortcut.exe.to_lowercase().ends_with(".exe") && !Path::new(&shortcut.exe).exists() {
+                issues.push(HealthIssue::new(format!("Steam shortcut {}", shortcut.app_name), format!("{} does not exist", shortcut.exe)));
+            }
+        }
+    }
+
+    if let Some(available) = available_space(&app_paths.games_root).filter(|available| *available < LOW_DISK_SPACE) {
+        issues.push(HealthIssue::new(app_paths.games_root.display().to_string(), format!("only {} free", format_size(available))));
+    }
+
+    issues
+}
+
+// What is wrong with an installed launcher's prefix
+fn check_prefix(prefix_path: &Path) -> Vec<String> {
+    if let Ok(target) = fs::read_link(prefix_path) {
+        if !target.exists() {
+            return vec![format!("its adopted prefix {} is gone", target.display())];
+        }
+    }
+    if !prefix_path.is_dir() {
+        return vec![format!("the prefix {} is missing", prefix_path.display())];
+    }
+
+    let mut problems: Vec<String> = ["system.reg", "user.reg", "drive_c"].iter()
+    .filter(|name| !prefix_path.join(name).exists())
+    .map(|name| format!("the prefix has no {}", name))
+    .collect();
+    let c_drive = prefix_path.join("dosdevices/c:");
+    if fs::symlink_metadata(&c_drive).is_ok() && !c_drive.exists() {
+        problems.push("the prefix's C: drive points nowhere".to_string());
+    }
+    let prefix = PrefixManager::new(prefix_path);
+    if let Some(wine) = prefix.runner_wine().filter(|wine| wine.starts_with('/') && !Path::new(wine).exists()) {
+        problems.push(format!("the wine it was last used with, {}, is gone; the next run updates the prefix for the current one", wine));
+    }
+    problems
+}
@@ -0,0 +1,16 @@
+//! Where HoYoPlay's games go. HoYoPlay keeps its settings to itself and reads no
+//! preconfiguration file, so their location can't be preset; post-setup only prepares a drive
+//! the user can pick.
+
+use std::fs;
+use std::path::Path;
+
+use crate::prefix::PrefixManager;
+
+/// Give a Linux directory for HoYoPlay's games a drive letter of its own, so it can be picked as
+/// the install location when HoYoPlay asks where to install a game. Returns the letter.
+pub fn map_games_dir(prefix: &PrefixManager, games_dir: &Path) -> Result<char, String> {
+    fs::create_dir_all(games_dir)
+    .map_err(|e| format!("Failed to create {}: {}", games_dir.display(), e))?;
+    Ok(prefix.map_drive(games_dir)?.to_ascii_uppercase())
+}
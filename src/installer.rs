@@ -0,0 +1,480 @@
+//! Downloading a launcher's installer and running it inside a prefix.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::dlloverrides::DllOverrides;
+use crate::download::download_file_segmented;
+use crate::journal;
+use crate::language::Language;
+use crate::launchers::{LauncherInfo, Runtime};
+use crate::output::*;
+use crate::policy::HashPolicy;
+use crate::prefix::PrefixManager;
+use crate::session::WineSession;
+use crate::watchdog::HangDetector;
+
+// How often a running silent installer is checked for activity
+const HANG_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A named set of variables that keeps overlays or capture layers out of installers, which some
+/// of them crash on. Picked with `installer_hygiene`.
+pub struct HygieneSet {
+    pub name: &'static str,
+    /// What the set turns off
+    pub description: &'static str,
+    pub variables: &'static [(&'static str, &'static str)],
+}
+
+pub const HYGIENE_SETS: &[HygieneSet] = &[
+    HygieneSet { name: "mangohud", description: "the MangoHud overlay", variables: &[("MANGOHUD", "0"), ("DISABLE_MANGOHUD", "1")] },
+    HygieneSet { name: "amd-switchable", description: "AMD's switchable graphics Vulkan layer", variables: &[("DISABLE_LAYER_AMD_SWITCHABLE_GRAPHICS_1", "1")] },
+    HygieneSet { name: "vkbasalt", description: "vkBasalt post-processing", variables: &[("ENABLE_VKBASALT", "0")] },
+    HygieneSet { name: "obs", description: "OBS game capture (obs-vkcapture)", variables: &[("OBS_VKCAPTURE", "0")] },
+    // Vulkan loader 1.3.234 and newer
+    HygieneSet { name: "implicit-layers", description: "every implicit Vulkan layer", variables: &[("VK_LOADER_LAYERS_DISABLE", "~implicit~")] },
+];
+
+/// The hygiene sets installers use unless `installer_hygiene` says otherwise.
+pub const DEFAULT_HYGIENE: &[&str] = &["mangohud", "amd-switchable"];
+
+/// Where a variable in an installer's environment comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvSource {
+    /// The prefix's wine session, see `WineSession::environment`
+    Session,
+    /// One of the `installer_hygiene` sets
+    Hygiene,
+    /// The install's language, DLL overrides or display
+    Install,
+    /// An `env.` line in the config
+    Config,
+    /// Set for one run only, e.g. wine debug channels for a logged run
+    Run,
+}
+
+impl EnvSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnvSource::Session => "session",
+            EnvSource::Hygiene => "hygiene",
+            EnvSource::Install => "install",
+            EnvSource::Config => "config",
+            EnvSource::Run => "this run",
+        }
+    }
+}
+
+/// A variable an installer runs with. A None value removes it from the environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvEntry {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: EnvSource,
+    /// The value and source this entry replaced, e.g. a default overridden in the config
+    pub replaced: Option<(Option<String>, EnvSource)>,
+}
+
+/// Runs Windows installers with a given wine binary inside one prefix.
+pub struct Installer {
+    wine_path: String,
+    prefix: PrefixManager,
+    session: WineSession,
+    language: Option<Language>,
+    dll_overrides: DllOverrides,
+    installer_source: Option<String>,
+    hash_policy: HashPolicy,
+    // X display silent installs run on
+    display: String,
+    // How long a watched installer may show no activity before it counts as hung
+    hang_timeout: Option<Duration>,
+    download_connections: u32,
+    // Names of the HYGIENE_SETS applied to every installer
+    hygiene: Vec<String>,
+    // Variables from the config's `env.` lines, applied over everything else
+    env_overrides: Vec<(String, String)>,
+    // Print the environment of every command before it runs
+    verbose: bool,
+}
+
+impl Installer {
+    pub fn new(wine_path: &str, prefix: PrefixManager) -> Installer {
+        Installer {
+            wine_path: wine_path.to_string(),
+            session: prefix.session(wine_path),
+            prefix,
+            language: None,
+            dll_overrides: DllOverrides::default(),
+            installer_source: None,
+            hash_policy: HashPolicy::default(),
+            display: ":99".to_string(),
+            hang_timeout: None,
+            download_connections: 1,
+            hygiene: DEFAULT_HYGIENE.iter().map(|name| name.to_string()).collect(),
+            env_overrides: Vec::new(),
+            verbose: false,
+        }
+    }
+
+    /// DLL overrides for silent installs, replacing the default of disabling mscoree and mshtml.
+    pub fn with_dll_overrides(mut self, dll_overrides: DllOverrides) -> Installer {
+        self.dll_overrides = dll_overrides;
+        self
+    }
+
+    /// Install from a pinned installer URL or local file instead of the launcher's current one.
+    pub fn with_installer_source(mut self, source: Option<String>) -> Installer {
+        self.installer_source = source;
+        self
+    }
+
+    /// Check fetched installers against an allowlist of hashes. Off by default.
+    pub fn with_hash_policy(mut self, hash_policy: HashPolicy) -> Installer {
+        self.hash_policy = hash_policy;
+        self
+    }
+
+    /// Run silent installs on this X display, e.g. a private Xvfb server, instead of `:99`.
+    pub fn with_display(mut self, display: Option<String>) -> Installer {
+        if let Some(display) = display {
+            self.display = display;
+        }
+        self
+    }
+
+    /// Report installers run with `run_watched` as hung after this long without activity.
+    pub fn with_hang_timeout(mut self, hang_timeout: Option<Duration>) -> Installer {
+        self.hang_timeout = hang_timeout;
+        self
+    }
+
+    /// Download installers over this many connections at once. One by default.
+    pub fn with_download_connections(mut self, download_connections: u32) -> Installer {
+        self.download_connections = download_connections;
+        self
+    }
+
+    /// Apply these `HYGIENE_SETS` instead of `DEFAULT_HYGIENE`; unknown names are skipped.
+    pub fn with_hygiene(mut self, hygiene: Vec<String>) -> Installer {
+        self.hygiene = hygiene;
+        self
+    }
+
+    /// Set (or, for an empty value, remove) these variables for every installer, over the
+    /// defaults. See `Config::env_for`.
+    pub fn with_env_overrides(mut self, env_overrides: Vec<(String, String)>) -> Installer {
+        self.env_overrides = env_overrides;
+        self
+    }
+
+    /// Print the environment of every command, and where each variable came from, before it runs.
+    pub fn with_verbose(mut self, verbose: bool) -> Installer {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Run installers under the given language's locale.
+    pub fn with_language(mut self, language: Language) -> Installer {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn wine_path(&self) -> &str {
+        &self.wine_path
+    }
+
+    /// X display silent installs run on.
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    pub fn prefix(&self) -> &PrefixManager {
+        &self.prefix
+    }
+
+    /// The wineserver session installers run in.
+    pub fn session(&self) -> &WineSession {
+        &self.session
+    }
+
+    /// Where `fetch` puts (or finds) the launcher's installer.
+    pub fn installer_path(&self, launcher: &LauncherInfo, cache_dir: &Path) -> PathBuf {
+        match &self.installer_source {
+            Some(source) if !is_url(source) => PathBuf::from(source),
+            Some(url) => cache_dir.join(pinned_file_name(url, launcher.installer_file_name)),
+            None => cache_dir.join(launcher.installer_file_name),
+        }
+    }
+
+    /// Download the launcher's installer into `cache_dir`, reusing an existing download,
+    /// and check it against the hash policy. A pinned local file is used where it is.
+    pub fn fetch(&self, launcher: &LauncherInfo, cache_dir: &Path) -> Result<PathBuf, String> {
+        fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("Failed to create {} directory: {}", launcher.name, e))?;
+
+        let installer_path = self.installer_path(launcher, cache_dir);
+        match &self.installer_source {
+            Some(source) if !is_url(source) => {
+                if !installer_path.is_file() {
+                    return Err(format!("Pinned installer {} does not exist.", installer_path.display()));
+                }
+                println!("{}Using pinned installer {}{}", COLOR_YELLOW, installer_path.display(), COLOR_RESET);
+                self.hash_policy.check(launcher.id, &installer_path)?;
+                return Ok(installer_path);
+            },
+            Some(url) => {
+                println!("{}Using pinned installer {}{}", COLOR_YELLOW, url, COLOR_RESET);
+                download_file_segmented(url, &installer_path, self.download_connections)?;
+            },
+            None => download_file_segmented(launcher.installer_url, &installer_path, self.download_connections)?,
+        }
+        self.hash_policy.check(launcher.id, &installer_path)?;
+
+        // Make installer executable
+        if let Err(e) = fs::set_permissions(&installer_path, fs::Permissions::from_mode(0o755)) {
+            println!("{}Warning: Could not make installer executable: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+        }
+
+        Ok(installer_path)
+    }
+
+    /// Download a runtime into `cache_dir` and install it silently, unless the prefix already
+    /// has it. Fails if the runtime's directory doesn't exist afterwards.
+    pub fn install_runtime(&self, runtime: &Runtime, cache_dir: &Path) -> Result<(), String> {
+        let installed_dir = self.prefix.path().join("drive_c").join(runtime.installed_dir);
+        if installed_dir.is_dir() {
+            return Ok(());
+        }
+        fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("Failed to create {} directory: {}", runtime.name, e))?;
+
+        println!("{}Installing {} into the prefix...{}", COLOR_BLUE, runtime.name, COLOR_RESET);
+        emit_progress("prefix", None, &format!("Installing {}", runtime.name));
+        let installer_path = cache_dir.join(runtime.installer_file_name);
+        download_file_segmented(runtime.installer_url, &installer_path, self.download_connections)?;
+
+        let mut command = self.silent_command(&installer_path);
+        command.args(runtime.silent_args);
+        match self.run(&mut command)? {
+            0 => {},
+            code => return Err(format!("The {} installer failed with exit code {}.", runtime.name, code)),
+        }
+        if !installed_dir.is_dir() {
+            return Err(format!("The {} installer finished, but {} does not exist.", runtime.name, installed_dir.display()));
+        }
+        Ok(())
+    }
+
+    /// Command that runs a Windows program in the prefix with overlays and wine debug output off.
+    pub fn interactive_command(&self, exe: &Path) -> Command {
+        self.command(exe, false)
+    }
+
+    /// Like `interactive_command`, but hides the GUI and applies the DLL overrides
+    /// (by default disabling the browser component) for unattended installs.
+    pub fn silent_command(&self, exe: &Path) -> Command {
+        self.command(exe, true)
+    }
+
+    fn command(&self, exe: &Path, silent: bool) -> Command {
+        // wine only runs Windows Installer packages through msiexec
+        let mut command = if exe.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("msi")) {
+            let mut command = self.session.command("msiexec");
+            command.arg("/i").arg(exe);
+            command
+        } else {
+            self.session.command(exe)
+        };
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+        for entry in self.environment(silent) {
+            match entry.value {
+                Some(value) => command.env(entry.name, value),
+                None => command.env_remove(entry.name),
+            };
+        }
+        command
+    }
+
+    /// The environment `silent_command` (or, if not `silent`, `interactive_command`) runs
+    /// installers with, one entry per variable, in the order the sources are applied.
+    pub fn environment(&self, silent: bool) -> Vec<EnvEntry> {
+        let mut entries: Vec<EnvEntry> = Vec::new();
+        let mut set = |name: &str, value: Option<String>, source: EnvSource| {
+            let replaced = entries.iter().position(|entry| entry.name == name)
+            .map(|index| entries.remove(index))
+            .map(|entry| (entry.value, entry.source));
+            entries.push(EnvEntry { name: name.to_string(), value, source, replaced });
+        };
+
+        for (name, value) in self.session.environment() {
+            set(name, value.map(|value| value.to_string_lossy().to_string()), EnvSource::Session);
+        }
+        for hygiene in HYGIENE_SETS.iter().filter(|hygiene| self.hygiene.iter().any(|name| name == hygiene.name)) {
+            for (name, value) in hygiene.variables {
+                set(name, Some(value.to_string()), EnvSource::Hygiene);
+            }
+        }
+        if let Some(language) = self.language {
+            set("LC_ALL", Some(language.posix.to_string()), EnvSource::Install);
+        }
+        if silent {
+            set("WINEDLLOVERRIDES", Some(self.dll_overrides.to_env()), EnvSource::Install);
+            // A display nobody sees, so the installer's windows stay hidden
+            set("DISPLAY", Some(self.display.clone()), EnvSource::Install);
+            set("WAYLAND_DISPLAY", None, EnvSource::Install);
+        }
+        for (name, value) in &self.env_overrides {
+            set(name, Some(value.clone()).filter(|value| !value.is_empty()), EnvSource::Config);
+        }
+        entries
+    }
+
+    /// Run a command to completion and return its exit code (1 if it was killed by a signal).
+    /// Starts the prefix's wineserver session first, which fails if another process is
+    /// installing into the same prefix.
+    pub fn run(&self, command: &mut Command) -> Result<i32, String> {
+        self.session.start()?;
+        journal_command(command);
+        if self.verbose {
+            self.print_command_environment(command);
+        }
+        let status = command.status()
+        .map_err(|e| format!("Failed to execute wine command: {}", e))?;
+        Ok(status.code().unwrap_or(1))
+    }
+
+    /// Like `run`, but with wine's debug output for `channels` turned on and everything the
+    /// command prints written to `log`.
+    pub fn run_logged(&self, command: &mut Command, channels: &str, log: &Path) -> Result<i32, String> {
+        let file = fs::File::create(log).map_err(|e| format!("Failed to create {}: {}", log.display(), e))?;
+        let stderr = file.try_clone().map_err(|e| format!("Failed to open {}: {}", log.display(), e))?;
+        command.env("WINEDEBUG", channels).stdout(file).stderr(stderr);
+        self.run(command)
+    }
+
+    /// Like `run`, but checks the prefix for activity while the installer runs. When there was
+    /// none for the hang timeout, `on_hang` is called with the idle time; if it returns true,
+    /// every process in the prefix is stopped and `None` is returned. Otherwise the idle time
+    /// starts over.
+    pub fn run_watched(&self, command: &mut Command, mut on_hang: impl FnMut(Duration) -> bool) -> Result<Option<i32>, String> {
+        let hang_timeout = match self.hang_timeout {
+            Some(hang_timeout) => hang_timeout,
+            None => return self.run(command).map(Some),
+        };
+
+        self.session.start()?;
+        journal_command(command);
+        if self.verbose {
+            self.print_command_environment(command);
+        }
+        let mut child = command.spawn()
+        .map_err(|e| format!("Failed to execute wine command: {}", e))?;
+        let mut detector = HangDetector::new(&self.prefix, hang_timeout);
+        loop {
+            if let Some(status) = child.try_wait().map_err(|e| format!("Failed to wait for the installer: {}", e))? {
+                return Ok(Some(status.code().unwrap_or(1)));
+            }
+            if let Some(idle) = detector.check() {
+                if on_hang(idle) {
+                    self.session.shutdown();
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Ok(None);
+                }
+                detector.reset();
+            }
+            thread::sleep(HANG_POLL_INTERVAL);
+        }
+    }
+
+    // Print the variables a command was given, labelled with their source. A variable set
+    // after the command was built, such as WINEDEBUG for a logged run, is labelled `this run`.
+    fn print_command_environment(&self, command: &Command) {
+        let known = self.environment(true);
+        let mut entries: Vec<EnvEntry> = command.get_envs()
+        .map(|(name, value)| {
+            let name = name.to_string_lossy().to_string();
+            let value = value.map(|value| value.to_string_lossy().to_string());
+            match known.iter().find(|entry| entry.name == name && entry.value == value) {
+                Some(entry) => entry.clone(),
+                None => EnvEntry { name, value, source: EnvSource::Run, replaced: None },
+            }
+        })
+        .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        println!("{}Running {} with:{}", COLOR_BLUE, command.get_args().next().unwrap_or_default().to_string_lossy(), COLOR_RESET);
+        print_environment(&entries);
+    }
+
+    /// Wait for everything the installer spawned, then shut the prefix's wineserver down.
+    /// The installer's exit code only covers the bootstrapper, so its children are waited for too.
+    pub fn finish(&self) {
+        if let Err(e) = self.prefix.wait_for_quiescence() {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+        }
+        println!("{}Stopping the prefix's wineserver...{}", COLOR_YELLOW, COLOR_RESET);
+        self.session.shutdown();
+    }
+}
+
+/// Print one line per variable: its value (or that it is removed), where it comes from, what it
+/// replaced, and the value it has in this process's own environment if that differs.
+pub fn print_environment(entries: &[EnvEntry]) {
+    let width = entries.iter().map(|entry| entry_text(&entry.name, &entry.value).len()).max().unwrap_or(0);
+    for entry in entries {
+        let mut notes = vec![entry.source.name().to_string()];
+        if let Some((value, source)) = &entry.replaced {
+            notes.push(format!("replaces {} from {}", value_text(value), source.name()));
+        }
+        let inherited = std::env::var(&entry.name).ok();
+        if inherited.is_some() && inherited != entry.value {
+            notes.push(format!("your environment has {}", value_text(&inherited)));
+        }
+        println!("  {:<width$}  {}", entry_text(&entry.name, &entry.value), notes.join("; "), width = width);
+    }
+}
+
+fn entry_text(name: &str, value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("{}={}", name, value),
+        None => format!("{} (removed)", name),
+    }
+}
+
+fn value_text(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("`{}`", value),
+        None => "nothing".to_string(),
+    }
+}
+
+// Record the installer command line and the prefix it runs in
+fn journal_command(command: &Command) {
+    let line = std::iter::once(command.get_program())
+    .chain(command.get_args())
+    .map(|part| part.to_string_lossy())
+    .collect::<Vec<_>>()
+    .join(" ");
+    let prefix = command.get_envs()
+    .find(|(key, _)| *key == "WINEPREFIX")
+    .and_then(|(_, value)| value)
+    .map(|value| value.to_string_lossy())
+    .unwrap_or_default();
+    journal::record("command", &[("command", &line), ("prefix", &prefix)]);
+}
+
+/// Whether an installer source is a URL rather than a local path.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+// Cache file name for a pinned URL, distinct per URL so it never reuses the unpinned download
+fn pinned_file_name(url: &str, default_name: &str) -> String {
+    // FNV-1a, stable across builds unlike std's hasher
+    let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("pinned-{:016x}-{}", hash, default_name)
+}
@@ -0,0 +1,154 @@
+//! An append-only journal of what each invocation did: the arguments it was started with,
+//! progress steps, downloads, hashes and commands, and how it ended. Every record is synced to
+//! disk as it is written, so after a crash or power loss the journal shows where a run stopped.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The journal is moved aside to `journal.log.1` once it grows past this
+const MAX_JOURNAL_SIZE: u64 = 1024 * 1024;
+
+// How often `follow_run` looks for new records
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+// Open journal of this process, set by `open_journal`
+static JOURNAL: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Where the journal is kept.
+pub fn journal_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(".local/state/game-launcher-installer/journal.log")
+}
+
+/// One line of the journal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// Seconds since the epoch
+    pub time: u64,
+    pub pid: u32,
+    /// `start`, `step`, `download`, `hash`, `command`, `copy`, `panic` or `end`
+    pub event: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Record {
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+
+    /// The record's fields as `key=value` pairs, for display.
+    pub fn describe(&self) -> String {
+        self.fields.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join(" ")
+    }
+
+    fn parse(line: &str) -> Option<Record> {
+        let mut parts = line.split('\t');
+        let time = parts.next()?.parse().ok()?;
+        let pid = parts.next()?.parse().ok()?;
+        let event = parts.next()?.to_string();
+        let fields = parts.filter_map(|part| part.split_once('=')).map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        Some(Record { time, pid, event, fields })
+    }
+}
+
+/// Start journaling this process with a `start` record holding its arguments. Panics are
+/// journaled too, so a crash shows up with its message.
+pub fn open_journal(home_dir: &Path, args: &[String]) -> Result<(), String> {
+    let path = journal_path(home_dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_JOURNAL_SIZE) {
+        let _ = fs::rename(&path, path.with_extension("log.1"));
+    }
+    let file = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&path)
+    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    JOURNAL.set(Mutex::new(file)).map_err(|_| "The journal is already open.".to_string())?;
+
+    record("start", &[("args", &args.join(" "))]);
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        record("panic", &[("message", &info.to_string())]);
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+/// Append a record. Does nothing unless the journal was opened.
+pub fn record(event: &str, fields: &[(&str, &str)]) {
+    let journal = match JOURNAL.get() {
+        Some(journal) => journal,
+        None => return,
+    };
+
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let mut line = format!("{}\t{}\t{}", time, std::process::id(), event);
+    for (key, value) in fields {
+        // One record per line, one field per tab
+        let value: String = value.chars().map(|c| if c == '\t' || c == '\n' || c == '\r' { ' ' } else { c }).collect();
+        line.push_str(&format!("\t{}={}", key, value));
+    }
+    line.push('\n');
+
+    if let Ok(mut file) = journal.lock() {
+        let _ = file.write_all(line.as_bytes());
+        let _ = file.sync_data();
+    }
+}
+
+/// Record how this process ended.
+pub fn close_journal(outcome: Result<(), &str>) {
+    match outcome {
+        Ok(()) => record("end", &[("result", "ok")]),
+        Err(e) => record("end", &[("result", "error"), ("message", e)]),
+    }
+}
+
+/// Every run in the journal, including the part moved aside to `journal.log.1`, oldest first,
+/// each with its own records.
+pub fn runs(home_dir: &Path) -> Vec<Vec<Record>> {
+    let path = journal_path(home_dir);
+    let content = fs::read_to_string(path.with_extension("log.1")).unwrap_or_default() + &fs::read_to_string(&path).unwrap_or_default();
+    let mut runs: Vec<Vec<Record>> = Vec::new();
+    // Runs at the same time write to the journal too; each record goes to its process's run
+    let mut open: HashMap<u32, usize> = HashMap::new();
+    for record in content.lines().filter_map(Record::parse) {
+        if record.event == "start" {
+            open.insert(record.pid, runs.len());
+            runs.push(vec![record]);
+        } else if let Some(index) = open.get(&record.pid) {
+            runs[*index].push(record);
+        }
+    }
+    runs
+}
+
+/// The records of the last run in the journal, oldest first.
+pub fn last_run(home_dir: &Path) -> Vec<Record> {
+    runs(home_dir).pop().unwrap_or_default()
+}
+
+/// Call `on_record` with each record the run of process `pid` adds to the journal from now on,
+/// until it ends or the process is gone.
+pub fn follow_run(home_dir: &Path, pid: u32, mut on_record: impl FnMut(&Record)) {
+    let run_of = |home_dir: &Path| runs(home_dir).into_iter().rev().find(|run| run.first().is_some_and(|start| start.pid == pid)).unwrap_or_default();
+    let mut seen = run_of(home_dir).len();
+    loop {
+        let run = run_of(home_dir);
+        for record in run.iter().skip(seen) {
+            on_record(record);
+        }
+        seen = seen.max(run.len());
+        if run.iter().any(|record| record.event == "end") || !Path::new(&format!("/proc/{}", pid)).exists() {
+            return;
+        }
+        thread::sleep(FOLLOW_INTERVAL);
+    }
+}
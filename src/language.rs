@@ -0,0 +1,127 @@
+//! Installer languages, how each launcher spells them, and the matching Windows regional settings.
+
+/// One language the launchers can install in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Language {
+    /// Blizzard locale code passed to the Battle.net installer's `--lang`.
+    pub battlenet: &'static str,
+    /// POSIX locale exported as `LC_ALL` while the installer runs.
+    pub posix: &'static str,
+    /// Windows locale name, e.g. `en-US`.
+    pub windows: &'static str,
+    /// Windows locale identifier in hex.
+    pub lcid: &'static str,
+    /// ANSI and OEM codepages Windows uses for this locale.
+    pub ansi_codepage: &'static str,
+    pub oem_codepage: &'static str,
+}
+
+/// Supported languages. The first entry is the fallback.
+pub const LANGUAGES: &[Language] = &[
+    Language { battlenet: "enUS", posix: "en_US.UTF-8",
+               windows: "en-US", lcid: "0409", ansi_codepage: "1252", oem_codepage: "437" },
+    Language { battlenet: "enGB", posix: "en_GB.UTF-8",
+               windows: "en-GB", lcid: "0809", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "deDE", posix: "de_DE.UTF-8",
+               windows: "de-DE", lcid: "0407", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "frFR", posix: "fr_FR.UTF-8",
+               windows: "fr-FR", lcid: "040c", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "esES", posix: "es_ES.UTF-8",
+               windows: "es-ES", lcid: "0c0a", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "esMX", posix: "es_MX.UTF-8",
+               windows: "es-MX", lcid: "080a", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "itIT", posix: "it_IT.UTF-8",
+               windows: "it-IT", lcid: "0410", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "ptBR", posix: "pt_BR.UTF-8",
+               windows: "pt-BR", lcid: "0416", ansi_codepage: "1252", oem_codepage: "850" },
+    Language { battlenet: "ruRU", posix: "ru_RU.UTF-8",
+               windows: "ru-RU", lcid: "0419", ansi_codepage: "1251", oem_codepage: "866" },
+    Language { battlenet: "plPL", posix: "pl_PL.UTF-8",
+               windows: "pl-PL", lcid: "0415", ansi_codepage: "1250", oem_codepage: "852" },
+    Language { battlenet: "koKR", posix: "ko_KR.UTF-8",
+               windows: "ko-KR", lcid: "0412", ansi_codepage: "949", oem_codepage: "949" },
+    Language { battlenet: "jaJP", posix: "ja_JP.UTF-8",
+               windows: "ja-JP", lcid: "0411", ansi_codepage: "932", oem_codepage: "932" },
+    Language { battlenet: "zhCN", posix: "zh_CN.UTF-8",
+               windows: "zh-CN", lcid: "0804", ansi_codepage: "936", oem_codepage: "936" },
+    Language { battlenet: "zhTW", posix: "zh_TW.UTF-8",
+               windows: "zh-TW", lcid: "0404", ansi_codepage: "950", oem_codepage: "950" },
+];
+
+impl Language {
+    /// Look up a language from `enUS`, `en-us`, `en_US` or `en_US.UTF-8` style codes.
+    pub fn parse(code: &str) -> Option<Language> {
+        let code = code.split('.').next().unwrap_or("");
+        let normalized: String = code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+        LANGUAGES.iter()
+        .find(|lang| lang.battlenet.to_lowercase() == normalized)
+        .copied()
+    }
+
+    /// The language of the host session (`LC_ALL`, then `LANG`), or English if it is not supported.
+    pub fn from_environment() -> Language {
+        ["LC_ALL", "LANG"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|value| Language::parse(&value))
+        .unwrap_or(LANGUAGES[0])
+    }
+
+    /// Comma-separated list of the accepted `--lang` codes.
+    pub fn supported_codes() -> String {
+        LANGUAGES.iter().map(|lang| lang.battlenet).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// IANA timezone names and the Windows timezone each maps to.
+pub const TIMEZONES: &[(&str, &str)] = &[
+    ("UTC", "UTC"),
+    ("Europe/London", "GMT Standard Time"),
+    ("Europe/Berlin", "W. Europe Standard Time"),
+    ("Europe/Paris", "Romance Standard Time"),
+    ("Europe/Madrid", "Romance Standard Time"),
+    ("Europe/Rome", "W. Europe Standard Time"),
+    ("Europe/Warsaw", "Central European Standard Time"),
+    ("Europe/Moscow", "Russian Standard Time"),
+    ("America/New_York", "Eastern Standard Time"),
+    ("America/Chicago", "Central Standard Time"),
+    ("America/Denver", "Mountain Standard Time"),
+    ("America/Los_Angeles", "Pacific Standard Time"),
+    ("America/Mexico_City", "Central Standard Time (Mexico)"),
+    ("America/Sao_Paulo", "E. South America Standard Time"),
+    ("Asia/Shanghai", "China Standard Time"),
+    ("Asia/Taipei", "Taipei Standard Time"),
+    ("Asia/Hong_Kong", "China Standard Time"),
+    ("Asia/Singapore", "Singapore Standard Time"),
+    ("Asia/Tokyo", "Tokyo Standard Time"),
+    ("Asia/Seoul", "Korea Standard Time"),
+    ("Australia/Sydney", "AUS Eastern Standard Time"),
+];
+
+/// The host's IANA timezone, from `TZ` or the `/etc/localtime` symlink.
+pub fn host_timezone() -> Option<String> {
+    if let Ok(tz) = std::env::var("TZ") {
+        let tz = tz.trim_start_matches(':');
+        if !tz.is_empty() {
+            return Some(tz.to_string());
+        }
+    }
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_string_lossy();
+    target.split("zoneinfo/").nth(1).map(|name| name.to_string())
+}
+
+/// Windows timezone name for an IANA timezone, if it is in [`TIMEZONES`].
+pub fn windows_timezone(iana: &str) -> Option<&'static str> {
+    TIMEZONES.iter()
+    .find(|(name, _)| *name == iana)
+    .map(|(_, windows)| *windows)
+}
+
+impl Default for Language {
+    fn default() -> Language {
+        LANGUAGES[0]
+    }
+}
@@ -0,0 +1,591 @@
+//! Built-in launcher definitions and per-launcher data tables.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::language::Language;
+use crate::minecraft::check_java_runtime;
+use crate::prefix::PrefixArch;
+
+/// Group a launcher is listed under in the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LauncherCategory {
+    /// Storefronts for games from many publishers
+    Store,
+    /// Launchers for one publisher's or one game's titles
+    GameSpecific,
+}
+
+impl LauncherCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LauncherCategory::Store => "Stores",
+            LauncherCategory::GameSpecific => "Game-specific",
+        }
+    }
+}
+
+/// Compatibility notes for a launcher, shown before it is installed and by `notes <launcher>`.
+#[derive(Clone, Debug)]
+pub struct LauncherNotes {
+    /// Known problems under wine and how to get around them
+    pub known_issues: &'static [&'static str],
+    /// Whether the anti-cheat of the launcher's games works on Linux
+    pub anti_cheat: &'static str,
+    /// Runner versions known to work
+    pub recommended_runner: &'static str,
+    /// What to do once the install has finished
+    pub after_install: &'static [&'static str],
+}
+
+/// Static description of a launcher this tool can install.
+#[derive(Clone, Debug)]
+pub struct LauncherInfo {
+    /// Short ID used on the command line and in file names
+    pub id: &'static str,
+    /// Name shown to the user
+    pub name: &'static str,
+    /// Directory name under the Games root
+    pub dir_name: &'static str,
+    /// Where the installer may put the launcher, relative to `drive_c`, most likely first.
+    /// A `*` component matches any directory, e.g. the user name in `users/*/AppData`.
+    pub install_dirs: &'static [&'static str],
+    /// Executable to add to Steam
+    pub exe_name: &'static str,
+    pub installer_url: &'static str,
+    pub installer_file_name: &'static str,
+    /// Arguments for an unattended install; `{lang}` stands for the install language's
+    /// Blizzard locale code, see `silent_install_args`
+    pub silent_args: &'static [&'static str],
+    /// Approximate size of the installed launcher, in MiB
+    pub install_size_mb: u64,
+    /// Files or directories under the Windows user profile that the launcher writes every time
+    /// it starts, checked by the post-install smoke test
+    pub startup_files: &'static [&'static str],
+    /// winetricks verbs installed into the prefix before the launcher's installer runs
+    pub winetricks_verbs: &'static [&'static str],
+    /// Runtimes winetricks doesn't provide, installed into the prefix after the winetricks verbs
+    pub runtimes: &'static [Runtime],
+    /// Runtime the launcher's own installer unpacks next to it, checked once it is installed
+    pub bundled_runtime: Option<BundledRuntime>,
+    /// DLL overrides a new prefix for the launcher starts with; empty for the usual defaults,
+    /// which disable .NET and the browser component
+    pub dll_overrides: &'static [(&'static str, &'static str)],
+    /// Prefix architecture the launcher runs in
+    pub arch: PrefixArch,
+    /// URI schemes the launcher handles, e.g. `battlenet` for `battlenet://` links
+    pub uri_schemes: &'static [&'static str],
+    /// Games the launcher can be told to download right after it is installed, as (name,
+    /// product code for Battle.net's `--install --game <code>`)
+    pub games: &'static [(&'static str, &'static str)],
+    /// Whether the launcher's updater writes to the directory it was installed to rather than
+    /// the one it runs from, so the install links that directory to the copy
+    pub updates_install_path: bool,
+    /// AutoHotkey answer script that clicks through the installer when it has to run
+    /// interactively; `<id>.ahk` in the automation config directory overrides it
+    pub automation_script: Option<&'static str>,
+    pub category: LauncherCategory,
+    pub notes: LauncherNotes,
+}
+
+impl LauncherInfo {
+    /// The arguments for an unattended install in `language`.
+    pub fn silent_install_args(&self, language: Language) -> Vec<String> {
+        self.silent_args.iter().map(|arg| arg.replace("{lang}", language.battlenet)).collect()
+    }
+
+    /// The first of the launcher's install directories that exists in the prefix.
+    pub fn find_install_dir(&self, prefix: &Path) -> Option<PathBuf> {
+        self.install_dir_candidates(prefix).into_iter().find(|dir| dir.is_dir())
+    }
+
+    /// The launcher's install directories in the prefix, with `*` components expanded to the
+    /// directories that exist there, in `install_dirs` order.
+    pub fn install_dir_candidates(&self, prefix: &Path) -> Vec<PathBuf> {
+        let drive_c = prefix.join("drive_c");
+        self.install_dirs.iter().flat_map(|dir| {
+            Path::new(dir).components().fold(vec![drive_c.clone()], |paths, component| {
+                if component.as_os_str() != "*" {
+                    return paths.into_iter().map(|path| path.join(component)).collect();
+                }
+                let mut expanded: Vec<PathBuf> = paths.iter()
+                .filter_map(|path| fs::read_dir(path).ok())
+                .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+                .filter(|path| path.is_dir())
+                .collect();
+                expanded.sort();
+                expanded
+            })
+        }).collect()
+    }
+
+    /// Whether `dir` ends with one of the launcher's install directories.
+    pub fn is_install_dir(&self, dir: &Path) -> bool {
+        self.install_dirs.iter().any(|install_dir| {
+            let pattern: Vec<_> = Path::new(install_dir).components().collect();
+            let tail: Vec<_> = dir.components().rev().take(pattern.len()).collect();
+            tail.len() == pattern.len() && pattern.iter().rev().zip(&tail)
+            .all(|(pattern, component)| pattern.as_os_str() == "*" || pattern == component)
+        })
+    }
+}
+
+/// A runtime a launcher needs that winetricks doesn't provide, installed with its own installer.
+#[derive(Clone, Debug)]
+pub struct Runtime {
+    pub name: &'static str,
+    pub installer_url: &'static str,
+    pub installer_file_name: &'static str,
+    /// Arguments for an unattended install
+    pub silent_args: &'static [&'static str],
+    /// Directory relative to `drive_c` that exists once the runtime is installed
+    pub installed_dir: &'static str,
+}
+
+/// A runtime a launcher's installer unpacks along with the launcher, e.g. the Minecraft
+/// Launcher's Java.
+#[derive(Clone, Debug)]
+pub struct BundledRuntime {
+    pub name: &'static str,
+    /// Checks the runtime in the install directory, returning its directory or what is wrong with it
+    pub check: fn(&Path) -> Result<PathBuf, String>,
+}
+
+/// The Microsoft Edge WebView2 runtime, which launchers built on it render their windows with.
+/// The standalone installer, since the small bootstrapper downloads the runtime itself and
+/// gives up quietly when that fails under wine.
+pub const WEBVIEW2: Runtime = Runtime {
+    name: "Microsoft Edge WebView2",
+    installer_url: "https://go.microsoft.com/fwlink/?linkid=2124701",
+    installer_file_name: "MicrosoftEdgeWebView2RuntimeInstallerX64.exe",
+    silent_args: &["/silent", "/install"],
+    installed_dir: "Program Files (x86)/Microsoft/EdgeWebView/Application",
+};
+
+// Shared by both HoYoPlay editions
+const HOYOPLAY_NOTES: LauncherNotes = LauncherNotes {
+    known_issues: &[
+        "The installer always installs to the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+        "Windows have wine's title bar and borders until post-setup turns window decorations off.",
+    ],
+    anti_cheat: "Genshin Impact, Honkai: Star Rail and Zenless Zone Zero currently run under Proton. HoYoverse does not support Linux, so a game update can break them until Proton catches up.",
+    recommended_runner: "GE-Proton or Proton Experimental",
+    after_install: &[
+        "Launch the launcher once from Steam, or use 'Pre-create compatdata prefix', before running 'Run HoYoPlay Post-Setup'.",
+        "This makes sure all the files and settings post-setup changes exist.",
+    ],
+};
+
+// Battle.net's installer stops on its language and location page without a silent mode
+const BATTLENET_AUTOMATION: &str = r#"#NoTrayIcon
+SetTitleMatchMode, 2
+WinWait, Battle.net Setup, , 600
+WinActivate
+Sleep, 2000
+; "Continue" on the language/location page
+Send, {Enter}
+WinWaitClose, Battle.net Setup, , 1800
+ExitApp
+"#;
+
+const BUILTIN_LAUNCHERS: &[LauncherInfo] = &[
+    LauncherInfo {
+        id: "battlenet",
+        name: "Battle.net",
+        dir_name: "Battle.net",
+        install_dirs: &["Program Files/Battle.net", "Program Files (x86)/Battle.net", "Games/Battle.net", "Blizzard/Battle.net"],
+        exe_name: "Battle.net.exe",
+        installer_url: "https://downloader.battle.net/download/getInstaller?os=win&installer=Battle.net-Setup.exe",
+        installer_file_name: "Battle.net-Setup.exe",
+        silent_args: &["--lang={lang}", "--installpath=C:\\Program Files (x86)\\Battle.net"],
+        install_size_mb: 700,
+        startup_files: &["AppData/Roaming/Battle.net/Battle.net.config", "AppData/Local/Battle.net/Logs"],
+        winetricks_verbs: &[],
+        runtimes: &[],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &["battlenet", "blizzard"],
+        games: BATTLENET_GAMES,
+        updates_install_path: false,
+        automation_script: Some(BATTLENET_AUTOMATION),
+        category: LauncherCategory::Store,
+        notes: LauncherNotes {
+            known_issues: &[
+                "The login window stays blank unless mscoree and mshtml are disabled, which the default DLL overrides do.",
+                "A black window or a login page that never loads usually means the wine version is too old; Battle.net follows recent wine closely.",
+                "A login window that stays blank, freezes or keeps reloading is usually fixed by `battlenet-fix-login` (also in the menu's utilities).",
+                "Downloads that hang at \"Updating Battle.net Update Agent\" continue after restarting the launcher.",
+            ],
+            anti_cheat: "Most Blizzard games run under wine and Proton. Call of Duty titles that use Ricochet do not start on Linux.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[],
+        },
+    },
+    LauncherInfo {
+        id: "ea-app",
+        name: "EA app",
+        dir_name: "EA app",
+        install_dirs: &["Program Files/Electronic Arts/EA Desktop/EA Desktop", "Program Files (x86)/Electronic Arts/EA Desktop/EA Desktop"],
+        exe_name: "EADesktop.exe",
+        installer_url: "https://origin-a.akamaihd.net/EA-Desktop-Client-Download/installer-releases/EAappInstaller.exe",
+        installer_file_name: "EAappInstaller.exe",
+        // A WiX bundle: no UI, and no reboot once the EA background service is installed
+        silent_args: &["/quiet", "/norestart"],
+        install_size_mb: 1100,
+        startup_files: &["AppData/Local/Electronic Arts/EA Desktop/Logs"],
+        winetricks_verbs: &[],
+        runtimes: &[],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &["origin2", "link2ea"],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::Store,
+        notes: LauncherNotes {
+            known_issues: &[
+                "The installer always installs to the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+                "A blank or black window after logging in usually goes away after restarting the EA app once it has finished updating itself.",
+                "Games bought on Steam that need the EA app start it through Steam's own Proton prefix, not this one.",
+            ],
+            anti_cheat: "Games using EA Javelin anti-cheat (Battlefield 2042, Battlefield 6, EA Sports FC) do not start on Linux. Most other EA games run under wine and Proton.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[],
+        },
+    },
+    LauncherInfo {
+        id: "gog-galaxy",
+        name: "GOG Galaxy",
+        dir_name: "GOG Galaxy",
+        install_dirs: &["Program Files (x86)/GOG Galaxy", "Program Files/GOG Galaxy"],
+        exe_name: "GalaxyClient.exe",
+        installer_url: "https://webinstallers.gog-statics.com/download/GOG_Galaxy_2.0.exe",
+        installer_file_name: "GOG_Galaxy_2.0.exe",
+        // Inno Setup: no wizard, no message boxes, no reboot
+        silent_args: &["/VERYSILENT", "/SUPPRESSMSGBOXES", "/NORESTART"],
+        install_size_mb: 900,
+        startup_files: &["AppData/Local/GOG.com/Galaxy"],
+        winetricks_verbs: &["dotnet48"],
+        runtimes: &[],
+        bundled_runtime: None,
+        // The .NET Framework from winetricks has to be loaded instead of wine-mono
+        dll_overrides: &[("mscoree", "native"), ("mshtml", "")],
+        arch: PrefixArch::Win64,
+        uri_schemes: &["goggalaxy"],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::Store,
+        notes: LauncherNotes {
+            known_issues: &[
+                "Needs the .NET Framework 4.8: winetricks installs `dotnet48` into the prefix before the installer runs, which takes several minutes.",
+                "The installer always installs to the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+                "A blank window after logging in usually goes away after restarting GOG Galaxy once it has finished updating itself.",
+            ],
+            anti_cheat: "GOG games are DRM-free and rarely use anti-cheat; most run under wine and Proton.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[],
+        },
+    },
+    LauncherInfo {
+        id: "hoyoplay",
+        name: "HoYoPlay",
+        dir_name: "HoYoPlay",
+        install_dirs: &["Program Files/HoYoPlay"],
+        exe_name: "HoYoPlay.exe",
+        installer_url: "https://download-porter.hoyoverse.com/download-porter/2025/02/21/VYTpXlbWo8_1.4.5.222_1_0_hyp_hoyoverse_prod_202502081529_XFGRLkBk.exe?trace_key=HoYoPlay_install_ua_5ca9c7368584",
+        installer_file_name: "HoYoPlay-Setup.exe",
+        silent_args: &[],
+        install_size_mb: 450,
+        startup_files: &["AppData/Roaming/Cognosphere/HYP/1_0"],
+        winetricks_verbs: &[],
+        runtimes: &[],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &[],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::GameSpecific,
+        notes: HOYOPLAY_NOTES,
+    },
+    LauncherInfo {
+        id: "hoyoplay-cn",
+        name: "miHoYo Launcher",
+        dir_name: "miHoYo Launcher",
+        install_dirs: &["Program Files/miHoYo Launcher"],
+        exe_name: "launcher.exe",
+        installer_url: "https://ys-api.mihoyo.com/event/download_porter/link/hyp/mihoyo/pc_default",
+        installer_file_name: "miHoYoLauncher-Setup.exe",
+        silent_args: &[],
+        install_size_mb: 450,
+        startup_files: &["AppData/Roaming/miHoYo/HYP/1_0"],
+        winetricks_verbs: &[],
+        runtimes: &[],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &[],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::GameSpecific,
+        notes: LauncherNotes {
+            known_issues: &[
+                "Only serves the China servers; accounts from other regions need HoYoPlay.",
+                HOYOPLAY_NOTES.known_issues[0],
+                HOYOPLAY_NOTES.known_issues[1],
+            ],
+            ..HOYOPLAY_NOTES
+        },
+    },
+    LauncherInfo {
+        id: "minecraft",
+        name: "Minecraft Launcher",
+        dir_name: "Minecraft Launcher",
+        install_dirs: &["Program Files (x86)/Minecraft Launcher", "Program Files/Minecraft Launcher"],
+        exe_name: "MinecraftLauncher.exe",
+        installer_url: "https://launcher.mojang.com/download/MinecraftInstaller.msi",
+        installer_file_name: "MinecraftInstaller.msi",
+        // Windows Installer: no UI
+        silent_args: &["/qn"],
+        install_size_mb: 350,
+        startup_files: &["AppData/Roaming/.minecraft/launcher_log.txt", "AppData/Roaming/.minecraft"],
+        winetricks_verbs: &[],
+        runtimes: &[],
+        bundled_runtime: Some(BundledRuntime { name: "Java", check: check_java_runtime }),
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &[],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::GameSpecific,
+        notes: LauncherNotes {
+            known_issues: &[
+                "Installs Java Edition and its older versions. Bedrock Edition for Windows is a Microsoft Store app and does not run under wine.",
+                "The installer unpacks a Java runtime next to the launcher; the install checks that it is complete. If it isn't, reinstall or let the launcher download Java on its first start.",
+                "The installer always installs to the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+            ],
+            anti_cheat: "Minecraft has no anti-cheat; servers with their own checks work as on Windows.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[
+                "Log in with your Microsoft account in the launcher; Java Edition also has a native Linux launcher if wine gives you trouble.",
+            ],
+        },
+    },
+    LauncherInfo {
+        id: "paradox",
+        name: "Paradox Launcher",
+        dir_name: "Paradox Launcher",
+        install_dirs: &["users/*/AppData/Local/Programs/Paradox Interactive", "Program Files (x86)/Paradox Interactive", "Program Files/Paradox Interactive"],
+        // Starts the newest of the versioned launcher builds next to it
+        exe_name: "bootstrapper-v2.exe",
+        installer_url: "https://launcher.paradoxinteractive.com/v2/paradox-launcher-installer-2022_10.msi",
+        installer_file_name: "paradox-launcher-installer.msi",
+        // Windows Installer: no UI
+        silent_args: &["/qn"],
+        install_size_mb: 400,
+        startup_files: &["AppData/Roaming/Paradox Interactive/launcher-v2"],
+        winetricks_verbs: &[],
+        runtimes: &[WEBVIEW2],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &[],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::Store,
+        notes: LauncherNotes {
+            known_issues: &[
+                "Needs the Microsoft Edge WebView2 runtime, which is installed into the prefix before the launcher; its installer is about 170 MB.",
+                "The launcher installs into the Windows user profile on the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+                "A blank launcher window usually means WebView2 is missing or the wine version is too old for it.",
+            ],
+            anti_cheat: "Paradox games do not use anti-cheat and run under wine and Proton. Most also have native Linux versions on Steam.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[
+                "Games bought outside Steam are downloaded from the launcher's library once you log in with your Paradox account.",
+            ],
+        },
+    },
+    LauncherInfo {
+        id: "plarium-play",
+        name: "Plarium Play",
+        dir_name: "Plarium Play",
+        install_dirs: &["users/*/AppData/Local/PlariumPlay"],
+        exe_name: "PlariumPlay.exe",
+        installer_url: "https://plarium.com/services/api/downloads/desktop?lid=1&arc=64&os=windows",
+        installer_file_name: "PlariumPlaySetup.exe",
+        // NSIS
+        silent_args: &["/S"],
+        install_size_mb: 300,
+        startup_files: &["AppData/Local/PlariumPlay"],
+        winetricks_verbs: &[],
+        runtimes: &[],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &[],
+        games: &[],
+        updates_install_path: true,
+        automation_script: None,
+        category: LauncherCategory::GameSpecific,
+        notes: LauncherNotes {
+            known_issues: &[
+                "The launcher installs into the Windows user profile on the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+                "It updates itself on its first start, into the directory it was installed to rather than the one it runs from. The install links that directory to the copy so the update lands there.",
+            ],
+            anti_cheat: "Most Plarium games (RAID: Shadow Legends, Mech Arena) run under wine and Proton; a game update can occasionally break them for a while.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[
+                "Start the launcher once and let it finish updating before logging in or installing games.",
+            ],
+        },
+    },
+    LauncherInfo {
+        id: "ubisoft-connect",
+        name: "Ubisoft Connect",
+        dir_name: "Ubisoft Connect",
+        install_dirs: &["Program Files (x86)/Ubisoft/Ubisoft Game Launcher", "Program Files/Ubisoft/Ubisoft Game Launcher"],
+        exe_name: "UbisoftConnect.exe",
+        installer_url: "https://static3.cdn.ubi.com/orbit/launcher_installer/UbisoftConnectInstaller.exe",
+        installer_file_name: "UbisoftConnectInstaller.exe",
+        // NSIS
+        silent_args: &["/S"],
+        install_size_mb: 500,
+        startup_files: &["AppData/Local/Ubisoft Game Launcher"],
+        // Without the Visual C++ runtime the launcher crashes on start; without the core fonts
+        // its text renders as boxes
+        winetricks_verbs: &["vcrun2019", "corefonts"],
+        runtimes: &[],
+        bundled_runtime: None,
+        dll_overrides: &[],
+        arch: PrefixArch::Win64,
+        uri_schemes: &["uplay"],
+        games: &[],
+        updates_install_path: false,
+        automation_script: None,
+        category: LauncherCategory::Store,
+        notes: LauncherNotes {
+            known_issues: &[
+                "Needs the Visual C++ 2019 runtime and the core fonts: winetricks installs `vcrun2019` and `corefonts` into the prefix before the installer runs.",
+                "The installer always installs to the prefix's C: drive; the files are copied to the chosen directory afterwards.",
+                "The launcher starts offline when it can't reach Ubisoft's servers on the first try; restarting it usually helps.",
+            ],
+            anti_cheat: "Most Ubisoft games run under wine and Proton. Games using BattlEye or EasyAntiCheat without Linux support enabled, such as Rainbow Six Siege, do not.",
+            recommended_runner: "wine 9 or newer, or a recent Proton or GE-Proton",
+            after_install: &[],
+        },
+    },
+];
+
+/// The set of launchers this tool knows how to install.
+pub struct LauncherRegistry {
+    launchers: Vec<LauncherInfo>,
+}
+
+impl LauncherRegistry {
+    /// The launchers shipped with this tool.
+    pub fn builtin() -> LauncherRegistry {
+        LauncherRegistry {
+            launchers: BUILTIN_LAUNCHERS.to_vec(),
+        }
+    }
+
+    pub fn all(&self) -> &[LauncherInfo] {
+        &self.launchers
+    }
+
+    /// Look up a launcher by its ID.
+    pub fn get(&self, id: &str) -> Option<&LauncherInfo> {
+        self.launchers.iter().find(|launcher| launcher.id == id)
+    }
+
+    /// Look up a launcher by its ID or, ignoring case, its name, e.g. `battle.net`.
+    pub fn find(&self, name: &str) -> Option<&LauncherInfo> {
+        self.get(name).or_else(|| self.launchers.iter().find(|launcher| launcher.name.eq_ignore_ascii_case(name)))
+    }
+}
+
+/// Battle.net games that can be queued for install after the launcher is set up (display name, product code)
+pub const BATTLENET_GAMES: &[(&str, &str)] = &[
+    ("World of Warcraft", "wow"),
+    ("World of Warcraft Classic", "wow_classic"),
+    ("Overwatch 2", "pro"),
+    ("Diablo IV", "fenris"),
+    ("Diablo III", "diablo3"),
+    ("Diablo II: Resurrected", "osi"),
+    ("Hearthstone", "hs_beta"),
+    ("StarCraft II", "s2"),
+    ("Heroes of the Storm", "heroes"),
+];
+
+/// Which HoYoPlay build to install: the global HoYoPlay or the China-only miHoYo Launcher.
+#[derive(Clone, Copy, PartialEq)]
+pub enum HoyoplayEdition {
+    Global,
+    China,
+}
+
+impl HoyoplayEdition {
+    /// The edition a launcher ID belongs to, if it is a HoYoPlay edition.
+    pub fn from_launcher_id(id: &str) -> Option<HoyoplayEdition> {
+        match id {
+            "hoyoplay" => Some(HoyoplayEdition::Global),
+            "hoyoplay-cn" => Some(HoyoplayEdition::China),
+            _ => None,
+        }
+    }
+
+    /// The edition installed in a prefix, if exactly one of them is.
+    pub fn detect(prefix: &Path) -> Option<HoyoplayEdition> {
+        let installed: Vec<HoyoplayEdition> = [HoyoplayEdition::Global, HoyoplayEdition::China].into_iter()
+        .filter(|edition| edition.launcher().find_install_dir(prefix).is_some())
+        .collect();
+        match installed.as_slice() {
+            [edition] => Some(*edition),
+            _ => None,
+        }
+    }
+
+    /// The registry entry for this edition.
+    pub fn launcher(self) -> LauncherInfo {
+        let id = match self {
+            HoyoplayEdition::Global => "hoyoplay",
+            HoyoplayEdition::China => "hoyoplay-cn",
+        };
+        LauncherRegistry::builtin().get(id).cloned().expect("HoYoPlay editions are built-in launchers")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn silent_install_args_fill_in_the_language() {
+        let registry = LauncherRegistry::builtin();
+        let german = Language::parse("deDE").unwrap();
+        assert_eq!(registry.get("battlenet").unwrap().silent_install_args(german),
+                   ["--lang=deDE", "--installpath=C:\\Program Files (x86)\\Battle.net"]);
+        assert_eq!(registry.get("gog-galaxy").unwrap().silent_install_args(german), ["/VERYSILENT", "/SUPPRESSMSGBOXES", "/NORESTART"]);
+        assert_eq!(registry.get("paradox").unwrap().silent_install_args(german), ["/qn"]);
+        assert!(registry.get("hoyoplay").unwrap().silent_install_args(german).is_empty());
+    }
+
+    #[test]
+    fn matches_install_dirs_with_wildcards() {
+        let registry = LauncherRegistry::builtin();
+        let plarium = registry.get("plarium-play").unwrap();
+        assert!(plarium.is_install_dir(Path::new("/prefix/drive_c/users/steamuser/AppData/Local/PlariumPlay")));
+        assert!(!plarium.is_install_dir(Path::new("/prefix/drive_c/users/steamuser/AppData/Roaming/PlariumPlay")));
+        let battlenet = registry.get("battlenet").unwrap();
+        assert!(battlenet.is_install_dir(Path::new("/prefix/drive_c/Program Files (x86)/Battle.net")));
+        assert!(!battlenet.is_install_dir(Path::new("/home/user/Battle.net")));
+    }
+}
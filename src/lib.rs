@@ -0,0 +1,116 @@
+//! Install Windows game launchers (Battle.net, HoYoPlay) with wine and prepare them for Steam.
+//!
+//! The `game-launcher-installer` binary is an interactive frontend over this library.
+//! Other tools can use the same pieces directly:
+//!
+//! - [`LauncherRegistry`] lists the launchers that can be installed.
+//! - [`Installer`] downloads a launcher's installer and runs it inside a prefix.
+//! - [`PrefixManager`] initializes prefixes, watches their processes and applies tweaks.
+//! - [`WineSession`] starts, waits for and stops the wineserver of one prefix.
+//! - [`SteamIntegration`] finds Steam libraries and the compatdata prefixes of shortcuts.
+//!
+//! Functions report progress on stdout and, when enabled with
+//! [`open_progress_sink`], as JSON lines.
+//!
+//! The items re-exported at the crate root are the public API and follow semver. The modules
+//! behind them are public only for the binary, hidden from the documentation, and may change
+//! in any release.
+
+#[doc(hidden)]
+pub mod archive;
+#[doc(hidden)]
+pub mod audio;
+#[doc(hidden)]
+pub mod automation;
+#[doc(hidden)]
+pub mod battlenet;
+#[doc(hidden)]
+pub mod btrfs;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod dedupe;
+#[doc(hidden)]
+pub mod dlloverrides;
+#[doc(hidden)]
+pub mod download;
+#[doc(hidden)]
+pub mod fsutil;
+#[doc(hidden)]
+pub mod gc;
+#[doc(hidden)]
+pub mod hdr;
+#[doc(hidden)]
+pub mod health;
+#[doc(hidden)]
+pub mod hoyoplay;
+#[doc(hidden)]
+pub mod installer;
+#[doc(hidden)]
+pub mod journal;
+#[doc(hidden)]
+pub mod language;
+#[doc(hidden)]
+pub mod launchers;
+#[doc(hidden)]
+pub mod links;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod menus;
+#[doc(hidden)]
+pub mod minecraft;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod pe;
+#[doc(hidden)]
+pub mod picker;
+#[doc(hidden)]
+pub mod policy;
+#[doc(hidden)]
+pub mod prefix;
+#[doc(hidden)]
+pub mod protocol;
+#[doc(hidden)]
+pub mod purge;
+#[doc(hidden)]
+pub mod report;
+#[doc(hidden)]
+pub mod scan;
+#[doc(hidden)]
+pub mod session;
+#[doc(hidden)]
+pub mod shared;
+#[doc(hidden)]
+pub mod smoke;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod steam;
+#[doc(hidden)]
+pub mod systemd;
+#[doc(hidden)]
+pub mod template;
+#[doc(hidden)]
+pub mod troubleshoot;
+#[doc(hidden)]
+pub mod wayland;
+pub(crate) mod watchdog;
+#[doc(hidden)]
+pub mod wine;
+#[doc(hidden)]
+pub mod wrapper;
+#[doc(hidden)]
+pub mod xvfb;
+
+pub use config::{AppPaths, Config};
+pub use installer::Installer;
+pub use language::Language;
+pub use launchers::{HoyoplayEdition, LauncherInfo, LauncherRegistry};
+pub use output::open_progress_sink;
+pub use prefix::PrefixManager;
+pub use session::WineSession;
+pub use steam::SteamIntegration;
@@ -0,0 +1,190 @@
+//! Links from a prefix's `C:` drive to Linux directories, so file dialogs of Windows programs
+//! can reach them: the whole filesystem as `C:\Linux Root`, or single directories such as
+//! `~/Downloads`.
+//!
+//! Every symlink this tool creates, here or elsewhere, is recorded so `links audit` can find
+//! the ones a moved or deleted directory has broken.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use crate::fsutil::write_atomic;
+
+/// Name of the link to `/` that post-setup creates.
+pub const LINUX_ROOT: &str = "Linux Root";
+
+/// Where a link named `name` goes in the prefix.
+pub fn link_path(prefix: &Path, name: &str) -> PathBuf {
+    prefix.join("drive_c").join(name)
+}
+
+/// The name a link to `target` gets unless one is given: `Linux Root` for `/`, otherwise the
+/// directory's own name, e.g. `Downloads`.
+pub fn default_link_name(target: &Path) -> String {
+    match target.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => LINUX_ROOT.to_string(),
+    }
+}
+
+/// Link `C:\<name>` to `target`. Fails if something else already has the name; a link to the
+/// same target is left as it is. Returns the link's path.
+pub fn add_link(prefix: &Path, target: &Path, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!("`{}` is not a valid link name.", name));
+    }
+    if !target.is_dir() {
+        return Err(format!("{} is not a directory.", target.display()));
+    }
+    let link = link_path(prefix, name);
+    if fs::symlink_metadata(&link).is_ok() {
+        return match fs::read_link(&link) {
+            Ok(existing) if existing == target => Ok(link),
+            Ok(existing) => Err(format!("{} already links to {}.", link.display(), existing.display())),
+            Err(_) => Err(format!("{} already exists and is not a link.", link.display())),
+        };
+    }
+
+    symlink(target, &link)
+    .map_err(|e| format!("Failed to create symlink {}: {}", link.display(), e))?;
+    if let Some(home_dir) = dirs::home_dir() {
+        record_link(&home_dir, &link, target)?;
+    }
+    Ok(link)
+}
+
+/// Replace the directory `dir` with a link to `target`, e.g. a launcher's original install
+/// directory with a link to the copy made of it, and record the link. A link that is already
+/// there is left as it is.
+pub fn replace_with_link(dir: &Path, target: &Path) -> Result<(), String> {
+    if fs::read_link(dir).is_ok_and(|existing| existing == target) {
+        return Ok(());
+    }
+    fs::remove_dir_all(dir)
+    .map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+    symlink(target, dir)
+    .map_err(|e| format!("Failed to create symlink {}: {}", dir.display(), e))?;
+    if let Some(home_dir) = dirs::home_dir() {
+        record_link(&home_dir, dir, target)?;
+    }
+    Ok(())
+}
+
+/// Remove the link `C:\<name>`. Only symlinks are removed, never a real directory.
+pub fn remove_link(prefix: &Path, name: &str) -> Result<(), String> {
+    let link = link_path(prefix, name);
+    match fs::symlink_metadata(&link) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(&link).map_err(|e| format!("Failed to remove {}: {}", link.display(), e))?;
+            match dirs::home_dir() {
+                Some(home_dir) => forget_links(&home_dir, &[link]),
+                None => Ok(()),
+            }
+        },
+        Ok(_) => Err(format!("{} is not a link.", link.display())),
+        Err(_) => Err(format!("{} does not exist.", link.display())),
+    }
+}
+
+/// Links on the prefix's `C:` drive to absolute Linux paths, as (name, target), sorted by name.
+pub fn list_links(prefix: &Path) -> Vec<(String, PathBuf)> {
+    let mut links: Vec<(String, PathBuf)> = fs::read_dir(prefix.join("drive_c")).into_iter().flatten().flatten()
+    .filter_map(|entry| {
+        let target = fs::read_link(entry.path()).ok().filter(|target| target.is_absolute())?;
+        Some((entry.file_name().to_string_lossy().to_string(), target))
+    })
+    .collect();
+    links.sort();
+    links
+}
+
+/// Where the symlinks this tool created are recorded, one `<link>\t<target>` line each.
+pub fn links_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(".local/state/game-launcher-installer/links")
+}
+
+/// Record a symlink this tool created, unless it is recorded already.
+pub fn record_link(home_dir: &Path, link: &Path, target: &Path) -> Result<(), String> {
+    if recorded_links(home_dir).iter().any(|(recorded, _)| recorded == link) {
+        return Ok(());
+    }
+    let path = links_path(home_dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)
+    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}\t{}", link.display(), target.display())
+    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The recorded symlinks, as (link, target).
+pub fn recorded_links(home_dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    fs::read_to_string(links_path(home_dir)).unwrap_or_default().lines()
+    .filter_map(|line| line.split_once('\t'))
+    .map(|(link, target)| (PathBuf::from(link), PathBuf::from(target)))
+    .collect()
+}
+
+/// Drop the given links from the record.
+pub fn forget_links(home_dir: &Path, links: &[PathBuf]) -> Result<(), String> {
+    let recorded = recorded_links(home_dir);
+    if !recorded.iter().any(|(link, _)| links.contains(link)) {
+        return Ok(());
+    }
+    let contents: String = recorded.iter()
+    .filter(|(link, _)| !links.contains(link))
+    .map(|(link, target)| format!("{}\t{}\n", link.display(), target.display()))
+    .collect();
+    let path = links_path(home_dir);
+    write_atomic(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// What `audit` found at a link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkStatus {
+    Ok,
+    /// The link is there but its target is gone, e.g. after the directory was moved
+    Broken,
+    /// The link itself was removed
+    Missing,
+    /// The link now points somewhere else than recorded
+    Replaced(PathBuf),
+}
+
+/// A symlink checked by `audit`.
+#[derive(Clone, Debug)]
+pub struct AuditedLink {
+    pub link: PathBuf,
+    pub target: PathBuf,
+    pub status: LinkStatus,
+}
+
+/// Check the recorded symlinks, plus the links on the `C:` drives of `prefixes` that were made
+/// before links were recorded, sorted by path.
+pub fn audit(home_dir: &Path, prefixes: &[PathBuf]) -> Vec<AuditedLink> {
+    let mut links = recorded_links(home_dir);
+    for prefix in prefixes {
+        for (name, target) in list_links(prefix) {
+            let link = link_path(prefix, &name);
+            if !links.iter().any(|(recorded, _)| *recorded == link) {
+                links.push((link, target));
+            }
+        }
+    }
+
+    let mut audited: Vec<AuditedLink> = links.into_iter().map(|(link, target)| {
+        let status = match fs::read_link(&link) {
+            Err(_) => LinkStatus::Missing,
+            Ok(current) if current != target => LinkStatus::Replaced(current),
+            Ok(_) if fs::metadata(&link).is_err() => LinkStatus::Broken,
+            Ok(_) => LinkStatus::Ok,
+        };
+        AuditedLink { link, target, status }
+    })
+    .collect();
+    audited.sort_by(|a, b| a.link.cmp(&b.link));
+    audited
+}
@@ -1,750 +1,4607 @@
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::str;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
-use reqwest::blocking::Client;
-use regex::Regex;
-use std::os::unix::fs::PermissionsExt;
-
-// ANSI color codes
-const COLOR_GREEN: &str = "\x1b[0;32m";
-const COLOR_YELLOW: &str = "\x1b[0;33m";
-const COLOR_RED: &str = "\x1b[0;31m";
-const COLOR_BLUE: &str = "\x1b[0;34m";
-const COLOR_RESET: &str = "\x1b[0m";
-
-// Struct to hold application paths
-struct AppPaths {
-    home_dir: PathBuf,
-    battlenet_installer: PathBuf,
-    hoyoplay_installer: PathBuf,
-}
-
-// Find system wine installation
-fn find_system_wine() -> Option<String> {
-    println!("{}Searching for system wine installation...{}", COLOR_BLUE, COLOR_RESET);
-
-    // Check common wine paths
-    let wine_paths = vec![
-        PathBuf::from("/usr/bin/wine"),
-        PathBuf::from("/usr/local/bin/wine"),
-    ];
-
-    // First try the PATH environment variable
-    if let Ok(output) = Command::new("which").arg("wine").output() {
-        if output.status.success() {
-            let path = str::from_utf8(&output.stdout).unwrap_or("").trim();
-            if !path.is_empty() {
-                let path_buf = PathBuf::from(path);
-                if path_buf.exists() {
-                    // Get wine version
-                    if let Ok(version_output) = Command::new(&path_buf).arg("--version").output() {
-                        if version_output.status.success() {
-                            let version = str::from_utf8(&version_output.stdout).unwrap_or("unknown").trim();
-                            println!("{}Found system wine at: {}{}", COLOR_GREEN, path, COLOR_RESET);
-                            println!("{}Wine version: {}{}", COLOR_GREEN, version, COLOR_RESET);
-                            return Some(path.to_string());
-                        }
-                    }
-                }
-            }
-        }
-    }
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use game_launcher_installer::archive::{create_archive, extract_archive, read_manifest, ArchiveManifest, ARCHIVE_EXTENSION};
+use game_launcher_installer::audio::AudioStack;
+use game_launcher_installer::automation::*;
+use game_launcher_installer::battlenet::{client_config_path, find_leftover_agent, fix_login, reset_agent, set_default_install_path};
+use game_launcher_installer::btrfs;
+use game_launcher_installer::config::parse_path_input;
+use game_launcher_installer::dedupe::{find_duplicates, link_duplicates};
+use game_launcher_installer::dlloverrides::DllOverrides;
+use game_launcher_installer::download::{remote_file_identity, remote_file_size};
+use game_launcher_installer::fsutil::{available_space, copy_changed_with_report, format_size, move_path, verify_copy, DeltaCheck};
+use game_launcher_installer::gc::{find_orphans, remove_orphans};
+use game_launcher_installer::hdr::{detect_hdr_session, has_hdr, with_hdr, without_hdr};
+use game_launcher_installer::health::check_health;
+use game_launcher_installer::hoyoplay::map_games_dir;
+use game_launcher_installer::installer::{is_url, print_environment, HYGIENE_SETS};
+use game_launcher_installer::journal::{self, close_journal, journal_path, last_run, open_journal};
+use game_launcher_installer::language::{host_timezone, windows_timezone, LANGUAGES, TIMEZONES};
+use game_launcher_installer::launchers::{BundledRuntime, LauncherCategory};
+use game_launcher_installer::links::{self, LinkStatus};
+use game_launcher_installer::manifest::{manifest_path, refresh_manifest};
+use game_launcher_installer::menus::{prefix_menu_files, remove_menu_files, MenuSnapshot};
+use game_launcher_installer::output::*;
+use game_launcher_installer::pe::PeInfo;
+use game_launcher_installer::prefix::{registry_backups, PrefixArch, Tweak};
+use game_launcher_installer::picker::FolderPicker;
+use game_launcher_installer::policy::{sha256_file, HashAllowlist, HashPolicy, InstallerPolicy};
+use game_launcher_installer::protocol::register_uri_handlers;
+use game_launcher_installer::purge::{find_artifacts, kept_compat_data_dirs, kept_install_dirs, remove_artifacts};
+use game_launcher_installer::report::{bound_log, create_report, gpu_info, markdown_report, redact, report_dir, system_info, DEBUG_CHANNELS, DEBUG_LOG_LIMIT};
+use game_launcher_installer::scan::{adopt, adopt_prefix, client_version, find_launcher_exes, scan};
+use game_launcher_installer::session::wineserver_for;
+use game_launcher_installer::shared::{publish, shared_launcher_dir};
+use game_launcher_installer::smoke::smoke_test;
+use game_launcher_installer::state::LauncherState;
+use game_launcher_installer::stats::DiskUsage;
+use game_launcher_installer::steam::{extract_appid, launch_shortcut, list_nonsteam_games, protontricks_available, steam_running, with_compat_data_path, Shortcut, SteamIntegrationLevel};
+use game_launcher_installer::systemd::{disable_auto_update, disable_healthcheck_timer, disable_reapply_timer, enable_auto_update, enable_healthcheck_timer, enable_reapply_timer, notify};
+use game_launcher_installer::template::{bake_template, clone_template, download_template, template_path, template_verbs, DEFAULT_VERBS};
+use game_launcher_installer::troubleshoot::{check_32bit, check_free_space, check_wine_version, diagnose, Step, TroubleshootContext};
+use game_launcher_installer::wayland::{find_xwayland, is_wayland_session, use_wayland_driver};
+use game_launcher_installer::wine::{find_in_path, find_runner, find_system_wine, host_is_arm64, probe_wine, wine_kind, wine_major_version, Runner, WineArgs, WineKind};
+use game_launcher_installer::wrapper::{desktop_entry, desktop_entry_path, LaunchWrapper, SandboxTool};
+use game_launcher_installer::xvfb::{display_available, VirtualDisplay};
+use game_launcher_installer::{
+    AppPaths, Config, HoyoplayEdition, Installer, Language, LauncherInfo, LauncherRegistry,
+    PrefixManager, SteamIntegration,
+};
+
+#[cfg(feature = "dbus-service")]
+mod dbus_service;
+
+#[cfg(feature = "web-ui")]
+mod web_ui;
+
+// Settings from the command line that apply to every install
+#[derive(Clone)]
+struct InstallOptions {
+    language: Language,
+    // Hide the host filesystem from the prefix: no Z: drive, no Linux Root link, no menu entries
+    sandbox: bool,
+    // Installer URL or file from --version, overriding the launcher's pin in the config
+    installer_version: Option<String>,
+    // Run installers missing from the hash allowlist even under the strict policy
+    allow_unlisted_installer: bool,
+    // Accept a remote manifest without a valid signature
+    allow_unsigned: bool,
+    // Launcher ID or name from --launcher, installed without showing the menu
+    launcher: Option<String>,
+    // X display of the Xvfb server started for --headless, used for silent installs
+    display: Option<String>,
+    // App ID or name of an installed Steam game whose Proton prefix the launcher goes into (--steam-game)
+    steam_game: Option<String>,
+    // Run a failing silent install again with wine debug channels on and bundle a report (--debug-wine)
+    debug_wine: bool,
+    // Start installs as their summary shows them, without offering to change anything (--yes)
+    yes: bool,
+    // Start the installed launcher and check it writes its files before calling the install done (--smoke-test)
+    smoke_test: bool,
+    // Print the environment of every installer command before it runs (--verbose)
+    verbose: bool,
+    // Start a new Steam shortcut once and wait for its Proton prefix, without asking (--verify-shortcut)
+    verify_shortcut: bool,
+}
 
-    // Then check common paths
-    for path in wine_paths {
-        if path.exists() {
-            if let Ok(metadata) = fs::metadata(&path) {
-                if metadata.is_file() {
-                    // Try to get wine version
-                    if let Ok(output) = Command::new(&path).arg("--version").output() {
-                        if output.status.success() {
-                            let version = str::from_utf8(&output.stdout).unwrap_or("unknown").trim();
-                            println!("{}Found system wine at: {}{}", COLOR_GREEN, path.display(), COLOR_RESET);
-                            println!("{}Wine version: {}{}", COLOR_GREEN, version, COLOR_RESET);
-                            return Some(path.to_string_lossy().to_string());
-                        }
-                    }
-                }
-            }
-        }
-    }
+// Set by --headless: prompts take their defaults instead of reading stdin
+static HEADLESS: AtomicBool = AtomicBool::new(false);
 
-    println!("{}Error: System wine installation not found.{}", COLOR_RED, COLOR_RESET);
-    println!("Please install wine using your distribution's package manager.");
-    println!("Example: sudo apt install wine    # For Debian/Ubuntu");
-    println!("         sudo dnf install wine    # For Fedora");
-    println!("         sudo pacman -S wine      # For Arch Linux");
+// Set by --controller and in gamescope sessions: every prompt is a spaced-out numbered list,
+// answered with a number, so a controller's buttons or Steam Input mappings are enough
+static CONTROLLER: AtomicBool = AtomicBool::new(false);
 
-    None
+fn headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
 }
 
+fn controller_prompts() -> bool {
+    CONTROLLER.load(Ordering::Relaxed)
+}
 
+// Read one line of input for a prompt, returning 0 at end of input. In headless mode nothing
+// is read, so every prompt takes its default answer.
+fn read_input(input: &mut String) -> usize {
+    if headless() {
+        println!();
+        return 0;
+    }
+    io::stdin().read_line(input).unwrap()
+}
 
-// Download a file
-fn download_file(url: &str, destination: &Path) -> Result<(), String> {
-    if destination.exists() {
-        println!("{}File already exists at {}. Skipping download.{}",
-                 COLOR_YELLOW, destination.display(), COLOR_RESET);
-        return Ok(());
+// The configured installer hash policy, relaxed to a warning by --allow-unlisted-installer
+fn hash_policy(options: &InstallOptions, home_dir: &Path) -> Result<HashPolicy, String> {
+    let mut policy = Config::load(home_dir).installer_policy;
+    if options.allow_unlisted_installer && policy == InstallerPolicy::Strict {
+        policy = InstallerPolicy::Normal;
     }
+    let mut allowlist = HashAllowlist::load(&HashAllowlist::path(home_dir))?;
+    // Only written by refresh-manifest once its signature checked out
+    allowlist.merge(HashAllowlist::load(&manifest_path(home_dir))?);
+    Ok(HashPolicy { policy, allowlist })
+}
+
+// The installer to use for a launcher: --version, then the config pin, then the current one (None)
+fn installer_source(options: &InstallOptions, app_paths: &AppPaths, launcher: &LauncherInfo) -> Option<String> {
+    options.installer_version.clone()
+    .or_else(|| Config::load(&app_paths.home_dir).pinned_installer(launcher.id).map(str::to_string))
+}
 
-    // Create parent directories if they don't exist
-    if let Some(parent) = destination.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+// Print the outcome of a menu operation and report it on the progress stream
+fn report_outcome(result: Result<(), String>) -> Result<(), String> {
+    match &result {
+        Ok(()) => {
+            println!("{}Operation completed successfully.{}", COLOR_GREEN, COLOR_RESET);
+            emit_progress("done", Some(100), "Operation completed successfully.");
+        },
+        Err(e) => {
+            println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET);
+            emit_progress("error", None, e);
+        }
     }
+    result
+}
 
-    println!("{}Downloading file from {}...{}", COLOR_BLUE, url, COLOR_RESET);
+// Ask whether to drive the interactive installer with its answer script
+fn prompt_use_automation(home_dir: &Path, launcher: &LauncherInfo) -> bool {
+    if find_automation_script(home_dir, launcher).is_none() {
+        return false;
+    }
 
-    // Try to use wget or curl if available
-    if Command::new("which").arg("curl").status().is_ok() {
-        let status = Command::new("curl")
-        .arg("-L")
-        .arg("-o")
-        .arg(destination)
-        .arg(url)
-        .status()
-        .map_err(|e| format!("Failed to execute curl: {}", e))?;
+    confirm("An automation script is available for this installer. Click through it automatically?")
+}
 
-        if status.success() {
-            println!("{}Download complete!{}", COLOR_GREEN, COLOR_RESET);
-            return Ok(());
-        } else {
-            return Err(format!("curl failed with exit code: {}", status));
-        }
-    } else if Command::new("which").arg("wget").status().is_ok() {
-        let status = Command::new("wget")
-        .arg("-O")
-        .arg(destination)
-        .arg(url)
-        .status()
-        .map_err(|e| format!("Failed to execute wget: {}", e))?;
+// Offer the RGB font smoothing tweak for a prefix, returning whether it was applied
+fn prompt_font_smoothing(prefix: &PrefixManager) -> bool {
+    confirm("Would you like to enable RGB font smoothing for sharper launcher text?") && enable_font_smoothing(prefix)
+}
 
-        if status.success() {
-            println!("{}Download complete!{}", COLOR_GREEN, COLOR_RESET);
-            return Ok(());
-        } else {
-            return Err(format!("wget failed with exit code: {}", status));
-        }
-    } else {
-        // Fallback to using reqwest
-        let client = Client::new();
-        let response = client.get(url)
-        .send()
-        .map_err(|e| format!("Failed to download file: {}", e))?;
+// Apply the RGB font smoothing tweak, returning whether it was applied
+fn enable_font_smoothing(prefix: &PrefixManager) -> bool {
+    match prefix.enable_font_smoothing() {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+            false
+        },
+    }
+}
 
-        let mut file = fs::File::create(destination)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+// Make the launcher the handler for its links, e.g. battlenet:// from "Play" buttons and invites
+fn register_launcher_uri_handlers(wine_path: &str, app_paths: &AppPaths, launcher: &LauncherInfo, exe: &Path) {
+    let prefix = app_paths.prefix_dir(launcher.dir_name);
+    if let Err(e) = register_uri_handlers(&app_paths.home_dir, launcher, wine_path, &prefix, exe) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+}
 
-        let content = response.bytes()
-        .map_err(|e| format!("Failed to read response bytes: {}", e))?;
+// What an install will do, shown on one summary screen before anything is fetched or changed
+struct InstallPlan {
+    runner: String,
+    sandbox: bool,
+    // Installer URL or file, None for the launcher's current one
+    source: Option<String>,
+    install_dir: PathBuf,
+    // Create the launcher directory as a btrfs subvolume; None when it exists or isn't on btrfs
+    subvolume: Option<bool>,
+    // Set aside a prefix of the wrong architecture and install into a fresh one; None when the
+    // prefix is fine. Keeping it cancels the install.
+    set_aside_prefix: Option<bool>,
+    originals: Originals,
+    font_smoothing: bool,
+    // Open the launcher's links from the browser; None when it has none
+    uri_handlers: Option<bool>,
+    steam: SteamIntegrationLevel,
+    // Start the new shortcut once through Steam to check that it gets a Proton prefix
+    verify_shortcut: bool,
+}
 
-        file.write_all(&content)
-        .map_err(|e| format!("Failed to write to file: {}", e))?;
+// What happens to the launcher's files in Wine's C: drive once they are copied out
+#[derive(Clone, Copy, PartialEq)]
+enum Originals {
+    Keep,
+    Delete,
+    // Replace them with a link to the copy, for launchers whose updater writes to where they were installed
+    Link,
+}
 
-        println!("{}Download complete!{}", COLOR_GREEN, COLOR_RESET);
-        return Ok(());
+// The download size of a launcher's installer: 0 when it is already on disk, None when the
+// server doesn't say
+fn download_size(launcher: &LauncherInfo, app_paths: &AppPaths, source: Option<&str>) -> Option<u64> {
+    match source {
+        Some(file) if !is_url(file) => Some(0),
+        Some(url) => remote_file_size(url),
+        None if app_paths.cache_dir(launcher.dir_name).join(launcher.installer_file_name).exists() => Some(0),
+        None => remote_file_size(launcher.installer_url),
     }
 }
 
-// Install Battle.net
-fn install_battlenet(wine_path: &str, app_paths: &AppPaths) -> Result<(), String> {
-    println!("{}Preparing to install Battle.net...{}", COLOR_BLUE, COLOR_RESET);
+// Show what the install will do, with the download and on-disk size and a check that the
+// Games root has room for it, and let each item be changed before it starts. With --yes or
+// --headless the plan is taken as shown, except that a prefix of the wrong architecture is
+// never set aside without asking. Errors if the user backs out.
+fn plan_install(wine_path: &str, launcher: &LauncherInfo, app_paths: &AppPaths, options: &InstallOptions) -> Result<InstallPlan, String> {
+    let unattended = options.yes || headless();
+    let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+    let wrong_arch = wrong_prefix_arch(app_paths, launcher);
+    let mut plan = InstallPlan {
+        runner: wine_path.to_string(),
+        sandbox: options.sandbox,
+        source: installer_source(options, app_paths, launcher),
+        install_dir: app_paths.drive_dir(launcher.dir_name),
+        subvolume: (!launcher_dir.exists() && btrfs::is_btrfs(&app_paths.games_root)).then_some(true),
+        set_aside_prefix: wrong_arch.map(|_| !unattended),
+        originals: if launcher.updates_install_path { Originals::Link } else { Originals::Keep },
+        font_smoothing: false,
+        uri_handlers: (!launcher.uri_schemes.is_empty()).then_some(false),
+        steam: Config::load(&app_paths.home_dir).steam_integration,
+        verify_shortcut: options.verify_shortcut,
+    };
+    let mut size = download_size(launcher, app_paths, plan.source.as_deref());
 
-    // Create battlenet directory if it doesn't exist
-    let battlenet_dir = app_paths.home_dir.join(".battlenet");
-    fs::create_dir_all(&battlenet_dir)
-    .map_err(|e| format!("Failed to create Battle.net directory: {}", e))?;
+    loop {
+        print_install_plan(&plan, size, launcher, app_paths);
+        if unattended {
+            break;
+        }
+        let mut choices = vec!["Start the install", "Change the runner"];
+        if plan.set_aside_prefix.is_some() {
+            choices.push("Change the old prefix");
+        }
+        choices.extend(["Change the prefix setup", "Change the installer", "Change the install directory"]);
+        if plan.subvolume.is_some() {
+            choices.push("Change the btrfs subvolume");
+        }
+        choices.push("Change the original files");
+        choices.push("Change the font smoothing");
+        if plan.uri_handlers.is_some() {
+            choices.push("Change the links");
+        }
+        choices.push("Change the Steam integration");
+        choices.push("Cancel");
+        match choices[prompt_option("Start the install, or change an item first?", &choices)] {
+            "Start the install" => break,
+            "Change the runner" => {
+                if let Some(runner) = prompt_install_runner() {
+                    plan.runner = runner;
+                }
+            },
+            "Change the old prefix" => {
+                plan.set_aside_prefix = Some(prompt_option("Old prefix:", &["Set it aside and install into a fresh prefix", "Keep it, which cancels the install"]) == 0);
+            },
+            "Change the prefix setup" => {
+                plan.sandbox = prompt_option("Prefix setup:", &["Normal", "Sandboxed: no Z: drive, no Linux Root link, no menu entries"]) == 1;
+            },
+            "Change the installer" => {
+                println!("Installer URL or file (leave empty for the current {} installer): ", launcher.name);
+                print!("> ");
+                io::stdout().flush().unwrap();
+                let mut input = String::new();
+                read_input(&mut input);
+                plan.source = match input.trim() {
+                    "" => None,
+                    url if is_url(url) => Some(url.to_string()),
+                    file => Some(parse_path_input(file, &app_paths.home_dir).unwrap_or_default().to_string_lossy().to_string()),
+                };
+                size = download_size(launcher, app_paths, plan.source.as_deref());
+            },
+            "Change the install directory" => {
+                println!("{}Where do you want to install {}?{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+                println!("Installation directory (Default: {}): ", app_paths.drive_dir(launcher.dir_name).display());
+                plan.install_dir = prompt_dir(&format!("{} installation directory", launcher.name), &plan.install_dir, &app_paths.home_dir)
+                .unwrap_or_else(|| app_paths.drive_dir(launcher.dir_name));
+            },
+            "Change the btrfs subvolume" => {
+                plan.subvolume = Some(prompt_option("Launcher directory:", &["A btrfs subvolume, so it can be snapshotted and rolled back", "A plain directory"]) == 0);
+            },
+            "Change the original files" => {
+                plan.originals = match prompt_option("Files left in Wine's C: drive after the copy:", &["Keep them", "Delete them", "Replace them with a link to the copy"]) {
+                    0 => Originals::Keep,
+                    1 => Originals::Delete,
+                    _ => Originals::Link,
+                };
+            },
+            "Change the font smoothing" => {
+                plan.font_smoothing = prompt_option("Font smoothing:", &["Leave it as it is", "RGB, for sharper launcher text"]) == 1;
+            },
+            "Change the links" => {
+                plan.uri_handlers = Some(prompt_option("Links:", &["Leave them to the browser", &format!("Open them with {}", launcher.name)]) == 1);
+            },
+            "Change the Steam integration" => {
+                plan.steam = match prompt_option("Steam:", &["Add a non-Steam shortcut", "Show how to add it by hand", "Leave Steam alone"]) {
+                    0 => SteamIntegrationLevel::Shortcut,
+                    1 => SteamIntegrationLevel::Instructions,
+                    _ => SteamIntegrationLevel::Off,
+                };
+                if plan.steam == SteamIntegrationLevel::Shortcut {
+                    plan.verify_shortcut = prompt_option("After adding it:", &["Leave it", "Start it once through Steam to check that it gets a Proton prefix"]) == 1;
+                }
+            },
+            _ => return Err("Installation cancelled.".to_string()),
+        }
+    }
 
-    let installer_url = "https://downloader.battle.net/download/getInstaller?os=win&installer=Battle.net-Setup.exe";
-    download_file(installer_url, &app_paths.battlenet_installer)?;
+    if let (Some(arch), Some(false)) = (wrong_arch, plan.set_aside_prefix) {
+        let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+        let mut refused = format!("{} cannot be installed into the {} prefix at {}.", launcher.name, arch.describe(), prefix_path.display());
+        if unattended {
+            refused.push_str(" --yes and --headless never set a prefix aside; run the install without them to choose.");
+        }
+        return Err(refused);
+    }
 
-    // Make installer executable
-    if let Err(e) = fs::set_permissions(&app_paths.battlenet_installer, fs::Permissions::from_mode(0o755)) {
-        println!("{}Warning: Could not make installer executable: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    // A runner picked here stays the launcher's runner, like one from `migrate-prefix`
+    if plan.runner != wine_path {
+        let state_path = app_paths.state_path(launcher.dir_name);
+        let mut state = LauncherState::load(&state_path);
+        state.runner = Some(plan.runner.clone());
+        state.runner_kind = wine_kind(Path::new(&plan.runner));
+        state.save(&state_path)?;
     }
+    Ok(plan)
+}
 
-    // Determine wine prefix
-    let wine_prefix = app_paths.home_dir.join(".wine");
+// A prefix of the wrong architecture, e.g. one made with WINEARCH=win32, makes the installer
+// fail with wine errors that don't say why, so the plan offers to set it aside
+fn wrong_prefix_arch(app_paths: &AppPaths, launcher: &LauncherInfo) -> Option<PrefixArch> {
+    let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+    let arch = PrefixManager::new(&prefix_path).arch().filter(|arch| *arch != launcher.arch)?;
+    println!("{}{} is a {} ({}) prefix, but {} needs a {} one.{}",
+             COLOR_YELLOW, prefix_path.display(), arch.describe(), arch.name(), launcher.name, launcher.arch.describe(), COLOR_RESET);
+    Some(arch)
+}
 
-    // Prompt for install directory
-    println!("{}Where do you want to install Battle.net?{}", COLOR_BLUE, COLOR_RESET);
-    let default_install_dir = app_paths.home_dir.join("Games/Battle.net").to_string_lossy().to_string();
-    println!("Installation directory (Default: {}): ", default_install_dir);
+// Set aside a prefix of the wrong architecture, so the install gets a fresh one. An adopted
+// prefix stays with the tool it came from and only the link goes.
+fn set_aside_prefix(app_paths: &AppPaths, launcher: &LauncherInfo) -> Result<(), String> {
+    let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+    let Some(arch) = PrefixManager::new(&prefix_path).arch() else { return Ok(()) };
+
+    if let Ok(target) = fs::read_link(&prefix_path) {
+        fs::remove_file(&prefix_path).map_err(|e| format!("Failed to remove {}: {}", prefix_path.display(), e))?;
+        links::forget_links(&app_paths.home_dir, std::slice::from_ref(&prefix_path))?;
+        let state_path = app_paths.state_path(launcher.dir_name);
+        let mut state = LauncherState::load(&state_path);
+        state.adopted_from = None;
+        state.save(&state_path)?;
+        println!("{}{} no longer uses the {} prefix at {}, which was left as it is.{}", COLOR_GREEN, launcher.name, arch.describe(), target.display(), COLOR_RESET);
+        return Ok(());
+    }
 
-    io::stdout().flush().unwrap();
-    let mut install_dir = String::new();
-    io::stdin().read_line(&mut install_dir).unwrap();
-    install_dir = install_dir.trim().to_string();
+    let aside = set_aside_path(&prefix_path, arch);
+    fs::rename(&prefix_path, &aside).map_err(|e| format!("Failed to move {} to {}: {}", prefix_path.display(), aside.display(), e))?;
+    println!("{}Moved the {} prefix to {}.{}", COLOR_GREEN, arch.describe(), aside.display(), COLOR_RESET);
+    Ok(())
+}
 
-    let install_dir = if install_dir.is_empty() {
-        default_install_dir
-    } else {
-        install_dir
-    };
-
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(&install_dir).map_err(|e| format!("Failed to create installation directory: {}", e))?;
-
-    println!("\n{}Running Battle.net installer in silent mode...{}", COLOR_BLUE, COLOR_RESET);
-
-    // Use the exact command that the user confirmed works
-    let mut command = Command::new(wine_path);
-    command
-    .env("WINEPREFIX", wine_prefix.to_string_lossy().to_string())
-    .env("WINEDEBUG", "-all")  // Suppress all Wine debug messages
-    .env("MANGOHUD", "0")      // Disable MangoHud
-    .env("DISABLE_MANGOHUD", "1") // Another way to disable MangoHud
-    .env("WINEDLLOVERRIDES", "mscoree,mshtml=") // Disable browser component
-    .env("DISPLAY", ":99")     // Use a fake display to hide GUI
-    .env("DISABLE_LAYER_AMD_SWITCHABLE_GRAPHICS_1", "1") // Disable AMD layers
-    .arg(&app_paths.battlenet_installer)
-    .arg("--lang=enUS")
-    .arg("--installpath=\"C:\\Program Files (x86)\\Battle.net\"")
-    .stdout(std::process::Stdio::null())
-    .stderr(std::process::Stdio::null());
+// `prefix.win32`, or `prefix.win32.2` and up if that is taken
+fn set_aside_path(prefix_path: &Path, arch: PrefixArch) -> PathBuf {
+    let name = format!("{}.{}", prefix_path.file_name().unwrap_or_default().to_string_lossy(), arch.name());
+    (1..).map(|n| if n == 1 { prefix_path.with_file_name(&name) } else { prefix_path.with_file_name(format!("{}.{}", name, n)) })
+    .find(|path| fs::symlink_metadata(path).is_err())
+    .expect("Some numbered name is free")
+}
 
-    let silent_status = command.status().map_err(|e| format!("Failed to execute wine command: {}", e))?;
-    let install_status = silent_status.code().unwrap_or(1);
+// Print the install summary, numbered in the order its items can be changed
+fn print_install_plan(plan: &InstallPlan, download_size: Option<u64>, launcher: &LauncherInfo, app_paths: &AppPaths) {
+    let prefix = app_paths.prefix_dir(launcher.dir_name);
+    let cached = app_paths.cache_dir(launcher.dir_name).join(launcher.installer_file_name);
+    let installer = match &plan.source {
+        Some(source) => source.clone(),
+        None if cached.exists() => cached.display().to_string(),
+        None => launcher.installer_url.to_string(),
+    };
+    let download = match download_size {
+        Some(0) => "none, the installer is already on disk".to_string(),
+        Some(size) => match Config::load(&app_paths.home_dir).download_connections {
+            1 => format_size(size),
+            connections => format!("{}, over up to {} connections", format_size(size), connections),
+        },
+        None => "unknown size (the server did not report one)".to_string(),
+    };
 
-    if install_status != 0 {
-        println!("{}Silent install failed. Falling back to interactive mode...{}",
-                 COLOR_RED, COLOR_RESET);
-        println!("\n{}Running Battle.net installer interactively...{}", COLOR_BLUE, COLOR_RESET);
-        println!("{}Please follow the installation instructions in the installer window.{}", COLOR_YELLOW, COLOR_RESET);
+    println!("\n{}=== Installing {} ==={}", COLOR_BLUE, launcher.name, COLOR_RESET);
+    println!("  Runner:       {}", plan.runner);
+    println!("  Prefix:       {} ({}{})", prefix.display(),
+             if prefix.join("system.reg").exists() && plan.set_aside_prefix != Some(true) { "existing" } else { "new" },
+             if plan.sandbox { ", sandboxed" } else { "" });
+    if let (Some(set_aside), Some(arch)) = (plan.set_aside_prefix, PrefixManager::new(&prefix).arch()) {
+        let old_prefix = match fs::read_link(&prefix) {
+            Ok(target) if set_aside => format!("stop using the {} prefix at {} and leave it as it is", arch.describe(), target.display()),
+            _ if set_aside => format!("move the {} prefix to {}", arch.describe(), set_aside_path(&prefix, arch).display()),
+            _ => format!("keep the {} prefix, which cancels the install", arch.describe()),
+        };
+        println!("  Old prefix:   {}", old_prefix);
+    }
+    println!("  Installer:    {}", installer);
+    println!("  Download:     {}", download);
+    println!("  Install dir:  {}", plan.install_dir.display());
+    if let Some(subvolume) = plan.subvolume {
+        println!("  Subvolume:    {}", if subvolume { "create the launcher directory as a btrfs subvolume" } else { "no" });
+    }
+    println!("  Originals:    {}", match plan.originals {
+        Originals::Keep => "keep the files in Wine's C: drive",
+        Originals::Delete => "delete the files in Wine's C: drive once they are copied",
+        Originals::Link => "replace the files in Wine's C: drive with a link to the copy",
+    });
+    println!("  Fonts:        {}", if plan.font_smoothing { "enable RGB font smoothing" } else { "no changes" });
+    if let Some(uri_handlers) = plan.uri_handlers {
+        let schemes: Vec<String> = launcher.uri_schemes.iter().map(|scheme| format!("{}://", scheme)).collect();
+        println!("  Links:        {}", if uri_handlers { format!("open {} links with {}", schemes.join(", "), launcher.name) } else { "no changes".to_string() });
+    }
+    let steam = match (plan.steam, Config::load(&app_paths.home_dir).steam_collection) {
+        (SteamIntegrationLevel::Shortcut, Some(collection)) => format!("add a non-Steam shortcut in the {} collection", collection),
+        (SteamIntegrationLevel::Shortcut, None) => "add a non-Steam shortcut".to_string(),
+        (SteamIntegrationLevel::Instructions, _) => "show how to add it by hand".to_string(),
+        (SteamIntegrationLevel::Off, _) => "no changes".to_string(),
+    };
+    let check = if plan.steam == SteamIntegrationLevel::Shortcut && plan.verify_shortcut { ", then start it once to check its Proton prefix" } else { "" };
+    println!("  Steam:        {}{}", steam, check);
+
+    let install_size = launcher.install_size_mb * 1024 * 1024;
+    println!("{}Estimated size on disk: {}{}", COLOR_BLUE, format_size(install_size), COLOR_RESET);
+    let required = install_size + download_size.unwrap_or(0);
+    if let Some(available) = available_space(&app_paths.games_root) {
+        if available < required {
+            println!("{}Warning: only {} free in {}, about {} is needed.{}",
+                     COLOR_RED, format_size(available), app_paths.games_root.display(), format_size(required), COLOR_RESET);
+        }
+    }
+    println!();
+}
 
-        // For interactive mode
-        let mut interactive_command = Command::new(wine_path);
-        interactive_command
-        .env("WINEPREFIX", wine_prefix.to_string_lossy().to_string())
-        .env("WINEDEBUG", "-all")  // Suppress all Wine debug messages
-        .env("MANGOHUD", "0")      // Disable MangoHud
-        .env("DISABLE_MANGOHUD", "1") // Another way to disable MangoHud
-        .env("DISABLE_LAYER_AMD_SWITCHABLE_GRAPHICS_1", "1") // Try to disable some AMD layers
-        .arg(&app_paths.battlenet_installer)
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null());
-
-        let interactive_status = interactive_command.status()
-        .map_err(|e| format!("Failed to execute wine command: {}", e))?
-        .code()
-        .unwrap_or(1);
-
-        if interactive_status != 0 {
-            println!("{}The Battle.net installer encountered an error (status code: {}).{}",
-                     COLOR_RED, interactive_status, COLOR_RESET);
-
-            print!("Would you like to continue anyway? (yes/no)\n> ");
+// Pick another runner for an install: system wine, a Proton build from Steam or a wine at a
+// given path. None keeps the current one.
+fn prompt_install_runner() -> Option<String> {
+    let proton = SteamIntegration::discover().ok();
+    let versions = proton.as_ref().map(|steam| steam.proton_versions()).unwrap_or_default();
+    let mut choices = vec!["Keep the current runner".to_string(), "System wine".to_string()];
+    choices.extend(versions.iter().cloned());
+    choices.push("Another wine (enter its path)".to_string());
+    let choices: Vec<&str> = choices.iter().map(String::as_str).collect();
+
+    let choice = prompt_option("Runner:", &choices);
+    let runner = match choice {
+        0 => return None,
+        1 => find_system_wine(),
+        _ if choice == choices.len() - 1 => {
+            println!("Path to wine: ");
+            print!("> ");
             io::stdout().flush().unwrap();
-
             let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+            read_input(&mut input);
+            let home_dir = dirs::home_dir().unwrap_or_default();
+            parse_path_input(&input, &home_dir).map(|path| path.to_string_lossy().to_string())
+        },
+        _ => proton.as_ref().and_then(|steam| steam.proton_wine(&versions[choice - 2])).map(|wine| wine.to_string_lossy().to_string()),
+    };
+    match runner.as_deref().map(probe_wine) {
+        Some(Ok(probed)) => {
+            println!("{}Using {} ({}).{}", COLOR_GREEN, probed.path, probed.version, COLOR_RESET);
+            Some(probed.path)
+        },
+        Some(Err(e)) => {
+            println!("{}{}{}", COLOR_RED, e, COLOR_RESET);
+            None
+        },
+        None => {
+            println!("{}No runner was found there.{}", COLOR_RED, COLOR_RESET);
+            None
+        },
+    }
+}
 
-            if input.trim().to_lowercase() != "yes" && input.trim().to_lowercase() != "y" {
-                return Err("Operation cancelled based on installer error.".to_string());
+// On btrfs, create a new launcher directory as a subvolume when the plan says so, and snapshot
+// an existing one before it is reinstalled so `rollback` can restore it
+fn prepare_launcher_dir(app_paths: &AppPaths, launcher: &LauncherInfo, subvolume: Option<bool>) {
+    let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+    if launcher_dir.exists() {
+        if btrfs::is_subvolume(&launcher_dir) {
+            if let Err(e) = btrfs::snapshot(&launcher_dir, &app_paths.snapshots_dir(launcher.dir_name)) {
+                println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
             }
         }
+        return;
     }
 
-    // Run wineserver -k with suppressed output
-    println!("{}Running wineserver -k to clean up...{}", COLOR_YELLOW, COLOR_RESET);
-    let _ = Command::new("wineserver")
-    .arg("-k")
-    .stdout(std::process::Stdio::null())
-    .stderr(std::process::Stdio::null())
-    .status();
-    thread::sleep(Duration::from_secs(1));
-
-    // Look for the actual Battle.net installation location
-    let possible_locations = [
-        app_paths.home_dir.join(".wine/drive_c/Program Files/Battle.net"),
-        app_paths.home_dir.join(".wine/drive_c/Program Files (x86)/Battle.net"),
-        app_paths.home_dir.join(".wine/drive_c/Games/Battle.net"),
-        app_paths.home_dir.join(".wine/drive_c/Blizzard/Battle.net"),
-    ];
-
-    let mut found_location = None;
-    for location in &possible_locations {
-        if location.exists() && location.is_dir() {
-            found_location = Some(location);
-            break;
+    if subvolume == Some(true) {
+        if let Err(e) = btrfs::create_subvolume(&launcher_dir) {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
         }
     }
+}
 
-    match found_location {
-        Some(source_path) => {
-            println!("{}Found Battle.net installation at: {}{}", COLOR_GREEN, source_path.display(), COLOR_RESET);
+// What to do when a silent install failed and nothing can show the interactive installer
+fn print_no_display_steps(launcher_id: &str) {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    println!("{}No usable display was found (DISPLAY={:?}, WAYLAND_DISPLAY={:?}), so the installer can't be shown.{}",
+             COLOR_RED, var("DISPLAY"), var("WAYLAND_DISPLAY"), COLOR_RESET);
+    println!("Next steps:");
+    println!("  - Run the install again from a desktop session, or over `ssh -X` so the window can be forwarded.");
+    println!("  - Run `doctor` to check the runner and the display setup.");
+    println!("  - Try another installer with `--launcher {} --version <url or file>`.", launcher_id);
+}
 
-            // Copy files from the Wine C: drive to the user's specified location
-            if source_path.to_string_lossy() != install_dir {
-                println!("{}Copying Battle.net files to {}...{}", COLOR_BLUE, install_dir, COLOR_RESET);
+// What the troubleshooter needs to know about an install
+fn troubleshoot_context(app_paths: &AppPaths, launcher: &LauncherInfo, installer: &Installer) -> TroubleshootContext {
+    let cache_dir = app_paths.cache_dir(launcher.dir_name);
+    let installer_path = installer.installer_path(launcher, &cache_dir);
+    TroubleshootContext {
+        wine_path: installer.wine_path().to_string(),
+        games_root: app_paths.games_root.clone(),
+        required_space: launcher.install_size_mb * 1024 * 1024,
+        // A pinned local installer is the user's own file and is never deleted
+        installer_path: installer_path.starts_with(&cache_dir).then_some(installer_path),
+        installer_display: installer.display().to_string(),
+    }
+}
 
-                // Copy all files recursively
-                match copy_dir_recursive(&source_path, &PathBuf::from(&install_dir)) {
-                    Ok(_) => {
-                        println!("{}Files copied successfully.{}", COLOR_GREEN, COLOR_RESET);
+// Run an install step. When it fails, offer the troubleshooter: it runs the checks relevant to
+// the step, applies the fixes the user accepts and runs only that step again. An Xvfb server
+// it starts is kept in `virtual_display` for the rest of the install. In headless mode nobody
+// can answer, so the error is returned as it is.
+fn run_step<T>(step: Step, context: &TroubleshootContext, virtual_display: &mut Option<VirtualDisplay>, mut run: impl FnMut() -> Result<T, String>) -> Result<T, String> {
+    loop {
+        let error = match run() {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        if headless() {
+            return Err(error);
+        }
+        println!("{}{} failed: {}{}", COLOR_RED, step.name(), error, COLOR_RESET);
+        if !confirm_or("Run the troubleshooter?", true) {
+            return Err(error);
+        }
 
-                        println!("{}Would you like to delete the original files in Wine's C: drive? (yes/no){}",
-                                 COLOR_YELLOW, COLOR_RESET);
+        let findings = diagnose(step, context);
+        for finding in &findings {
+            let color = if finding.ok { COLOR_GREEN } else { COLOR_YELLOW };
+            println!("{}{}: {}{}", color, finding.check, finding.message, COLOR_RESET);
+        }
+        if findings.iter().all(|finding| finding.ok) {
+            println!("{}No problem was found that explains the failure.{}", COLOR_YELLOW, COLOR_RESET);
+        }
+        for finding in &findings {
+            let Some(fix) = &finding.fix else { continue };
+            if !confirm_or(&format!("{}?", fix.describe()), !finding.ok) {
+                continue;
+            }
+            match fix.apply(context) {
+                Ok(started) => {
+                    if started.is_some() {
+                        *virtual_display = started;
+                    }
+                    println!("{}Done.{}", COLOR_GREEN, COLOR_RESET);
+                },
+                Err(e) => println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET),
+            }
+        }
 
-                        print!("> ");
-                        io::stdout().flush().unwrap();
+        // Defaults to no, so a closed stdin doesn't retry forever
+        if !confirm(&format!("Try {} again?", step.name().to_lowercase())) {
+            return Err(error);
+        }
+    }
+}
 
-                        let mut delete_choice = String::new();
-                        io::stdin().read_line(&mut delete_choice).unwrap();
+// Start a new launcher prefix from the prefix template, if one was baked
+fn clone_prefix_template(app_paths: &AppPaths, prefix: &PrefixManager) {
+    if let Err(e) = clone_template(&app_paths.home_dir, prefix.path()) {
+        println!("{}Warning: {}; the prefix is set up from scratch instead.{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+}
 
-                        if delete_choice.trim().to_lowercase() == "yes" || delete_choice.trim().to_lowercase() == "y" {
-                            match fs::remove_dir_all(source_path) {
-                                Ok(_) => println!("{}Original directory deleted.{}", COLOR_GREEN, COLOR_RESET),
-                                Err(e) => println!("{}Error deleting original directory: {}{}", COLOR_RED, e, COLOR_RESET)
-                            }
-                        }
-                    },
-                    Err(e) => println!("{}Error copying files: {}{}", COLOR_RED, e, COLOR_RESET)
-                }
-            }
-        },
-        None => {
-            println!("{}Warning: Could not find Battle.net installation directory in Wine C: drive.{}",
-                     COLOR_YELLOW, COLOR_RESET);
-            println!("{}Please check if Battle.net was installed correctly.{}", COLOR_YELLOW, COLOR_RESET);
+// Run a failed installer step again with wine debug channels on, and bundle its output with
+// system info into a report archive to attach to a bug report. Problems are only warned about,
+// so the install goes on as it would without --debug-wine.
+fn capture_wine_debug(app_paths: &AppPaths, installer: &Installer, launcher: &LauncherInfo, command: &mut Command) {
+    let channels = std::env::var("WINEDEBUG").ok().filter(|channels| !channels.is_empty() && channels != "-all")
+    .unwrap_or_else(|| DEBUG_CHANNELS.to_string());
+    println!("{}Running the {} installer again with WINEDEBUG={} to capture a debug log...{}", COLOR_BLUE, launcher.name, channels, COLOR_RESET);
+    emit_progress("debug", None, &format!("Capturing a wine debug log of the {} installer", launcher.name));
+
+    let result = (|| {
+        let dir = report_dir(&app_paths.home_dir);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let log = dir.join(format!("{}-wine-debug.log", launcher.id));
+        let code = installer.run_logged(command, &channels, &log)?;
+        // Programs the installer started keep logging until they exit
+        let _ = installer.session().wait(Some(Duration::from_secs(120)));
+        if bound_log(&log, DEBUG_LOG_LIMIT)? {
+            println!("{}The debug log was cut to {} from its beginning and end.{}", COLOR_YELLOW, format_size(DEBUG_LOG_LIMIT), COLOR_RESET);
         }
+        let mut info = system_info(Some(installer.wine_path()));
+        info.push(("Launcher".to_string(), launcher.name.to_string()));
+        info.push(("Exit code of the debug run".to_string(), code.to_string()));
+        info.push(("WINEDEBUG".to_string(), channels.clone()));
+        let report = create_report(&app_paths.home_dir, &format!("{}-debug", launcher.id), &info, std::slice::from_ref(&log))?;
+        let _ = fs::remove_file(&log);
+        Ok::<PathBuf, String>(report)
+    })();
+    match result {
+        Ok(report) => {
+            println!("{}Debug report written to {}{}", COLOR_GREEN, report.display(), COLOR_RESET);
+            println!("Attach it to a bug report at https://github.com/SulfurNitride/Simple-Launcher-Installer/issues");
+        },
+        Err(e) => println!("{}Warning: Could not capture a wine debug log: {}{}", COLOR_YELLOW, e, COLOR_RESET),
     }
+}
 
-    println!("{}Battle.net installation completed.{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}Installed to: {}{}", COLOR_GREEN, install_dir, COLOR_RESET);
+// Ask whether to stop a silent installer that stopped making progress. Headless runs always do.
+fn prompt_stop_hung_installer(idle: Duration) -> bool {
+    let minutes = idle.as_secs() / 60;
+    println!("{}The installer has shown no activity (CPU, disk or prefix changes) for {} minute{}.{}",
+             COLOR_YELLOW, minutes, if minutes == 1 { "" } else { "s" }, COLOR_RESET);
+    if headless() {
+        return true;
+    }
+    confirm("Stop it and continue in interactive mode?")
+}
 
-    // Steam integration instructions - simplified
-    println!("\n{}=== How to Add Battle.net to Steam ==={}", COLOR_BLUE, COLOR_RESET);
-    println!("{}1. Open Steam and click on 'Add a Game' in the bottom-left corner{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}2. Select 'Add a Non-Steam Game...'{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}3. Click 'BROWSE' and navigate to your Battle.net installation folder:{}", COLOR_GREEN, COLOR_RESET);
-    println!("   {}{}", COLOR_YELLOW, install_dir);
-    println!("{}4. Select the 'Battle.net.exe' file and click 'Open'{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}5. Click 'Add Selected Program'{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}6. Battle.net is now ready to use in Steam!{}\n", COLOR_GREEN, COLOR_RESET);
+// Run the installer in its window after the silent install failed or hung, offering the
+// launcher's automation script to click through it. Returns the installer's exit code.
+fn run_interactive_installer(installer: &Installer, app_paths: &AppPaths, options: &InstallOptions, launcher: &LauncherInfo, installer_path: &Path) -> Result<i32, String> {
+    let automation = if prompt_use_automation(&app_paths.home_dir, launcher) {
+        let allowlist = hash_policy(options, &app_paths.home_dir)?.allowlist;
+        match start_installer_automation(installer.wine_path(), installer.prefix().path(), &app_paths.home_dir, launcher, &allowlist) {
+            Ok(child) => child,
+            Err(e) => {
+                println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+                None
+            }
+        }
+    } else {
+        None
+    };
 
-    Ok(())
+    println!("\n{}Running the {} installer interactively...{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+    if automation.is_none() {
+        println!("{}Please follow the installation instructions in the installer window.{}", COLOR_YELLOW, COLOR_RESET);
+    }
+    let status = installer.run(&mut installer.interactive_command(installer_path));
+    stop_installer_automation(automation);
+    status
 }
 
-// Install HoYoPlay
-fn install_hoyoplay(wine_path: &str, app_paths: &AppPaths) -> Result<(), String> {
-    println!("{}Preparing to install HoYoPlay...{}", COLOR_BLUE, COLOR_RESET);
+// Create the prefix if needed and cut it off from the host filesystem
+fn sandbox_prefix(prefix: &PrefixManager, wine_path: &str) -> Result<(), String> {
+    if !prefix.path().join("system.reg").exists() {
+        prefix.initialize(wine_path)?;
+    }
+    prefix.sandbox()
+}
 
-    // Create hoyoplay directory if it doesn't exist
-    let hoyoplay_dir = app_paths.home_dir.join(".hoyoplay");
-    fs::create_dir_all(&hoyoplay_dir)
-    .map_err(|e| format!("Failed to create HoYoPlay directory: {}", e))?;
+// The installer for a launcher's prefix, set up from the config and the command line options
+fn launcher_installer(wine_path: &str, app_paths: &AppPaths, config: &Config, options: &InstallOptions, launcher: &LauncherInfo) -> Result<Installer, String> {
+    Ok(Installer::new(wine_path, PrefixManager::new(app_paths.prefix_dir(launcher.dir_name)))
+    .with_language(options.language)
+    .with_display(options.display.clone())
+    .with_hang_timeout(config.hang_timeout())
+    .with_download_connections(config.download_connections)
+    .with_hygiene(config.installer_hygiene.clone())
+    .with_env_overrides(config.env_for(launcher.id))
+    .with_verbose(options.verbose)
+    .with_hash_policy(hash_policy(options, &app_paths.home_dir)?)
+    .with_dll_overrides(DllOverrides::load(&app_paths.dll_overrides_path(launcher.dir_name))?))
+}
 
-    let installer_url = "https://download-porter.hoyoverse.com/download-porter/2025/02/21/VYTpXlbWo8_1.4.5.222_1_0_hyp_hoyoverse_prod_202502081529_XFGRLkBk.exe?trace_key=HoYoPlay_install_ua_5ca9c7368584";
-    download_file(installer_url, &app_paths.hoyoplay_installer)?;
+// Run the launcher's installer silently, and interactively if that fails or hangs and there is
+// someone to click through it. Headless runs and runs without a display fail instead.
+fn run_launcher_installer(installer: &Installer, app_paths: &AppPaths, options: &InstallOptions, launcher: &LauncherInfo, installer_path: &Path) -> Result<(), String> {
+    println!("\n{}Running the {} installer in silent mode...{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+    emit_progress("install", Some(20), &format!("Running the {} installer", launcher.name));
+
+    let mut command = installer.silent_command(installer_path);
+    command.args(launcher.silent_install_args(options.language));
+    let install_status = installer.run_watched(&mut command, prompt_stop_hung_installer)?;
+    if options.debug_wine && install_status.is_some_and(|code| code != 0) {
+        let mut command = installer.silent_command(installer_path);
+        command.args(launcher.silent_install_args(options.language));
+        capture_wine_debug(app_paths, installer, launcher, &mut command);
+    }
+    if install_status == Some(0) {
+        return Ok(());
+    }
 
-    // Make installer executable
-    if let Err(e) = fs::set_permissions(&app_paths.hoyoplay_installer, fs::Permissions::from_mode(0o755)) {
-        println!("{}Warning: Could not make installer executable: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    if headless() {
+        return Err(match install_status {
+            Some(code) => format!("The silent install failed with exit code {}; there is no interactive fallback in headless mode.", code),
+            None => "The silent install hung and was stopped; there is no interactive fallback in headless mode.".to_string(),
+        });
     }
+    if !display_available() {
+        print_no_display_steps(launcher.id);
+        return Err(match install_status {
+            Some(code) => format!("The silent install failed with exit code {} and there is no display to run the installer interactively.", code),
+            None => "The silent install hung and there is no display to run the installer interactively.".to_string(),
+        });
+    }
+    let reason = if install_status.is_some() { "failed" } else { "was stopped" };
+    println!("{}Silent install {}. Falling back to interactive mode...{}", COLOR_RED, reason, COLOR_RESET);
+    let interactive_status = run_interactive_installer(installer, app_paths, options, launcher, installer_path)?;
+    if interactive_status != 0 {
+        println!("{}The {} installer encountered an error (status code: {}).{}",
+                 COLOR_RED, launcher.name, interactive_status, COLOR_RESET);
+
+        if !confirm("Would you like to continue anyway?") {
+            return Err("Operation cancelled based on installer error.".to_string());
+        }
+    }
+    Ok(())
+}
 
-    // Determine wine prefix
-    let wine_prefix = app_paths.home_dir.join(".wine");
+// Install a launcher from the registry: the summary, the download, the prefix with the
+// winetricks verbs and runtimes the launcher needs, its installer, copying the installed files
+// out of the prefix, and the launcher's own steps after it is installed
+fn run_install(wine_path: &str, app_paths: &AppPaths, config: &Config, options: &InstallOptions, launcher: &LauncherInfo) -> Result<(), String> {
+    println!("{}Preparing to install {}...{}", COLOR_BLUE, launcher.name, COLOR_RESET);
 
-    // Prompt for install directory
-    println!("{}Where do you want to install HoYoPlay?{}", COLOR_BLUE, COLOR_RESET);
-    let default_hoyo_dest = app_paths.home_dir.join("Games/HoYoPlay").to_string_lossy().to_string();
-    println!("Destination folder (Default: {}): ", default_hoyo_dest);
+    let plan = plan_install(wine_path, launcher, app_paths, options)?;
+    let wine_path = plan.runner.as_str();
+    if plan.set_aside_prefix == Some(true) {
+        set_aside_prefix(app_paths, launcher)?;
+    }
+    prepare_launcher_dir(app_paths, launcher, plan.subvolume);
 
-    io::stdout().flush().unwrap();
-    let mut hoyo_dest = String::new();
-    io::stdin().read_line(&mut hoyo_dest).unwrap();
-    hoyo_dest = hoyo_dest.trim().to_string();
+    let overrides_path = app_paths.dll_overrides_path(launcher.dir_name);
+    if !launcher.dll_overrides.is_empty() && !overrides_path.exists() {
+        DllOverrides::from_entries(launcher.dll_overrides).save(&overrides_path)?;
+    }
 
-    let hoyo_dest_path = if hoyo_dest.is_empty() {
-        default_hoyo_dest
-    } else {
-        hoyo_dest
-    };
-
-    // Create destination directory if it doesn't exist
-    fs::create_dir_all(&hoyo_dest_path)
-    .map_err(|e| format!("Failed to create directory: {}", e))?;
-
-    println!("\n{}Running HoYoPlay installer...{}", COLOR_BLUE, COLOR_RESET);
-
-    // Create command with suppressed output and environment variables similar to Battle.net
-    let mut command = Command::new(wine_path);
-    command
-    .env("WINEPREFIX", wine_prefix.to_string_lossy().to_string())
-    .env("WINEDEBUG", "-all")  // Suppress all Wine debug messages
-    .env("MANGOHUD", "0")      // Disable MangoHud
-    .env("DISABLE_MANGOHUD", "1") // Another way to disable MangoHud
-    .env("WINEDLLOVERRIDES", "mscoree,mshtml=") // Disable browser component
-    .env("DISPLAY", ":99")     // Use a fake display to hide GUI
-    .env("DISABLE_LAYER_AMD_SWITCHABLE_GRAPHICS_1", "1") // Try to disable some AMD layers
-    .arg(&app_paths.hoyoplay_installer)
-    .stdout(std::process::Stdio::null()) // Redirect stdout to null
-    .stderr(std::process::Stdio::null()); // Redirect stderr to null
-
-    // Run the HoYoPlay installer
-    let install_status = command.status()
-    .map_err(|e| format!("Failed to execute wine command: {}", e))?
-    .code()
-    .unwrap_or(1);
-
-    // Run wineserver -k with suppressed output
-    println!("{}Running wineserver -k to clean up...{}", COLOR_YELLOW, COLOR_RESET);
-    let _ = Command::new("wineserver")
-    .arg("-k")
-    .stdout(std::process::Stdio::null())
-    .stderr(std::process::Stdio::null())
-    .status();
-    thread::sleep(Duration::from_secs(1));
+    let wine_prefix = app_paths.prefix_dir(launcher.dir_name);
+    let installer = launcher_installer(wine_path, app_paths, config, options, launcher)?
+    .with_installer_source(plan.source.clone());
+    let troubleshoot = troubleshoot_context(app_paths, launcher, &installer);
+    let mut virtual_display = None;
+    let cache_dir = app_paths.cache_dir(launcher.dir_name);
+    let installer_path = run_step(Step::Download, &troubleshoot, &mut virtual_display, || installer.fetch(launcher, &cache_dir))?;
+    let installer_version = show_installer_info(&installer_path);
+    let mut install_dir = plan.install_dir.clone();
+
+    let mut install_dir_created = create_install_dir(&install_dir)?;
+
+    clone_prefix_template(app_paths, installer.prefix());
+    run_step(Step::PrefixSetup, &troubleshoot, &mut virtual_display, || installer.prefix().update_for_runner(wine_path))?;
+    if plan.sandbox {
+        sandbox_prefix(installer.prefix(), wine_path)?;
+        record_tweak(app_paths, launcher, Tweak::Sandbox);
+    }
+    if !launcher.winetricks_verbs.is_empty() {
+        run_step(Step::PrefixSetup, &troubleshoot, &mut virtual_display, || installer.prefix().install_verbs(wine_path, launcher.winetricks_verbs))?;
+    }
+    for runtime in launcher.runtimes {
+        run_step(Step::PrefixSetup, &troubleshoot, &mut virtual_display, || installer.install_runtime(runtime, &cache_dir))?;
+    }
+    offer_agent_reset(installer.prefix());
+
+    run_step(Step::Installer, &troubleshoot, &mut virtual_display, || {
+        if !installer_path.exists() {
+            installer.fetch(launcher, &cache_dir)?;
+        }
+        run_launcher_installer(&installer, app_paths, options, launcher, &installer_path)
+    })?;
+
+    installer.finish();
+    record_installed_version(app_paths, launcher, installer_version);
+
+    // A reinstall over an original location that links to the copy installs into the copy itself
+    match launcher.find_install_dir(&wine_prefix).as_deref() {
+        Some(source_path) if *source_path != install_dir && fs::canonicalize(source_path).ok() != fs::canonicalize(&install_dir).ok() => {
+            println!("{}Found the {} installation at: {}{}", COLOR_GREEN, launcher.name, source_path.display(), COLOR_RESET);
+            println!("{}Copying {} files to {}...{}", COLOR_BLUE, launcher.name, install_dir.display(), COLOR_RESET);
+            emit_progress("relocate", Some(70), &format!("Copying {} files to {}", launcher.name, install_dir.display()));
+
+            install_dir = resolve_install_dir_conflict(source_path, &install_dir, &app_paths.home_dir, &mut install_dir_created)?;
+            match copy_launcher_files(source_path, &install_dir) {
+                Ok(false) => {
+                    println!("{}Some files were not copied, so the originals in Wine's C: drive were kept.{}", COLOR_YELLOW, COLOR_RESET);
+                },
+                Ok(true) => {
+                    println!("{}Files copied successfully.{}", COLOR_GREEN, COLOR_RESET);
+
+                    // Most launchers update themselves in place, so the copy in the prefix is only a
+                    // fallback. An updater that writes to where it was installed instead needs that
+                    // to lead to the copy.
+                    match plan.originals {
+                        Originals::Link => link_updater_path(source_path, &install_dir),
+                        Originals::Delete => match fs::remove_dir_all(source_path) {
+                            Ok(_) => println!("{}Original directory deleted.{}", COLOR_GREEN, COLOR_RESET),
+                            Err(e) => println!("{}Error deleting original directory: {}{}", COLOR_RED, e, COLOR_RESET)
+                        },
+                        Originals::Keep if launcher.updates_install_path => {
+                            println!("{}{} updates itself on its first start, into the original files at {}. Start it from there once and copy it again to get the update.{}",
+                                     COLOR_YELLOW, launcher.name, source_path.display(), COLOR_RESET);
+                        },
+                        Originals::Keep => {},
+                    }
+                },
+                Err(e) => println!("{}Error copying files: {}{}", COLOR_RED, e, COLOR_RESET)
+            }
+        },
+        Some(_) => {},
+        None => {
+            println!("{}Warning: Could not find the {} installation directory in Wine C: drive.{}", COLOR_YELLOW, launcher.name, COLOR_RESET);
+            println!("{}Please check if {} was installed correctly.{}", COLOR_YELLOW, launcher.name, COLOR_RESET);
+        }
+    }
 
-    if install_status != 0 {
-        println!("{}The HoYoPlay installer encountered an error (status code: {}).{}",
-                 COLOR_RED, install_status, COLOR_RESET);
+    if let Some(runtime) = &launcher.bundled_runtime {
+        if install_dir.join(launcher.exe_name).exists() {
+            report_bundled_runtime(runtime, &install_dir);
+        }
+    }
 
-        print!("Would you like to continue anyway? (yes/no)\n> ");
-        io::stdout().flush().unwrap();
+    if plan.font_smoothing && enable_font_smoothing(installer.prefix()) {
+        record_tweak(app_paths, launcher, Tweak::FontSmoothing);
+    }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+    let verified = report_installed(wine_path, app_paths, options, launcher, &install_dir, install_dir_created);
 
-        if input.trim().to_lowercase() != "yes" && input.trim().to_lowercase() != "y" {
-            return Err("Operation cancelled based on HoYoPlay installer error.".to_string());
+    let exe = install_dir.join(launcher.exe_name);
+    if exe.exists() && !launcher.games.is_empty() {
+        prompt_battlenet_games_dir(installer.prefix(), &app_paths.home_dir);
+        if let Err(e) = queue_battlenet_game_installs(&installer, launcher, &exe) {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
         }
     }
+    if exe.exists() && plan.uri_handlers == Some(true) {
+        register_launcher_uri_handlers(wine_path, app_paths, launcher, &exe);
+    }
 
-    println!("{}HoYoPlay installation finished. Installed to default Wine C: drive.{}", COLOR_GREEN, COLOR_RESET);
-
-    // Copy files from Wine C: drive to the destination directory
-    let hoyo_src = app_paths.home_dir.join(".wine/drive_c/Program Files/HoYoPlay");
+    integrate_with_steam(app_paths, launcher, launcher.name, &install_dir, plan.steam, Some(plan.verify_shortcut));
 
-    if hoyo_src.exists() && hoyo_src.is_dir() {
-        println!("{}Copying HoYoPlay files to {}...{}", COLOR_BLUE, hoyo_dest_path, COLOR_RESET);
+    verified
+}
 
-        // Copy all files recursively
-        copy_dir_recursive(&hoyo_src, &PathBuf::from(&hoyo_dest_path))
-        .map_err(|e| format!("Failed to copy files: {}", e))?;
+// Replace the launcher's original install directory with a link to its copy, so its
+// self-update on the first start updates the copy. Without the link the copy stays at the
+// installer's version and the update goes to the files left in the prefix.
+fn link_updater_path(source_path: &Path, install_dir: &Path) {
+    match links::replace_with_link(source_path, install_dir) {
+        Ok(()) => println!("{}{} now links to {}.{}", COLOR_GREEN, source_path.display(), install_dir.display(), COLOR_RESET),
+        Err(e) => println!("{}Warning: {}. The update will go to the original files.{}", COLOR_YELLOW, e.trim_end_matches('.'), COLOR_RESET),
+    }
+}
 
-        println!("{}Files copied successfully.{}", COLOR_GREEN, COLOR_RESET);
+// Check a runtime the launcher's installer unpacks, like the Minecraft Launcher's Java. A broken
+// one isn't fatal: the launcher downloads it itself when it has none
+fn report_bundled_runtime(runtime: &BundledRuntime, install_dir: &Path) {
+    match (runtime.check)(install_dir) {
+        Ok(dir) => println!("{}The bundled {} runtime is complete: {}{}", COLOR_GREEN, runtime.name, dir.display(), COLOR_RESET),
+        Err(e) => {
+            println!("{}The bundled {} runtime did not unpack correctly: {}{}", COLOR_YELLOW, runtime.name, e, COLOR_RESET);
+            println!("{}Reinstall the launcher, or let it download {} on its first start.{}", COLOR_YELLOW, runtime.name, COLOR_RESET);
+        },
+    }
+}
 
-        println!("{}Deleting original HoYoPlay directory in .wine...{}", COLOR_YELLOW, COLOR_RESET);
-        fs::remove_dir_all(&hoyo_src)
-        .map_err(|e| format!("Failed to delete directory: {}", e))?;
+// Record where the launcher was installed, and whether this or an earlier install to the same
+// directory created it, and say that the install completed. With --smoke-test the launcher is
+// started first, and the install only counts as verified if it wrote its files.
+fn report_installed(wine_path: &str, app_paths: &AppPaths, options: &InstallOptions, launcher: &LauncherInfo, install_dir: &Path, created: bool) -> Result<(), String> {
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.install_dir_created = created || (state.install_dir_created && state.install_dir.as_deref() == Some(install_dir));
+    state.install_dir = Some(install_dir.to_path_buf());
+    if let Err(e) = state.save(&state_path) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
 
-        println!("{}Original directory deleted.{}", COLOR_GREEN, COLOR_RESET);
+    let result = if options.smoke_test {
+        let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+        smoke_test(wine_path, &prefix, launcher, &install_dir.join(launcher.exe_name)).map(Some)
     } else {
-        println!("{}HoYoPlay directory not found in .wine!{}", COLOR_RED, COLOR_RESET);
+        Ok(None)
+    };
+    match &result {
+        Ok(Some(file)) => println!("{}{} installation completed and verified: it started and wrote {}.{}",
+                                   COLOR_GREEN, launcher.name, file.display(), COLOR_RESET),
+        Ok(None) => println!("{}{} installation completed.{}", COLOR_GREEN, launcher.name, COLOR_RESET),
+        Err(e) => println!("{}{} installation completed, but the smoke test failed: {}{}", COLOR_RED, launcher.name, e, COLOR_RESET),
+    }
+    println!("{}Installed to: {}{}", COLOR_GREEN, install_dir.display(), COLOR_RESET);
+    result.map(|_| ()).map_err(|e| format!("Smoke test failed: {}", e))
+}
+
+// Let the user pick the executable of a launcher that isn't where it was expected from the
+// known launchers' executables in its prefix, so no path has to be typed
+fn pick_prefix_exe(prefix: &Path, launcher: &LauncherInfo) -> Option<PathBuf> {
+    let mut exes = find_launcher_exes(prefix, &LauncherRegistry::builtin());
+    exes.sort_by_key(|(found, _)| found.id != launcher.id);
+    if exes.is_empty() {
+        return None;
+    }
+    if headless() {
+        // Only an unambiguous match is used without asking
+        let own: Vec<&PathBuf> = exes.iter().filter(|(found, _)| found.id == launcher.id).map(|(_, exe)| exe).collect();
+        return if own.len() == 1 { Some(own[0].clone()) } else { None };
+    }
+
+    println!("{}{} is not where it was expected. These launcher executables are in {}:{}",
+             COLOR_YELLOW, launcher.exe_name, prefix.display(), COLOR_RESET);
+    let mut labels: Vec<String> = exes.iter()
+    .map(|(found, exe)| format!("{} ({})", exe.strip_prefix(prefix.join("drive_c")).unwrap_or(exe).display(), found.name))
+    .collect();
+    labels.push("None of these".to_string());
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    let choice = prompt_option(&format!("Which one is {}?", launcher.name), &labels);
+    exes.get(choice).map(|(_, exe)| exe.clone())
+}
+
+// Add the installed launcher to Steam as `level` asks. `verify` is whether to check the new
+// shortcut's Proton prefix; None asks.
+fn integrate_with_steam(app_paths: &AppPaths, launcher: &LauncherInfo, display_name: &str, install_dir: &Path, level: SteamIntegrationLevel, verify: Option<bool>) {
+    if level == SteamIntegrationLevel::Off {
+        return;
+    }
+    let mut exe = install_dir.join(launcher.exe_name);
+    if !exe.exists() {
+        if let Some(found) = pick_prefix_exe(&app_paths.prefix_dir(launcher.dir_name), launcher) {
+            exe = found;
+        }
+    }
+    let install_dir = exe.parent().unwrap_or(install_dir);
+    let exe_name = exe.file_name().unwrap_or_default().to_string_lossy();
+
+    if level == SteamIntegrationLevel::Shortcut {
+        let overrides = DllOverrides::load(&app_paths.dll_overrides_path(launcher.dir_name)).unwrap_or_default();
+        let mut launch_options = format!("WINEDLLOVERRIDES=\"{}\" %command%", overrides.to_env());
+        if let Some(dir) = Config::load(&app_paths.home_dir).compat_data_path(launcher.id) {
+            if let Err(e) = create_compat_data_dir(app_paths, launcher, dir) {
+                println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+            }
+            launch_options = with_compat_data_path(&launch_options, Some(dir));
+        }
+        let collection = Config::load(&app_paths.home_dir).steam_collection;
+        let tags: Vec<&str> = collection.iter().map(String::as_str).collect();
+        let result = SteamIntegration::discover()
+        .and_then(|steam| {
+            let existed = steam.shortcut_for_exe(&exe).is_some();
+            steam.add_shortcut(display_name, &exe, &launch_options, &tags).map(|app_id| (app_id, existed))
+        });
+        match result {
+            Ok((app_id, existed)) => {
+                // Only a shortcut added here is this tool's to remove again
+                if !existed {
+                    record_steam_shortcut(app_paths, launcher, &exe);
+                }
+                println!("\n{}Added {} to Steam (app ID {}).{}", COLOR_GREEN, display_name, app_id, COLOR_RESET);
+                println!("{}Open its Properties > Compatibility in Steam and pick a Proton version.{}\n", COLOR_YELLOW, COLOR_RESET);
+                if verify.unwrap_or_else(|| confirm("Start it once through Steam now to check that Steam creates its Proton prefix?")) {
+                    report_shortcut_check(app_paths, launcher, display_name, app_id);
+                }
+                return;
+            },
+            Err(e) => println!("\n{}Could not add the Steam shortcut: {}{}", COLOR_YELLOW, e, COLOR_RESET),
+        }
     }
 
-    // Steam integration instructions - simplified
-    println!("\n{}=== How to Add HoYoPlay to Steam ==={}", COLOR_BLUE, COLOR_RESET);
+    println!("\n{}=== How to Add {} to Steam ==={}", COLOR_BLUE, display_name, COLOR_RESET);
     println!("{}1. Open Steam and click on 'Add a Game' in the bottom-left corner{}", COLOR_GREEN, COLOR_RESET);
     println!("{}2. Select 'Add a Non-Steam Game...'{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}3. Click 'BROWSE' and navigate to your HoYoPlay installation folder:{}", COLOR_GREEN, COLOR_RESET);
-    println!("   {}{}", COLOR_YELLOW, hoyo_dest_path);
-    println!("{}4. Select the 'HoYoPlay.exe' file and click 'Open'{}", COLOR_GREEN, COLOR_RESET);
+    println!("{}3. Click 'BROWSE' and navigate to your {} installation folder:{}", COLOR_GREEN, display_name, COLOR_RESET);
+    println!("   {}{}", COLOR_YELLOW, install_dir.display());
+    println!("{}4. Select the '{}' file and click 'Open'{}", COLOR_GREEN, exe_name, COLOR_RESET);
     println!("{}5. Click 'Add Selected Program'{}", COLOR_GREEN, COLOR_RESET);
-    println!("{}6. HoYoPlay is now ready to use in Steam!{}\n", COLOR_GREEN, COLOR_RESET);
-
-    // Important note about running HoYoPlay once before post-setup
-    println!("{}IMPORTANT: You should launch HoYoPlay once from Steam before running{}", COLOR_YELLOW, COLOR_RESET);
-    println!("{}the 'Run HoYoPlay Post-Setup' option from this installer.{}", COLOR_YELLOW, COLOR_RESET);
-    println!("{}This ensures all necessary files and settings are properly initialized.{}\n", COLOR_YELLOW, COLOR_RESET);
+    println!("{}6. {} is now ready to use in Steam!{}\n", COLOR_GREEN, display_name, COLOR_RESET);
+}
 
-    Ok(())
+// Remember the Steam shortcut added for a launcher, so `purge-all` removes it and no other
+fn record_steam_shortcut(app_paths: &AppPaths, launcher: &LauncherInfo, exe: &Path) {
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.steam_shortcut = Some(exe.to_path_buf());
+    if let Err(e) = state.save(&state_path) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
 }
 
-// Recursively copy a directory
-fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<()> {
-    if !dst.exists() {
-        fs::create_dir_all(dst)?;
+// How long Steam gets to start and create a new shortcut's Proton prefix
+const SHORTCUT_CHECK_TIMEOUT: Duration = Duration::from_secs(180);
+
+// Check a new Steam shortcut and say how it went
+fn report_shortcut_check(app_paths: &AppPaths, launcher: &LauncherInfo, display_name: &str, app_id: u32) {
+    println!("{}Starting {} through Steam...{}", COLOR_BLUE, display_name, COLOR_RESET);
+    match verify_steam_shortcut(app_paths, launcher, app_id) {
+        Ok((prefix, true)) => println!("{}Shortcut check passed: Steam started {} and created its prefix at {}.{}",
+                                       COLOR_GREEN, display_name, prefix.display(), COLOR_RESET),
+        Ok((prefix, false)) => println!("{}Shortcut check passed: Steam started {}, which uses the existing prefix at {}.{}",
+                                        COLOR_GREEN, display_name, prefix.display(), COLOR_RESET),
+        Err(e) => println!("{}Shortcut check failed: {}{}", COLOR_RED, e, COLOR_RESET),
     }
+}
 
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
+// Start a new shortcut through Steam and wait for Steam to create its Proton prefix, which
+// shows the shortcut and its compatibility tool work. Returns the prefix and whether it is new.
+fn verify_steam_shortcut(app_paths: &AppPaths, launcher: &LauncherInfo, app_id: u32) -> Result<(PathBuf, bool), String> {
+    let steam = SteamIntegration::discover()?;
+    let prefix = match Config::load(&app_paths.home_dir).compat_data_path(launcher.id) {
+        Some(dir) => dir.join("pfx"),
+        None => steam.shortcut_prefix_path(&app_id.to_string())?,
+    };
+    if steam.compat_tool(app_id).is_none() {
+        return Err(format!("no compatibility tool is set for app ID {}, so Steam would run the Windows executable without Proton and create no prefix. Pick a Proton version in its Properties > Compatibility and start it from Steam once.", app_id));
+    }
+    if find_in_path("steam").is_none() {
+        return Err("the `steam` command was not found in PATH.".to_string());
+    }
 
-        if file_type.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
-        } else {
-            fs::copy(&src_path, &dst_path)?;
+    let existed = prefix.join("system.reg").exists();
+    launch_shortcut(app_id)?;
+    let start = Instant::now();
+    let mut steam_started = false;
+    while start.elapsed() < SHORTCUT_CHECK_TIMEOUT {
+        steam_started = steam_started || steam_running();
+        if steam_started && (existed || prefix.join("system.reg").exists()) {
+            return Ok((prefix, !existed));
         }
+        thread::sleep(Duration::from_secs(2));
     }
-
-    Ok(())
+    Err(if steam_started {
+        format!("Steam is running, but no prefix appeared at {} within {} seconds. Start the shortcut from Steam to see its error.",
+                prefix.display(), SHORTCUT_CHECK_TIMEOUT.as_secs())
+    } else {
+        format!("Steam did not start within {} seconds.", SHORTCUT_CHECK_TIMEOUT.as_secs())
+    })
 }
 
-// Check if protontricks is installed
-fn check_protontricks() -> bool {
-    Command::new("which")
-    .arg("protontricks")
-    .status()
-    .map(|status| status.success())
-    .unwrap_or(false)
+// Print what the installer says about itself before it runs, returning its version
+fn show_installer_info(installer_path: &Path) -> Option<String> {
+    match PeInfo::inspect(installer_path) {
+        Ok(info) => {
+            println!("{}Installer: {}{}", COLOR_BLUE, info.summary(), COLOR_RESET);
+            if !info.signed {
+                println!("{}Warning: the installer has no digital signature.{}", COLOR_YELLOW, COLOR_RESET);
+            }
+            info.version
+        },
+        Err(e) => {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+            None
+        }
+    }
 }
 
-// List non-Steam games using protontricks
-fn list_nonsteam_games() -> Result<Vec<String>, String> {
-    let output = Command::new("protontricks")
-    .arg("-l")
-    .output()
-    .map_err(|e| format!("Failed to execute protontricks: {}", e))?;
+// Remember the installer version that was run, for `update` to compare against, and the
+// version of the client it installed, for `list`
+fn record_installed_version(app_paths: &AppPaths, launcher: &LauncherInfo, version: Option<String>) {
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.installer_version = version;
+    state.client_version = client_version(launcher, &app_paths.drive_dir(launcher.dir_name), &app_paths.prefix_dir(launcher.dir_name));
+    if let Err(e) = state.save(&state_path) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+}
 
-    if !output.status.success() {
-        return Err("protontricks -l command failed".to_string());
+// The runner for a launcher: the one its prefix was migrated to, or else the configured one.
+// Whether it is wine's binary or a wrapper script is recorded in the launcher's state.
+fn launcher_runner(app_paths: &AppPaths, launcher: &LauncherInfo) -> Option<String> {
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    let runner = match &state.runner {
+        Some(runner) if Path::new(runner).exists() => Some(runner.clone()),
+        _ => find_runner(&Config::load(&app_paths.home_dir), &app_paths.home_dir),
+    };
+    let kind = runner.as_deref().and_then(|runner| wine_kind(Path::new(runner)));
+    if kind.is_some() && kind != state.runner_kind {
+        state.runner_kind = kind;
+        if let Err(e) = state.save(&state_path) {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+        }
     }
+    runner
+}
 
-    let output_str = str::from_utf8(&output.stdout)
-    .map_err(|e| format!("Invalid UTF-8 in protontricks output: {}", e))?;
+// Remember a tweak applied to a launcher's prefix so `reapply` can apply it again
+fn record_tweak(app_paths: &AppPaths, launcher: &LauncherInfo, tweak: Tweak) {
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.record_tweak(tweak);
+    if let Err(e) = state.save(&state_path) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+}
 
-    let mut games: Vec<String> = Vec::new();
+// Point Battle.net's default game install location at a Linux directory through its own drive letter
+fn set_battlenet_games_dir(prefix: &PrefixManager, games_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(games_dir)
+    .map_err(|e| format!("Failed to create {}: {}", games_dir.display(), e))?;
+    let letter = prefix.map_drive(games_dir)?.to_ascii_uppercase();
+    let config_path = client_config_path(prefix).ok_or("Could not find the Windows user profile in the prefix.")?;
+    set_default_install_path(&config_path, &format!("{}:/", letter))?;
+    println!("{}Battle.net will install games to {}: ({}).{}", COLOR_GREEN, letter, games_dir.display(), COLOR_RESET);
+    Ok(())
+}
 
-    for line in output_str.lines() {
-        if line.contains("Non-Steam shortcut:") {
-            games.push(line.to_string());
+// Ask where Battle.net should put game installs instead of filling drive_c
+fn prompt_battlenet_games_dir(prefix: &PrefixManager, home_dir: &Path) {
+    println!("\n{}Where should Battle.net install games? Games can take 100 GB or more.{}", COLOR_BLUE, COLOR_RESET);
+    println!("Directory (leave empty to keep Battle.net's default inside the prefix): ");
+    if let Some(games_dir) = prompt_dir("Battle.net games directory", home_dir, home_dir) {
+        if let Err(e) = set_battlenet_games_dir(prefix, &games_dir) {
+            println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
         }
     }
-
-    Ok(games)
 }
 
-// Extract App ID from a protontricks game line
-fn extract_appid(line: &str) -> Option<String> {
-    let re = Regex::new(r"\(([0-9]+)\)$").unwrap();
-    re.captures(line).and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
-}
+// How long Battle.net gets to start, or to take a request over to the running client
+const BATTLENET_HANDOFF_TIMEOUT: Duration = Duration::from_secs(60);
 
-// Find Steam library folders
-fn find_steam_libraries() -> Result<Vec<PathBuf>, String> {
-    let home_dir = dirs::home_dir().ok_or_else(|| "Could not determine home directory".to_string())?;
-    let steam_root = home_dir.join(".steam/steam");
-    let library_vdf = steam_root.join("steamapps/libraryfolders.vdf");
+// Offer to start downloading games right after Battle.net is installed. The first request starts
+// the client in the installer's session; each later one hands over to it once it is up and exits.
+fn queue_battlenet_game_installs(installer: &Installer, launcher: &LauncherInfo, battlenet_exe: &Path) -> Result<(), String> {
+    let games = launcher.games;
+    println!();
+    if !confirm(&format!("{}Would you like to queue any Battle.net games for installation?{}", COLOR_BLUE, COLOR_RESET)) {
+        return Ok(());
+    }
 
-    if !library_vdf.exists() {
-        return Err(format!("Could not find libraryfolders.vdf at {}", library_vdf.display()));
+    println!("{}Select games to install (comma-separated, e.g. 1,3):{}", COLOR_YELLOW, COLOR_RESET);
+    for (i, (name, _)) in games.iter().enumerate() {
+        println!("{:2}) {}", i + 1, name);
     }
+    print!("> ");
+    io::stdout().flush().unwrap();
 
-    let mut libraries = vec![steam_root];
+    let mut selection = String::new();
+    read_input(&mut selection);
 
-    // Parse libraryfolders.vdf to find additional library paths
-    let vdf_content = fs::read_to_string(&library_vdf)
-    .map_err(|e| format!("Failed to read libraryfolders.vdf: {}", e))?;
+    let mut codes = Vec::new();
+    for part in selection.split(',') {
+        match part.trim().parse::<usize>() {
+            Ok(i) if i >= 1 && i <= games.len() => codes.push(games[i - 1]),
+            _ if part.trim().is_empty() => {},
+            _ => return Err(format!("Invalid game selection: {}", part.trim())),
+        }
+    }
 
-    // Simple regex to extract paths from VDF
-    let re = Regex::new(r#""path"\s*"([^"]+)""#).unwrap();
-    for cap in re.captures_iter(&vdf_content) {
-        if let Some(path_match) = cap.get(1) {
-            let path = PathBuf::from(path_match.as_str().replace("\\\\", "/"));
-            libraries.push(path);
+    // The client writes its startup files once it is up and can take requests
+    let startup_files: Vec<PathBuf> = installer.prefix().user_dir()
+    .map(|user_dir| launcher.startup_files.iter().map(|file| user_dir.join(file)).collect())
+    .unwrap_or_default();
+    let modified = || startup_files.iter().map(|file| fs::metadata(file).and_then(|metadata| metadata.modified()).ok()).collect::<Vec<_>>();
+    let before = modified();
+
+    installer.session().start()?;
+    let mut clients = Vec::new();
+    for (name, code) in codes {
+        println!("{}Queueing {} ({})...{}", COLOR_BLUE, name, code, COLOR_RESET);
+
+        let mut command = installer.interactive_command(battlenet_exe);
+        command.args(WineArgs::new().arg("--install").option("--game", code).build());
+        let mut child = command.spawn()
+        .map_err(|e| format!("Failed to start Battle.net: {}", e))?;
+
+        let started = Instant::now();
+        loop {
+            if child.try_wait().map_err(|e| format!("Failed to wait for Battle.net: {}", e))?.is_some() {
+                break;
+            }
+            if clients.is_empty() && modified() != before {
+                clients.push(child);
+                break;
+            }
+            if started.elapsed() > BATTLENET_HANDOFF_TIMEOUT {
+                println!("{}Warning: Battle.net did not take the request for {} within {} seconds.{}",
+                         COLOR_YELLOW, name, BATTLENET_HANDOFF_TIMEOUT.as_secs(), COLOR_RESET);
+                clients.push(child);
+                break;
+            }
+            thread::sleep(Duration::from_secs(1));
         }
     }
+    // Battle.net stays open to run the downloads; reap it whenever it exits
+    for mut client in clients {
+        thread::spawn(move || client.wait());
+    }
 
-    Ok(libraries)
+    println!("{}Games queued. Log in to Battle.net to start the downloads.{}", COLOR_GREEN, COLOR_RESET);
+    Ok(())
 }
 
-// Find the compatdata prefix for an App ID
-fn find_prefix_path(app_id: &str, libraries: &[PathBuf]) -> Option<PathBuf> {
-    for lib in libraries {
-        let compatdata_path = lib.join(format!("steamapps/compatdata/{}/pfx", app_id));
-        if compatdata_path.exists() && compatdata_path.is_dir() {
-            return Some(compatdata_path);
+// Install any launcher from the registry
+fn install_launcher(wine_path: &str, app_paths: &AppPaths, options: &InstallOptions, launcher: &LauncherInfo) -> Result<(), String> {
+    print_launcher_notes(launcher);
+    if let Some(game) = &options.steam_game {
+        use_steam_game_prefix(app_paths, launcher, game)?;
+    }
+    let config = Config::load(&app_paths.home_dir);
+    let menus = MenuSnapshot::take(&app_paths.home_dir);
+    let result = run_install(wine_path, app_paths, &config, options, launcher);
+    sweep_menu_files(&app_paths.home_dir, &menus);
+    if result.is_ok() && !launcher.notes.after_install.is_empty() {
+        println!("{}IMPORTANT:{}", COLOR_YELLOW, COLOR_RESET);
+        for step in launcher.notes.after_install {
+            println!("{}{}{}", COLOR_YELLOW, step, COLOR_RESET);
         }
+        println!();
     }
-    None
+    result
 }
 
-// Set up symlink to Linux root in the Wine prefix
-fn setup_linux_root_symlink(prefix_path: &Path) -> Result<(), String> {
-    let linux_root_link = prefix_path.join("drive_c/Linux Root");
+// Make the Proton prefix of an installed Steam game the launcher's prefix, so the launcher is
+// installed into it and can be started from that game's entry in Steam
+fn use_steam_game_prefix(app_paths: &AppPaths, launcher: &LauncherInfo, game: &str) -> Result<(), String> {
+    let steam = SteamIntegration::discover()?;
+    let games = steam.installed_games();
+    let mut matches: Vec<_> = games.iter().filter(|installed| installed.app_id == game).collect();
+    if matches.is_empty() {
+        matches = games.iter().filter(|installed| installed.name.to_lowercase().contains(&game.to_lowercase())).collect();
+    }
+    if matches.is_empty() {
+        println!("{}Installed Steam games:{}", COLOR_YELLOW, COLOR_RESET);
+        for installed in &games {
+            println!("  {} {}", installed.app_id, installed.name);
+        }
+        return Err(format!("No installed Steam game matches {}.", game));
+    }
 
-    if linux_root_link.exists() {
-        println!("{}Symlink or folder 'Linux Root' already exists in drive_c. Skipping symlink creation.{}",
-                 COLOR_YELLOW, COLOR_RESET);
+    let chosen = if matches.len() == 1 {
+        matches[0]
+    } else if headless() {
+        return Err(format!("{} matches {} Steam games; give its app ID.", game, matches.len()));
     } else {
-        let status = Command::new("ln")
-        .arg("-s")
-        .arg("/")
-        .arg(&linux_root_link)
-        .status()
-        .map_err(|e| format!("Failed to create symlink: {}", e))?;
+        let labels: Vec<String> = matches.iter().map(|installed| format!("{} ({})", installed.name, installed.app_id)).collect();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        matches[prompt_option(&format!("Which game's prefix should {} be installed into?", launcher.name), &labels)]
+    };
+    let prefix = chosen.prefix.as_ref().ok_or_else(|| {
+        format!("{} has no Proton prefix yet. Start it from Steam once, then try again.", chosen.name)
+    })?;
+
+    adopt_prefix(app_paths, launcher, prefix)?;
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.steam_game = Some(chosen.app_id.clone());
+    state.save(&state_path)?;
+    println!("{}Installing {} into the prefix of {} ({}).{}", COLOR_GREEN, launcher.name, chosen.name, prefix.display(), COLOR_RESET);
+    if Config::load(&app_paths.home_dir).runner != Runner::Umu {
+        println!("{}The prefix belongs to Proton. With runner = umu the install uses Proton too; other runners update the prefix to their own wine.{}",
+                 COLOR_YELLOW, COLOR_RESET);
+    }
+    Ok(())
+}
 
-        if status.success() {
-            println!("{}Symlinked / to {}{}", COLOR_GREEN, linux_root_link.display(), COLOR_RESET);
-        } else {
-            return Err("Failed to create symlink.".to_string());
+// What to know about a launcher under wine before installing it
+fn print_launcher_notes(launcher: &LauncherInfo) {
+    println!("{}===== Notes for {} ====={}", COLOR_BLUE, launcher.name, COLOR_RESET);
+    if !launcher.notes.known_issues.is_empty() {
+        println!("{}Known issues:{}", COLOR_YELLOW, COLOR_RESET);
+        for issue in launcher.notes.known_issues {
+            println!("  - {}", issue);
         }
     }
+    println!("{}Anti-cheat:{} {}", COLOR_YELLOW, COLOR_RESET, launcher.notes.anti_cheat);
+    println!("{}Recommended runner:{} {}\n", COLOR_YELLOW, COLOR_RESET, launcher.notes.recommended_runner);
+}
 
-    Ok(())
+// Remove the menu entries, MIME types and icons winemenubuilder created since the snapshot
+fn sweep_menu_files(home_dir: &Path, snapshot: &MenuSnapshot) {
+    let created = snapshot.new_files(home_dir);
+    if created.is_empty() {
+        return;
+    }
+    match remove_menu_files(home_dir, &created) {
+        Ok(removed) => println!("{}Removed {} menu entries and file associations wine created for Windows programs.{}", COLOR_GREEN, removed, COLOR_RESET),
+        Err(e) => println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET),
+    }
 }
 
-// Set registry key to remove window decorations
-fn remove_window_decorations(prefix_path: &Path) -> Result<(), String> {
-    // Determine which Wine binary to use
-    let wine_bin = if Command::new("which").arg("wine64").status().map(|s| s.success()).unwrap_or(false) {
-        "wine64"
+// How many recently used launchers the menu lists above the categories
+const RECENT_LAUNCHERS: usize = 3;
+
+// Remember when a launcher was last installed or updated, for the menu's recent list
+fn record_launcher_use(app_paths: &AppPaths, launcher: &LauncherInfo) {
+    let path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&path);
+    state.last_used = SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|elapsed| elapsed.as_secs());
+    if let Err(e) = state.save(&path) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+}
+
+// What a main menu entry does
+#[derive(Clone, Copy)]
+enum MenuAction<'a> {
+    Install(&'a LauncherInfo),
+    Update(&'a LauncherInfo),
+    HoyoplayPostSetup,
+    BattlenetFixLogin,
+    PrecreatePrefix,
+    BatchSetup,
+    ChangeGamesRoot,
+    Exit,
+}
+
+struct MenuEntry<'a> {
+    category: &'static str,
+    label: String,
+    action: MenuAction<'a>,
+}
+
+// Main menu entries in display order: favorite and recently used launchers, every launcher
+// by category, then utilities
+fn menu_entries<'a>(registry: &'a LauncherRegistry, config: &Config, app_paths: &AppPaths) -> Vec<MenuEntry<'a>> {
+    let mut entries = Vec::new();
+
+    let favorites: Vec<&LauncherInfo> = config.favorites.iter().filter_map(|id| registry.get(id)).collect();
+    let mut recent: Vec<(u64, &LauncherInfo)> = registry.all().iter()
+    .filter(|launcher| !config.favorites.iter().any(|id| id == launcher.id))
+    .filter_map(|launcher| Some((LauncherState::load(&app_paths.state_path(launcher.dir_name)).last_used?, launcher)))
+    .collect();
+    recent.sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+    let recent = recent.into_iter().take(RECENT_LAUNCHERS).map(|(_, launcher)| launcher);
+
+    let quick = favorites.into_iter().map(|launcher| ("Favorites", launcher))
+    .chain(recent.map(|launcher| ("Recent", launcher)));
+    for (category, launcher) in quick {
+        // Installed launchers are offered an update instead of a reinstall
+        let (label, action) = if app_paths.prefix_dir(launcher.dir_name).exists() {
+            (format!("Update {}", launcher.name), MenuAction::Update(launcher))
+        } else {
+            (format!("Install {}", launcher.name), MenuAction::Install(launcher))
+        };
+        entries.push(MenuEntry { category, label, action });
+    }
+
+    for category in [LauncherCategory::Store, LauncherCategory::GameSpecific] {
+        for launcher in registry.all().iter().filter(|launcher| launcher.category == category) {
+            entries.push(MenuEntry {
+                category: category.name(),
+                label: format!("Install {}", launcher.name),
+                action: MenuAction::Install(launcher),
+            });
+        }
+    }
+
+    let utilities = [
+        ("Run HoYoPlay Post-Setup (removes window decorations)".to_string(), MenuAction::HoyoplayPostSetup),
+        ("Fix Battle.net login (blank, frozen or looping login window)".to_string(), MenuAction::BattlenetFixLogin),
+        ("Pre-create compatdata prefix for a non-Steam shortcut".to_string(), MenuAction::PrecreatePrefix),
+        ("Apply a post-setup tweak to several prefixes".to_string(), MenuAction::BatchSetup),
+        (format!("Change Games root directory (currently {})", config.games_root.display()), MenuAction::ChangeGamesRoot),
+        ("Exit".to_string(), MenuAction::Exit),
+    ];
+    for (label, action) in utilities {
+        entries.push(MenuEntry { category: "Utilities", label, action });
+    }
+    entries
+}
+
+// Show the menu grouped by category. Typing text instead of a number narrows it down;
+// a search that leaves a single entry picks it. Returns None once stdin is closed.
+fn prompt_main_menu<'a>(entries: &[MenuEntry<'a>]) -> Option<MenuAction<'a>> {
+    let mut search = String::new();
+    loop {
+        let matches: Vec<usize> = (0..entries.len())
+        .filter(|i| entries[*i].label.to_lowercase().contains(&search))
+        .collect();
+
+        println!("What would you like to do?");
+        let mut category = "";
+        for i in &matches {
+            if entries[*i].category != category {
+                category = entries[*i].category;
+                println!("{}{}{}", COLOR_BLUE, category, COLOR_RESET);
+            }
+            if controller_prompts() {
+                println!("\n    [ {} ]   {}", i + 1, entries[*i].label);
+            } else {
+                println!("  {}) {}", i + 1, entries[*i].label);
+            }
+        }
+        if controller_prompts() {
+            print!("\nEnter a number: ");
+        } else if search.is_empty() {
+            print!("Enter a number, or type to search: ");
+        } else {
+            print!("Enter a number, search again (showing '{}'), or press Enter to show everything: ", search);
+        }
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if read_input(&mut input) == 0 {
+            return None;
+        }
+        let input = input.trim().to_lowercase();
+        if let Ok(choice) = input.parse::<usize>() {
+            match entries.get(choice.wrapping_sub(1)) {
+                Some(entry) => return Some(entry.action),
+                None => println!("{}Invalid choice. Please enter a number between 1 and {}.{}", COLOR_RED, entries.len(), COLOR_RESET),
+            }
+            continue;
+        }
+
+        search = input;
+        let mut found: Vec<&MenuEntry> = entries.iter().filter(|entry| entry.label.to_lowercase().contains(&search)).collect();
+        // A favorite or recent launcher is also listed under its category
+        found.sort_by(|a, b| a.label.cmp(&b.label));
+        found.dedup_by(|a, b| a.label == b.label);
+        match found.as_slice() {
+            [entry] => {
+                println!("{}{}{}", COLOR_GREEN, entry.label, COLOR_RESET);
+                return Some(entry.action);
+            },
+            [] => {
+                println!("{}Nothing matches '{}'.{}", COLOR_RED, search, COLOR_RESET);
+                search.clear();
+            },
+            _ => {},
+        }
+    }
+}
+
+// Ask which HoYoPlay edition to use
+fn prompt_hoyoplay_edition() -> Result<HoyoplayEdition, String> {
+    println!("{}Which edition do you want to use?{}", COLOR_BLUE, COLOR_RESET);
+    println!("1) Global (HoYoPlay)");
+    println!("2) China (miHoYo Launcher)");
+    print!("Enter your choice [1-2] (Default: 1): ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    read_input(&mut input);
+
+    match input.trim() {
+        "" | "1" => Ok(HoyoplayEdition::Global),
+        "2" => Ok(HoyoplayEdition::China),
+        _ => Err("Invalid edition selection.".to_string()),
+    }
+}
+
+// Find leftovers from the old layout (~/.battlenet, ~/.hoyoplay and launcher files directly
+// under <root>/<Launcher>) and return the (from, to) moves needed to migrate them
+fn find_legacy_layout_moves(app_paths: &AppPaths) -> Vec<(PathBuf, PathBuf)> {
+    let mut moves = Vec::new();
+
+    // Cached installers
+    let legacy_caches = [
+        (app_paths.home_dir.join(".battlenet"), "Battle.net"),
+        (app_paths.home_dir.join(".hoyoplay"), "HoYoPlay"),
+    ];
+    for (legacy_dir, launcher) in &legacy_caches {
+        if let Ok(entries) = fs::read_dir(legacy_dir) {
+            for entry in entries.flatten() {
+                moves.push((entry.path(), app_paths.cache_dir(launcher).join(entry.file_name())));
+            }
+        }
+    }
+
+    // Launchers copied straight into <root>/<Launcher> by older versions
+    for launcher in LauncherRegistry::builtin().all() {
+        let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+        if !launcher_dir.join(launcher.exe_name).exists() {
+            continue;
+        }
+        if let Ok(entries) = fs::read_dir(&launcher_dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                if name == "prefix" || name == "drive" || name == "cache" {
+                    continue;
+                }
+                moves.push((entry.path(), app_paths.drive_dir(launcher.dir_name).join(name)));
+            }
+        }
+    }
+
+    moves
+}
+
+// Offer to move files from the old layout into the per-launcher Games root layout
+fn migrate_legacy_layout(app_paths: &AppPaths) -> Result<(), String> {
+    let moves = find_legacy_layout_moves(app_paths);
+    if moves.is_empty() {
+        return Ok(());
+    }
+
+    println!("{}Found files from an older installer layout:{}", COLOR_YELLOW, COLOR_RESET);
+    for (from, to) in &moves {
+        println!("  {} -> {}", from.display(), to.display());
+    }
+    if !confirm(&format!("Move them into {} now?", app_paths.games_root.display())) {
+        return Ok(());
+    }
+
+    for (from, to) in &moves {
+        move_path(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    }
+
+    // Remove the now-empty legacy cache directories
+    let _ = fs::remove_dir(app_paths.home_dir.join(".battlenet"));
+    let _ = fs::remove_dir(app_paths.home_dir.join(".hoyoplay"));
+
+    println!("{}Migration complete.{}", COLOR_GREEN, COLOR_RESET);
+    if app_paths.home_dir.join(".wine").exists() {
+        println!("{}Note: ~/.wine is no longer used by this installer and was left untouched.{}", COLOR_YELLOW, COLOR_RESET);
+    }
+    Ok(())
+}
+
+// Before copying a launcher's files into a directory that already has files in it, show which
+// top-level entries the copy would replace, add or leave alone and let the user merge, overwrite
+// or pick another directory. Returns the directory to copy to, and sets `created` when another
+// directory is picked; headless runs merge.
+fn resolve_install_dir_conflict(src: &Path, dst: &Path, home_dir: &Path, created: &mut bool) -> Result<PathBuf, String> {
+    let names = |dir: &Path| -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).into_iter().flatten().flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+        names.sort();
+        names
+    };
+    let incoming = names(src);
+    let mut dst = dst.to_path_buf();
+    loop {
+        let existing = names(&dst);
+        if existing.is_empty() {
+            return Ok(dst);
+        }
+
+        println!("{}{} already contains {} entries:{}", COLOR_YELLOW, dst.display(), existing.len(), COLOR_RESET);
+        let mut all: Vec<&String> = existing.iter().chain(incoming.iter()).collect();
+        all.sort();
+        all.dedup();
+        for name in all.iter().take(30) {
+            match (existing.contains(name), incoming.contains(name)) {
+                (true, true) => println!("  ~ {} (replaced by the copy)", name),
+                (true, false) => println!("    {} (not part of the launcher)", name),
+                _ => println!("  + {} (new)", name),
+            }
+        }
+        if all.len() > 30 {
+            println!("  ...and {} more", all.len() - 30);
+        }
+        if headless() {
+            return Ok(dst);
+        }
+
+        match prompt_option("How should the launcher's files be copied there?", &[
+            "Merge: replace entries marked ~ and keep the others",
+            "Overwrite: delete everything in the directory first",
+            "Choose another directory",
+            "Cancel",
+        ]) {
+            0 => return Ok(dst),
+            1 => {
+                if !confirm(&format!("{}Delete the {} entries in {}? This cannot be undone.{}", COLOR_RED, existing.len(), dst.display(), COLOR_RESET)) {
+                    continue;
+                }
+                // Only the contents go, so a mount point or a directory with special permissions stays
+                for name in &existing {
+                    let path = dst.join(name);
+                    let result = if path.is_dir() && !path.is_symlink() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+                    result.map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+                }
+                return Ok(dst);
+            },
+            2 => {
+                println!("Installation directory:");
+                if let Some(path) = prompt_dir("Installation directory", &dst, home_dir) {
+                    *created = create_install_dir(&path)?;
+                    dst = path;
+                }
+            },
+            _ => return Err(format!("Copying to {} was cancelled. The launcher stays in the prefix at {}.", dst.display(), src.display())),
+        }
+    }
+}
+
+// Create the directory the launcher's files are copied to. Returns whether it was created, as
+// opposed to an existing directory the user picked, which purge-all leaves alone
+fn create_install_dir(dir: &Path) -> Result<bool, String> {
+    let created = !dir.exists();
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create installation directory: {}", e))?;
+    Ok(created)
+}
+
+// Copy a launcher's files, finishing everything that can be copied. Files that fail are listed
+// with the reason and can be retried or skipped; returns whether the copy is complete and
+// matches the source file for file, so the originals can be deleted.
+fn copy_launcher_files(src: &Path, dst: &Path) -> Result<bool, String> {
+    journal::record("copy", &[("from", &src.to_string_lossy()), ("to", &dst.to_string_lossy())]);
+    // Files a merge into an earlier copy would only overwrite with the same contents are skipped
+    let mut report = copy_changed_with_report(src, dst, DeltaCheck::SizeAndTime);
+    if report.unchanged > 0 {
+        println!("{} files in {} are unchanged and were not copied again.", report.unchanged, dst.display());
+    }
+    while !report.is_complete() {
+        println!("{}Copied {} files, but {} could not be copied:{}", COLOR_YELLOW, report.copied, report.failures.len(), COLOR_RESET);
+        for failure in report.failures.iter().take(20) {
+            println!("  {} ({})", failure.src.display(), failure.reason());
+        }
+        if report.failures.len() > 20 {
+            println!("  ...and {} more", report.failures.len() - 20);
+        }
+        if headless() {
+            return Ok(false);
+        }
+        match prompt_option("What should be done with them?", &[
+            "Retry them (e.g. after fixing permissions or freeing space)",
+            "Skip them and keep the original files in the prefix",
+            "Cancel",
+        ]) {
+            0 => report.retry(),
+            1 => return Ok(false),
+            _ => return Err(format!("Copying {} was cancelled.", src.display())),
+        }
+    }
+
+    // A copy can report success and still come up short (e.g. a file truncated by a full disk),
+    // and the originals are only deleted after this, so compare both trees before saying so
+    let verification = verify_copy(src, dst);
+    if !verification.is_complete() {
+        println!("{}The copy in {} is incomplete: {} of {} files ({} of {}) are missing or differ.{}",
+                 COLOR_YELLOW, dst.display(), verification.missing_files, verification.files,
+                 format_size(verification.missing_bytes), format_size(verification.bytes), COLOR_RESET);
+        return Ok(false);
+    }
+    println!("Verified {} files ({}) in {}.", verification.files, format_size(verification.bytes), dst.display());
+    Ok(true)
+}
+
+// Read a directory after its question was printed: from the desktop's folder picker when one
+// can be shown, otherwise (over SSH, on a tty, or when the picker is cancelled) as typed input.
+// None means the answer was left empty.
+fn prompt_dir(title: &str, start: &Path, home_dir: &Path) -> Option<PathBuf> {
+    if controller_prompts() {
+        return pick_listed_dir(start, home_dir);
+    }
+    if let Some(picker) = FolderPicker::detect().filter(|_| !headless()) {
+        println!("Choose it in the folder picker, or cancel the picker to type it here.");
+        if let Some(dir) = picker.pick_directory(title, start) {
+            println!("> {}", dir.display());
+            return Some(dir);
+        }
+    }
+    print!("> ");
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    read_input(&mut input);
+    parse_path_input(&input, home_dir)
+}
+
+// Offer `start` and the same place on each mounted removable drive (e.g. a Steam Deck's SD card)
+// as a numbered list, for controller prompts where no path can be typed
+fn pick_listed_dir(start: &Path, home_dir: &Path) -> Option<PathBuf> {
+    let relative = start.strip_prefix(home_dir).ok()
+    .map(Path::to_path_buf)
+    .or_else(|| start.file_name().map(PathBuf::from))
+    .unwrap_or_default();
+    let user = home_dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut dirs = vec![start.to_path_buf()];
+    for media in [PathBuf::from("/run/media").join(&user), PathBuf::from("/run/media"), PathBuf::from("/media").join(&user), PathBuf::from("/mnt")] {
+        for entry in fs::read_dir(&media).into_iter().flatten().flatten() {
+            let dir = entry.path().join(&relative);
+            if entry.path().is_dir() && entry.path() != Path::new("/run/media").join(&user) && !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    let mut options = vec!["Keep the default".to_string()];
+    options.extend(dirs.iter().map(|dir| dir.display().to_string()));
+    let options: Vec<&str> = options.iter().map(String::as_str).collect();
+    match prompt_option("Choose a directory:", &options) {
+        0 => None,
+        choice => Some(dirs[choice - 1].clone()),
+    }
+}
+
+// Ask a yes/no question, taking no for an empty answer
+fn confirm(question: &str) -> bool {
+    confirm_or(question, false)
+}
+
+// Ask a yes/no question, taking `default` for an empty answer. With controller prompts it is
+// a numbered choice between Yes and No instead.
+fn confirm_or(question: &str, default: bool) -> bool {
+    if controller_prompts() {
+        let options = if default { ["Yes", "No"] } else { ["No", "Yes"] };
+        return (prompt_option(question, &options) == 0) == default;
+    }
+    print!("{} ({})\n> ", question, if default { "Y/n" } else { "yes/no" });
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    read_input(&mut input);
+    match input.trim().to_lowercase().as_str() {
+        "yes" | "y" => true,
+        "no" | "n" => false,
+        _ => default,
+    }
+}
+
+// Ask a numbered question, returning the index of the chosen option (the first on empty input)
+fn prompt_option(question: &str, options: &[&str]) -> usize {
+    loop {
+        println!("{}{}{}", COLOR_BLUE, question, COLOR_RESET);
+        for (i, option) in options.iter().enumerate() {
+            if controller_prompts() {
+                println!("\n    [ {} ]   {}", i + 1, option);
+            } else {
+                println!("{}) {}", i + 1, option);
+            }
+        }
+        if controller_prompts() {
+            println!();
+        }
+        print!("Enter your choice [1-{}, default 1]: ", options.len());
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if read_input(&mut input) == 0 || input.trim().is_empty() {
+            return 0;
+        }
+        match input.trim().parse::<usize>() {
+            Ok(choice) if (1..=options.len()).contains(&choice) => return choice - 1,
+            _ => println!("{}Invalid choice.{}", COLOR_RED, COLOR_RESET),
+        }
+    }
+}
+
+// Walk a new user through the settings that matter before the first install and save them
+fn run_setup_wizard(home_dir: &Path) -> Result<Config, String> {
+    let mut config = Config::load(home_dir);
+    println!("{}Welcome! Let's set up a few things first. Press Enter to accept the default.{}\n", COLOR_BLUE, COLOR_RESET);
+
+    // x86-64 wine needs an emulator on ARM64, so offer FEX-Emu and Box64 there (Proton-ARM builds work through umu)
+    let mut runners = vec![
+        (Runner::SystemWine, "System wine", "wine"),
+        (Runner::Umu, "Proton through umu-launcher", "umu-run"),
+    ];
+    if host_is_arm64() {
+        runners.push((Runner::Fex, "x86-64 wine under FEX-Emu", "FEXInterpreter"));
+        runners.push((Runner::Box64, "x86-64 wine under Box64", "box64"));
+    }
+    let labels: Vec<String> = runners.iter()
+    .map(|(_, label, program)| {
+        format!("{} ({})", label, if find_in_path(program).is_some() { "found" } else { "not installed" })
+    })
+    .collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    config.runner = runners[prompt_option("How should launchers be run?", &labels)].0;
+
+    println!("\n{}Where should launchers be installed? [{}]{}", COLOR_BLUE, config.games_root.display(), COLOR_RESET);
+    if let Some(games_root) = prompt_dir("Games root", &config.games_root, home_dir) {
+        config.games_root = games_root;
+    }
+
+    println!();
+    config.steam_integration = match prompt_option("What should be done in Steam after an install?", &[
+        "Show how to add the launcher as a non-Steam game",
+        "Add the non-Steam shortcut automatically (Steam must be closed)",
+        "Nothing, I don't use Steam",
+    ]) {
+        1 => SteamIntegrationLevel::Shortcut,
+        2 => SteamIntegrationLevel::Off,
+        _ => SteamIntegrationLevel::Instructions,
+    };
+
+    println!();
+    config.local_log = prompt_option(
+        &format!("Keep a log of installs in {}? It stays on this machine; nothing is ever sent anywhere.",
+                 Config::log_path(home_dir).display()),
+        &["No", "Yes"],
+    ) == 1;
+
+    config.save(home_dir)?;
+    println!("\n{}Settings saved to {}. Edit them there later, or check them with `config check`.{}\n",
+             COLOR_GREEN, Config::path(home_dir).display(), COLOR_RESET);
+    Ok(config)
+}
+
+// Write progress events to the local log when `logging = local` and no other target was given
+fn open_local_log(home_dir: &Path) {
+    let path = Config::log_path(home_dir);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Err(e) = open_progress_sink(&path.to_string_lossy()) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+}
+
+// Change the Games root directory and save it to the config file
+fn change_games_root(config: &mut Config, home_dir: &Path) -> Result<(), String> {
+    println!("{}Current Games root: {}{}", COLOR_BLUE, config.games_root.display(), COLOR_RESET);
+    println!("New Games root (leave empty to keep): ");
+    let games_root = match prompt_dir("Games root", &config.games_root, home_dir) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    config.games_root = games_root;
+    config.save(home_dir)?;
+    println!("{}Games root set to {}{}", COLOR_GREEN, config.games_root.display(), COLOR_RESET);
+    Ok(())
+}
+
+// Ask the user to pick a non-Steam shortcut and return its App ID
+fn select_nonsteam_appid(prompt: &str) -> Result<String, String> {
+    if !protontricks_available() {
+        return Err("protontricks is not installed. Please install it first.".to_string());
+    }
+
+    println!("{}Detecting non-Steam games (protontricks -l):{}", COLOR_BLUE, COLOR_RESET);
+    let games = list_nonsteam_games()?;
+
+    if games.is_empty() {
+        return Err("No non-Steam games found!".to_string());
+    }
+
+    println!("{}{}{}", COLOR_YELLOW, prompt, COLOR_RESET);
+    for (i, game) in games.iter().enumerate() {
+        println!("{:2}) {}", i+1, game);
+    }
+
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    read_input(&mut input);
+
+    let game_index = match input.trim().parse::<usize>() {
+        Ok(i) if i >= 1 && i <= games.len() => i - 1,
+        _ => return Err("Invalid selection.".to_string()),
+    };
+
+    let selected_line = &games[game_index];
+    extract_appid(selected_line)
+    .ok_or_else(|| "Could not extract App ID.".to_string())
+}
+
+// Find HoYoPlay's non-Steam shortcut by its executable in shortcuts.vdf, asking only when there
+// are several. None when there is none, so the user picks from the protontricks list instead.
+fn detect_hoyoplay_shortcut(steam: &SteamIntegration) -> Result<Option<String>, String> {
+    let registry = LauncherRegistry::builtin();
+    let exe_name = registry.get("hoyoplay").expect("HoYoPlay is a built-in launcher").exe_name;
+    let shortcuts = steam.shortcuts_named(exe_name);
+    let describe = |shortcut: &Shortcut| format!("{} ({}, App ID {})", shortcut.app_name, shortcut.exe, shortcut.app_id.unwrap_or_default());
+    let shortcut = match shortcuts.as_slice() {
+        [] => return Ok(None),
+        [shortcut] => {
+            println!("{}Found the HoYoPlay shortcut: {}{}", COLOR_GREEN, describe(shortcut), COLOR_RESET);
+            shortcut
+        },
+        _ => {
+            let labels: Vec<String> = shortcuts.iter().map(describe).collect();
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            &shortcuts[prompt_option("Several Steam shortcuts start HoYoPlay. Which one should be set up?", &labels)]
+        },
+    };
+    Ok(shortcut.app_id.map(|app_id| app_id.to_string()))
+}
+
+// Create and initialize the compatdata prefix for an App ID, like Steam does on first launch
+fn init_compatdata_prefix(wine_path: &str, app_id: &str, steam: &SteamIntegration) -> Result<PathBuf, String> {
+    if let Some(existing) = steam.find_prefix(app_id) {
+        println!("{}Prefix already exists at {}. Skipping initialization.{}",
+                 COLOR_YELLOW, existing.display(), COLOR_RESET);
+        return Ok(existing);
+    }
+
+    // Non-Steam shortcuts always get their compatdata in the main Steam library. initialize
+    // runs wineboot in the prefix's WineSession, so it starts and waits on the runner's own
+    // wineserver (wineserver_for(wine_path)) rather than whichever one is on PATH.
+    let prefix = PrefixManager::new(steam.shortcut_prefix_path(app_id)?);
+    prefix.initialize(wine_path)?;
+    Ok(prefix.path().to_path_buf())
+}
+
+// Pre-create the compatdata prefix for a newly added non-Steam shortcut
+fn run_prefix_precreate(wine_path: &str) -> Result<(), String> {
+    let app_id = select_nonsteam_appid("Select the shortcut to create a prefix for:")?;
+    let steam = SteamIntegration::discover()?;
+    let prefix_path = init_compatdata_prefix(wine_path, &app_id, &steam)?;
+    prompt_font_smoothing(&PrefixManager::new(prefix_path));
+    Ok(())
+}
+
+// Ask for a Linux directory for HoYoPlay's games and give it a drive letter, so games of 60 GB
+// and more don't have to go into the prefix's drive_c
+fn relocate_hoyoplay_downloads(prefix: &PrefixManager) -> Result<(), String> {
+    if !confirm("Would you like a directory outside the prefix for HoYoPlay's games?") {
+        return Ok(());
+    }
+    let edition = match HoyoplayEdition::detect(prefix.path()) {
+        Some(edition) => edition,
+        None => prompt_hoyoplay_edition()?,
+    };
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let games_dir = match prompt_dir("HoYoPlay games directory", &home_dir, &home_dir) {
+        Some(games_dir) => games_dir,
+        None => return Ok(()),
+    };
+    let letter = map_games_dir(prefix, &games_dir)?;
+    println!("{}{} is now drive {}: in the prefix.{}", COLOR_GREEN, games_dir.display(), letter, COLOR_RESET);
+    println!("{}When {} asks where to install a game, choose {}:\\ (or a folder on it) to keep the game there.{}",
+             COLOR_YELLOW, edition.launcher().name, letter, COLOR_RESET);
+    Ok(())
+}
+
+// Set the prefix locale, codepage and timezone, picked from a list or inherited from the host
+fn configure_prefix_region(prefix: &PrefixManager) -> Result<(), String> {
+    if !confirm("Would you like to set the prefix locale and timezone?") {
+        return Ok(());
+    }
+
+    let host_language = Language::from_environment();
+    println!("{}Select the prefix locale (Enter for host locale {}):{}", COLOR_YELLOW, host_language.windows, COLOR_RESET);
+    for (i, language) in LANGUAGES.iter().enumerate() {
+        println!("{:2}) {}", i + 1, language.windows);
+    }
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    read_input(&mut input);
+    let language = match input.trim() {
+        "" => host_language,
+        choice => match choice.parse::<usize>() {
+            Ok(i) if i >= 1 && i <= LANGUAGES.len() => LANGUAGES[i - 1],
+            _ => return Err("Invalid locale selection.".to_string()),
+        },
+    };
+
+    let host_zone = host_timezone().and_then(|tz| windows_timezone(&tz));
+    println!("{}Select the prefix timezone (Enter for host timezone {}):{}",
+             COLOR_YELLOW, host_zone.unwrap_or("unknown, keeps wine's default"), COLOR_RESET);
+    for (i, (name, _)) in TIMEZONES.iter().enumerate() {
+        println!("{:2}) {}", i + 1, name);
+    }
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    read_input(&mut input);
+    let timezone = match input.trim() {
+        "" => host_zone,
+        choice => match choice.parse::<usize>() {
+            Ok(i) if i >= 1 && i <= TIMEZONES.len() => Some(TIMEZONES[i - 1].1),
+            _ => return Err("Invalid timezone selection.".to_string()),
+        },
+    };
+
+    prefix.set_locale(&language)?;
+    if let Some(timezone) = timezone {
+        prefix.set_timezone(timezone)?;
+    }
+    Ok(())
+}
+
+// Set the prefix audio driver, defaulting to what suits the detected host audio stack
+fn configure_prefix_audio(prefix: &PrefixManager) -> Result<(), String> {
+    if !confirm("Would you like to configure the prefix audio driver (fixes crackling audio)?") {
+        return Ok(());
+    }
+
+    let detected = AudioStack::detect();
+    println!("{}Detected host audio: {}{}", COLOR_GREEN, detected.name(), COLOR_RESET);
+    println!("{}Select the audio setup (Enter for the {} default):{}", COLOR_YELLOW, detected.name(), COLOR_RESET);
+    println!("1) PipeWire (pulse driver, large buffers)");
+    println!("2) PulseAudio (pulse driver)");
+    println!("3) ALSA");
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    read_input(&mut input);
+    let stack = match input.trim() {
+        "" => detected,
+        "1" => AudioStack::PipeWire,
+        "2" => AudioStack::PulseAudio,
+        "3" => AudioStack::Alsa,
+        _ => return Err("Invalid audio selection.".to_string()),
+    };
+
+    prefix.set_audio(&stack.default_settings())
+}
+
+// Offer to turn HDR on (or back off) in the shortcut's Steam launch options
+fn prompt_hdr(steam: &SteamIntegration, app_id: &str) {
+    let app_id = match app_id.parse::<u32>() {
+        Ok(app_id) => app_id,
+        Err(_) => return,
+    };
+    let current = steam.shortcut_launch_options(app_id).unwrap_or_default();
+    let (question, updated) = if has_hdr(&current) {
+        ("HDR is turned on for this game. Turn it off?", without_hdr(&current))
+    } else if let Some(session) = detect_hdr_session() {
+        println!("{}HDR-capable session detected: {}.{}", COLOR_GREEN, session.describe(), COLOR_RESET);
+        ("Turn on HDR for this game?", with_hdr(&current, session))
+    } else {
+        println!("{}HDR is not offered: no gamescope session or Plasma 6 screen with HDR enabled was found.{}", COLOR_YELLOW, COLOR_RESET);
+        return;
+    };
+    if !confirm(question) {
+        return;
+    }
+
+    match steam.set_shortcut_launch_options(app_id, &updated) {
+        Ok(0) => println!("{}Set these Steam launch options for the game: {}{}", COLOR_YELLOW, updated, COLOR_RESET),
+        Ok(_) => println!("{}Steam launch options set to: {}{}", COLOR_GREEN, updated, COLOR_RESET),
+        Err(e) => {
+            println!("{}{}{}", COLOR_YELLOW, e, COLOR_RESET);
+            println!("{}Or set these Steam launch options for the game: {}{}", COLOR_YELLOW, updated, COLOR_RESET);
+        },
+    }
+}
+
+// Run HoYoPlay post-setup
+fn run_hoyoplay_postsetup(wine_path: &str, options: &InstallOptions) -> Result<(), String> {
+    let steam = SteamIntegration::discover()?;
+    let app_id = match detect_hoyoplay_shortcut(&steam)? {
+        Some(app_id) => app_id,
+        None => select_nonsteam_appid("Select the HoYoPlay entry from the list below:")?,
+    };
+
+    let prefix_path = match steam.find_prefix(&app_id) {
+        Some(path) => path,
+        None => {
+            println!("{}No compatdata prefix found for App ID {}.{}", COLOR_YELLOW, app_id, COLOR_RESET);
+            if !confirm("Create and initialize it now instead of launching from Steam first?") {
+                return Err(format!("Could not find compatdata prefix for App ID {} in any Steam library.", app_id));
+            }
+            init_compatdata_prefix(wine_path, &app_id, &steam)?
+        }
+    };
+
+    println!("{}Found prefix: {}{}", COLOR_GREEN, prefix_path.display(), COLOR_RESET);
+
+    let prefix = PrefixManager::new(&prefix_path);
+    let session = prefix.session(wine_path);
+    session.claim()?;
+    if options.sandbox {
+        prefix.sandbox()?;
+    } else {
+        prefix.link_linux_root()?;
+
+        println!("{}You can now access your Linux filesystem from within the game installer by navigating to C:\\Linux Root in the file dialog (look under 'Computer' > 'C:').{}",
+                 COLOR_GREEN, COLOR_RESET);
+    }
+
+    prefix.disable_window_decorations()?;
+
+    configure_prefix_region(&prefix)?;
+
+    prompt_font_smoothing(&prefix);
+
+    configure_prefix_audio(&prefix)?;
+
+    prompt_hdr(&steam, &app_id);
+
+    relocate_hoyoplay_downloads(&prefix)?;
+
+    // The registry changes are written out when the prefix's wineserver exits
+    session.wait(Some(Duration::from_secs(60)))?;
+
+    Ok(())
+}
+
+// `batch-setup`: apply one post-setup tweak to several prefixes in a single pass
+fn run_batch_setup_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let wine_path = find_runner(&config, &app_paths.home_dir).ok_or("No usable runner found. Please install wine and try again.")?;
+
+    // This tool's launcher prefixes, then the compatdata prefixes of non-Steam shortcuts
+    let registry = LauncherRegistry::builtin();
+    let mut targets: Vec<(String, PathBuf, Option<&LauncherInfo>)> = registry.all().iter()
+    .map(|launcher| (format!("{} (launcher prefix)", launcher.name), app_paths.prefix_dir(launcher.dir_name), Some(launcher)))
+    .filter(|(_, prefix, _)| prefix.join("system.reg").exists())
+    .collect();
+    if let Ok(steam) = SteamIntegration::discover() {
+        for shortcut in steam.shortcuts() {
+            let app_id = shortcut.app_id.unwrap_or_default().to_string();
+            if let Some(prefix) = steam.find_prefix(&app_id).filter(|prefix| !targets.iter().any(|(_, found, _)| found == prefix)) {
+                targets.push((format!("{} (Steam shortcut, App ID {})", shortcut.app_name, app_id), prefix, None));
+            }
+        }
+    }
+    if targets.is_empty() {
+        return Err("No prefixes found. Install a launcher, or start a non-Steam shortcut from Steam once.".to_string());
+    }
+
+    println!("{}Prefixes:{}", COLOR_BLUE, COLOR_RESET);
+    for (i, (label, _, _)) in targets.iter().enumerate() {
+        println!("{:2}) {}", i + 1, label);
+    }
+    print!("Enter the numbers of the prefixes to change, separated by commas, or `all`:\n> ");
+    io::stdout().flush().unwrap();
+    let mut selection = String::new();
+    read_input(&mut selection);
+    let selected: Vec<&(String, PathBuf, Option<&LauncherInfo>)> = if selection.trim().eq_ignore_ascii_case("all") {
+        targets.iter().collect()
+    } else {
+        let mut selected = Vec::new();
+        for part in selection.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.parse::<usize>() {
+                Ok(i) if i >= 1 && i <= targets.len() => selected.push(&targets[i - 1]),
+                _ => return Err(format!("Invalid prefix selection: {}", part)),
+            }
+        }
+        selected
+    };
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    let action = prompt_option("Which tweak should be applied?", &[
+        "Remove window decorations",
+        "Enable RGB font smoothing",
+        "Set the DPI (display scaling)",
+    ]);
+    let tweak = match action {
+        0 => Tweak::NoWindowDecorations,
+        1 => Tweak::FontSmoothing,
+        _ => Tweak::Dpi([96, 120, 144, 192][prompt_option("Which scaling?", &["100% (96 DPI)", "125% (120 DPI)", "150% (144 DPI)", "200% (192 DPI)"])]),
+    };
+
+    let mut results = Vec::new();
+    for (label, prefix_path, launcher) in &selected {
+        println!("\n{}{}{}", COLOR_BLUE, label, COLOR_RESET);
+        let prefix = PrefixManager::new(prefix_path);
+        let session = prefix.session(&wine_path);
+        let result = session.claim().and_then(|_| prefix.apply_tweak(tweak))
+        // The registry changes are written out when the prefix's wineserver exits
+        .and_then(|_| session.wait(Some(Duration::from_secs(60))));
+        match (&result, launcher) {
+            (Ok(()), Some(launcher)) => record_tweak(&app_paths, launcher, tweak),
+            (Err(e), _) => println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET),
+            _ => {},
+        }
+        results.push((label, result));
+    }
+
+    println!("\n{}Summary:{}", COLOR_BLUE, COLOR_RESET);
+    for (label, result) in &results {
+        match result {
+            Ok(()) => println!("  {}done{}    {}", COLOR_GREEN, COLOR_RESET, label),
+            Err(e) => println!("  {}failed{}  {}: {}", COLOR_RED, COLOR_RESET, label, e),
+        }
+    }
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        return Err(format!("The tweak could not be applied to {} of {} prefixes.", failed, results.len()));
+    }
+    Ok(())
+}
+
+// `dlloverrides <launcher> [list | add <dll> <mode> | remove <dll>]`
+fn run_dll_overrides_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: dlloverrides <launcher> [list | add <dll> <mode> | remove <dll>]")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let overrides_path = app_paths.dll_overrides_path(launcher.dir_name);
+    let mut overrides = DllOverrides::load(&overrides_path)?;
+
+    let mut removed = Vec::new();
+    match args.get(1).map(String::as_str).unwrap_or("list") {
+        "list" => {},
+        "add" => {
+            let dll = args.get(2).ok_or("Usage: dlloverrides <launcher> add <dll> <mode>")?;
+            let mode = args.get(3).ok_or("Usage: dlloverrides <launcher> add <dll> <mode>")?;
+            overrides.set(dll, mode)?;
+            overrides.save(&overrides_path)?;
+        },
+        "remove" => {
+            let dll = args.get(2).ok_or("Usage: dlloverrides <launcher> remove <dll>")?;
+            if !overrides.remove(dll) {
+                return Err(format!("No override for {} is set for {}.", dll, launcher.name));
+            }
+            overrides.save(&overrides_path)?;
+            removed.push(dll.to_lowercase());
+        },
+        other => return Err(format!("Unknown dlloverrides action: {}", other)),
+    }
+
+    if args.len() > 1 && args[1] != "list" {
+        let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+        if prefix.path().exists() {
+            prefix.apply_dll_overrides(&overrides, &removed)?;
+            println!("{}Updated the registry of {}.{}", COLOR_GREEN, prefix.path().display(), COLOR_RESET);
+        }
+    }
+
+    println!("{}DLL overrides for {}:{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+    if overrides.entries().is_empty() {
+        println!("  (none)");
+    }
+    for (dll, mode) in overrides.entries() {
+        println!("  {} = {}", dll, if mode.is_empty() { "disabled" } else { mode });
+    }
+    println!("{}Steam launch options: WINEDLLOVERRIDES=\"{}\" %command%{}",
+             COLOR_YELLOW, overrides.to_env(), COLOR_RESET);
+    Ok(())
+}
+
+// `wrapper <launcher> [bwrap | firejail] [install dir]`
+fn run_wrapper_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: wrapper <launcher> [bwrap | firejail] [install dir]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+    let tool = match args.get(1) {
+        Some(name) => SandboxTool::parse(name).ok_or_else(|| format!("Unknown sandbox tool: {} (use bwrap or firejail)", name))?,
+        None => SandboxTool::Bubblewrap,
+    };
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let install_dir = match args.get(2) {
+        Some(dir) => parse_path_input(dir, &app_paths.home_dir).ok_or(usage)?,
+        None => app_paths.drive_dir(launcher.dir_name),
+    };
+    let mut exe = install_dir.join(launcher.exe_name);
+    if !exe.exists() {
+        exe = args.get(2).is_none().then(|| pick_prefix_exe(&app_paths.prefix_dir(launcher.dir_name), launcher)).flatten()
+        .ok_or_else(|| format!("{} not found in {}. Pass the install directory as the last argument.",
+                               launcher.exe_name, install_dir.display()))?;
+    }
+    let install_dir = exe.parent().map(Path::to_path_buf).unwrap_or(install_dir);
+
+    let available = Command::new("which").arg(tool.command()).status().map(|s| s.success()).unwrap_or(false);
+    if !available {
+        println!("{}Warning: {} is not installed; the script will not run until it is.{}", COLOR_YELLOW, tool.command(), COLOR_RESET);
+    }
+
+    let config = Config::load(&app_paths.home_dir);
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    let wrapper = LaunchWrapper {
+        tool,
+        wine_path,
+        prefix: app_paths.prefix_dir(launcher.dir_name),
+        install_dir,
+        exe,
+        wayland_driver: use_wayland_driver(&config),
+    };
+    let script_path = app_paths.launch_script_path(launcher.dir_name);
+    wrapper.write(&script_path)?;
+    println!("{}Wrote sandboxed launch script {}{}", COLOR_GREEN, script_path.display(), COLOR_RESET);
+
+    let entry_path = desktop_entry_path(&app_paths.home_dir, launcher);
+    if let Some(parent) = entry_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&entry_path, desktop_entry(launcher, &script_path))
+    .map_err(|e| format!("Failed to write {}: {}", entry_path.display(), e))?;
+    println!("{}Wrote desktop entry {}{}", COLOR_GREEN, entry_path.display(), COLOR_RESET);
+
+    println!("{}To use it from Steam, add {} as a non-Steam game with compatibility tools off.{}",
+             COLOR_YELLOW, script_path.display(), COLOR_RESET);
+    Ok(())
+}
+
+// `export <launcher> [file]`
+fn run_export_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: export <launcher> [file]")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let output = match args.get(1) {
+        Some(file) => parse_path_input(file, &app_paths.home_dir).ok_or("Usage: export <launcher> [file]")?,
+        None => PathBuf::from(format!("{}.{}", launcher.id, ARCHIVE_EXTENSION)),
+    };
+
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+    if !prefix.path().exists() {
+        return Err(format!("{} has no prefix at {}", launcher.name, prefix.path().display()));
+    }
+    // A running wineserver may still be writing the registry
+    prefix.kill_wineserver();
+
+    let overrides = DllOverrides::load(&app_paths.dll_overrides_path(launcher.dir_name))?;
+    let manifest = ArchiveManifest::for_prefix(
+        prefix.path(),
+        launcher.id,
+        &prefix.runner().unwrap_or_default(),
+        vec![("WINEDLLOVERRIDES".to_string(), overrides.to_env())],
+        prefix.applied_tweaks(),
+    )?;
+    create_archive(prefix.path(), &manifest, &output)?;
+    println!("{}{} files, {} bytes.{}", COLOR_GREEN, manifest.files, manifest.bytes, COLOR_RESET);
+    Ok(())
+}
+
+// `import <file>`
+fn run_import_command(args: &[String]) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let archive = args.first()
+    .and_then(|file| parse_path_input(file, &home_dir))
+    .ok_or("Usage: import <file>")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+
+    let manifest = read_manifest(&archive)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(&manifest.launcher)
+    .ok_or_else(|| format!("Archive is for an unknown launcher: {}", manifest.launcher))?;
+
+    let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+    if prefix_path.exists() {
+        return Err(format!("{} already has a prefix at {}. Move it away before importing.",
+                           launcher.name, prefix_path.display()));
+    }
+    fs::create_dir_all(app_paths.launcher_dir(launcher.dir_name))
+    .map_err(|e| format!("Failed to create launcher directory: {}", e))?;
+
+    extract_archive(&archive, &prefix_path)?;
+    println!("{}Wine build: {}{}", COLOR_GREEN, if manifest.runner.is_empty() { "unknown" } else { &manifest.runner }, COLOR_RESET);
+    if !manifest.tweaks.is_empty() {
+        println!("{}Tweaks: {}{}", COLOR_GREEN, manifest.tweaks.join(", "), COLOR_RESET);
+    }
+    for (name, value) in &manifest.env {
+        println!("{}Steam launch options: {}=\"{}\" %command%{}", COLOR_YELLOW, name, value, COLOR_RESET);
+    }
+    Ok(())
+}
+
+// `update <launcher>`: reinstall the launcher silently if a new installer is being served.
+// Runs without prompts so the systemd timer from `enable-auto-update` can call it.
+fn run_update_command(args: &[String], options: &InstallOptions) -> Result<(), String> {
+    let usage = "Usage: update <launcher> [--checksum]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let check = match args.get(1).map(String::as_str) {
+        None => DeltaCheck::SizeAndTime,
+        Some("--checksum") => DeltaCheck::Hash,
+        Some(_) => return Err(usage.to_string()),
+    };
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+    if !prefix_path.exists() {
+        return Err(format!("{} is not installed (no prefix at {}).", launcher.name, prefix_path.display()));
+    }
+    if let Some(pin) = config.pinned_installer(launcher.id) {
+        println!("{}{} is pinned to {}; not updating.{}", COLOR_YELLOW, launcher.name, pin, COLOR_RESET);
+        return Ok(());
+    }
+
+    let latest = remote_file_identity(launcher.installer_url)
+    .ok_or_else(|| format!("Could not check for a new {} installer.", launcher.name))?;
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    match (&state.installer_identity, &state.installer_version) {
+        (Some(identity), _) if *identity == latest => {
+            println!("{}{} is up to date.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+            return Ok(());
+        },
+        (None, None) => {
+            // Nothing to compare against yet: remember what is served now and compare next time
+            state.installer_identity = Some(latest);
+            state.save(&state_path)?;
+            println!("{}Recorded the current {} installer; later checks will apply newer ones.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+            return Ok(());
+        },
+        _ => {},
+    }
+
+    println!("{}A new {} installer is available. Updating...{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    let installer = launcher_installer(&wine_path, &app_paths, &config, options, launcher)?;
+
+    let cached = app_paths.cache_dir(launcher.dir_name).join(launcher.installer_file_name);
+    let _ = fs::remove_file(&cached);
+    let installer_path = installer.fetch(launcher, &app_paths.cache_dir(launcher.dir_name))?;
+
+    // A new download can still be the version that is installed, e.g. a re-signed build
+    let version = show_installer_info(&installer_path);
+    if version.is_some() && version == state.installer_version {
+        state.installer_identity = Some(latest);
+        state.save(&state_path)?;
+        println!("{}{} is already at this version.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+        return Ok(());
+    }
+
+    installer.prefix().update_for_runner(&wine_path)?;
+    let mut command = installer.silent_command(&installer_path);
+    command.args(launcher.silent_install_args(options.language));
+    let menus = MenuSnapshot::take(&app_paths.home_dir);
+    // Nobody is there to answer, so a hung installer is always stopped
+    let status = installer.run_watched(&mut command, |_| true);
+    installer.finish();
+    sweep_menu_files(&app_paths.home_dir, &menus);
+    let status = status?.ok_or_else(|| format!("The {} installer hung and was stopped.", launcher.name))?;
+    if status != 0 {
+        return Err(format!("The {} installer failed with exit code {}.", launcher.name, status));
+    }
+
+    // Refresh the copy in the install directory if that is where the launcher lives. An original
+    // location that links to the copy was updated in place.
+    let install_dir = state.install_dir.clone().unwrap_or_else(|| app_paths.drive_dir(launcher.dir_name));
+    if install_dir.join(launcher.exe_name).exists() {
+        let source = launcher.find_install_dir(&prefix_path)
+        .filter(|source| fs::canonicalize(source).ok() != fs::canonicalize(&install_dir).ok());
+        if let Some(source) = source {
+            // Only the files the update changed are copied
+            let report = copy_changed_with_report(&source, &install_dir, check);
+            if let Some(failure) = report.failures.first() {
+                return Err(format!("Failed to copy {} of the updated files, e.g. {} ({}).",
+                                   report.failures.len(), failure.src.display(), failure.reason()));
+            }
+            println!("Copied {} changed files to {}; {} were unchanged.", report.copied, install_dir.display(), report.unchanged);
+        }
+    }
+
+    state.installer_identity = Some(latest);
+    state.installer_version = version.clone();
+    state.client_version = client_version(launcher, &install_dir, &prefix_path);
+    state.save(&state_path)?;
+    println!("{}{} updated.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+    let body = match version {
+        Some(version) => format!("Installed version {}.", version),
+        None => "A new launcher version was installed.".to_string(),
+    };
+    notify(&format!("{} updated", launcher.name), &body);
+    Ok(())
+}
+
+// `enable-auto-update <launcher>` and `disable-auto-update <launcher>`
+fn run_auto_update_command(enable: bool, args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: enable-auto-update <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+
+    if enable {
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to locate installer binary: {}", e))?;
+        enable_auto_update(&home_dir, launcher, &exe)
+    } else {
+        disable_auto_update(&home_dir, launcher)
+    }
+}
+
+// `reapply <launcher> [--timer | --no-timer]`: apply the tweaks recorded in the launcher's state
+// and its DLL overrides to its prefix again, e.g. after a self-update reset them
+fn run_reapply_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: reapply <launcher> [--timer | --no-timer]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    match args.get(1).map(String::as_str) {
+        Some("--timer") => {
+            let exe = std::env::current_exe().map_err(|e| format!("Failed to locate installer binary: {}", e))?;
+            return enable_reapply_timer(&home_dir, launcher, &exe);
+        },
+        Some("--no-timer") => return disable_reapply_timer(&home_dir, launcher),
+        Some(_) => return Err(usage.to_string()),
+        None => {},
+    }
+
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+    if !prefix.path().join("system.reg").exists() {
+        return Err(format!("{} is not installed ({} does not exist).", launcher.name, prefix.path().display()));
+    }
+    let state = LauncherState::load(&app_paths.state_path(launcher.dir_name));
+    let overrides = DllOverrides::load(&app_paths.dll_overrides_path(launcher.dir_name))?;
+    if state.tweaks.is_empty() && overrides.entries().is_empty() {
+        println!("{}No tweaks or DLL overrides are recorded for {}.{}", COLOR_YELLOW, launcher.name, COLOR_RESET);
+        return Ok(());
+    }
+
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    let session = prefix.session(&wine_path);
+    session.claim()?;
+    for tweak in &state.tweaks {
+        emit_progress("post-setup", None, &format!("Applying {}", tweak.name()));
+        prefix.apply_tweak(*tweak)?;
+    }
+    if !overrides.entries().is_empty() {
+        emit_progress("post-setup", None, "Applying DLL overrides");
+        prefix.apply_dll_overrides(&overrides, &[])?;
+        println!("{}DLL overrides applied to prefix {}.{}", COLOR_GREEN, prefix.path().display(), COLOR_RESET);
+    }
+    // The registry changes are written out when the prefix's wineserver exits
+    session.wait(Some(Duration::from_secs(60)))
+}
+
+// `restore-registry <launcher or prefix> [backup]`: undo registry changes from a backup taken
+// before a tweak changed them
+fn run_restore_registry_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: restore-registry <launcher or prefix directory> [backup]";
+    let target = args.first().ok_or(usage)?;
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let prefix_path = match LauncherRegistry::builtin().get(target) {
+        Some(launcher) => AppPaths { games_root: config.games_root.clone(), home_dir: home_dir.clone() }.prefix_dir(launcher.dir_name),
+        None => parse_path_input(target, &home_dir).ok_or(usage)?,
+    };
+
+    let backups = registry_backups(&home_dir, &prefix_path);
+    if backups.is_empty() {
+        return Err(format!("No registry backups of {} were found.", prefix_path.display()));
+    }
+    let describe = |(dir, keys): &(PathBuf, Vec<String>)| {
+        let time: u64 = dir.file_name()
+        .and_then(|name| name.to_string_lossy().split('-').next().and_then(|time| time.parse().ok()))
+        .unwrap_or(0);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+        let hours = now.saturating_sub(time) / 3600;
+        let ago = if hours < 48 { format!("{} hours ago", hours) } else { format!("{} days ago", hours / 24) };
+        format!("{} ({}): {}", dir.file_name().unwrap_or_default().to_string_lossy(), ago, keys.join(", "))
+    };
+    let backup = match args.get(1) {
+        Some(name) => backups.iter().find(|(dir, _)| dir.file_name().is_some_and(|dir_name| dir_name.to_string_lossy() == name.as_str()))
+        .ok_or_else(|| format!("No backup named {}. Run `restore-registry {}` to list them.", name, target))?,
+        None => {
+            // Newest first, since undoing the last change is the usual case
+            let labels: Vec<String> = backups.iter().rev().map(describe).collect();
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            &backups[backups.len() - 1 - prompt_option("Which backup should be restored?", &labels)]
+        },
+    };
+    if !confirm(&format!("Restore {} to how it was before {}?", prefix_path.display(), describe(backup))) {
+        return Ok(());
+    }
+
+    let wine_path = find_runner(&config, &home_dir).ok_or("No usable runner found. Please install wine and try again.")?;
+    let prefix = PrefixManager::new(&prefix_path);
+    let session = prefix.session(&wine_path);
+    session.claim()?;
+    let restored = prefix.restore_registry(&backup.0)?;
+    // The registry changes are written out when the prefix's wineserver exits
+    session.wait(Some(Duration::from_secs(60)))?;
+    println!("{}Restored {} registry keys in {}.{}", COLOR_GREEN, restored, prefix_path.display(), COLOR_RESET);
+    Ok(())
+}
+
+// `prefix link <launcher, app ID or prefix directory> [list | add <dir> [name] | remove <name>]`
+fn run_prefix_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: prefix link <launcher, app ID or prefix directory> [list | add <directory> [name] | remove <name>]";
+    if args.first().map(String::as_str) != Some("link") {
+        return Err(usage.to_string());
+    }
+    let target = args.get(1).ok_or(usage)?;
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let prefix_path = match LauncherRegistry::builtin().get(target) {
+        Some(launcher) => AppPaths { games_root: Config::load(&home_dir).games_root, home_dir: home_dir.clone() }.prefix_dir(launcher.dir_name),
+        None if target.chars().all(|c| c.is_ascii_digit()) => SteamIntegration::discover()?.find_prefix(target)
+        .ok_or_else(|| format!("No Proton prefix found for app ID {}.", target))?,
+        None => {
+            let path = parse_path_input(target, &home_dir).ok_or(usage)?;
+            // A compatdata directory holds the Wine prefix in pfx
+            if path.join("pfx/drive_c").is_dir() { path.join("pfx") } else { path }
+        },
+    };
+    if !prefix_path.join("drive_c").is_dir() {
+        return Err(format!("{} is not a Wine prefix.", prefix_path.display()));
+    }
+
+    match args.get(2).map(String::as_str).unwrap_or("list") {
+        "list" => {
+            let links = links::list_links(&prefix_path);
+            if links.is_empty() {
+                println!("No links to Linux directories in {}.", prefix_path.display());
+            }
+            for (name, target) in links {
+                let status = if target.exists() { String::new() } else { format!(" {}(missing){}", COLOR_RED, COLOR_RESET) };
+                println!("C:\\{} -> {}{}", name, target.display(), status);
+            }
+        },
+        "add" => {
+            let dir = args.get(3).and_then(|dir| parse_path_input(dir, &home_dir)).ok_or(usage)?;
+            let name = args.get(4).cloned().unwrap_or_else(|| links::default_link_name(&dir));
+            let link = links::add_link(&prefix_path, &dir, &name)?;
+            println!("{}Linked {} to {}{}", COLOR_GREEN, dir.display(), link.display(), COLOR_RESET);
+        },
+        "remove" => {
+            let name = args.get(3).ok_or(usage)?;
+            links::remove_link(&prefix_path, name)?;
+            println!("{}Removed C:\\{} from {}{}", COLOR_GREEN, name, prefix_path.display(), COLOR_RESET);
+        },
+        _ => return Err(usage.to_string()),
+    }
+    Ok(())
+}
+
+// `links audit [--clean]`: check the symlinks this tool created across all prefixes
+fn run_links_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: links audit [--clean]";
+    if args.first().map(String::as_str) != Some("audit") {
+        return Err(usage.to_string());
+    }
+    let clean = match args.get(1).map(String::as_str) {
+        None => false,
+        Some("--clean") => true,
+        Some(_) => return Err(usage.to_string()),
+    };
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths { games_root: Config::load(&home_dir).games_root, home_dir: home_dir.clone() };
+
+    let mut prefixes: Vec<PathBuf> = LauncherRegistry::builtin().all().iter().map(|launcher| app_paths.prefix_dir(launcher.dir_name)).collect();
+    if let Ok(steam) = SteamIntegration::discover() {
+        for library in steam.libraries() {
+            if let Ok(entries) = fs::read_dir(library.join("steamapps/compatdata")) {
+                prefixes.extend(entries.flatten().map(|entry| entry.path().join("pfx")));
+            }
+        }
+    }
+
+    let audited = links::audit(&home_dir, &prefixes);
+    if audited.is_empty() {
+        println!("No links found.");
+        return Ok(());
+    }
+    for entry in &audited {
+        let status = match &entry.status {
+            LinkStatus::Ok => format!("{}ok{}", COLOR_GREEN, COLOR_RESET),
+            LinkStatus::Broken => format!("{}broken, target is gone{}", COLOR_RED, COLOR_RESET),
+            LinkStatus::Missing => format!("{}link was removed{}", COLOR_YELLOW, COLOR_RESET),
+            LinkStatus::Replaced(current) => format!("{}now points to {}{}", COLOR_YELLOW, current.display(), COLOR_RESET),
+        };
+        println!("{} -> {} ({})", entry.link.display(), entry.target.display(), status);
+    }
+
+    let broken: Vec<PathBuf> = audited.iter().filter(|entry| entry.status == LinkStatus::Broken).map(|entry| entry.link.clone()).collect();
+    let missing: Vec<PathBuf> = audited.iter().filter(|entry| entry.status == LinkStatus::Missing).map(|entry| entry.link.clone()).collect();
+    if !clean {
+        if !broken.is_empty() || !missing.is_empty() {
+            println!("Run `links audit --clean` to remove the broken links and forget the removed ones.");
+        }
+        return Ok(());
+    }
+    if broken.is_empty() && missing.is_empty() {
+        println!("{}Nothing to clean up.{}", COLOR_GREEN, COLOR_RESET);
+        return Ok(());
+    }
+    // Links that now point elsewhere were changed by someone else and are left alone
+    if !broken.is_empty() && !confirm(&format!("Remove {} broken links?", broken.len())) {
+        println!("{}Nothing was removed.{}", COLOR_YELLOW, COLOR_RESET);
+        return Ok(());
+    }
+    for link in &broken {
+        fs::remove_file(link).map_err(|e| format!("Failed to remove {}: {}", link.display(), e))?;
+    }
+    links::forget_links(&home_dir, &[broken.as_slice(), missing.as_slice()].concat())?;
+    println!("{}Removed {} broken links and forgot {} removed ones.{}", COLOR_GREEN, broken.len(), missing.len(), COLOR_RESET);
+    Ok(())
+}
+
+// `migrate-prefix <launcher> --to <wine | Proton build> [--no-backup]`: move a prefix to
+// another runner family, e.g. a system wine install to GE-Proton
+fn run_migrate_prefix_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: migrate-prefix <launcher> --to <wine | Proton build, e.g. proton-ge-9> [--no-backup]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+    let mut target = None;
+    let mut backup = true;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--to" => target = rest.next().cloned(),
+            "--no-backup" => backup = false,
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let target = target.ok_or(usage)?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+    if !prefix.path().join("system.reg").exists() {
+        return Err(format!("{} is not installed ({} does not exist).", launcher.name, prefix.path().display()));
+    }
+    if !prefix.process_ids().is_empty() {
+        return Err(format!("Programs are running in {}'s prefix. Close them first.", launcher.name));
+    }
+
+    // Proton always runs as steamuser, wine as the Linux user
+    let (wine_path, user) = if target == "wine" {
+        let user = std::env::var("USER").map_err(|_| "Could not determine the user name")?;
+        (find_system_wine().ok_or("System wine was not found. Please install wine and try again.")?, user)
+    } else {
+        let steam = SteamIntegration::discover()?;
+        let wine = steam.proton_wine(&target).ok_or_else(|| {
+            format!("No installed Proton build matches {}. Installed: {}", target, steam.proton_versions().join(", "))
+        })?;
+        (wine.to_string_lossy().to_string(), "steamuser".to_string())
+    };
+    println!("{}Migrating {} from {} to {}.{}", COLOR_BLUE, launcher.name,
+             prefix.runner().unwrap_or_else(|| "an unknown wine build".to_string()), wine_path, COLOR_RESET);
+    if !confirm_or("Continue?", true) {
+        return Ok(());
+    }
+    prefix.kill_wineserver();
+
+    if backup {
+        let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+        if btrfs::is_subvolume(&launcher_dir) {
+            btrfs::snapshot(&launcher_dir, &app_paths.snapshots_dir(launcher.dir_name))?;
+            println!("{}Snapshotted {}; `rollback {}` undoes the migration.{}", COLOR_GREEN, launcher_dir.display(), launcher.id, COLOR_RESET);
+        } else {
+            let output = launcher_dir.join(format!("prefix-before-migration.{}", ARCHIVE_EXTENSION));
+            let manifest = ArchiveManifest::for_prefix(prefix.path(), launcher.id, &prefix.runner().unwrap_or_default(), Vec::new(), prefix.applied_tweaks())?;
+            create_archive(prefix.path(), &manifest, &output)?;
+            println!("{}Backed up the prefix to {}; move the prefix away and `import` it to undo the migration.{}",
+                     COLOR_GREEN, output.display(), COLOR_RESET);
+        }
+    }
+
+    if prefix.rename_user_dir(&user)? {
+        println!("{}Moved the Windows user profile to {}.{}", COLOR_GREEN, user, COLOR_RESET);
+    }
+    prefix.update_for_runner(&wine_path)?;
+
+    // wineboot reinstalls the runner's DLLs; put the overrides and tweaks back on top
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    let overrides = DllOverrides::load(&app_paths.dll_overrides_path(launcher.dir_name))?;
+    let session = prefix.session(&wine_path);
+    session.claim()?;
+    for tweak in &state.tweaks {
+        prefix.apply_tweak(*tweak)?;
+    }
+    if !overrides.entries().is_empty() {
+        prefix.apply_dll_overrides(&overrides, &[])?;
+    }
+    let registry_readable = session.command("reg")
+    .args(["query", "HKCU\\Software\\Wine"])
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .is_ok_and(|status| status.success());
+    session.wait(Some(Duration::from_secs(60)))?;
+
+    let mut problems = Vec::new();
+    if prefix.runner_wine().as_deref() != Some(wine_path.as_str()) {
+        problems.push("the prefix was not updated for the new runner");
+    }
+    if !registry_readable {
+        problems.push("the new runner could not read the prefix's registry");
+    }
+    if !prefix.path().join("drive_c/users").join(&user).is_dir() {
+        problems.push("the Windows user profile is missing");
+    }
+    if !problems.is_empty() {
+        return Err(format!("The migrated prefix failed validation: {}. Restore the backup to go back.", problems.join(", ")));
+    }
+
+    state.runner = Some(wine_path.clone());
+    state.save(&state_path)?;
+    println!("{}{} now runs with {}.{}", COLOR_GREEN, launcher.name, wine_path, COLOR_RESET);
+    Ok(())
+}
+
+// `trust-installer <launcher>`: add the hash of the installer an install would use to the allowlist
+fn run_trust_installer_command(args: &[String], options: &InstallOptions) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: trust-installer <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let installer = Installer::new("wine", PrefixManager::new(app_paths.prefix_dir(launcher.dir_name)))
+    .with_installer_source(installer_source(options, &app_paths, launcher));
+    let installer_path = installer.fetch(launcher, &app_paths.cache_dir(launcher.dir_name))?;
+
+    let sha256 = sha256_file(&installer_path)?;
+    HashAllowlist::add(&HashAllowlist::path(&app_paths.home_dir), &sha256, launcher.id)?;
+    println!("{}Trusted {} (SHA-256 {}).{}", COLOR_GREEN, installer_path.display(), sha256, COLOR_RESET);
+    Ok(())
+}
+
+// `pin <launcher> [<url or file> | --clear]`
+fn run_pin_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: pin <launcher> [<url or file> | --clear]")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let mut config = Config::load(&home_dir);
+    match args.get(1).map(String::as_str) {
+        None => {},
+        Some("--clear") => {
+            config.set_pinned_installer(launcher.id, None);
+            config.save(&home_dir)?;
+        },
+        Some(source) => {
+            let source = if is_url(source) {
+                source.to_string()
+            } else {
+                let path = parse_path_input(source, &home_dir).ok_or("Usage: pin <launcher> <url or file>")?;
+                let path = fs::canonicalize(&path).map_err(|e| format!("Cannot pin {}: {}", path.display(), e))?;
+                path.to_string_lossy().to_string()
+            };
+            config.set_pinned_installer(launcher.id, Some(&source));
+            config.save(&home_dir)?;
+        },
+    }
+
+    match config.pinned_installer(launcher.id) {
+        Some(source) => println!("{}{} is pinned to {}{}", COLOR_GREEN, launcher.name, source, COLOR_RESET),
+        None => println!("{}{} is not pinned and installs the current installer.{}", COLOR_GREEN, launcher.name, COLOR_RESET),
+    }
+    Ok(())
+}
+
+// Create a launcher's compatdata directory if it doesn't exist yet, and remember that this tool
+// created it so `purge-all` may remove it. An existing directory is the user's.
+fn create_compat_data_dir(app_paths: &AppPaths, launcher: &LauncherInfo, dir: &Path) -> Result<(), String> {
+    if dir.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let dir = fs::canonicalize(dir).map_err(|e| format!("Failed to resolve {}: {}", dir.display(), e))?;
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.compat_data_created = Some(dir);
+    state.save(&state_path)
+}
+
+// `compatdata <launcher> [<dir> | --clear]`
+fn run_compatdata_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: compatdata <launcher> [<dir> | --clear]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let mut config = Config::load(&home_dir);
+    let dir = match args.get(1).map(String::as_str) {
+        None => {
+            match config.compat_data_path(launcher.id) {
+                Some(dir) => println!("{}{}'s Steam shortcut keeps its prefix in {}{}", COLOR_GREEN, launcher.name, dir.display(), COLOR_RESET),
+                None => println!("{}{}'s Steam shortcut uses Steam's own compatdata.{}", COLOR_GREEN, launcher.name, COLOR_RESET),
+            }
+            return Ok(());
+        },
+        Some("--clear") => None,
+        Some(dir) => {
+            let dir = parse_path_input(dir, &home_dir).ok_or(usage)?;
+            let app_paths = AppPaths { games_root: config.games_root.clone(), home_dir: home_dir.clone() };
+            create_compat_data_dir(&app_paths, launcher, &dir)?;
+            Some(fs::canonicalize(&dir).map_err(|e| format!("Failed to resolve {}: {}", dir.display(), e))?)
+        },
+    };
+    config.set_compat_data_path(launcher.id, dir.as_deref());
+    config.save(&home_dir)?;
+    match &dir {
+        Some(dir) => println!("{}{}'s Steam shortcut now keeps its prefix in {}{}", COLOR_GREEN, launcher.name, dir.display(), COLOR_RESET),
+        None => println!("{}{}'s Steam shortcut uses Steam's own compatdata again.{}", COLOR_GREEN, launcher.name, COLOR_RESET),
+    }
+
+    // Point an existing shortcut there as well; new ones get it when they are added
+    let app_paths = AppPaths { games_root: config.games_root.clone(), home_dir };
+    let exe = app_paths.drive_dir(launcher.dir_name).join(launcher.exe_name);
+    let steam = match SteamIntegration::discover() {
+        Ok(steam) => steam,
+        Err(_) => return Ok(()),
+    };
+    let shortcut = match steam.shortcut_for_exe(&exe) {
+        Some(shortcut) => shortcut,
+        None => return Ok(()),
+    };
+    let updated = with_compat_data_path(&shortcut.launch_options, dir.as_deref());
+    match shortcut.app_id.map(|app_id| steam.set_shortcut_launch_options(app_id, &updated)) {
+        Some(Ok(updated_count)) if updated_count > 0 => println!("{}Steam launch options set to: {}{}", COLOR_GREEN, updated, COLOR_RESET),
+        Some(Err(e)) => {
+            println!("{}{}{}", COLOR_YELLOW, e, COLOR_RESET);
+            println!("{}Or set these Steam launch options for {}: {}{}", COLOR_YELLOW, launcher.name, updated, COLOR_RESET);
+        },
+        _ => println!("{}Set these Steam launch options for {}: {}{}", COLOR_YELLOW, launcher.name, updated, COLOR_RESET),
+    }
+    if dir.is_some() {
+        println!("{}Steam creates the prefix there the next time {} is started from it.{}", COLOR_YELLOW, launcher.name, COLOR_RESET);
+    }
+    Ok(())
+}
+
+// Offer to clear what an earlier, unfinished install left in the prefix (running Agent
+// processes, the Update Agent's state), which is the usual reason the installer exits at once
+fn offer_agent_reset(prefix: &PrefixManager) {
+    let leftover = match find_leftover_agent(prefix) {
+        Some(leftover) => leftover,
+        None => return,
+    };
+
+    println!("{}An earlier Battle.net install left this behind in the prefix:{}", COLOR_YELLOW, COLOR_RESET);
+    if !leftover.processes.is_empty() {
+        println!("  - Running: {}", leftover.processes.join(", "));
+    }
+    for dir in &leftover.state_dirs {
+        println!("  - {}", dir.display());
+    }
+    println!("{}The installer often exits immediately until this is cleared.{}", COLOR_YELLOW, COLOR_RESET);
+    if confirm_or("Stop these processes and clear the Agent's state before installing?", true) {
+        if let Err(e) = reset_agent(prefix, &leftover) {
+            println!("{}{}{}", COLOR_RED, e, COLOR_RESET);
+        }
+    }
+}
+
+// `battlenet-fix-login`
+fn run_battlenet_fix_login_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+
+    let prefix = PrefixManager::new(app_paths.prefix_dir("Battle.net"));
+    if !prefix.path().exists() {
+        return Err(format!("Battle.net is not installed (no prefix at {}).", prefix.path().display()));
+    }
+    // The client keeps its config and cache open while it runs
+    prefix.kill_wineserver();
+
+    let overrides_path = app_paths.dll_overrides_path("Battle.net");
+    let mut overrides = DllOverrides::load(&overrides_path)?;
+    fix_login(&prefix, &mut overrides)?;
+    overrides.save(&overrides_path)?;
+
+    println!("{}Login fixes applied. Start Battle.net again and log in.{}", COLOR_GREEN, COLOR_RESET);
+    println!("If it runs from a Steam shortcut, set its launch options to: WINEDLLOVERRIDES=\"{}\" %command%", overrides.to_env());
+    Ok(())
+}
+
+// `battlenet-library <dir>`
+fn run_battlenet_library_command(args: &[String]) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let games_dir = args.first()
+    .and_then(|dir| parse_path_input(dir, &home_dir))
+    .ok_or("Usage: battlenet-library <dir>")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+
+    let prefix = PrefixManager::new(app_paths.prefix_dir("Battle.net"));
+    if !prefix.path().exists() {
+        return Err(format!("Battle.net is not installed (no prefix at {}).", prefix.path().display()));
+    }
+    set_battlenet_games_dir(&prefix, &games_dir)
+}
+
+// `share <launcher> [install dir]`
+fn run_share_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: share <launcher> [install dir]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let shared_root = config.shared_root.clone().ok_or("Set `shared_root` in the config file first, e.g. shared_root = /opt/game-launchers")?;
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let install_dir = match args.get(1) {
+        Some(dir) => parse_path_input(dir, &app_paths.home_dir).ok_or(usage)?,
+        None => app_paths.drive_dir(launcher.dir_name),
+    };
+
+    let destination = publish(&install_dir, &shared_root, launcher, &config.shared_group)?;
+    println!("{}{} is now shared from {} (writable by group {}).{}",
+             COLOR_GREEN, launcher.name, destination.display(), config.shared_group, COLOR_RESET);
+    println!("Other users can set the same `shared_root` and run `use-shared {}`.", launcher.id);
+    Ok(())
+}
+
+// `use-shared <launcher>`: a prefix and Steam shortcut of this user's own for shared launcher files
+fn run_use_shared_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: use-shared <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let shared_root = config.shared_root.clone().ok_or("Set `shared_root` in the config file first.")?;
+    let shared_dir = shared_launcher_dir(&shared_root, launcher);
+    if !shared_dir.join(launcher.exe_name).exists() {
+        return Err(format!("{} has not been shared to {} yet. Run `share {}` as the user who installed it.",
+                           launcher.name, shared_dir.display(), launcher.id));
+    }
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+    fs::create_dir_all(app_paths.launcher_dir(launcher.dir_name))
+    .map_err(|e| format!("Failed to create launcher directory: {}", e))?;
+    clone_prefix_template(&app_paths, &prefix);
+    if !prefix.path().join("system.reg").exists() {
+        println!("{}Creating your prefix for {}...{}", COLOR_BLUE, launcher.name, COLOR_RESET);
+        prefix.initialize(&wine_path)?;
+    } else {
+        prefix.update_for_runner(&wine_path)?;
+    }
+    let overrides = DllOverrides::load(&app_paths.dll_overrides_path(launcher.dir_name))?;
+    prefix.apply_dll_overrides(&overrides, &[])?;
+
+    record_launcher_use(&app_paths, launcher);
+    println!("{}{} is set up for you with the shared files in {}.{}", COLOR_GREEN, launcher.name, shared_dir.display(), COLOR_RESET);
+    integrate_with_steam(&app_paths, launcher, launcher.name, &shared_dir, Config::load(&app_paths.home_dir).steam_integration, None);
+    Ok(())
+}
+
+// `favorite [<launcher> [--remove]]`
+fn run_favorite_command(args: &[String]) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let mut config = Config::load(&home_dir);
+    let registry = LauncherRegistry::builtin();
+
+    if let Some(launcher_id) = args.first() {
+        let launcher = registry.get(launcher_id)
+        .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+        config.favorites.retain(|id| id != launcher.id);
+        if args.get(1).map(String::as_str) != Some("--remove") {
+            config.favorites.push(launcher.id.to_string());
+        }
+        config.save(&home_dir)?;
+    }
+
+    if config.favorites.is_empty() {
+        println!("{}No favorite launchers.{}", COLOR_GREEN, COLOR_RESET);
+    } else {
+        println!("{}Favorite launchers: {}{}", COLOR_GREEN, config.favorites.join(", "), COLOR_RESET);
+    }
+    Ok(())
+}
+
+// `notes <launcher>`
+fn run_notes_command(args: &[String]) -> Result<(), String> {
+    let name = args.first().ok_or("Usage: notes <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.find(name).ok_or_else(|| format!("Unknown launcher: {}", name))?;
+
+    print_launcher_notes(launcher);
+    if !launcher.notes.after_install.is_empty() {
+        println!("{}After installing:{}", COLOR_YELLOW, COLOR_RESET);
+        for step in launcher.notes.after_install {
+            println!("  - {}", step);
+        }
+    }
+    Ok(())
+}
+
+// `env <launcher>`: the environment the launcher's installers run with, without running anything
+fn run_env_command(args: &[String], options: &InstallOptions) -> Result<(), String> {
+    let name = args.first().ok_or("Usage: env <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.find(name).ok_or_else(|| format!("Unknown launcher: {}", name))?;
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths { games_root: config.games_root.clone(), home_dir };
+
+    let wine_path = launcher_runner(&app_paths, launcher).unwrap_or_else(|| "wine".to_string());
+    let installer = launcher_installer(&wine_path, &app_paths, &config, options, launcher)?;
+
+    println!("{}Silent installs of {} run {} with:{}", COLOR_BLUE, launcher.name, wine_path, COLOR_RESET);
+    print_environment(&installer.environment(true));
+    println!("\n{}Installers that show their window run with:{}", COLOR_BLUE, COLOR_RESET);
+    print_environment(&installer.environment(false));
+    println!("\nChange a variable with `env.<NAME> = <value>` (every launcher) or `env.{}.<NAME> = <value>` in {}; an empty value removes it.",
+             launcher.id, Config::path(&app_paths.home_dir).display());
+    println!("`installer_hygiene` picks the sets of variables that keep overlays out of installers (* = used, `off` for none):");
+    for set in HYGIENE_SETS {
+        let used = config.installer_hygiene.iter().any(|name| name == set.name);
+        println!("  {} {:<16} {}", if used { "*" } else { " " }, set.name, set.description);
+    }
+    Ok(())
+}
+
+// `scan`
+fn run_scan_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let steam = SteamIntegration::discover().ok();
+    let found = scan(&app_paths, &LauncherRegistry::builtin(), steam.as_ref());
+    if found.is_empty() {
+        println!("{}No launchers found in Lutris, Bottles, Steam or ~/.wine prefixes.{}", COLOR_GREEN, COLOR_RESET);
+        return Ok(());
+    }
+
+    let mut adopted = 0;
+    for install in &found {
+        let launcher = &install.launcher;
+        println!("{}Found {} ({}) in {}{}", COLOR_BLUE, launcher.name, install.source, install.install_dir.display(), COLOR_RESET);
+        if fs::symlink_metadata(app_paths.prefix_dir(launcher.dir_name)).is_ok() {
+            println!("{}{} is already managed by this tool; skipping.{}", COLOR_YELLOW, launcher.name, COLOR_RESET);
+            continue;
+        }
+        if !confirm("Adopt it, so update and post-setup manage this prefix?") {
+            continue;
+        }
+        adopt(&app_paths, install)?;
+        println!("{}{} now uses {}{}", COLOR_GREEN, app_paths.prefix_dir(launcher.dir_name).display(), install.prefix.display(), COLOR_RESET);
+        adopted += 1;
+    }
+
+    if adopted > 0 {
+        println!("{}Adopted {} launchers. Run `update <launcher>` to record their installer for later updates.{}", COLOR_GREEN, adopted, COLOR_RESET);
+    }
+    Ok(())
+}
+
+// `gc`
+fn run_gc_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let steam = SteamIntegration::discover().ok();
+    let orphans = find_orphans(&app_paths, &LauncherRegistry::builtin(), steam.as_ref());
+    if orphans.is_empty() {
+        println!("{}Nothing to clean up.{}", COLOR_GREEN, COLOR_RESET);
+        return Ok(());
+    }
+
+    println!("{}Found {} orphaned items:{}", COLOR_YELLOW, orphans.len(), COLOR_RESET);
+    for orphan in &orphans {
+        println!("  {}", orphan.describe());
+    }
+    if !confirm("Remove them?") {
+        println!("{}Nothing was removed.{}", COLOR_YELLOW, COLOR_RESET);
+        return Ok(());
+    }
+
+    let removed = remove_orphans(&orphans)?;
+    println!("{}Removed {} items.{}", COLOR_GREEN, removed, COLOR_RESET);
+    Ok(())
+}
+
+// `purge-all`
+fn run_purge_all_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let registry = LauncherRegistry::builtin();
+    let steam = SteamIntegration::discover().ok();
+    let artifacts = find_artifacts(&app_paths, &registry, steam.as_ref());
+    let kept = kept_install_dirs(&app_paths, &registry);
+    let kept_compat_data = kept_compat_data_dirs(&app_paths, &registry);
+    if artifacts.is_empty() {
+        println!("{}Nothing this tool created was found.{}", COLOR_GREEN, COLOR_RESET);
+        return Ok(());
+    }
+
+    println!("{}This removes everything the tool created ({} items):{}", COLOR_YELLOW, artifacts.len(), COLOR_RESET);
+    for artifact in &artifacts {
+        println!("  {}", artifact.describe());
+    }
+    if !kept.is_empty() {
+        println!("These install directories existed before the launchers were installed into them and are left alone:");
+        for dir in &kept {
+            println!("  {}", dir.display());
+        }
+    }
+    if !kept_compat_data.is_empty() {
+        println!("These compatdata directories existed before they were set for a launcher's shortcut and are left alone:");
+        for dir in &kept_compat_data {
+            println!("  {}", dir.display());
+        }
+    }
+    println!("Adopted prefixes, Steam's own game prefixes and the targets of links are left alone.");
+    if !confirm("Remove all of this? Installed launchers and their games cannot be recovered.") {
+        println!("{}Nothing was removed.{}", COLOR_YELLOW, COLOR_RESET);
+        return Ok(());
+    }
+
+    // Stop wine in the prefixes first, so nothing writes to them while they are removed
+    for launcher in registry.all() {
+        let prefix = app_paths.prefix_dir(launcher.dir_name);
+        if prefix.exists() {
+            PrefixManager::new(prefix).kill_wineserver();
+        }
+    }
+
+    let (removed, failures) = remove_artifacts(&app_paths.home_dir, &artifacts);
+    println!("{}Removed {} items.{}", COLOR_GREEN, removed, COLOR_RESET);
+    if !failures.is_empty() {
+        return Err(format!("Some items could not be removed:\n  {}", failures.join("\n  ")));
+    }
+    Ok(())
+}
+
+// `dedupe`
+fn run_dedupe_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+
+    // Adopted prefixes belong to other tools and are left alone, as are prefixes in use
+    let mut prefixes = Vec::new();
+    for launcher in LauncherRegistry::builtin().all() {
+        let prefix = app_paths.prefix_dir(launcher.dir_name);
+        if !fs::symlink_metadata(&prefix).is_ok_and(|metadata| metadata.is_dir()) {
+            continue;
+        }
+        if !PrefixManager::new(prefix.clone()).process_ids().is_empty() {
+            println!("{}Skipping {}: programs are running in its prefix.{}", COLOR_YELLOW, launcher.name, COLOR_RESET);
+            continue;
+        }
+        prefixes.push(prefix);
+    }
+    if prefixes.len() < 2 {
+        println!("{}Deduplication needs at least two prefixes; found {}.{}", COLOR_YELLOW, prefixes.len(), COLOR_RESET);
+        return Ok(());
+    }
+
+    println!("{}Looking for identical files in {} prefixes...{}", COLOR_BLUE, prefixes.len(), COLOR_RESET);
+    let sets = find_duplicates(&prefixes);
+    let files: usize = sets.iter().map(|set| set.duplicates.len()).sum();
+    let savings: u64 = sets.iter().map(|set| set.savings()).sum();
+    if sets.is_empty() {
+        println!("{}No duplicate files to reflink.{}", COLOR_GREEN, COLOR_RESET);
+        return Ok(());
+    }
+
+    println!("{}Found {} duplicate files; reflinking them saves {}. Largest:{}", COLOR_YELLOW, files, format_size(savings), COLOR_RESET);
+    for set in sets.iter().take(10) {
+        println!("  {} x{} ({})", set.original.display(), set.duplicates.len() + 1, format_size(set.size));
+    }
+    if !confirm("Replace the duplicates with reflinks?") {
+        println!("{}Nothing was changed.{}", COLOR_YELLOW, COLOR_RESET);
+        return Ok(());
+    }
+
+    let (linked, saved) = link_duplicates(&sets)?;
+    if linked < files {
+        println!("{}{} files changed since they were found and were left alone.{}", COLOR_YELLOW, files - linked, COLOR_RESET);
+    }
+    println!("{}Reflinked {} files, saving {}.{}", COLOR_GREEN, linked, format_size(saved), COLOR_RESET);
+    Ok(())
+}
+
+// `clean-menus`
+fn run_clean_menus_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let mut roots = vec![config.games_root.clone()];
+    roots.extend(config.shared_root.clone());
+    let files = prefix_menu_files(&home_dir, &roots);
+    if files.is_empty() {
+        println!("{}No wine menu entries from prefixes under {}.{}", COLOR_GREEN, config.games_root.display(), COLOR_RESET);
+        return Ok(());
+    }
+
+    println!("{}Found {} menu files created by wine in this tool's prefixes:{}", COLOR_YELLOW, files.len(), COLOR_RESET);
+    for file in &files {
+        println!("  {}", file.display());
+    }
+    if !confirm("Remove them?") {
+        println!("{}Nothing was removed.{}", COLOR_YELLOW, COLOR_RESET);
+        return Ok(());
+    }
+
+    let removed = remove_menu_files(&home_dir, &files)?;
+    println!("{}Removed {} files.{}", COLOR_GREEN, removed, COLOR_RESET);
+    Ok(())
+}
+
+// `template [show | bake [verbs...] | download [wine major] | remove]`
+fn run_template_command(args: &[String]) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let path = template_path(&home_dir);
+    match args.first().map(String::as_str).unwrap_or("show") {
+        "show" => {
+            if !path.is_file() {
+                println!("{}No prefix template. Run `template bake` to create one.{}", COLOR_YELLOW, COLOR_RESET);
+                return Ok(());
+            }
+            let manifest = read_manifest(&path)?;
+            let verbs = template_verbs(&manifest);
+            println!("{}Prefix template: {}{}", COLOR_BLUE, path.display(), COLOR_RESET);
+            println!("Wine build: {}", if manifest.runner.is_empty() { "unknown" } else { &manifest.runner });
+            println!("Verbs: {}", if verbs.is_empty() { "none".to_string() } else { verbs.join(", ") });
+            println!("Size: {} in {} files", format_size(manifest.bytes), manifest.files);
+            Ok(())
+        },
+        "bake" => {
+            let verbs: Vec<String> = if args.len() > 1 {
+                args[1..].to_vec()
+            } else {
+                DEFAULT_VERBS.iter().map(|verb| verb.to_string()).collect()
+            };
+            let config = Config::load(&home_dir);
+            let wine_path = find_runner(&config, &home_dir).ok_or("No usable runner found. Please install wine and try again.")?;
+            let manifest = bake_template(&home_dir, &wine_path, &verbs)?;
+            println!("{}Prefix template baked ({}, {} files). New launcher prefixes start from it.{}",
+                     COLOR_GREEN, format_size(manifest.bytes), manifest.files, COLOR_RESET);
+            Ok(())
+        },
+        "download" => {
+            let config = Config::load(&home_dir);
+            let wine_major = match args.get(1) {
+                Some(major) => major.parse::<u32>().map_err(|_| format!("Not a wine major version: {}", major))?,
+                None => {
+                    let wine_path = find_runner(&config, &home_dir).ok_or("No usable runner found. Please install wine and try again.")?;
+                    wine_major_version(&wine_path)
+                    .ok_or("Could not tell the runner's wine version. Give it, e.g. `template download 9`.")?
+                },
+            };
+            println!("{}Downloading the pre-baked template for wine {}...{}", COLOR_BLUE, wine_major, COLOR_RESET);
+            let manifest = download_template(&home_dir, &config.template_url, config.signing_key.as_deref(), wine_major)?;
+            let verbs = template_verbs(&manifest);
+            println!("{}Prefix template installed (verbs: {}). New launcher prefixes start from it.{}",
+                     COLOR_GREEN, if verbs.is_empty() { "none".to_string() } else { verbs.join(", ") }, COLOR_RESET);
+            Ok(())
+        },
+        "remove" => {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            println!("{}Prefix template removed.{}", COLOR_GREEN, COLOR_RESET);
+            Ok(())
+        },
+        other => Err(format!("Unknown template action: {}. Usage: template [show | bake [verbs...] | download [wine major] | remove]", other)),
+    }
+}
+
+// `config check` / `config schema`
+fn run_config_command(args: &[String]) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    match args.first().map(String::as_str) {
+        Some("check") => {
+            let path = Config::path(&home_dir);
+            let issues = Config::check(&home_dir)?;
+            if issues.is_empty() {
+                println!("{}{} is valid.{}", COLOR_GREEN, path.display(), COLOR_RESET);
+                return Ok(());
+            }
+            for issue in &issues {
+                println!("{}{}: {}{}", COLOR_RED, path.display(), issue, COLOR_RESET);
+            }
+            Err(format!("Found {} problems in {}", issues.len(), path.display()))
+        },
+        Some("schema") => {
+            print!("{}", Config::json_schema());
+            Ok(())
+        },
+        _ => Err("Usage: config <check | schema>".to_string()),
+    }
+}
+
+// Warn about config lines that are being ignored, once per run
+fn warn_config_issues() {
+    let home_dir = match dirs::home_dir() {
+        Some(home_dir) => home_dir,
+        None => return,
+    };
+    if let Ok(issues) = Config::check(&home_dir) {
+        for issue in &issues {
+            println!("{}Warning: {}: {}{}", COLOR_YELLOW, Config::path(&home_dir).display(), issue, COLOR_RESET);
+        }
+        if !issues.is_empty() {
+            println!("{}Invalid lines are ignored. Run `config check` after fixing them.{}", COLOR_YELLOW, COLOR_RESET);
+        }
+    }
+}
+
+// `doctor`
+fn run_doctor_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let mut warnings = 0;
+
+    // The troubleshooter's runner and disk space checks, so both report the same problems
+    let mut findings = Vec::new();
+    match find_runner(&config, &home_dir) {
+        Some(path) => {
+            let kind = if wine_kind(Path::new(&path)) == Some(WineKind::Wrapper) { ", a wrapper script" } else { "" };
+            println!("{}Runner: {} ({}{}){}", COLOR_GREEN, config.runner.description(), path, kind, COLOR_RESET);
+            findings.push(check_32bit(&path));
+            findings.push(check_wine_version(&path));
+        },
+        None => {
+            println!("{}Warning: no usable runner for `runner = {}`.{}", COLOR_YELLOW, config.runner.name(), COLOR_RESET);
+            warnings += 1;
+        },
+    }
+    // Enough for the biggest launcher
+    let required_space = LauncherRegistry::builtin().all().iter().map(|launcher| launcher.install_size_mb).max().unwrap_or(0) * 1024 * 1024;
+    findings.push(check_free_space(&config.games_root, required_space));
+    for finding in findings {
+        if finding.ok {
+            println!("{}{}: {}{}", COLOR_GREEN, finding.check, finding.message, COLOR_RESET);
+        } else {
+            println!("{}Warning: {}: {}{}", COLOR_YELLOW, finding.check, finding.message, COLOR_RESET);
+            warnings += 1;
+        }
+    }
+
+    if is_wayland_session() {
+        let wayland_driver = use_wayland_driver(&config);
+        println!("{}Session: Wayland{}", COLOR_GREEN, COLOR_RESET);
+        if wayland_driver {
+            println!("Launch scripts use wine's Wayland driver.");
+        } else {
+            println!("Launch scripts go through Xwayland.");
+        }
+        if find_xwayland().is_none() {
+            if wayland_driver {
+                println!("{}Warning: Xwayland is not installed. Launchers can use wine's Wayland driver, but installers still need X11.{}", COLOR_YELLOW, COLOR_RESET);
+            } else {
+                println!("{}Warning: Xwayland is not installed and {} has no Wayland driver, so its windows cannot open. Install Xwayland.{}",
+                         COLOR_YELLOW, config.runner.description(), COLOR_RESET);
+            }
+            warnings += 1;
+        } else if std::env::var_os("DISPLAY").is_none() {
+            println!("{}Warning: DISPLAY is not set, so X11 programs cannot find Xwayland. Launch scripts look for its socket themselves.{}", COLOR_YELLOW, COLOR_RESET);
+            warnings += 1;
+        }
+    } else if std::env::var_os("DISPLAY").is_some() {
+        println!("{}Session: X11{}", COLOR_GREEN, COLOR_RESET);
+    } else {
+        println!("{}Session: none. Installs need --headless (and Xvfb) here.{}", COLOR_YELLOW, COLOR_RESET);
+        if find_in_path("Xvfb").is_none() {
+            println!("{}Warning: Xvfb is not installed.{}", COLOR_YELLOW, COLOR_RESET);
+            warnings += 1;
+        }
+    }
+    let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    if (set("DISPLAY") || set("WAYLAND_DISPLAY")) && !display_available() {
+        println!("{}Warning: DISPLAY or WAYLAND_DISPLAY is set, but no display server's socket exists for it (e.g. a stale value in tmux). Installers can't fall back to interactive mode.{}",
+                 COLOR_YELLOW, COLOR_RESET);
+        warnings += 1;
+    }
+
+    if warnings == 0 {
+        println!("{}No problems found.{}", COLOR_GREEN, COLOR_RESET);
     } else {
-        "wine"
+        println!("{}Found {} problems.{}", COLOR_YELLOW, warnings, COLOR_RESET);
+    }
+    Ok(())
+}
+
+// `healthcheck [--notify | --timer | --no-timer]`: find what an upgrade or a moved directory broke.
+// `--notify`, which the timer uses, also shows the result as a desktop notification.
+fn run_healthcheck_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: healthcheck [--notify | --timer | --no-timer]";
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let notify_result = match args.first().map(String::as_str) {
+        None => false,
+        Some("--notify") => true,
+        Some("--timer") => {
+            let exe = std::env::current_exe().map_err(|e| format!("Failed to locate installer binary: {}", e))?;
+            return enable_healthcheck_timer(&home_dir, &exe);
+        },
+        Some("--no-timer") => return disable_healthcheck_timer(&home_dir),
+        Some(_) => return Err(usage.to_string()),
+    };
+
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let steam = SteamIntegration::discover().ok();
+    let issues = check_health(&app_paths, &LauncherRegistry::builtin(), steam.as_ref());
+    if issues.is_empty() {
+        println!("{}No problems found.{}", COLOR_GREEN, COLOR_RESET);
+        return Ok(());
+    }
+
+    println!("{}Found {} problems:{}", COLOR_YELLOW, issues.len(), COLOR_RESET);
+    for issue in &issues {
+        println!("  {}", issue.describe());
+    }
+    println!("Run `links audit --clean` for broken links and `gc` for shortcuts of removed launchers.");
+    if notify_result {
+        // Notifications show a few lines at most, so only the first problems are listed
+        let mut body: Vec<String> = issues.iter().take(3).map(|issue| issue.describe()).collect();
+        if issues.len() > 3 {
+            body.push(format!("and {} more; run `healthcheck` for all of them.", issues.len() - 3));
+        }
+        notify(&format!("{} launcher problems found", issues.len()), &body.join("\n"));
+    }
+    Ok(())
+}
+
+// `list`: the launchers with their installed client versions and whether a newer installer is served
+fn run_list_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
     };
 
-    println!("{}Setting registry key to remove window decorations...{}", COLOR_YELLOW, COLOR_RESET);
+    println!("{}{:<18} {:<12} {:<18} Latest{}", COLOR_BLUE, "Launcher", "ID", "Installed", COLOR_RESET);
+    for launcher in LauncherRegistry::builtin().all() {
+        let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+        if !prefix_path.join("system.reg").exists() {
+            println!("{:<18} {:<12} {:<18} -", launcher.name, launcher.id, "not installed");
+            continue;
+        }
+        let state = LauncherState::load(&app_paths.state_path(launcher.dir_name));
+        let installed = state.client_version.clone()
+        .or_else(|| client_version(launcher, &app_paths.drive_dir(launcher.dir_name), &prefix_path))
+        .unwrap_or_else(|| "unknown".to_string());
+
+        // The installer served at the launcher's URL is compared to the one last installed, as `update` does
+        let latest = if let Some(pin) = config.pinned_installer(launcher.id) {
+            format!("pinned to {}", pin)
+        } else {
+            match (remote_file_identity(launcher.installer_url), &state.installer_identity) {
+                (None, _) => "could not check".to_string(),
+                (Some(latest), Some(identity)) if latest == *identity => {
+                    let version = state.installer_version.as_deref().unwrap_or("installer unchanged");
+                    format!("{}{}{}", COLOR_GREEN, version, COLOR_RESET)
+                },
+                (Some(_), Some(_)) => format!("{}newer installer, run `update {}`{}", COLOR_YELLOW, launcher.id, COLOR_RESET),
+                (Some(_), None) => "unknown until the next `update`".to_string(),
+            }
+        };
+        println!("{:<18} {:<12} {:<18} {}", launcher.name, launcher.id, installed, latest);
+    }
+    Ok(())
+}
 
-    let status = Command::new(wine_bin)
-    .env("WINEPREFIX", prefix_path)
-    .args(&["reg", "add", "HKCU\\Software\\Wine\\X11 Driver", "/v", "Decorated", "/t", "REG_SZ", "/d", "N", "/f"])
-    .status()
-    .map_err(|e| format!("Failed to execute Wine registry command: {}", e))?;
+// `stats`
+fn run_stats_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let registry = LauncherRegistry::builtin();
+    let usages: Vec<(&LauncherInfo, DiskUsage)> = registry.all().iter()
+    .filter_map(|launcher| Some((launcher, DiskUsage::measure(&app_paths, launcher)?)))
+    .collect();
+    if usages.is_empty() {
+        println!("{}No launchers are installed in {}.{}", COLOR_YELLOW, app_paths.games_root.display(), COLOR_RESET);
+        return Ok(());
+    }
 
-    if status.success() {
-        println!("{}Window decorations disabled for prefix {}.{}", COLOR_GREEN, prefix_path.display(), COLOR_RESET);
-        Ok(())
-    } else {
-        Err("Failed to set registry key.".to_string())
+    println!("{}{:<18} {:>11} {:>11} {:>11} {:>11} {:>11}{}",
+             COLOR_BLUE, "Launcher", "Install", "Prefix", "Shaders", "Installers", "Total", COLOR_RESET);
+    for (launcher, usage) in &usages {
+        println!("{:<18} {:>11} {:>11} {:>11} {:>11} {:>11}",
+                 launcher.name, format_size(usage.install), format_size(usage.prefix),
+                 format_size(usage.shader_caches), format_size(usage.installers), format_size(usage.total()));
+    }
+    let total: u64 = usages.iter().map(|(_, usage)| usage.total()).sum();
+    println!("{:<18} {:>59}", "All launchers", format_size(total));
+
+    let reclaimable: u64 = usages.iter().map(|(_, usage)| usage.reclaimable()).sum();
+    if reclaimable > 0 && confirm(&format!("\nDelete cached installers and shader caches to free {}? They are recreated when needed.",
+                                           format_size(reclaimable))) {
+        for (launcher, usage) in &usages {
+            usage.clean(&app_paths, launcher)?;
+        }
+        println!("{}Freed {}.{}", COLOR_GREEN, format_size(reclaimable), COLOR_RESET);
     }
+    println!("Run `gc` to find leftovers of launchers that are no longer installed.");
+    Ok(())
 }
 
-// Run HoYoPlay post-setup
-fn run_hoyoplay_postsetup() -> Result<(), String> {
-    if !check_protontricks() {
-        return Err("protontricks is not installed. Please install it first.".to_string());
+// `last-run`
+fn run_last_run_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let records = last_run(&home_dir);
+    let start = match records.first() {
+        Some(start) => start,
+        None => {
+            println!("{}No runs recorded in {} yet.{}", COLOR_YELLOW, journal_path(&home_dir).display(), COLOR_RESET);
+            return Ok(());
+        },
+    };
+
+    println!("{}Last run: game-launcher-installer {} (PID {}), started {}{}",
+             COLOR_BLUE, start.field("args").unwrap_or_default(), start.pid, time_ago(start.time), COLOR_RESET);
+
+    let steps: Vec<_> = records.iter().skip(1).filter(|record| record.event != "end").collect();
+    if steps.len() > 30 {
+        println!("  ...{} earlier records", steps.len() - 30);
+    }
+    for record in steps.iter().skip(steps.len().saturating_sub(30)) {
+        println!("  +{}s {}: {}", record.time.saturating_sub(start.time), record.event, record.describe());
     }
 
-    println!("{}Detecting non-Steam games (protontricks -l):{}", COLOR_BLUE, COLOR_RESET);
-    let games = list_nonsteam_games()?;
+    let (color, outcome) = run_outcome(&records);
+    println!("{}{}{}", color, outcome, COLOR_RESET);
+    Ok(())
+}
 
-    if games.is_empty() {
-        return Err("No non-Steam games found!".to_string());
+// How long ago a journal time was, e.g. "3 hours ago"
+fn time_ago(time: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let minutes = now.saturating_sub(time) / 60;
+    match minutes {
+        0 => "less than a minute ago".to_string(),
+        1..=119 => format!("{} minutes ago", minutes),
+        120..=2879 => format!("{} hours ago", minutes / 60),
+        _ => format!("{} days ago", minutes / 1440),
     }
+}
 
-    println!("{}Select the HoYoPlay entry from the list below:{}", COLOR_YELLOW, COLOR_RESET);
-    for (i, game) in games.iter().enumerate() {
-        println!("{:2}) {}", i+1, game);
+// How a run from the journal ended, with the color to show it in
+fn run_outcome(records: &[journal::Record]) -> (ThemeColor, String) {
+    let pid = records.first().map(|start| start.pid).unwrap_or(0);
+    let end = records.iter().find(|record| record.event == "end");
+    let panic = records.iter().find(|record| record.event == "panic");
+    match end {
+        Some(end) if end.field("result") == Some("ok") => (COLOR_GREEN, "It finished successfully.".to_string()),
+        Some(end) => (COLOR_RED, format!("It failed: {}", end.field("message").unwrap_or("unknown error"))),
+        None if panic.is_some() => (COLOR_RED, format!("It crashed: {}", panic.and_then(|panic| panic.field("message")).unwrap_or_default())),
+        None if Path::new(&format!("/proc/{}", pid)).exists() => (COLOR_YELLOW, "It is still running.".to_string()),
+        None => (COLOR_RED, "It stopped without finishing (crash, kill or power loss) after the last record above.".to_string()),
     }
+}
 
-    print!("> ");
-    io::stdout().flush().unwrap();
+// `logs [<launcher>] [<number> | --follow]`
+fn run_logs_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: game-launcher-installer logs [<launcher>] [<number> | --follow]";
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let registry = LauncherRegistry::builtin();
+    let mut args = args.iter().peekable();
+    let launcher = args.peek().and_then(|arg| registry.get(arg));
+    if launcher.is_some() {
+        args.next();
+    }
+    let action = args.next();
+    if args.next().is_some() {
+        return Err(usage.to_string());
+    }
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+    // Runs started from the menu have no launcher in their arguments, so its name in any record counts
+    let mut runs = journal::runs(&home_dir);
+    if let Some(launcher) = launcher {
+        let id = launcher.id.to_lowercase();
+        let name = launcher.name.to_lowercase();
+        runs.retain(|run| run.iter().any(|record| record.fields.iter().any(|(_, value)| {
+            let value = value.to_lowercase();
+            value.contains(&id) || value.contains(&name)
+        })));
+    }
+    runs.reverse();
+    if runs.is_empty() {
+        println!("{}No runs recorded in {} yet.{}", COLOR_YELLOW, journal_path(&home_dir).display(), COLOR_RESET);
+        return Ok(());
+    }
 
-    let game_index = match input.trim().parse::<usize>() {
-        Ok(i) if i >= 1 && i <= games.len() => i - 1,
-        _ => return Err("Invalid selection.".to_string()),
+    match action.map(String::as_str) {
+        None => {
+            for (number, run) in runs.iter().enumerate().take(20) {
+                let start = &run[0];
+                let args = start.field("args").filter(|args| !args.is_empty()).unwrap_or("(menu)");
+                let (color, outcome) = run_outcome(run);
+                println!("{:>3}  {:<24} {:<40} {}{}{}", number + 1, time_ago(start.time), args, color, outcome, COLOR_RESET);
+            }
+            println!("Run `logs {}<number>` to read one, or `logs --follow` to watch a running one.",
+                     launcher.map(|launcher| format!("{} ", launcher.id)).unwrap_or_default());
+            Ok(())
+        },
+        Some("--follow") => {
+            let run = runs.iter().find(|run| run.iter().all(|record| record.event != "end") && Path::new(&format!("/proc/{}", run[0].pid)).exists())
+            .ok_or("No run is in progress.")?;
+            let start = &run[0];
+            println!("{}Following game-launcher-installer {} (PID {}), started {}{}",
+                     COLOR_BLUE, start.field("args").unwrap_or_default(), start.pid, time_ago(start.time), COLOR_RESET);
+            for record in run.iter().skip(1) {
+                println!("  +{}s {}: {}", record.time.saturating_sub(start.time), record.event, record.describe());
+            }
+            journal::follow_run(&home_dir, start.pid, |record| if record.event != "end" {
+                println!("  +{}s {}: {}", record.time.saturating_sub(start.time), record.event, record.describe());
+            });
+            let (color, outcome) = run_outcome(&journal::runs(&home_dir).into_iter().rev()
+                                               .find(|run| run[0].pid == start.pid && run[0].time == start.time)
+                                               .unwrap_or_default());
+            println!("{}{}{}", color, outcome, COLOR_RESET);
+            Ok(())
+        },
+        Some(number) => {
+            let run = number.parse::<usize>().ok().and_then(|number| runs.get(number.checked_sub(1)?)).ok_or(usage)?;
+            let start = &run[0];
+            let (_, outcome) = run_outcome(run);
+            let mut text = format!("game-launcher-installer {} (PID {}), started {}\n",
+                                   start.field("args").unwrap_or_default(), start.pid, time_ago(start.time));
+            for record in run.iter().skip(1).filter(|record| record.event != "end") {
+                text.push_str(&format!("+{}s {}: {}\n", record.time.saturating_sub(start.time), record.event, record.describe()));
+            }
+            text.push_str(&outcome);
+            text.push('\n');
+            page(&text)
+        },
+    }
+}
+
+// Show text through $PAGER (`less` if unset), or print it when not on a terminal
+fn page(text: &str) -> Result<(), String> {
+    if !io::stdout().is_terminal() {
+        print!("{}", text);
+        return Ok(());
+    }
+    let pager = std::env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty()).unwrap_or_else(|| "less".to_string());
+    let mut child = match Command::new("sh").arg("-c").arg(&pager).stdin(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(_) => {
+            print!("{}", text);
+            return Ok(());
+        },
     };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing early is not an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait().map_err(|e| format!("Failed to run {}: {}", pager, e))?;
+    Ok(())
+}
 
-    let selected_line = &games[game_index];
-    let app_id = extract_appid(selected_line)
-    .ok_or_else(|| "Could not extract App ID.".to_string())?;
+// `report`
+fn run_report_command() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+
+    let mut info = system_info(find_runner(&config, &home_dir).as_deref());
+    info.push(("Runner".to_string(), config.runner.description().to_string()));
+    info.extend(gpu_info());
+    let session = if is_wayland_session() { "Wayland" } else if std::env::var_os("DISPLAY").is_some() { "X11" } else { "none" };
+    info.push(("Session".to_string(), session.to_string()));
+    let proton = SteamIntegration::discover().map(|steam| steam.proton_versions()).unwrap_or_default();
+    info.push(("Proton".to_string(), if proton.is_empty() { "none found".to_string() } else { proton.join(", ") }));
+    for (_, value) in info.iter_mut() {
+        *value = redact(value, &home_dir);
+    }
+
+    let mut sections = Vec::new();
+    let config_text = fs::read_to_string(Config::path(&home_dir)).unwrap_or_default();
+    sections.push(("Config".to_string(), redact(&config_text, &home_dir)));
+    let records = last_run(&home_dir);
+    let start = records.first().map(|record| record.time).unwrap_or(0);
+    let records: String = records.iter()
+    .map(|record| format!("+{}s {}: {}\n", record.time.saturating_sub(start), record.event, record.describe()))
+    .collect();
+    sections.push(("Last run".to_string(), redact(&records, &home_dir)));
+    let markdown = markdown_report(&info, &sections);
+
+    let dir = report_dir(&home_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let markdown_path = dir.join("report.md");
+    fs::write(&markdown_path, &markdown).map_err(|e| format!("Failed to write {}: {}", markdown_path.display(), e))?;
+    let mut files = vec![markdown_path.clone()];
+    // The progress log, kept with `logging = local`, goes in redacted like the rest
+    let progress_log = Config::log_path(&home_dir);
+    let redacted_log = dir.join("progress.log");
+    if let Ok(log) = fs::read_to_string(&progress_log) {
+        fs::write(&redacted_log, redact(&log, &home_dir)).map_err(|e| format!("Failed to write {}: {}", redacted_log.display(), e))?;
+        bound_log(&redacted_log, DEBUG_LOG_LIMIT)?;
+        files.push(redacted_log.clone());
+    }
+    let report = create_report(&home_dir, "report", &info, &files);
+    let _ = fs::remove_file(&redacted_log);
+    let report = report?;
 
-    let libraries = find_steam_libraries()?;
+    println!("{}", markdown);
+    println!("{}Report written to {} and {}{}", COLOR_GREEN, markdown_path.display(), report.display(), COLOR_RESET);
+    println!("Paste the markdown above into an issue at https://github.com/SulfurNitride/Simple-Launcher-Installer/issues and attach the archive.");
+    Ok(())
+}
 
-    let prefix_path = find_prefix_path(&app_id, &libraries)
-    .ok_or_else(|| format!("Could not find compatdata prefix for App ID {} in any Steam library.", app_id))?;
+// `rollback <launcher>`
+fn run_rollback_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: rollback <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
 
-    println!("{}Found prefix: {}{}", COLOR_GREEN, prefix_path.display(), COLOR_RESET);
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+    if !btrfs::is_subvolume(&launcher_dir) {
+        return Err(format!("{} is not a btrfs subvolume.", launcher_dir.display()));
+    }
+
+    PrefixManager::new(app_paths.prefix_dir(launcher.dir_name)).kill_wineserver();
+    btrfs::rollback(&launcher_dir, &app_paths.snapshots_dir(launcher.dir_name))?;
+    Ok(())
+}
+
+// `run <launcher> [program [args...]]`
+fn run_run_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: run <launcher> [program [args...]]")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+    if !prefix.path().exists() {
+        return Err(format!("{} has no prefix at {}", launcher.name, prefix.path().display()));
+    }
+    let program = match args.get(1) {
+        Some(program) => PathBuf::from(program),
+        None => {
+            let exe = app_paths.drive_dir(launcher.dir_name).join(launcher.exe_name);
+            if exe.exists() {
+                exe
+            } else {
+                pick_prefix_exe(prefix.path(), launcher).ok_or_else(|| {
+                    format!("{} is not in {}; give the program to run.", launcher.exe_name, exe.parent().unwrap_or(&exe).display())
+                })?
+            }
+        },
+    };
+
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    prefix.update_for_runner(&wine_path)?;
+    let session = prefix.session(&wine_path);
+    session.start()?;
+
+    println!("{}Running {} in {}...{}", COLOR_BLUE, program.display(), prefix.path().display(), COLOR_RESET);
+    record_launcher_use(&app_paths, launcher);
+    let status = session.command(&program)
+    .args(args.iter().skip(2))
+    .status()
+    .map_err(|e| format!("Failed to execute {}: {}", program.display(), e))?;
+
+    // Launchers often hand off to an updater or a game and exit; wait for everything in the prefix
+    session.wait(None)?;
+    if !status.success() {
+        return Err(format!("{} exited with {}.", program.display(), status));
+    }
+    Ok(())
+}
+
+// `kill <launcher>`
+fn run_kill_command(args: &[String]) -> Result<(), String> {
+    let launcher_id = args.first().ok_or("Usage: kill <launcher>")?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+    let wine_path = prefix.runner_wine().unwrap_or_else(|| "wine".to_string());
+    let session = prefix.session(&wine_path);
+    if !session.is_running() {
+        println!("{}Nothing is running in {}'s prefix.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+        return Ok(());
+    }
+
+    let processes = prefix.running_processes();
+    session.shutdown();
+    if session.is_running() {
+        return Err(format!("The wineserver of {} did not exit.", prefix.path().display()));
+    }
+    if processes.is_empty() {
+        println!("{}Stopped {}'s wineserver.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+    } else {
+        println!("{}Stopped {}'s wineserver and {}.{}", COLOR_GREEN, launcher.name, processes.join(", "), COLOR_RESET);
+    }
+    Ok(())
+}
+
+// `tricks <launcher> [verbs...]`
+fn run_tricks_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: tricks <launcher> [winetricks verbs and options...]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+    let verbs = &args[1..];
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let prefix = PrefixManager::new(app_paths.prefix_dir(launcher.dir_name));
+
+    // Proton prefixes are Steam's to manage, so protontricks finds their Proton and prefix by app ID
+    let steam_app_id = LauncherState::load(&app_paths.state_path(launcher.dir_name)).steam_game.or_else(|| {
+        if prefix.path().exists() {
+            return None;
+        }
+        let steam = SteamIntegration::discover().ok()?;
+        let exe = app_paths.drive_dir(launcher.dir_name).join(launcher.exe_name);
+        let app_id = steam.shortcut_for_exe(&exe)?.app_id?.to_string();
+        steam.find_prefix(&app_id).map(|_| app_id)
+    });
+    if let Some(app_id) = steam_app_id {
+        if !protontricks_available() {
+            return Err("protontricks is not installed. Please install it first.".to_string());
+        }
+        if verbs.is_empty() {
+            return Err(format!("{} runs under Proton; give the verbs to install. {}", launcher.name, usage));
+        }
+        println!("{}Running protontricks {} {} for {}...{}", COLOR_BLUE, app_id, verbs.join(" "), launcher.name, COLOR_RESET);
+        journal::record("command", &[("command", &format!("protontricks {} {}", app_id, verbs.join(" ")))]);
+        let status = Command::new("protontricks")
+        .arg(&app_id)
+        .args(verbs)
+        .status()
+        .map_err(|e| format!("Failed to execute protontricks: {}", e))?;
+        return if status.success() { Ok(()) } else { Err(format!("protontricks failed ({}).", status)) };
+    }
 
-    setup_linux_root_symlink(&prefix_path)?;
+    if !prefix.path().exists() {
+        return Err(format!("{} has no prefix at {}", launcher.name, prefix.path().display()));
+    }
+    if find_in_path("winetricks").is_none() {
+        return Err("winetricks is not installed. Please install it first.".to_string());
+    }
+    if verbs.is_empty() && !display_available() {
+        return Err(format!("Without verbs winetricks opens its window, and no display is available. {}", usage));
+    }
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    let session = prefix.session(&wine_path);
+    session.start()?;
+
+    // umu-run sets up Proton for winetricks itself; other runners point winetricks at their wine
+    let mut command = if config.runner == Runner::Umu {
+        let mut command = Command::new(&wine_path);
+        command.arg("winetricks");
+        command
+    } else {
+        let mut command = Command::new("winetricks");
+        command.env("WINE", &wine_path).env("WINESERVER", wineserver_for(&wine_path));
+        command
+    };
+    command.env("WINEPREFIX", prefix.path()).args(verbs);
+    println!("{}Running winetricks {} in {}...{}", COLOR_BLUE, verbs.join(" "), prefix.path().display(), COLOR_RESET);
+    journal::record("command", &[("command", &format!("winetricks {}", verbs.join(" "))), ("prefix", &prefix.path().to_string_lossy())]);
+    let status = command.status().map_err(|e| format!("Failed to execute winetricks: {}", e))?;
+    session.wait(Some(Duration::from_secs(60)))?;
+    if !status.success() {
+        return Err(format!("winetricks failed ({}).", status));
+    }
+    println!("{}winetricks finished for {}.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+    Ok(())
+}
 
-    println!("{}You can now access your Linux filesystem from within the game installer by navigating to C:\\Linux Root in the file dialog (look under 'Computer' > 'C:').{}",
-             COLOR_GREEN, COLOR_RESET);
+// `open <launcher> [prefix|install|logs|cache]`
+fn run_open_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: open <launcher> [prefix|install|logs|cache]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
 
-    remove_window_decorations(&prefix_path)?;
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let config = Config::load(&home_dir);
+    let app_paths = AppPaths {
+        games_root: config.games_root.clone(),
+        home_dir,
+    };
+    let dir = match args.get(1).map(String::as_str).unwrap_or("prefix") {
+        // Launched from Steam, the launcher runs in its shortcut's compatdata prefix instead
+        "prefix" => config.compat_data_path(launcher.id).map(|dir| dir.join("pfx")).filter(|pfx| pfx.is_dir())
+        .or_else(|| {
+            let steam = SteamIntegration::discover().ok()?;
+            let exe = app_paths.drive_dir(launcher.dir_name).join(launcher.exe_name);
+            steam.find_prefix(&steam.shortcut_for_exe(&exe)?.app_id?.to_string())
+        })
+        .unwrap_or_else(|| app_paths.prefix_dir(launcher.dir_name)),
+        "install" => app_paths.drive_dir(launcher.dir_name),
+        "cache" => app_paths.cache_dir(launcher.dir_name),
+        "logs" => journal_path(&app_paths.home_dir).parent().map(Path::to_path_buf).unwrap_or_default(),
+        _ => return Err(usage.to_string()),
+    };
+    if !dir.is_dir() {
+        return Err(format!("{} does not exist yet.", dir.display()));
+    }
 
+    println!("{}", dir.display());
+    if find_in_path("xdg-open").is_none() || !display_available() {
+        println!("{}No file manager can be opened here; the path is printed above.{}", COLOR_YELLOW, COLOR_RESET);
+        return Ok(());
+    }
+    Command::new("xdg-open")
+    .arg(&dir)
+    .stdout(std::process::Stdio::null())
+    .stderr(std::process::Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to execute xdg-open: {}", e))?;
     Ok(())
 }
 
+// `protocol <launcher> [install dir]`
+fn run_protocol_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: protocol <launcher> [install dir]";
+    let launcher_id = args.first().ok_or(usage)?;
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(launcher_id)
+    .ok_or_else(|| format!("Unknown launcher: {}", launcher_id))?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_paths = AppPaths {
+        games_root: Config::load(&home_dir).games_root,
+        home_dir,
+    };
+    let install_dir = match args.get(1) {
+        Some(dir) => parse_path_input(dir, &app_paths.home_dir).ok_or(usage)?,
+        None => app_paths.drive_dir(launcher.dir_name),
+    };
+    let mut exe = install_dir.join(launcher.exe_name);
+    if !exe.exists() {
+        exe = args.get(1).is_none().then(|| pick_prefix_exe(&app_paths.prefix_dir(launcher.dir_name), launcher)).flatten()
+        .ok_or_else(|| format!("{} not found in {}. Pass the install directory as the last argument.",
+                               launcher.exe_name, install_dir.display()))?;
+    }
+
+    let wine_path = launcher_runner(&app_paths, launcher).ok_or("No usable runner found. Please install wine and try again.")?;
+    register_uri_handlers(&app_paths.home_dir, launcher, &wine_path, &app_paths.prefix_dir(launcher.dir_name), &exe)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // `last-run` and `report` read the journal and aren't worth a run of their own in it
+    if args.first().is_none_or(|arg| arg != "last-run" && arg != "logs" && arg != "report") {
+        if let Some(home_dir) = dirs::home_dir() {
+            // Printed without colors: the theme isn't set yet
+            if let Err(e) = open_journal(&home_dir, &args) {
+                eprintln!("Warning: {}", e);
+            }
+        }
+    }
+
+    let result = run();
+    match &result {
+        Ok(()) => close_journal(Ok(())),
+        Err(e) => close_journal(Err(&e.to_string())),
+    }
+    result
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let mut options = InstallOptions {
+        language: Language::from_environment(),
+        sandbox: false,
+        installer_version: None,
+        allow_unlisted_installer: false,
+        allow_unsigned: false,
+        launcher: None,
+        display: None,
+        steam_game: None,
+        debug_wine: false,
+        yes: false,
+        smoke_test: false,
+        verbose: false,
+        verify_shortcut: false,
+    };
+    let headless = std::env::args().any(|arg| arg == "--headless");
+    HEADLESS.store(headless, Ordering::Relaxed);
+    // Steam's Game Mode runs in a gamescope session, where a controller is usually all there is
+    let gamescope = std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.eq_ignore_ascii_case("gamescope"));
+    CONTROLLER.store(std::env::args().any(|arg| arg == "--controller") || gamescope, Ordering::Relaxed);
+    if let Some(home_dir) = dirs::home_dir() {
+        // https://no-color.org: any non-empty NO_COLOR turns colors off
+        let no_color = std::env::var_os("NO_COLOR").map(|value| !value.is_empty()).unwrap_or(false);
+        let plain = no_color || headless;
+        set_theme(if plain { Theme::named("none").unwrap_or_default() } else { Config::load(&home_dir).theme() });
+    }
+    if !std::env::args().any(|arg| arg == "config") {
+        warn_config_issues();
+    }
+    if let Some(home_dir) = dirs::home_dir() {
+        if Config::load(&home_dir).local_log && !headless && !std::env::args().any(|arg| arg == "--progress-events") {
+            open_local_log(&home_dir);
+        }
+    }
+    if headless && !std::env::args().any(|arg| arg == "--progress-events") {
+        progress_to_stderr()?;
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => {
+                let code = args.next().ok_or("--lang requires a language code")?;
+                options.language = Language::parse(&code).ok_or_else(|| {
+                    format!("Unsupported language: {} (supported: {})", code, Language::supported_codes())
+                })?;
+            },
+            "--progress-events" => {
+                let target = args.next().ok_or("--progress-events requires a target (fd://N with N > 2, or a file path)")?;
+                open_progress_sink(&target)?;
+            },
+            "--sandbox" => options.sandbox = true,
+            "--headless" => {},
+            "--controller" => {},
+            "--allow-unlisted-installer" => options.allow_unlisted_installer = true,
+            "--allow-unsigned" => options.allow_unsigned = true,
+            "--debug-wine" => options.debug_wine = true,
+            "--yes" => options.yes = true,
+            "--smoke-test" => options.smoke_test = true,
+            "--verbose" => options.verbose = true,
+            "--verify-shortcut" => options.verify_shortcut = true,
+            #[cfg(feature = "dbus-service")]
+            "--service" => {
+                let home_dir = dirs::home_dir().expect("Could not determine home directory");
+                dbus_service::serve(&home_dir)?;
+                return Ok(());
+            },
+            "--launcher" => {
+                let name = args.next().ok_or("--launcher requires a launcher ID or name")?;
+                options.launcher = Some(name);
+            },
+            "--steam-game" => {
+                let game = args.next().ok_or("--steam-game requires a Steam app ID or game name")?;
+                options.steam_game = Some(game);
+            },
+            "--version" => {
+                let source = args.next().ok_or("--version requires an installer URL or file")?;
+                options.installer_version = Some(source);
+            },
+            "dlloverrides" => {
+                let rest: Vec<String> = args.collect();
+                run_dll_overrides_command(&rest)?;
+                return Ok(());
+            },
+            "export" => {
+                let rest: Vec<String> = args.collect();
+                run_export_command(&rest)?;
+                return Ok(());
+            },
+            "import" => {
+                let rest: Vec<String> = args.collect();
+                run_import_command(&rest)?;
+                return Ok(());
+            },
+            "refresh-manifest" => {
+                let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+                let config = Config::load(&home_dir);
+                let entries = refresh_manifest(&home_dir, &config.manifest_url, config.signing_key.as_deref(), options.allow_unsigned)?;
+                println!("{}Manifest updated: {} installer hashes.{}", COLOR_GREEN, entries, COLOR_RESET);
+                return Ok(());
+            },
+            "trust-installer" => {
+                let rest: Vec<String> = args.collect();
+                run_trust_installer_command(&rest, &options)?;
+                return Ok(());
+            },
+            "pin" => {
+                let rest: Vec<String> = args.collect();
+                run_pin_command(&rest)?;
+                return Ok(());
+            },
+            "update" => {
+                let rest: Vec<String> = args.collect();
+                run_update_command(&rest, &options)?;
+                return Ok(());
+            },
+            "enable-auto-update" | "disable-auto-update" => {
+                let rest: Vec<String> = args.collect();
+                run_auto_update_command(arg == "enable-auto-update", &rest)?;
+                return Ok(());
+            },
+            "template" => {
+                let rest: Vec<String> = args.collect();
+                run_template_command(&rest)?;
+                return Ok(());
+            },
+            "config" => {
+                let rest: Vec<String> = args.collect();
+                run_config_command(&rest)?;
+                return Ok(());
+            },
+            "healthcheck" => {
+                let rest: Vec<String> = args.collect();
+                run_healthcheck_command(&rest)?;
+                return Ok(());
+            },
+            "list" => {
+                run_list_command()?;
+                return Ok(());
+            },
+            "stats" => {
+                run_stats_command()?;
+                return Ok(());
+            },
+            "doctor" => {
+                run_doctor_command()?;
+                return Ok(());
+            },
+            "compatdata" => {
+                let rest: Vec<String> = args.collect();
+                run_compatdata_command(&rest)?;
+                return Ok(());
+            },
+            "battlenet-fix-login" => {
+                run_battlenet_fix_login_command()?;
+                return Ok(());
+            },
+            "battlenet-library" => {
+                let rest: Vec<String> = args.collect();
+                run_battlenet_library_command(&rest)?;
+                return Ok(());
+            },
+            "share" => {
+                let rest: Vec<String> = args.collect();
+                run_share_command(&rest)?;
+                return Ok(());
+            },
+            "use-shared" => {
+                let rest: Vec<String> = args.collect();
+                run_use_shared_command(&rest)?;
+                return Ok(());
+            },
+            "favorite" => {
+                let rest: Vec<String> = args.collect();
+                run_favorite_command(&rest)?;
+                return Ok(());
+            },
+            "prefix" => {
+                let rest: Vec<String> = args.collect();
+                run_prefix_command(&rest)?;
+                return Ok(());
+            },
+            "links" => {
+                let rest: Vec<String> = args.collect();
+                run_links_command(&rest)?;
+                return Ok(());
+            },
+            "migrate-prefix" => {
+                let rest: Vec<String> = args.collect();
+                run_migrate_prefix_command(&rest)?;
+                return Ok(());
+            },
+            "restore-registry" => {
+                let rest: Vec<String> = args.collect();
+                run_restore_registry_command(&rest)?;
+                return Ok(());
+            },
+            "reapply" => {
+                let rest: Vec<String> = args.collect();
+                run_reapply_command(&rest)?;
+                return Ok(());
+            },
+            "batch-setup" => {
+                run_batch_setup_command()?;
+                return Ok(());
+            },
+            "gc" => {
+                run_gc_command()?;
+                return Ok(());
+            },
+            "purge-all" => {
+                run_purge_all_command()?;
+                return Ok(());
+            },
+            "notes" => {
+                let rest: Vec<String> = args.collect();
+                run_notes_command(&rest)?;
+                return Ok(());
+            },
+            "env" => {
+                let rest: Vec<String> = args.collect();
+                run_env_command(&rest, &options)?;
+                return Ok(());
+            },
+            "scan" => {
+                run_scan_command()?;
+                return Ok(());
+            },
+            "dedupe" => {
+                run_dedupe_command()?;
+                return Ok(());
+            },
+            "clean-menus" => {
+                run_clean_menus_command()?;
+                return Ok(());
+            },
+            "last-run" => {
+                run_last_run_command()?;
+                return Ok(());
+            },
+            "logs" => {
+                let rest: Vec<String> = args.collect();
+                run_logs_command(&rest)?;
+                return Ok(());
+            },
+            "report" => {
+                run_report_command()?;
+                return Ok(());
+            },
+            "rollback" => {
+                let rest: Vec<String> = args.collect();
+                run_rollback_command(&rest)?;
+                return Ok(());
+            },
+            "run" => {
+                let rest: Vec<String> = args.collect();
+                run_run_command(&rest)?;
+                return Ok(());
+            },
+            "kill" => {
+                let rest: Vec<String> = args.collect();
+                run_kill_command(&rest)?;
+                return Ok(());
+            },
+            "tricks" => {
+                let rest: Vec<String> = args.collect();
+                run_tricks_command(&rest)?;
+                return Ok(());
+            },
+            "open" => {
+                let rest: Vec<String> = args.collect();
+                run_open_command(&rest)?;
+                return Ok(());
+            },
+            "protocol" => {
+                let rest: Vec<String> = args.collect();
+                run_protocol_command(&rest)?;
+                return Ok(());
+            },
+            "wrapper" => {
+                let rest: Vec<String> = args.collect();
+                run_wrapper_command(&rest)?;
+                return Ok(());
+            },
+            #[cfg(feature = "web-ui")]
+            "serve" => {
+                let mut port = web_ui::DEFAULT_PORT;
+                while let Some(option) = args.next() {
+                    match option.as_str() {
+                        "--port" => {
+                            port = args.next()
+                            .and_then(|p| p.parse().ok())
+                            .ok_or("--port requires a port number")?;
+                        },
+                        _ => return Err(format!("Unknown serve option: {}", option).into()),
+                    }
+                }
+                let home_dir = dirs::home_dir().expect("Could not determine home directory");
+                web_ui::serve(port, &home_dir)?;
+                return Ok(());
+            },
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+
+    if headless && options.launcher.is_none() {
+        return Err("--headless needs --launcher <id>; the menu is interactive.".into());
+    }
+
     println!("{}===== Game Launcher Installer ====={}", COLOR_BLUE, COLOR_RESET);
 
-    // Find system wine before showing menu
-    let wine_path = match find_system_wine() {
+    // Installers need an X server even when their windows are hidden
+    let virtual_display = if headless {
+        let display = VirtualDisplay::start()?;
+        println!("Started Xvfb on {}", display.display());
+        std::env::set_var("DISPLAY", display.display());
+        std::env::remove_var("WAYLAND_DISPLAY");
+        options.display = Some(display.display().to_string());
+        Some(display)
+    } else {
+        None
+    };
+
+    // Setup application paths, asking for them on the first interactive run
+    let home_dir = dirs::home_dir().expect("Could not determine home directory");
+    let mut config = if !Config::exists(&home_dir) && !headless && io::stdin().is_terminal() {
+        run_setup_wizard(&home_dir)?
+    } else {
+        Config::load(&home_dir)
+    };
+
+    // Find the runner before showing menu
+    let wine_path = match find_runner(&config, &home_dir) {
         Some(path) => path,
         None => {
             println!("{}Please install wine and try again.{}", COLOR_RED, COLOR_RESET);
+            close_journal(Err("no runner found"));
             std::process::exit(1);
         }
     };
 
-    println!("{}Using system wine: {}{}", COLOR_GREEN, wine_path, COLOR_RESET);
+    println!("{}Using {}: {}{}", COLOR_GREEN, config.runner.description(), wine_path, COLOR_RESET);
 
-    // Setup application paths
-    let home_dir = dirs::home_dir().expect("Could not determine home directory");
-    let app_paths = AppPaths {
+    let legacy_paths = AppPaths {
         home_dir: home_dir.clone(),
-        battlenet_installer: home_dir.join(".battlenet/Battle.net-Setup.exe"),
-        hoyoplay_installer: home_dir.join(".hoyoplay/HoYoPlay-Setup.exe"),
+        games_root: config.games_root.clone(),
     };
+    if let Err(e) = migrate_legacy_layout(&legacy_paths) {
+        println!("{}Warning: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+    }
+
+    let registry = LauncherRegistry::builtin();
+    if let Some(name) = &options.launcher {
+        let launcher = registry.find(name).ok_or_else(|| format!("Unknown launcher: {}", name))?;
+        let app_paths = AppPaths { home_dir, games_root: config.games_root.clone() };
+        record_launcher_use(&app_paths, launcher);
+        let outcome = report_outcome(install_launcher(&wine_path, &app_paths, &options, launcher));
+        drop(virtual_display);
+        if outcome.is_err() && headless {
+            // The error event was already emitted; keep stderr to JSON lines
+            close_journal(Err("install failed"));
+            std::process::exit(1);
+        }
+        outcome?;
+        return Ok(());
+    }
 
     // Show main menu
     loop {
-        println!("What would you like to do?");
-        println!("1) Install Battle.net");
-        println!("2) Install HoYoPlay");
-        println!("3) Run HoYoPlay Post-Setup (removes window decorations)");
-        println!("4) Exit");
-
-        print!("Enter your choice [1-4]: ");
-        io::stdout().flush().unwrap();
-
-        let mut choice = String::new();
-        io::stdin().read_line(&mut choice).unwrap();
-
-        match choice.trim() {
-            "1" => {
-                if let Err(e) = install_battlenet(&wine_path, &app_paths) {
-                    println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET);
-                    return Err(e.into());
-                }
-                println!("{}Operation completed successfully.{}", COLOR_GREEN, COLOR_RESET);
+        let app_paths = AppPaths {
+            home_dir: home_dir.clone(),
+            games_root: config.games_root.clone(),
+        };
+
+        let action = match prompt_main_menu(&menu_entries(&registry, &config, &app_paths)) {
+            Some(action) => action,
+            // stdin closed, nothing more to read
+            None => break,
+        };
+
+        match action {
+            MenuAction::Install(launcher) => {
+                record_launcher_use(&app_paths, launcher);
+                report_outcome(install_launcher(&wine_path, &app_paths, &options, launcher))?;
                 break;
             },
-            "2" => {
-                if let Err(e) = install_hoyoplay(&wine_path, &app_paths) {
-                    println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET);
-                    return Err(e.into());
-                }
-                println!("{}Operation completed successfully.{}", COLOR_GREEN, COLOR_RESET);
+            MenuAction::Update(launcher) => {
+                record_launcher_use(&app_paths, launcher);
+                report_outcome(run_update_command(&[launcher.id.to_string()], &options))?;
                 break;
             },
-            "3" => {
+            MenuAction::HoyoplayPostSetup => {
                 println!("\n{}===== HoYoPlay Post-Setup ====={}", COLOR_BLUE, COLOR_RESET);
                 println!("{}Before running this tool, make sure you have:{}", COLOR_YELLOW, COLOR_RESET);
                 println!("{}1. Added HoYoPlay to Steam using the instructions provided after installation{}", COLOR_YELLOW, COLOR_RESET);
-                println!("{}2. Launched HoYoPlay from Steam at least once{}", COLOR_YELLOW, COLOR_RESET);
+                println!("{}2. Launched HoYoPlay from Steam at least once (or let this tool create its prefix){}", COLOR_YELLOW, COLOR_RESET);
                 println!("{}3. Created a non-Steam shortcut in Steam for the game you want to play{}", COLOR_YELLOW, COLOR_RESET);
                 println!("{}This tool will remove window decorations to give a cleaner gaming experience.{}\n", COLOR_YELLOW, COLOR_RESET);
 
-                print!("Do you want to continue? (yes/no): ");
-                io::stdout().flush().unwrap();
-
-                let mut confirm = String::new();
-                io::stdin().read_line(&mut confirm).unwrap();
-
-                if confirm.trim().to_lowercase() == "yes" || confirm.trim().to_lowercase() == "y" {
-                    if let Err(e) = run_hoyoplay_postsetup() {
-                        println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET);
-                        return Err(e.into());
-                    }
-                    println!("{}Operation completed successfully.{}", COLOR_GREEN, COLOR_RESET);
+                if confirm("Do you want to continue?") {
+                    report_outcome(run_hoyoplay_postsetup(&wine_path, &options))?;
                 } else {
                     println!("{}Post-setup cancelled.{}", COLOR_YELLOW, COLOR_RESET);
                 }
                 break;
             },
-            "4" => {
+            MenuAction::BattlenetFixLogin => {
+                report_outcome(run_battlenet_fix_login_command())?;
+                break;
+            },
+            MenuAction::PrecreatePrefix => {
+                println!("{}Make sure you have added the launcher to Steam as a non-Steam game first.{}", COLOR_YELLOW, COLOR_RESET);
+                report_outcome(run_prefix_precreate(&wine_path))?;
+                break;
+            },
+            MenuAction::BatchSetup => {
+                report_outcome(run_batch_setup_command())?;
+                break;
+            },
+            MenuAction::ChangeGamesRoot => {
+                if let Err(e) = change_games_root(&mut config, &home_dir) {
+                    println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET);
+                }
+            },
+            MenuAction::Exit => {
                 println!("{}Exiting.{}", COLOR_YELLOW, COLOR_RESET);
                 break;
             },
-            _ => {
-                println!("{}Invalid choice. Please enter a number between 1 and 4.{}", COLOR_RED, COLOR_RESET);
-            }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_aside_path_picks_a_free_name() {
+        let dir = std::env::temp_dir().join(format!("gli-set-aside-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let prefix_path = dir.join("battlenet");
+
+        assert_eq!(set_aside_path(&prefix_path, PrefixArch::Win32), dir.join("battlenet.win32"));
+        fs::create_dir(dir.join("battlenet.win32")).unwrap();
+        assert_eq!(set_aside_path(&prefix_path, PrefixArch::Win32), dir.join("battlenet.win32.2"));
+        // A dangling symlink still takes the name
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("battlenet.win32.2")).unwrap();
+        assert_eq!(set_aside_path(&prefix_path, PrefixArch::Win32), dir.join("battlenet.win32.3"));
+        assert_eq!(set_aside_path(&prefix_path, PrefixArch::Win64), dir.join("battlenet.win64"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn yes_never_sets_a_prefix_aside() {
+        let dir = std::env::temp_dir().join(format!("gli-plan-yes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let app_paths = AppPaths { home_dir: dir.join("home"), games_root: dir.join("Games") };
+        let registry = LauncherRegistry::builtin();
+        let launcher = registry.get("battlenet").unwrap();
+        let prefix_path = app_paths.prefix_dir(launcher.dir_name);
+        fs::create_dir_all(&prefix_path).unwrap();
+        fs::write(prefix_path.join("system.reg"), "WINE REGISTRY Version 2\n#arch=win32\n").unwrap();
+        let installer = dir.join("installer.exe");
+        fs::write(&installer, "").unwrap();
+        let options = InstallOptions {
+            language: Language::from_environment(),
+            sandbox: false,
+            installer_version: Some(installer.to_string_lossy().to_string()),
+            allow_unlisted_installer: false,
+            allow_unsigned: false,
+            launcher: None,
+            display: None,
+            steam_game: None,
+            debug_wine: false,
+            yes: true,
+            smoke_test: false,
+            verbose: false,
+            verify_shortcut: false,
+        };
+
+        let error = plan_install("wine", launcher, &app_paths, &options).err().unwrap();
+        assert!(error.contains("never set a prefix aside"), "{}", error);
+        assert!(prefix_path.join("system.reg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+//! The remote launcher manifest: known-good installer hashes published alongside an ed25519
+//! signature, verified before anything is merged. The project's public key is built in;
+//! `signing_key` in the config file points at another one, for a manifest someone else
+//! publishes and signs with their own key:
+//!
+//! ```text
+//! openssl genpkey -algorithm ed25519 -out signing.pem
+//! openssl pkey -in signing.pem -pubout -out signing.pub.pem
+//! openssl pkeyutl -sign -rawin -inkey signing.pem -in manifest.txt -out manifest.txt.sig
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::download::download_file;
+use crate::output::*;
+use crate::policy::HashAllowlist;
+
+/// Where the manifest is published unless `manifest_url` is set in the config.
+/// The signature is expected at the same URL with `.sig` appended.
+pub const DEFAULT_MANIFEST_URL: &str =
+    "https://raw.githubusercontent.com/SulfurNitride/Simple-Launcher-Installer/main/manifest.txt";
+
+/// The project's ed25519 public key, which the manifest and template checksums are checked
+/// against unless `signing_key` is set.
+pub const PROJECT_SIGNING_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEAUujlcELrVi1PAwwvIgDK4Yi9G+9UqfJ2xr7YHLKU5JU=
+-----END PUBLIC KEY-----
+";
+
+/// The PEM file signatures are checked against: `signing_key` if it is set, otherwise the
+/// project's key, written to `dir` for `openssl` to read.
+pub fn signing_key_file(signing_key: Option<&Path>, dir: &Path) -> Result<PathBuf, String> {
+    if let Some(key) = signing_key {
+        return Ok(key.to_path_buf());
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join("project.pub.pem");
+    fs::write(&path, PROJECT_SIGNING_KEY).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Local copy of the last manifest that was accepted.
+pub fn manifest_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(".config/game-launcher-installer/manifest")
+}
+
+/// Download the manifest and its signature, verify it against `signing_key` or the project's
+/// key, and replace the local copy. A manifest without a signature is refused unless
+/// `allow_unsigned` is set; one whose signature doesn't match, or can't be checked because
+/// `openssl` is missing, is always refused. Returns the number of installer hashes it lists.
+pub fn refresh_manifest(home_dir: &Path, url: &str, signing_key: Option<&Path>, allow_unsigned: bool) -> Result<usize, String> {
+    let staging = std::env::temp_dir().join(format!("game-launcher-manifest-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    let manifest = staging.join("manifest");
+    let signature = staging.join("manifest.sig");
+
+    let result = (|| {
+        download_file(url, &manifest)?;
+        let signed = match download_file(&format!("{}.sig", url), &signature) {
+            Ok(()) => Some(signature.as_path()),
+            Err(e) => {
+                println!("{}No signature for the manifest: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+                None
+            },
+        };
+        let key = signing_key_file(signing_key, &staging)?;
+        check_manifest(&manifest, signed, &key, allow_unsigned)
+        .map_err(|e| format!("The manifest from {} was refused: {}", url, e))?;
+
+        let entries = HashAllowlist::load(&manifest)?.len();
+        let destination = manifest_path(home_dir);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::copy(&manifest, &destination)
+        .map_err(|e| format!("Failed to save manifest: {}", e))?;
+        Ok(entries)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+// Whether a downloaded manifest may be used. `allow_unsigned` only stands in for a missing
+// signature; a signature that is there has to verify.
+fn check_manifest(manifest: &Path, signature: Option<&Path>, key: &Path, allow_unsigned: bool) -> Result<(), String> {
+    match signature {
+        Some(signature) => {
+            verify_signature(manifest, signature, key)?;
+            println!("{}Manifest signature verified.{}", COLOR_GREEN, COLOR_RESET);
+        },
+        None if allow_unsigned => println!("{}Warning: using an unsigned manifest.{}", COLOR_YELLOW, COLOR_RESET),
+        None => return Err("it has no signature. Pass --allow-unsigned to use it anyway.".to_string()),
+    }
+    Ok(())
+}
+
+/// Check an ed25519 signature over `data` against the public key in `key` (PEM) with `openssl`.
+pub fn verify_signature(data: &Path, signature: &Path, key: &Path) -> Result<(), String> {
+    let output = Command::new("openssl")
+    .args(["pkeyutl", "-verify", "-pubin", "-rawin", "-inkey"])
+    .arg(key)
+    .arg("-in").arg(data)
+    .arg("-sigfile").arg(signature)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|e| format!("failed to execute openssl: {}", e))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+    // openssl prints the verdict on stdout and why it couldn't reach one on stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !key.is_file() {
+        Err(format!("the signing key {} does not exist", key.display()))
+    } else if String::from_utf8_lossy(&output.stdout).contains("Signature Verification Failure") {
+        Err(format!("the signature does not match the key {}", key.display()))
+    } else {
+        Err(format!("openssl could not check the signature: {}", stderr.lines().next().unwrap_or("unknown error")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    use crate::wine::find_in_path;
+
+    fn openssl(dir: &Path, args: &[&str]) {
+        let status = Command::new("openssl").args(args).current_dir(dir).stderr(Stdio::null()).status().unwrap();
+        assert!(status.success(), "openssl {:?}", args);
+    }
+
+    // Make a key pair named `name` in `dir`; false, with the skip printed past the test
+    // harness's capture, if openssl isn't installed
+    fn key_pair(dir: &Path, name: &str) -> bool {
+        if find_in_path("openssl").is_none() {
+            let _ = writeln!(std::io::stderr(), "skipped: openssl is not installed");
+            return false;
+        }
+        fs::create_dir_all(dir).unwrap();
+        openssl(dir, &["genpkey", "-algorithm", "ed25519", "-out", &format!("{}.pem", name)]);
+        openssl(dir, &["pkey", "-in", &format!("{}.pem", name), "-pubout", "-out", &format!("{}.pub.pem", name)]);
+        true
+    }
+
+    #[test]
+    fn verifies_signatures_against_the_given_key() {
+        let dir = std::env::temp_dir().join(format!("gli-manifest-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        if !key_pair(&dir, "signing") || !key_pair(&dir, "other") {
+            return;
+        }
+        fs::write(dir.join("manifest"), "0123abcd  installer.exe\n").unwrap();
+        openssl(&dir, &["pkeyutl", "-sign", "-rawin", "-inkey", "signing.pem", "-in", "manifest", "-out", "manifest.sig"]);
+
+        let (manifest, signature) = (dir.join("manifest"), dir.join("manifest.sig"));
+        assert_eq!(verify_signature(&manifest, &signature, &dir.join("signing.pub.pem")), Ok(()));
+        let wrong_key = verify_signature(&manifest, &signature, &dir.join("other.pub.pem")).unwrap_err();
+        assert!(wrong_key.starts_with("the signature does not match"), "{}", wrong_key);
+        let missing_key = verify_signature(&manifest, &signature, &dir.join("missing.pem")).unwrap_err();
+        assert!(missing_key.contains("does not exist"), "{}", missing_key);
+
+        fs::write(&manifest, "0123abcd  other.exe\n").unwrap();
+        assert!(verify_signature(&manifest, &signature, &dir.join("signing.pub.pem")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn allow_unsigned_never_covers_a_bad_signature() {
+        let dir = std::env::temp_dir().join(format!("gli-manifest-check-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        if !key_pair(&dir, "signing") || !key_pair(&dir, "other") {
+            return;
+        }
+        fs::write(dir.join("manifest"), "0123abcd  installer.exe\n").unwrap();
+        openssl(&dir, &["pkeyutl", "-sign", "-rawin", "-inkey", "other.pem", "-in", "manifest", "-out", "manifest.sig"]);
+
+        let (manifest, signature, key) = (dir.join("manifest"), dir.join("manifest.sig"), dir.join("signing.pub.pem"));
+        assert!(check_manifest(&manifest, Some(&signature), &key, true).is_err());
+        assert!(check_manifest(&manifest, None, &key, false).is_err());
+        assert_eq!(check_manifest(&manifest, None, &key, true), Ok(()));
+        assert_eq!(check_manifest(&manifest, Some(&signature), &dir.join("other.pub.pem"), false), Ok(()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn project_key_is_the_default() {
+        let dir = std::env::temp_dir().join(format!("gli-manifest-key-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let key = signing_key_file(None, &dir).unwrap();
+        assert_eq!(fs::read_to_string(&key).unwrap(), PROJECT_SIGNING_KEY);
+        assert_eq!(signing_key_file(Some(Path::new("/etc/key.pem")), &dir).unwrap(), PathBuf::from("/etc/key.pem"));
+        if key_pair(&dir, "unused") {
+            openssl(&dir, &["pkey", "-pubin", "-in", "project.pub.pem", "-noout"]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
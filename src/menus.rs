@@ -0,0 +1,158 @@
+//! Cleaning up the menu entries, MIME types and icons winemenubuilder creates for Windows programs.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use regex::Regex;
+
+/// The winemenubuilder files that exist at one point in time.
+#[derive(Clone, Debug, Default)]
+pub struct MenuSnapshot {
+    files: HashSet<PathBuf>,
+}
+
+impl MenuSnapshot {
+    /// Record the wine menu files currently in the home directory.
+    pub fn take(home_dir: &Path) -> MenuSnapshot {
+        MenuSnapshot { files: wine_menu_files(home_dir).into_iter().collect() }
+    }
+
+    /// Wine menu files that were created since the snapshot was taken.
+    pub fn new_files(&self, home_dir: &Path) -> Vec<PathBuf> {
+        wine_menu_files(home_dir).into_iter().filter(|path| !self.files.contains(path)).collect()
+    }
+}
+
+/// Every file winemenubuilder may have written: entries under `applications/wine`, file type
+/// and protocol associations, menu folders, MIME packages and extracted icons.
+pub fn wine_menu_files(home_dir: &Path) -> Vec<PathBuf> {
+    let share = home_dir.join(".local/share");
+    let mut files = Vec::new();
+
+    collect_files(&share.join("applications/wine"), &mut files);
+    collect_matching(&share.join("applications"), |name| {
+        (name.starts_with("wine-extension-") || name.starts_with("wine-protocol-")) && name.ends_with(".desktop")
+    }, &mut files);
+    collect_matching(&share.join("desktop-directories"), |name| name.starts_with("wine-") && name.ends_with(".directory"), &mut files);
+    collect_matching(&home_dir.join(".config/menus/applications-merged"), |name| name.starts_with("wine-") && name.ends_with(".menu"), &mut files);
+    collect_matching(&share.join("mime/packages"), |name| name.starts_with("x-wine-") && name.ends_with(".xml"), &mut files);
+
+    // Icons are named after the resource they were extracted from, e.g. `1F2E_Battle.net.0.png`
+    let icon_name = Regex::new(r"^[0-9A-Fa-f]{4}_.+\.[0-9]+\.(png|xpm)$").unwrap();
+    if let Ok(sizes) = fs::read_dir(share.join("icons/hicolor")) {
+        for size in sizes.flatten() {
+            collect_matching(&size.path().join("apps"), |name| icon_name.is_match(name), &mut files);
+            collect_matching(&size.path().join("mimetypes"), |name| name.starts_with("application-x-wine-extension-"), &mut files);
+        }
+    }
+
+    files
+}
+
+/// Wine menu entries whose `WINEPREFIX` lies under one of `roots`, along with the menu
+/// files and MIME packages that only exist for them.
+pub fn prefix_menu_files(home_dir: &Path, roots: &[PathBuf]) -> Vec<PathBuf> {
+    let prefix = Regex::new(r#"WINEPREFIX="?([^"\s]+)"?"#).unwrap();
+    let all = wine_menu_files(home_dir);
+
+    let entries: Vec<PathBuf> = all.iter()
+    .filter(|path| path.extension().is_some_and(|extension| extension == "desktop"))
+    .filter(|path| {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        prefix.captures_iter(&content).any(|captures| roots.iter().any(|root| Path::new(&captures[1]).starts_with(root)))
+    })
+    .cloned()
+    .collect();
+    // Desktop file IDs: the path below `applications` with `/` replaced by `-`
+    let applications = home_dir.join(".local/share/applications");
+    let names: Vec<String> = entries.iter()
+    .filter_map(|path| Some(path.strip_prefix(&applications).ok()?.with_extension("").to_string_lossy().replace('/', "-")))
+    .collect();
+
+    let mut files = entries.clone();
+    for path in &all {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let belongs = if name.ends_with(".menu") {
+            // A merged menu file adds a single entry, named after its .desktop file
+            let content = fs::read_to_string(path).unwrap_or_default();
+            names.iter().any(|entry| content.contains(&format!(">{}.desktop<", entry)))
+        } else if let Some(extension) = name.strip_prefix("x-wine-extension-").and_then(|rest| rest.strip_suffix(".xml")) {
+            names.iter().any(|entry| *entry == format!("wine-extension-{}", extension))
+        } else {
+            false
+        };
+        if belongs {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+/// Remove wine menu files, prune the folders left empty and refresh the desktop and MIME
+/// databases. Returns how many files were removed.
+pub fn remove_menu_files(home_dir: &Path, files: &[PathBuf]) -> Result<usize, String> {
+    let mut removed = 0;
+    for file in files {
+        match fs::remove_file(file) {
+            Ok(()) => removed += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+            Err(e) => return Err(format!("Failed to remove {}: {}", file.display(), e)),
+        }
+    }
+
+    let share = home_dir.join(".local/share");
+    prune_empty_dirs(&share.join("applications/wine"));
+
+    // Not every desktop ships these; the entries are gone either way
+    let _ = Command::new("update-desktop-database")
+    .arg(share.join("applications"))
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status();
+    if files.iter().any(|file| file.starts_with(share.join("mime"))) {
+        let _ = Command::new("update-mime-database")
+        .arg(share.join("mime"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    }
+
+    Ok(removed)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_files(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+}
+
+fn collect_matching(dir: &Path, matches: impl Fn(&str) -> bool, files: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if matches(&entry.file_name().to_string_lossy()) && entry.path().is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+}
+
+// Remove empty directories below `dir`, and `dir` itself if it ends up empty
+fn prune_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                prune_empty_dirs(&entry.path());
+            }
+        }
+    }
+    let _ = fs::remove_dir(dir);
+}
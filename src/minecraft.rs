@@ -0,0 +1,68 @@
+//! The Java runtime the Minecraft Launcher's installer unpacks next to the launcher.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::pe::PeInfo;
+
+// Where the installer unpacks runtimes, relative to the install directory
+const RUNTIME_DIR: &str = "runtime";
+
+// How deep under the runtime directory `javaw.exe` is looked for: `jre-x64/bin/javaw.exe` in
+// older builds, `java-runtime-gamma/windows-x64/java-runtime-gamma/bin/javaw.exe` in newer ones
+const MAX_SEARCH_DEPTH: usize = 5;
+
+// Class library of the runtime: `lib/modules` from Java 9 on, `lib/rt.jar` before
+const CLASS_LIBRARIES: &[&str] = &["lib/modules", "lib/rt.jar"];
+
+/// Check that the launcher's bundled Java runtime was unpacked completely: its `javaw.exe` is a
+/// valid Windows executable and its class library is not empty. Returns the runtime's directory,
+/// or what is wrong with it.
+pub fn check_java_runtime(install_dir: &Path) -> Result<PathBuf, String> {
+    let runtime_dir = install_dir.join(RUNTIME_DIR);
+    let mut found = Vec::new();
+    find_javaw(&runtime_dir, MAX_SEARCH_DEPTH, &mut found);
+    if found.is_empty() {
+        return Err(format!("No Java runtime was unpacked to {}.", runtime_dir.display()));
+    }
+
+    let mut problems = Vec::new();
+    for javaw in found {
+        let java_home = match javaw.parent().and_then(Path::parent) {
+            Some(java_home) => java_home.to_path_buf(),
+            None => continue,
+        };
+        if let Err(e) = PeInfo::inspect(&javaw) {
+            problems.push(e);
+            continue;
+        }
+        let has_classes = CLASS_LIBRARIES.iter()
+        .any(|library| fs::metadata(java_home.join(library)).is_ok_and(|metadata| metadata.len() > 0));
+        if !has_classes {
+            problems.push(format!("{} has no class library ({}).", java_home.display(), CLASS_LIBRARIES.join(" or ")));
+            continue;
+        }
+        return Ok(java_home);
+    }
+    Err(problems.join(" "))
+}
+
+// Collect the `bin/javaw.exe` files under `dir`, in name order
+fn find_javaw(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let javaw = dir.join("bin/javaw.exe");
+    if javaw.is_file() {
+        found.push(javaw);
+        return;
+    }
+    if depth == 0 {
+        return;
+    }
+    let mut subdirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect(),
+        Err(_) => return,
+    };
+    subdirs.sort();
+    for subdir in subdirs {
+        find_javaw(&subdir, depth - 1, found);
+    }
+}
@@ -0,0 +1,268 @@
+//! Terminal colors, their theme, and the JSONL progress event stream.
+
+use std::fs;
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+
+use crate::journal;
+
+// Colors by what they signal, named after the default palette. They print the escape
+// code of the active theme, so `println!("{}Done{}", COLOR_GREEN, COLOR_RESET)` follows it.
+pub const COLOR_GREEN: ThemeColor = ThemeColor::Success;
+pub const COLOR_YELLOW: ThemeColor = ThemeColor::Warning;
+pub const COLOR_RED: ThemeColor = ThemeColor::Error;
+pub const COLOR_BLUE: ThemeColor = ThemeColor::Info;
+pub const COLOR_RESET: ThemeColor = ThemeColor::Reset;
+
+/// A role in the terminal palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeColor {
+    Success,
+    Warning,
+    Error,
+    /// Headings and progress messages
+    Info,
+    Reset,
+}
+
+impl ThemeColor {
+    /// Role names used for `color.<role>` in the config file
+    pub const ROLES: &'static [(&'static str, ThemeColor)] = &[
+        ("success", ThemeColor::Success),
+        ("warning", ThemeColor::Warning),
+        ("error", ThemeColor::Error),
+        ("info", ThemeColor::Info),
+    ];
+
+    pub fn parse(role: &str) -> Option<ThemeColor> {
+        ThemeColor::ROLES.iter().find(|(name, _)| *name == role).map(|(_, color)| *color)
+    }
+}
+
+impl std::fmt::Display for ThemeColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let theme = THEME.get_or_init(Theme::default);
+        let sgr = match self {
+            ThemeColor::Success => &theme.success,
+            ThemeColor::Warning => &theme.warning,
+            ThemeColor::Error => &theme.error,
+            ThemeColor::Info => &theme.info,
+            ThemeColor::Reset if theme.is_plain() => return Ok(()),
+            ThemeColor::Reset => "0",
+        };
+        if sgr.is_empty() {
+            Ok(())
+        } else {
+            write!(f, "\x1b[{}m", sgr)
+        }
+    }
+}
+
+/// SGR parameters (the `0;32` in `\x1b[0;32m`) for each color role. Empty prints no color.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub success: String,
+    pub warning: String,
+    pub error: String,
+    pub info: String,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::named("default").expect("the default theme exists")
+    }
+}
+
+impl Theme {
+    pub const NAMES: &'static [&'static str] = &["default", "colorblind", "none"];
+
+    /// A built-in theme: `default` (green/yellow/red/blue), `colorblind` (blue for success and
+    /// orange for errors, also bold so they differ in brightness) or `none`.
+    pub fn named(name: &str) -> Option<Theme> {
+        let (success, warning, error, info) = match name {
+            "default" => ("0;32", "0;33", "0;31", "0;34"),
+            "colorblind" => ("1;34", "0;33", "1;38;5;208", "0;36"),
+            "none" => ("", "", "", ""),
+            _ => return None,
+        };
+        Some(Theme {
+            success: success.to_string(),
+            warning: warning.to_string(),
+            error: error.to_string(),
+            info: info.to_string(),
+        })
+    }
+
+    /// Replace the SGR parameters of one role.
+    pub fn set(&mut self, color: ThemeColor, sgr: &str) {
+        let sgr = sgr.to_string();
+        match color {
+            ThemeColor::Success => self.success = sgr,
+            ThemeColor::Warning => self.warning = sgr,
+            ThemeColor::Error => self.error = sgr,
+            ThemeColor::Info => self.info = sgr,
+            ThemeColor::Reset => {},
+        }
+    }
+
+    fn is_plain(&self) -> bool {
+        self.success.is_empty() && self.warning.is_empty() && self.error.is_empty() && self.info.is_empty()
+    }
+}
+
+/// Whether a value is valid SGR parameters for `color.<role>`, e.g. `1;36` or `38;5;208`.
+pub fn is_sgr(value: &str) -> bool {
+    !value.is_empty() && value.split(';').all(|part| !part.is_empty() && part.len() <= 3 && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+// Palette used by the COLOR_* values, set once at startup
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Use a theme for all further output. Only the first call has an effect.
+pub fn set_theme(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+// Destination for JSONL progress events, set with --progress-events
+static PROGRESS_SINK: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+/// Escape a string for inclusion in a JSON string literal.
+pub fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Open the progress event target: `fd://N` for a descriptor inherited from the caller, or a
+/// file path. The descriptor is opened again through `/proc/self/fd`, so a wrong number fails
+/// instead of taking over a file the process uses; 0 to 2 are refused.
+pub fn open_progress_sink(target: &str) -> Result<(), String> {
+    let path = match target.strip_prefix("fd://") {
+        Some(fd) => {
+            let fd: u32 = fd.parse().map_err(|_| format!("Invalid file descriptor: {}", target))?;
+            if fd <= 2 {
+                return Err(format!("Progress events can't go to standard input, output or error ({}).", target));
+            }
+            format!("/proc/self/fd/{}", fd)
+        },
+        None => target.to_string(),
+    };
+    let file = fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&path)
+    .map_err(|e| format!("Failed to open progress event target {}: {}", target, e))?;
+    set_progress_sink(Box::new(file))
+}
+
+/// Write progress events to standard error, as headless runs do.
+pub fn progress_to_stderr() -> Result<(), String> {
+    set_progress_sink(Box::new(io::stderr()))
+}
+
+fn set_progress_sink(sink: Box<dyn Write + Send>) -> Result<(), String> {
+    PROGRESS_SINK
+    .set(Mutex::new(sink))
+    .map_err(|_| "Progress event target already set.".to_string())
+}
+
+/// Emit one progress event as a JSON line. Does nothing unless a progress sink was opened.
+/// The step is also recorded in the journal.
+pub fn emit_progress(stage: &str, percent: Option<u8>, message: &str) {
+    journal::record("step", &[("stage", stage), ("message", message)]);
+    let sink = match PROGRESS_SINK.get() {
+        Some(sink) => sink,
+        None => return,
+    };
+
+    let percent = percent.map(|p| p.to_string()).unwrap_or_else(|| "null".to_string());
+    let line = format!(
+        "{{\"stage\":\"{}\",\"percent\":{},\"message\":\"{}\"}}\n",
+        json_escape(stage), percent, json_escape(message)
+    );
+
+    if let Ok(mut file) = sink.lock() {
+        let _ = file.write_all(line.as_bytes());
+        let _ = file.flush();
+    }
+}
+
+/// Undo the escaping done by `json_escape`.
+pub fn json_unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    result.push(c);
+                }
+            },
+            Some(other) => result.push(other),
+            None => {},
+        }
+    }
+    result
+}
+
+/// Read back a line written by `emit_progress`: its stage, percent and message.
+pub fn parse_progress_event(line: &str) -> Option<(String, Option<u8>, String)> {
+    let event = Regex::new(r#"^\{"stage":"((?:[^"\\]|\\.)*)","percent":(\d+|null),"message":"((?:[^"\\]|\\.)*)"\}$"#).unwrap();
+    let caps = event.captures(line.trim_end())?;
+    let percent = match &caps[2] {
+        "null" => None,
+        percent => Some(percent.parse().ok()?),
+    };
+    Some((json_unescape(&caps[1]), percent, json_unescape(&caps[3])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_escape("C:\\Program Files \"x86\"\n\u{1}"), "C:\\\\Program Files \\\"x86\\\"\\n\\u0001");
+    }
+
+    #[test]
+    fn parses_progress_events() {
+        let line = format!("{{\"stage\":\"download\",\"percent\":42,\"message\":\"{}\"}}\n", json_escape("C:\\x \"y\""));
+        assert_eq!(parse_progress_event(&line), Some(("download".to_string(), Some(42), "C:\\x \"y\"".to_string())));
+        assert_eq!(parse_progress_event("{\"stage\":\"prefix\",\"percent\":null,\"message\":\"\"}"), Some(("prefix".to_string(), None, String::new())));
+        assert_eq!(parse_progress_event("{\"stage\":\"prefix\",\"percent\":300,\"message\":\"\"}"), None);
+        assert_eq!(parse_progress_event("{\"stage\":\"prefix\""), None);
+    }
+
+    // None of these get as far as setting the process-wide sink
+    #[test]
+    fn refuses_standard_and_invalid_descriptors() {
+        for target in ["fd://0", "fd://1", "fd://2"] {
+            assert!(open_progress_sink(target).unwrap_err().contains("standard input, output or error"), "{}", target);
+        }
+        for target in ["fd://-1", "fd://x", "fd://"] {
+            assert_eq!(open_progress_sink(target), Err(format!("Invalid file descriptor: {}", target)));
+        }
+        assert!(open_progress_sink("fd://999999").unwrap_err().starts_with("Failed to open progress event target fd://999999"));
+    }
+}
@@ -0,0 +1,237 @@
+//! Reading version information out of Windows installers (PE files) before they are run.
+
+use std::fs;
+use std::path::Path;
+
+// Data directory indices in the PE optional header
+const RESOURCE_DIRECTORY: usize = 2;
+const SECURITY_DIRECTORY: usize = 4;
+
+// Resource type ID of version information
+const RT_VERSION: u32 = 16;
+
+// Signature at the start of VS_FIXEDFILEINFO
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF04BD;
+
+/// What an installer says about itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeInfo {
+    pub product_name: Option<String>,
+    pub company_name: Option<String>,
+    /// `ProductVersion` string, falling back to the fixed file version numbers
+    pub version: Option<String>,
+    /// Whether an Authenticode signature is attached. It is not validated.
+    pub signed: bool,
+}
+
+impl PeInfo {
+    /// Read the version resource and certificate table of a PE file.
+    pub fn inspect(path: &Path) -> Result<PeInfo, String> {
+        let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        PeInfo::parse(&data).ok_or_else(|| format!("{} is not a valid Windows executable.", path.display()))
+    }
+
+    pub fn parse(data: &[u8]) -> Option<PeInfo> {
+        if data.get(0..2)? != b"MZ" {
+            return None;
+        }
+        let pe = read_u32(data, 0x3c)? as usize;
+        if data.get(pe..pe + 4)? != b"PE\0\0" {
+            return None;
+        }
+        let section_count = read_u16(data, pe + 6)? as usize;
+        let optional_size = read_u16(data, pe + 20)? as usize;
+        let optional = pe + 24;
+        let (directory_count_offset, directories) = match read_u16(data, optional)? {
+            0x10b => (optional + 92, optional + 96),
+            0x20b => (optional + 108, optional + 112),
+            _ => return None,
+        };
+        let directory_count = read_u32(data, directory_count_offset)? as usize;
+        let directory = |index: usize| -> Option<(u32, u32)> {
+            if index >= directory_count {
+                return None;
+            }
+            Some((read_u32(data, directories + index * 8)?, read_u32(data, directories + index * 8 + 4)?))
+        };
+
+        let mut info = PeInfo {
+            // The security directory holds a file offset, not an RVA
+            signed: directory(SECURITY_DIRECTORY).map(|(offset, size)| offset != 0 && size != 0).unwrap_or(false),
+            ..PeInfo::default()
+        };
+
+        let sections: Vec<(u32, u32, u32)> = (0..section_count)
+        .filter_map(|i| {
+            let section = optional + optional_size + i * 40;
+            let virtual_size = read_u32(data, section + 8)?;
+            let virtual_address = read_u32(data, section + 12)?;
+            let raw_size = read_u32(data, section + 16)?;
+            let raw_offset = read_u32(data, section + 20)?;
+            Some((virtual_address, virtual_size.max(raw_size), raw_offset))
+        })
+        .collect();
+        let rva_to_offset = |rva: u32| -> Option<usize> {
+            sections.iter()
+            .find(|(address, size, _)| rva >= *address && rva - address < *size)
+            .map(|(address, _, raw)| (rva - address) as usize + *raw as usize)
+        };
+
+        let version = directory(RESOURCE_DIRECTORY)
+        .filter(|(rva, _)| *rva != 0)
+        .and_then(|(rva, _)| rva_to_offset(rva))
+        .and_then(|resources| find_version_resource(data, resources))
+        .and_then(|(rva, size)| {
+            let offset = rva_to_offset(rva)?;
+            data.get(offset..offset + size as usize)
+        });
+        if let Some(version) = version {
+            read_version_info(version, &mut info);
+        }
+        Some(info)
+    }
+
+    /// One-line summary, e.g. `Battle.net Setup 2.30.0.15000 (Blizzard Entertainment), signed`.
+    pub fn summary(&self) -> String {
+        let mut summary = self.product_name.clone().unwrap_or_else(|| "Unknown product".to_string());
+        if let Some(version) = &self.version {
+            summary.push_str(&format!(" {}", version));
+        }
+        if let Some(company) = &self.company_name {
+            summary.push_str(&format!(" ({})", company));
+        }
+        summary.push_str(if self.signed { ", signed" } else { ", not signed" });
+        summary
+    }
+}
+
+// Walk the resource tree (type, name, language) to the first RT_VERSION entry's data RVA and size
+fn find_version_resource(data: &[u8], resources: usize) -> Option<(u32, u32)> {
+    let entries = |directory: usize| -> Option<Vec<(u32, u32)>> {
+        let count = read_u16(data, directory + 12)? as usize + read_u16(data, directory + 14)? as usize;
+        (0..count)
+        .map(|i| Some((read_u32(data, directory + 16 + i * 8)?, read_u32(data, directory + 20 + i * 8)?)))
+        .collect()
+    };
+    const SUBDIRECTORY: u32 = 0x8000_0000;
+
+    let (_, names) = entries(resources)?.into_iter().find(|(id, _)| *id == RT_VERSION)?;
+    let (_, languages) = *entries(resources + (names & !SUBDIRECTORY) as usize)?.first()?;
+    let (_, leaf) = *entries(resources + (languages & !SUBDIRECTORY) as usize)?.first()?;
+    if leaf & SUBDIRECTORY != 0 {
+        return None;
+    }
+    let leaf = resources + leaf as usize;
+    Some((read_u32(data, leaf)?, read_u32(data, leaf + 4)?))
+}
+
+// One block of a VS_VERSIONINFO tree
+struct VersionBlock {
+    key: String,
+    value_start: usize,
+    value_length: u16,
+    is_text: bool,
+    children_start: usize,
+    end: usize,
+}
+
+fn read_block(data: &[u8], start: usize) -> Option<VersionBlock> {
+    let length = read_u16(data, start)? as usize;
+    let value_length = read_u16(data, start + 2)?;
+    let is_text = read_u16(data, start + 4)? == 1;
+    if length < 6 {
+        return None;
+    }
+    let (key, key_end) = read_utf16z(data, start + 6)?;
+    let value_start = align4(key_end);
+    let value_bytes = if is_text { value_length as usize * 2 } else { value_length as usize };
+    Some(VersionBlock {
+        key,
+        value_start,
+        value_length,
+        is_text,
+        children_start: align4(value_start + value_bytes),
+        end: (start + length).min(data.len()),
+    })
+}
+
+fn children(data: &[u8], block: &VersionBlock) -> Vec<VersionBlock> {
+    let mut blocks = Vec::new();
+    let mut position = block.children_start;
+    while position + 6 <= block.end {
+        match read_block(data, position) {
+            Some(child) => {
+                position = align4(child.end);
+                blocks.push(child);
+            },
+            None => break,
+        }
+    }
+    blocks
+}
+
+// Fill in the product, company and version from VS_VERSIONINFO
+fn read_version_info(data: &[u8], info: &mut PeInfo) {
+    let root = match read_block(data, 0) {
+        Some(root) if root.key == "VS_VERSION_INFO" => root,
+        _ => return,
+    };
+
+    let mut file_version = None;
+    if root.value_length >= 52 && read_u32(data, root.value_start) == Some(FIXED_FILE_INFO_SIGNATURE) {
+        if let (Some(ms), Some(ls)) = (read_u32(data, root.value_start + 8), read_u32(data, root.value_start + 12)) {
+            file_version = Some(format!("{}.{}.{}.{}", ms >> 16, ms & 0xffff, ls >> 16, ls & 0xffff));
+        }
+    }
+
+    for string_file_info in children(data, &root).iter().filter(|block| block.key == "StringFileInfo") {
+        for table in children(data, string_file_info) {
+            for string in children(data, &table) {
+                if !string.is_text || string.value_length == 0 {
+                    continue;
+                }
+                let value = match read_utf16z(data, string.value_start) {
+                    Some((value, _)) => value.trim().to_string(),
+                    None => continue,
+                };
+                match string.key.as_str() {
+                    "ProductName" if info.product_name.is_none() => info.product_name = Some(value),
+                    "CompanyName" if info.company_name.is_none() => info.company_name = Some(value),
+                    "ProductVersion" if info.version.is_none() => info.version = Some(value),
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    if info.version.is_none() {
+        info.version = file_version;
+    }
+}
+
+// Read a NUL-terminated UTF-16LE string, returning it and the offset just past the terminator
+fn read_utf16z(data: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut units = Vec::new();
+    let mut position = start;
+    loop {
+        let unit = read_u16(data, position)?;
+        position += 2;
+        if unit == 0 {
+            break;
+        }
+        units.push(unit);
+    }
+    Some((String::from_utf16_lossy(&units), position))
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
@@ -0,0 +1,65 @@
+//! Native folder picker for choosing install directories in a desktop session.
+//!
+//! The picker is `kdialog` under KDE and `zenity` elsewhere. Both hand the dialog to
+//! xdg-desktop-portal when it runs, so Flatpak and sandboxed sessions get the desktop's own
+//! file chooser.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::wine::find_in_path;
+use crate::xvfb::display_available;
+
+/// Which program shows the folder picker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FolderPicker {
+    Kdialog,
+    Zenity,
+}
+
+impl FolderPicker {
+    /// The picker to use here, if any: only in a local desktop session, since over SSH the
+    /// dialog would open on a screen the user isn't looking at.
+    pub fn detect() -> Option<FolderPicker> {
+        let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        if set("SSH_CONNECTION") || set("SSH_TTY") || !display_available() {
+            return None;
+        }
+        let kde = std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.to_uppercase().contains("KDE"));
+        let kdialog = find_in_path("kdialog").is_some();
+        let zenity = find_in_path("zenity").is_some();
+        match (kde, kdialog, zenity) {
+            (true, true, _) | (false, true, false) => Some(FolderPicker::Kdialog),
+            (_, _, true) => Some(FolderPicker::Zenity),
+            _ => None,
+        }
+    }
+
+    /// Let the user pick a directory, starting at `start` (or its closest existing parent).
+    /// Returns None when the picker was cancelled or could not be shown.
+    pub fn pick_directory(&self, title: &str, start: &Path) -> Option<PathBuf> {
+        let start = start.ancestors().find(|ancestor| ancestor.is_dir()).unwrap_or(start);
+        let mut command = match self {
+            FolderPicker::Kdialog => {
+                let mut command = Command::new("kdialog");
+                command.arg("--title").arg(title).arg("--getexistingdirectory").arg(start);
+                command
+            },
+            FolderPicker::Zenity => {
+                let mut command = Command::new("zenity");
+                // Without it GTK only uses the portal inside Flatpak
+                command.env("GTK_USE_PORTAL", "1")
+                .args(["--file-selection", "--directory"])
+                .arg(format!("--title={}", title))
+                .arg(format!("--filename={}/", start.display()));
+                command
+            },
+        };
+        let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let path = String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string();
+        if path.is_empty() { None } else { Some(PathBuf::from(path)) }
+    }
+}
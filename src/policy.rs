@@ -0,0 +1,159 @@
+//! Which downloaded installers may be run: SHA-256 hashes checked against an allowlist.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::fsutil::write_atomic;
+use crate::journal;
+use crate::output::*;
+
+/// How installers missing from the allowlist are treated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstallerPolicy {
+    /// Refuse to run them
+    Strict,
+    /// Run them after printing a warning with their hash
+    Normal,
+    /// Don't hash installers at all
+    Off,
+}
+
+impl InstallerPolicy {
+    pub fn parse(value: &str) -> Option<InstallerPolicy> {
+        match value.trim().to_lowercase().as_str() {
+            "strict" => Some(InstallerPolicy::Strict),
+            "normal" => Some(InstallerPolicy::Normal),
+            "off" => Some(InstallerPolicy::Off),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InstallerPolicy::Strict => "strict",
+            InstallerPolicy::Normal => "normal",
+            InstallerPolicy::Off => "off",
+        }
+    }
+}
+
+/// Known-good installer hashes, one `<sha256> <launcher>` per line.
+#[derive(Clone, Debug, Default)]
+pub struct HashAllowlist {
+    entries: Vec<(String, String)>,
+}
+
+impl HashAllowlist {
+    pub fn path(home_dir: &Path) -> PathBuf {
+        home_dir.join(".config/game-launcher-installer/allowlist")
+    }
+
+    /// Load the allowlist; a missing file is an empty list.
+    pub fn load(path: &Path) -> Result<HashAllowlist, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashAllowlist::default()),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+
+        let entries = contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?.to_lowercase(), fields.next()?.to_string()))
+        })
+        .collect();
+        Ok(HashAllowlist { entries })
+    }
+
+    /// Append a hash to the allowlist file.
+    pub fn add(path: &Path, sha256: &str, launcher: &str) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut contents = fs::read_to_string(path).unwrap_or_default();
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&format!("{} {}\n", sha256, launcher));
+        write_atomic(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Add the entries of another list, e.g. the verified remote manifest.
+    pub fn merge(&mut self, other: HashAllowlist) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, sha256: &str, launcher: &str) -> bool {
+        self.entries.iter().any(|(hash, id)| hash == sha256 && id == launcher)
+    }
+}
+
+/// The policy and allowlist an `Installer` checks fetched installers against.
+#[derive(Clone, Debug)]
+pub struct HashPolicy {
+    pub policy: InstallerPolicy,
+    pub allowlist: HashAllowlist,
+}
+
+impl Default for HashPolicy {
+    fn default() -> HashPolicy {
+        HashPolicy { policy: InstallerPolicy::Off, allowlist: HashAllowlist::default() }
+    }
+}
+
+impl HashPolicy {
+    /// Check an installer before it is run. Errors in strict mode if its hash isn't listed.
+    pub fn check(&self, launcher: &str, installer: &Path) -> Result<(), String> {
+        if self.policy == InstallerPolicy::Off {
+            return Ok(());
+        }
+
+        let sha256 = sha256_file(installer)?;
+        if self.allowlist.contains(&sha256, launcher) {
+            println!("{}Installer hash is on the allowlist.{}", COLOR_GREEN, COLOR_RESET);
+            return Ok(());
+        }
+
+        match self.policy {
+            InstallerPolicy::Strict => Err(format!(
+                "{} (SHA-256 {}) is not on the installer allowlist. Run `refresh-manifest` to get the project's latest hashes, `trust-installer {}` if you trust it, or pass --allow-unlisted-installer.",
+                installer.display(), sha256, launcher
+            )),
+            _ => {
+                println!("{}Warning: {} (SHA-256 {}) is not on the installer allowlist.{}",
+                         COLOR_YELLOW, installer.display(), sha256, COLOR_RESET);
+                Ok(())
+            },
+        }
+    }
+}
+
+/// SHA-256 of a file as lowercase hex, computed with `sha256sum`.
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let output = Command::new("sha256sum")
+    .arg(path)
+    .output()
+    .map_err(|e| format!("Failed to execute sha256sum: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to hash {}", path.display()));
+    }
+    let hash = String::from_utf8_lossy(&output.stdout)
+    .split_whitespace()
+    .next()
+    .map(str::to_lowercase)
+    .ok_or_else(|| format!("Failed to hash {}", path.display()))?;
+    journal::record("hash", &[("path", &path.to_string_lossy()), ("sha256", &hash)]);
+    Ok(hash)
+}
@@ -0,0 +1,689 @@
+//! Managing a single wine prefix: initialization, process monitoring and tweaks.
+
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::audio::AudioSettings;
+use crate::config::Config;
+use crate::dlloverrides::DllOverrides;
+use crate::language::Language;
+use crate::links;
+use crate::output::*;
+use crate::session::{wineserver_for, WineSession};
+use crate::wine::find_in_path;
+
+// Windows processes that wine keeps alive on its own and that don't indicate installer activity
+const WINE_SYSTEM_PROCESSES: &[&str] = &[
+    "services.exe", "winedevice.exe", "plugplay.exe", "explorer.exe",
+    "rpcss.exe", "svchost.exe", "conhost.exe", "tabtip.exe", "start.exe",
+];
+
+// File in the prefix recording which wine build last updated it
+const RUNNER_MARKER: &str = ".runner";
+
+// Maximum time to wait for wineboot to write out a new or updated prefix
+const WINEBOOT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// How long winetricks may take to download and install verbs such as dotnet48
+const WINETRICKS_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+// Maximum time to wait for installer child processes before giving up
+const INSTALLER_QUIESCENCE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+// Index of a registry backup: `<file>\t<key>` per line, with `-` for keys that did not exist
+const BACKUP_INDEX: &str = "keys";
+
+// Name of this run's registry backups, `<time>-<pid>`, shared by every prefix it changes
+static BACKUP_RUN: OnceLock<String> = OnceLock::new();
+
+/// Where the registry backups of a prefix are kept, one directory per run that changed it.
+pub fn registry_backup_dir(home_dir: &Path, prefix: &Path) -> PathBuf {
+    let escaped = prefix.to_string_lossy().trim_matches('/').replace('/', "-");
+    home_dir.join(".local/state/game-launcher-installer/registry-backups").join(escaped)
+}
+
+/// Registry backups of a prefix, oldest first, with the keys each one holds.
+pub fn registry_backups(home_dir: &Path, prefix: &Path) -> Vec<(PathBuf, Vec<String>)> {
+    let mut backups: Vec<(PathBuf, Vec<String>)> = fs::read_dir(registry_backup_dir(home_dir, prefix)).into_iter().flatten().flatten()
+    .map(|entry| entry.path())
+    .filter_map(|dir| {
+        let index = fs::read_to_string(dir.join(BACKUP_INDEX)).ok()?;
+        let keys = index.lines().filter_map(|line| line.split_once('\t')).map(|(_, key)| key.to_string()).collect();
+        Some((dir, keys))
+    })
+    .collect();
+    // `<time>-<pid>` names sort by time as long as the times have the same number of digits
+    backups.sort();
+    backups
+}
+
+/// A tweak this tool can apply to a prefix. The ones applied to a launcher's prefix are
+/// recorded in its state so `reapply` can restore them after the launcher updates itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tweak {
+    NoWindowDecorations,
+    FontSmoothing,
+    Sandbox,
+    /// DPI wine renders at, see `set_dpi`
+    Dpi(u32),
+}
+
+impl Tweak {
+    pub fn parse(value: &str) -> Option<Tweak> {
+        match value.trim() {
+            "no-window-decorations" => Some(Tweak::NoWindowDecorations),
+            "font-smoothing" => Some(Tweak::FontSmoothing),
+            "sandbox" => Some(Tweak::Sandbox),
+            value => value.strip_prefix("dpi:").and_then(|dpi| dpi.parse().ok()).map(Tweak::Dpi),
+        }
+    }
+
+    /// Name as written to the state file, e.g. `font-smoothing` or `dpi:144`
+    pub fn name(&self) -> String {
+        match self {
+            Tweak::NoWindowDecorations => "no-window-decorations".to_string(),
+            Tweak::FontSmoothing => "font-smoothing".to_string(),
+            Tweak::Sandbox => "sandbox".to_string(),
+            Tweak::Dpi(dpi) => format!("dpi:{}", dpi),
+        }
+    }
+
+    /// Whether both set the same setting, so the later one replaces the earlier
+    pub fn same_setting(&self, other: &Tweak) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// Architecture of a prefix, fixed by `WINEARCH` when wine created it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefixArch {
+    Win32,
+    Win64,
+}
+
+impl PrefixArch {
+    /// Name as used by `WINEARCH` and the `#arch=` line of `system.reg`
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrefixArch::Win32 => "win32",
+            PrefixArch::Win64 => "win64",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            PrefixArch::Win32 => "32-bit",
+            PrefixArch::Win64 => "64-bit",
+        }
+    }
+}
+
+/// A wine prefix, either one of ours under the Games root or a Steam compatdata `pfx`.
+pub struct PrefixManager {
+    path: PathBuf,
+}
+
+impl PrefixManager {
+    pub fn new(path: impl Into<PathBuf>) -> PrefixManager {
+        PrefixManager { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Create the prefix directory and run `wineboot --init` in it, like Steam does on first launch.
+    pub fn initialize(&self, wine_path: &str) -> Result<(), String> {
+        fs::create_dir_all(&self.path)
+        .map_err(|e| format!("Failed to create prefix directory: {}", e))?;
+
+        println!("{}Initializing prefix at {}...{}", COLOR_BLUE, self.path.display(), COLOR_RESET);
+        emit_progress("prefix", None, &format!("Initializing prefix at {}", self.path.display()));
+
+        let session = self.session(wine_path);
+        session.start()?;
+        let status = session.command("wineboot")
+        .env("WINEDLLOVERRIDES", "mscoree,mshtml=")
+        .arg("--init")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to execute wineboot: {}", e))?;
+
+        if !status.success() {
+            return Err(format!("wineboot failed with exit code: {}", status));
+        }
+
+        // Wait for the prefix's wineserver to finish writing the registry
+        session.wait(Some(WINEBOOT_TIMEOUT))?;
+
+        self.record_runner(wine_path);
+
+        println!("{}Prefix initialized: {}{}", COLOR_GREEN, self.path.display(), COLOR_RESET);
+        Ok(())
+    }
+
+    /// Run `wineboot -u` if the prefix was last used with a different wine build, e.g. after
+    /// switching from system wine to Proton-GE or upgrading wine. A prefix left on an old
+    /// runner's files makes launchers crash.
+    pub fn update_for_runner(&self, wine_path: &str) -> Result<(), String> {
+        if !self.path.join("system.reg").exists() {
+            // Not created yet; wine sets it up for this runner on first use
+            return Ok(());
+        }
+
+        let runner = runner_id(wine_path);
+        let previous = fs::read_to_string(self.path.join(RUNNER_MARKER)).ok();
+        if previous.as_deref().map(str::trim) == Some(runner.as_str()) {
+            return Ok(());
+        }
+
+        println!("{}Prefix was last used with {}; updating it for {}...{}",
+                 COLOR_YELLOW, previous.as_deref().map(str::trim).unwrap_or("an unknown wine build"), runner, COLOR_RESET);
+        emit_progress("prefix", None, &format!("Updating prefix for {}", runner));
+
+        let session = self.session(wine_path);
+        session.start()?;
+        let status = session.command("wineboot")
+        .arg("-u")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to execute wineboot: {}", e))?;
+
+        if !status.success() {
+            return Err(format!("wineboot -u failed with exit code: {}", status));
+        }
+
+        // wineboot returns before the prefix update is written out
+        session.wait(Some(WINEBOOT_TIMEOUT))?;
+
+        self.record_runner(wine_path);
+        println!("{}Prefix updated.{}", COLOR_GREEN, COLOR_RESET);
+        Ok(())
+    }
+
+    // Remember which wine build last set up the prefix
+    fn record_runner(&self, wine_path: &str) {
+        if let Err(e) = fs::write(self.path.join(RUNNER_MARKER), runner_id(wine_path)) {
+            println!("{}Warning: Could not record prefix runner: {}{}", COLOR_YELLOW, e, COLOR_RESET);
+        }
+    }
+
+    /// Install winetricks verbs into the prefix, skipping the ones winetricks has logged as
+    /// installed already. The prefix is initialized first if needed.
+    pub fn install_verbs(&self, wine_path: &str, verbs: &[&str]) -> Result<(), String> {
+        let installed = fs::read_to_string(self.path.join("winetricks.log")).unwrap_or_default();
+        let missing: Vec<&str> = verbs.iter().copied().filter(|verb| !installed.lines().any(|line| line.trim() == *verb)).collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        if find_in_path("winetricks").is_none() {
+            return Err(format!("winetricks is not installed; it is needed to install {} into the prefix.", missing.join(", ")));
+        }
+        if !self.path.join("system.reg").exists() {
+            self.initialize(wine_path)?;
+        }
+
+        println!("{}Installing {} into the prefix with winetricks...{}", COLOR_BLUE, missing.join(", "), COLOR_RESET);
+        emit_progress("prefix", None, &format!("Installing {}", missing.join(", ")));
+        let session = self.session(wine_path);
+        session.start()?;
+        let status = Command::new("winetricks")
+        .arg("-q")
+        .args(&missing)
+        .env("WINEPREFIX", &self.path)
+        .env("WINE", wine_path)
+        .env("WINESERVER", wineserver_for(wine_path))
+        .env("WINEDEBUG", "-all")
+        .status()
+        .map_err(|e| format!("Failed to execute winetricks: {}", e))?;
+        session.wait(Some(WINETRICKS_TIMEOUT))?;
+        if !status.success() {
+            return Err(format!("winetricks failed to install {} ({}).", missing.join(", "), status));
+        }
+        Ok(())
+    }
+
+    /// A wineserver session on this prefix for the given wine build.
+    pub fn session(&self, wine_path: &str) -> WineSession {
+        WineSession::new(wine_path, &self.path)
+    }
+
+    /// Stop every process in the prefix with `wineserver -k` and wait for its server to exit.
+    /// Uses the wineserver of the wine build that last set the prefix up.
+    pub fn kill_wineserver(&self) {
+        println!("{}Running wineserver -k to clean up...{}", COLOR_YELLOW, COLOR_RESET);
+        let wine_path = self.runner_wine().unwrap_or_else(|| "wine".to_string());
+        self.session(&wine_path).shutdown();
+    }
+
+    /// PIDs of every process started for the prefix, wine's own ones included.
+    pub fn process_ids(&self) -> Vec<u32> {
+        let mut prefix_env = b"WINEPREFIX=".to_vec();
+        prefix_env.extend_from_slice(self.path.as_os_str().as_bytes());
+        let mut pids = Vec::new();
+
+        let entries = match fs::read_dir("/proc") {
+            Ok(entries) => entries,
+            Err(_) => return pids,
+        };
+
+        for entry in entries.flatten() {
+            let pid = match entry.file_name().to_string_lossy().parse::<u32>() {
+                Ok(pid) => pid,
+                Err(_) => continue,
+            };
+
+            // Only look at processes started for this prefix
+            let environ = match fs::read(entry.path().join("environ")) {
+                Ok(environ) => environ,
+                Err(_) => continue,
+            };
+            if environ.split(|b| *b == 0).any(|var| var == prefix_env.as_slice()) {
+                pids.push(pid);
+            }
+        }
+
+        pids
+    }
+
+    /// List the non-system Windows executables currently running inside the prefix.
+    pub fn running_processes(&self) -> Vec<String> {
+        let mut processes = Vec::new();
+
+        for pid in self.process_ids() {
+            let pid_dir = PathBuf::from(format!("/proc/{}", pid));
+            let cmdline = match fs::read(pid_dir.join("cmdline")) {
+                Ok(cmdline) => cmdline,
+                Err(_) => continue,
+            };
+            let first_arg = cmdline.split(|b| *b == 0).next().unwrap_or(&[]);
+            let exe = String::from_utf8_lossy(first_arg).replace('\\', "/");
+            let exe_name = exe.rsplit('/').next().unwrap_or("").to_lowercase();
+
+            if exe_name.ends_with(".exe") && !WINE_SYSTEM_PROCESSES.contains(&exe_name.as_str()) {
+                processes.push(exe_name);
+            }
+        }
+
+        processes
+    }
+
+    /// Wait until no installer processes remain in the prefix for several consecutive polls.
+    /// Bootstrappers like Battle.net-Setup.exe exit before the Agent.exe they spawn is done.
+    pub fn wait_for_quiescence(&self) -> Result<(), String> {
+        const POLL_INTERVAL: Duration = Duration::from_secs(2);
+        const QUIET_POLLS_REQUIRED: u32 = 3;
+
+        println!("{}Waiting for installer processes to finish...{}", COLOR_BLUE, COLOR_RESET);
+        emit_progress("wait", Some(50), "Waiting for installer processes to finish");
+
+        let start = std::time::Instant::now();
+        let mut quiet_polls = 0;
+        let mut last_seen: Vec<String> = Vec::new();
+
+        while quiet_polls < QUIET_POLLS_REQUIRED {
+            if start.elapsed() > INSTALLER_QUIESCENCE_TIMEOUT {
+                return Err(format!("Timed out waiting for installer processes: {}", last_seen.join(", ")));
+            }
+
+            let processes = self.running_processes();
+            if processes.is_empty() {
+                quiet_polls += 1;
+            } else {
+                quiet_polls = 0;
+                if processes != last_seen {
+                    println!("{}Still running: {}{}", COLOR_YELLOW, processes.join(", "), COLOR_RESET);
+                }
+                last_seen = processes;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        println!("{}All installer processes have finished.{}", COLOR_GREEN, COLOR_RESET);
+        Ok(())
+    }
+
+    /// Find the Windows user profile directory inside the prefix (steamuser under Proton).
+    pub fn user_dir(&self) -> Option<PathBuf> {
+        let users_dir = self.path.join("drive_c/users");
+        let steamuser = users_dir.join("steamuser");
+        if steamuser.is_dir() {
+            return Some(steamuser);
+        }
+
+        fs::read_dir(&users_dir).ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| path.is_dir() && path.file_name().map(|n| n != "Public").unwrap_or(false))
+    }
+
+    /// Rename the Windows user profile to `user`, which runners expect: Proton always runs as
+    /// `steamuser`, wine as the Linux user. The old name is left as a link to the profile so
+    /// paths stored in the registry keep working. Returns whether anything was moved.
+    pub fn rename_user_dir(&self, user: &str) -> Result<bool, String> {
+        let target = self.path.join("drive_c/users").join(user);
+        if fs::symlink_metadata(&target).is_ok() {
+            return Ok(false);
+        }
+        let current = match self.user_dir() {
+            Some(current) => current,
+            None => return Ok(false),
+        };
+        fs::rename(&current, &target)
+        .map_err(|e| format!("Failed to rename {} to {}: {}", current.display(), target.display(), e))?;
+        std::os::unix::fs::symlink(user, &current)
+        .map_err(|e| format!("Failed to link {} to {}: {}", current.display(), target.display(), e))?;
+        if let Some(home_dir) = dirs::home_dir() {
+            links::record_link(&home_dir, &current, Path::new(user))?;
+        }
+        Ok(true)
+    }
+
+    /// Symlink `/` to `C:\Linux Root` so file dialogs inside the prefix can reach the Linux filesystem.
+    pub fn link_linux_root(&self) -> Result<(), String> {
+        let linux_root_link = links::link_path(&self.path, links::LINUX_ROOT);
+
+        if fs::symlink_metadata(&linux_root_link).is_ok() {
+            println!("{}Symlink or folder 'Linux Root' already exists in drive_c. Skipping symlink creation.{}",
+                     COLOR_YELLOW, COLOR_RESET);
+        } else {
+            links::add_link(&self.path, Path::new("/"), links::LINUX_ROOT)?;
+            println!("{}Symlinked / to {}{}", COLOR_GREEN, linux_root_link.display(), COLOR_RESET);
+        }
+
+        Ok(())
+    }
+
+    /// Give a Linux directory its own drive letter (the first free one from D:), reusing a
+    /// letter that already points there. Returns the letter.
+    pub fn map_drive(&self, target: &Path) -> Result<char, String> {
+        let dosdevices = self.path.join("dosdevices");
+        let free = ('d'..='y').find(|letter| {
+            let link = dosdevices.join(format!("{}:", letter));
+            match fs::read_link(&link) {
+                Ok(existing) => existing == target,
+                Err(_) => fs::symlink_metadata(&link).is_err(),
+            }
+        })
+        .ok_or("No free drive letter left in the prefix.")?;
+
+        let link = dosdevices.join(format!("{}:", free));
+        if fs::read_link(&link).is_err() {
+            std::os::unix::fs::symlink(target, &link)
+            .map_err(|e| format!("Failed to map {} to {}: {}", target.display(), free.to_ascii_uppercase(), e))?;
+            if let Some(home_dir) = dirs::home_dir() {
+                links::record_link(&home_dir, &link, target)?;
+            }
+        }
+        Ok(free)
+    }
+
+    /// Hide the host filesystem from Windows programs: remove the Z: drive that maps to `/`
+    /// and disable winemenubuilder so the prefix creates no desktop and menu entries.
+    pub fn sandbox(&self) -> Result<(), String> {
+        let z_drive = self.path.join("dosdevices/z:");
+        if fs::symlink_metadata(&z_drive).is_ok() {
+            fs::remove_file(&z_drive)
+            .map_err(|e| format!("Failed to remove Z: drive mapping: {}", e))?;
+        }
+
+        self.set_registry_value("HKCU\\Software\\Wine\\DllOverrides", "winemenubuilder.exe", "REG_SZ", "")?;
+
+        println!("{}Prefix {} sandboxed (no Z: drive, no menu integration).{}", COLOR_GREEN, self.path.display(), COLOR_RESET);
+        Ok(())
+    }
+
+    /// Set the X11 driver registry key that removes window decorations.
+    pub fn disable_window_decorations(&self) -> Result<(), String> {
+        println!("{}Setting registry key to remove window decorations...{}", COLOR_YELLOW, COLOR_RESET);
+
+        self.set_registry_value("HKCU\\Software\\Wine\\X11 Driver", "Decorated", "REG_SZ", "N")?;
+
+        println!("{}Window decorations disabled for prefix {}.{}", COLOR_GREEN, self.path.display(), COLOR_RESET);
+        Ok(())
+    }
+
+    /// Enable ClearType-style RGB subpixel font smoothing; wine renders launcher UI text unsmoothed by default.
+    pub fn enable_font_smoothing(&self) -> Result<(), String> {
+        let desktop = "HKCU\\Control Panel\\Desktop";
+        self.set_registry_value(desktop, "FontSmoothing", "REG_SZ", "2")?;
+        self.set_registry_value(desktop, "FontSmoothingType", "REG_DWORD", "2")?;
+        self.set_registry_value(desktop, "FontSmoothingGamma", "REG_DWORD", "1400")?;
+        self.set_registry_value(desktop, "FontSmoothingOrientation", "REG_DWORD", "1")?;
+
+        println!("{}RGB font smoothing enabled for prefix {}.{}", COLOR_GREEN, self.path.display(), COLOR_RESET);
+        Ok(())
+    }
+
+    /// Apply a tweak with the method that sets it.
+    pub fn apply_tweak(&self, tweak: Tweak) -> Result<(), String> {
+        match tweak {
+            Tweak::NoWindowDecorations => self.disable_window_decorations(),
+            Tweak::FontSmoothing => self.enable_font_smoothing(),
+            Tweak::Sandbox => self.sandbox(),
+            Tweak::Dpi(dpi) => self.set_dpi(dpi),
+        }
+    }
+
+    /// Set the DPI wine renders at, e.g. 144 for 150% scaling on small high-resolution screens.
+    pub fn set_dpi(&self, dpi: u32) -> Result<(), String> {
+        self.set_registry_value("HKCU\\Control Panel\\Desktop", "LogPixels", "REG_DWORD", &dpi.to_string())?;
+
+        println!("{}DPI set to {} for prefix {}.{}", COLOR_GREEN, dpi, self.path.display(), COLOR_RESET);
+        Ok(())
+    }
+
+    /// Set the wine audio driver and DirectSound buffer sizes.
+    pub fn set_audio(&self, settings: &AudioSettings) -> Result<(), String> {
+        self.set_registry_value("HKCU\\Software\\Wine\\Drivers", "Audio", "REG_SZ", settings.driver)?;
+
+        let direct_sound = "HKCU\\Software\\Wine\\DirectSound";
+        self.set_registry_value(direct_sound, "HelBuflen", "REG_SZ", &settings.buffer_length.to_string())?;
+        self.set_registry_value(direct_sound, "SndQueueMax", "REG_SZ", &settings.queue_max.to_string())?;
+
+        println!("{}Audio driver set to {} (buffer {} bytes, queue {}).{}",
+                 COLOR_GREEN, settings.driver, settings.buffer_length, settings.queue_max, COLOR_RESET);
+        Ok(())
+    }
+
+    /// Set the prefix's user locale and system codepages to match `language`.
+    pub fn set_locale(&self, language: &Language) -> Result<(), String> {
+        let international = "HKCU\\Control Panel\\International";
+        self.set_registry_value(international, "Locale", "REG_SZ", &format!("0000{}", language.lcid))?;
+        self.set_registry_value(international, "LocaleName", "REG_SZ", language.windows)?;
+
+        let codepage = "HKLM\\System\\CurrentControlSet\\Control\\Nls\\CodePage";
+        self.set_registry_value(codepage, "ACP", "REG_SZ", language.ansi_codepage)?;
+        self.set_registry_value(codepage, "OEMCP", "REG_SZ", language.oem_codepage)?;
+
+        println!("{}Prefix locale set to {} (codepage {}).{}",
+                 COLOR_GREEN, language.windows, language.ansi_codepage, COLOR_RESET);
+        Ok(())
+    }
+
+    /// Set the prefix's timezone to a Windows timezone name such as `W. Europe Standard Time`.
+    pub fn set_timezone(&self, windows_timezone: &str) -> Result<(), String> {
+        let key = "HKLM\\System\\CurrentControlSet\\Control\\TimeZoneInformation";
+        self.set_registry_value(key, "TimeZoneKeyName", "REG_SZ", windows_timezone)?;
+        self.set_registry_value(key, "StandardName", "REG_SZ", windows_timezone)?;
+
+        println!("{}Prefix timezone set to {}.{}", COLOR_GREEN, windows_timezone, COLOR_RESET);
+        Ok(())
+    }
+
+    /// Architecture recorded in the prefix's `system.reg`; None if the prefix does not exist yet
+    /// or was made by a wine too old to record it.
+    pub fn arch(&self) -> Option<PrefixArch> {
+        let system_reg = fs::read_to_string(self.path.join("system.reg")).ok()?;
+        let arch = system_reg.lines().find_map(|line| line.strip_prefix("#arch="))?;
+        match arch.trim() {
+            "win32" => Some(PrefixArch::Win32),
+            "win64" => Some(PrefixArch::Win64),
+            _ => None,
+        }
+    }
+
+    /// The wine build that last set up the prefix, if recorded.
+    pub fn runner(&self) -> Option<String> {
+        fs::read_to_string(self.path.join(RUNNER_MARKER)).ok().map(|runner| runner.trim().to_string())
+    }
+
+    /// Path of the wine binary recorded by `runner`.
+    pub fn runner_wine(&self) -> Option<String> {
+        let runner = self.runner()?;
+        let path = runner.rsplit_once(" (")?.1.strip_suffix(')')?;
+        Some(path.to_string())
+    }
+
+    /// Names of the tweaks from this tool found in the prefix registry, e.g. `font-smoothing`.
+    pub fn applied_tweaks(&self) -> Vec<String> {
+        let user_reg = fs::read_to_string(self.path.join("user.reg")).unwrap_or_default();
+        let markers = [
+            ("no-window-decorations", "\"Decorated\"=\"N\""),
+            ("font-smoothing", "\"FontSmoothingType\"=dword:00000002"),
+            ("sandbox", "\"winemenubuilder.exe\"=\"\""),
+            ("audio", "\"HelBuflen\"="),
+            ("dpi", "\"LogPixels\"="),
+        ];
+        markers.iter()
+        .filter(|(_, marker)| user_reg.contains(marker))
+        .map(|(name, _)| name.to_string())
+        .collect()
+    }
+
+    /// Write the overrides to the prefix registry, removing any listed in `removed`.
+    pub fn apply_dll_overrides(&self, overrides: &DllOverrides, removed: &[String]) -> Result<(), String> {
+        let key = "HKCU\\Software\\Wine\\DllOverrides";
+        for (dll, mode) in overrides.entries() {
+            self.set_registry_value(key, dll, "REG_SZ", mode)?;
+        }
+        for dll in removed {
+            self.delete_registry_value(key, dll)?;
+        }
+        Ok(())
+    }
+
+    /// Add or overwrite a single registry value in the prefix with `wine reg add`.
+    /// The key is backed up first, see `backup_registry_key`.
+    pub fn set_registry_value(&self, key: &str, name: &str, kind: &str, data: &str) -> Result<(), String> {
+        self.backup_registry_key(key)?;
+        if self.reg(&["add", key, "/v", name, "/t", kind, "/d", data, "/f"])? {
+            Ok(())
+        } else {
+            Err(format!("Failed to set registry value {}\\{}.", key, name))
+        }
+    }
+
+    /// Delete a registry value from the prefix with `wine reg delete`.
+    /// The key is backed up first, see `backup_registry_key`.
+    pub fn delete_registry_value(&self, key: &str, name: &str) -> Result<(), String> {
+        self.backup_registry_key(key)?;
+        if self.reg(&["delete", key, "/v", name, "/f"])? {
+            Ok(())
+        } else {
+            Err(format!("Failed to delete registry value {}\\{}.", key, name))
+        }
+    }
+
+    /// Export a registry key with `wine reg export` into this run's backup of the prefix before
+    /// it is first changed, or note that it didn't exist. Does nothing with `registry_backups = off`.
+    pub fn backup_registry_key(&self, key: &str) -> Result<(), String> {
+        let home_dir = match dirs::home_dir() {
+            Some(home_dir) if Config::load(&home_dir).registry_backups => home_dir,
+            _ => return Ok(()),
+        };
+        let run = BACKUP_RUN.get_or_init(|| {
+            let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+            format!("{}-{}", time, std::process::id())
+        });
+        let dir = registry_backup_dir(&home_dir, &self.path).join(run);
+        let index_path = dir.join(BACKUP_INDEX);
+        let index = fs::read_to_string(&index_path).unwrap_or_default();
+        // Only the state before this run's first change is worth keeping
+        if index.lines().any(|line| line.split_once('\t').is_some_and(|(_, backed_up)| backed_up == key)) {
+            return Ok(());
+        }
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        // reg.exe writes Windows paths, and the Z: drive may be gone, so export into the prefix first
+        let temp_dir = self.path.join("drive_c/windows/temp");
+        let temp_name = format!("registry-backup-{}.reg", std::process::id());
+        fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create {}: {}", temp_dir.display(), e))?;
+        let file = if self.reg(&["export", key, &format!("C:\\windows\\temp\\{}", temp_name), "/y"])? {
+            let file = format!("{}.reg", index.lines().count());
+            let copied = fs::copy(temp_dir.join(&temp_name), dir.join(&file));
+            let _ = fs::remove_file(temp_dir.join(&temp_name));
+            copied.map_err(|e| format!("Failed to back up {}: {}", key, e))?;
+            file
+        } else {
+            "-".to_string()
+        };
+        fs::write(&index_path, format!("{}{}\t{}\n", index, file, key))
+        .map_err(|e| format!("Failed to write {}: {}", index_path.display(), e))
+    }
+
+    /// Put the keys of a registry backup back as they were: keys that didn't exist are deleted,
+    /// the others are replaced by their exported contents. Returns how many keys were restored.
+    pub fn restore_registry(&self, backup: &Path) -> Result<usize, String> {
+        let index = fs::read_to_string(backup.join(BACKUP_INDEX))
+        .map_err(|e| format!("Failed to read {}: {}", backup.display(), e))?;
+        let temp_dir = self.path.join("drive_c/windows/temp");
+        fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create {}: {}", temp_dir.display(), e))?;
+
+        let mut restored = 0;
+        for (file, key) in index.lines().filter_map(|line| line.split_once('\t')) {
+            // Fails for keys that don't exist (any more), which is fine
+            self.reg(&["delete", key, "/f"])?;
+            if file != "-" {
+                let temp_name = format!("registry-restore-{}.reg", std::process::id());
+                fs::copy(backup.join(file), temp_dir.join(&temp_name))
+                .map_err(|e| format!("Failed to read the backup of {}: {}", key, e))?;
+                let imported = self.reg(&["import", &format!("C:\\windows\\temp\\{}", temp_name)]);
+                let _ = fs::remove_file(temp_dir.join(&temp_name));
+                if !imported? {
+                    return Err(format!("Failed to restore {}.", key));
+                }
+            }
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    // Run `wine reg` with the given arguments in this prefix and report whether it succeeded
+    fn reg(&self, args: &[&str]) -> Result<bool, String> {
+        // Determine which Wine binary to use
+        let wine_bin = if Command::new("which").arg("wine64").status().map(|s| s.success()).unwrap_or(false) {
+            "wine64"
+        } else {
+            "wine"
+        };
+
+        let status = self.session(wine_bin).command("reg")
+        .args(args)
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to execute Wine registry command: {}", e))?;
+
+        Ok(status.success())
+    }
+}
+
+// Identify a wine build by its path and reported version
+fn runner_id(wine_path: &str) -> String {
+    let version = Command::new(wine_path)
+    .arg("--version")
+    .output()
+    .ok()
+    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    .filter(|version| !version.is_empty())
+    .unwrap_or_else(|| "unknown version".to_string());
+    format!("{} ({})", version, wine_path)
+}
@@ -0,0 +1,70 @@
+//! Registering launchers as handlers for their URI schemes (`battlenet://` and similar).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::launchers::LauncherInfo;
+use crate::output::*;
+use crate::wrapper::desktop_exec_quote;
+
+/// Where the URI handler `.desktop` entry for a launcher goes.
+pub fn handler_entry_path(home_dir: &Path, launcher: &LauncherInfo) -> PathBuf {
+    home_dir.join(".local/share/applications").join(format!("game-launcher-installer-{}-handler.desktop", launcher.id))
+}
+
+/// A hidden `.desktop` entry that passes the opened URI to the launcher inside its prefix.
+pub fn handler_entry(launcher: &LauncherInfo, wine_path: &str, prefix: &Path, exe: &Path) -> String {
+    let mime_types: String = launcher.uri_schemes.iter()
+    .map(|scheme| format!("x-scheme-handler/{};", scheme))
+    .collect();
+    format!(
+        "[Desktop Entry]\nType=Application\nName={} link handler\nExec=env WINEPREFIX={} {} {} %u\nMimeType={}\nNoDisplay=true\nTerminal=false\n",
+        launcher.name,
+        desktop_exec_quote(prefix),
+        desktop_exec_quote(Path::new(wine_path)),
+        desktop_exec_quote(exe),
+        mime_types
+    )
+}
+
+/// Write the handler entry and make it the default for each of the launcher's URI schemes.
+pub fn register_uri_handlers(home_dir: &Path, launcher: &LauncherInfo, wine_path: &str, prefix: &Path, exe: &Path)
+-> Result<(), String> {
+    if launcher.uri_schemes.is_empty() {
+        return Err(format!("{} does not handle any URI schemes.", launcher.name));
+    }
+
+    let entry_path = handler_entry_path(home_dir, launcher);
+    let applications_dir = entry_path.parent().expect("handler entry has a parent directory");
+    fs::create_dir_all(applications_dir)
+    .map_err(|e| format!("Failed to create {}: {}", applications_dir.display(), e))?;
+    fs::write(&entry_path, handler_entry(launcher, wine_path, prefix, exe))
+    .map_err(|e| format!("Failed to write {}: {}", entry_path.display(), e))?;
+
+    let entry_name = entry_path.file_name().unwrap_or_default();
+    for scheme in launcher.uri_schemes {
+        let status = Command::new("xdg-mime")
+        .arg("default")
+        .arg(entry_name)
+        .arg(format!("x-scheme-handler/{}", scheme))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to execute xdg-mime: {}", e))?;
+        if !status.success() {
+            return Err(format!("xdg-mime could not register the {}:// handler.", scheme));
+        }
+    }
+
+    // Not every desktop ships it; the mimeapps.list entry from xdg-mime is enough without it
+    let _ = Command::new("update-desktop-database")
+    .arg(applications_dir)
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status();
+
+    let schemes: Vec<String> = launcher.uri_schemes.iter().map(|scheme| format!("{}://", scheme)).collect();
+    println!("{}{} now opens {} links.{}", COLOR_GREEN, launcher.name, schemes.join(", "), COLOR_RESET);
+    Ok(())
+}
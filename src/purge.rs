@@ -0,0 +1,380 @@
+//! Finding and removing everything this tool created, for `purge-all`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::btrfs;
+use crate::config::{AppPaths, Config};
+use crate::launchers::LauncherRegistry;
+use crate::links::recorded_links;
+use crate::menus::{prefix_menu_files, remove_menu_files};
+use crate::protocol::handler_entry_path;
+use crate::state::LauncherState;
+use crate::steam::{self, SteamIntegration};
+use crate::systemd::{reapply_unit_name, remove_timer, update_unit_name, user_unit_dir, HEALTHCHECK_UNIT_NAME};
+use crate::wrapper::desktop_entry_path;
+
+// This tool's own directories under the home directory
+const TOOL_DIRS: &[&str] = &[
+    ".config/game-launcher-installer",
+    ".local/state/game-launcher-installer",
+    ".local/share/game-launcher-installer",
+    ".cache/game-launcher-installer",
+];
+
+/// Something this tool created. `find_artifacts` lists them in the order they are removed in.
+#[derive(Clone, Debug)]
+pub enum Artifact {
+    /// A non-Steam shortcut this tool added for a launcher
+    SteamShortcut { file: PathBuf, app_name: String, exe: String },
+    /// A systemd user timer (update, reapply or health check), with its service
+    SystemdTimer(String),
+    /// A launch script entry, a URI handler entry, or a menu entry wine made for a launcher prefix
+    DesktopFile(PathBuf),
+    /// A symlink this tool made, e.g. a drive letter or `C:\Linux Root`; its target is left alone
+    Link(PathBuf),
+    /// A btrfs snapshot of a launcher directory
+    Snapshot(PathBuf),
+    /// A launcher's directory under the Games root: prefix, cache, state, and installed files
+    /// unless they went elsewhere. An adopted prefix there is only a link and is left alone.
+    LauncherDir(PathBuf),
+    /// Installed launcher files outside the Games root, in a directory the install created
+    InstallDir(PathBuf),
+    /// A compatdata directory this tool created for a launcher's Steam shortcut, with the prefix Steam made in it
+    CompatData(PathBuf),
+    /// Config, state, logs, runners, templates, caches and the snapshots directory of the tool itself
+    ToolDir(PathBuf),
+}
+
+impl Artifact {
+    pub fn describe(&self) -> String {
+        match self {
+            Artifact::SteamShortcut { app_name, exe, .. } => format!("Steam shortcut '{}' ({})", app_name, exe),
+            Artifact::SystemdTimer(name) => format!("systemd user timer {}.timer and its service", name),
+            Artifact::DesktopFile(path) => format!("Desktop file {}", path.display()),
+            Artifact::Link(path) => format!("Link {}", path.display()),
+            Artifact::Snapshot(path) => format!("Btrfs snapshot {}", path.display()),
+            Artifact::LauncherDir(path) => format!("Launcher directory {}", path.display()),
+            Artifact::InstallDir(path) => format!("Installed files {}", path.display()),
+            Artifact::CompatData(path) => format!("Compatdata directory {}", path.display()),
+            Artifact::ToolDir(path) => format!("Tool data {}", path.display()),
+        }
+    }
+}
+
+/// Everything this tool created that still exists, from the launchers' state, the recorded
+/// links, the config and, if Steam was found, the shortcuts recorded in the state.
+pub fn find_artifacts(app_paths: &AppPaths, registry: &LauncherRegistry, steam: Option<&SteamIntegration>) -> Vec<Artifact> {
+    let config = Config::load(&app_paths.home_dir);
+    let mut artifacts = Vec::new();
+    let install_dirs = recorded_install_dirs(app_paths, registry);
+    let prefixes: Vec<PathBuf> = registry.all().iter()
+    .filter(|launcher| app_paths.launcher_dir(launcher.dir_name).exists())
+    .map(|launcher| app_paths.prefix_dir(launcher.dir_name))
+    .collect();
+
+    // Only the shortcuts this tool added; one the user made to the same launcher stays
+    if let Some(steam) = steam {
+        let added: Vec<PathBuf> = registry.all().iter()
+        .filter_map(|launcher| LauncherState::load(&app_paths.state_path(launcher.dir_name)).steam_shortcut)
+        .collect();
+        for file in steam.shortcut_files() {
+            for shortcut in steam::read_shortcuts(&file).unwrap_or_default() {
+                if added.iter().any(|exe| Path::new(&shortcut.exe) == exe) {
+                    artifacts.push(Artifact::SteamShortcut { file: file.clone(), app_name: shortcut.app_name, exe: shortcut.exe });
+                }
+            }
+        }
+    }
+
+    let unit_dir = user_unit_dir(&app_paths.home_dir);
+    let mut timers: Vec<String> = registry.all().iter()
+    .flat_map(|launcher| [update_unit_name(launcher), reapply_unit_name(launcher)])
+    .collect();
+    timers.push(HEALTHCHECK_UNIT_NAME.to_string());
+    artifacts.extend(timers.into_iter()
+    .filter(|name| unit_dir.join(format!("{}.timer", name)).exists())
+    .map(Artifact::SystemdTimer));
+
+    let mut desktop_files: Vec<PathBuf> = registry.all().iter()
+    .flat_map(|launcher| [desktop_entry_path(&app_paths.home_dir, launcher), handler_entry_path(&app_paths.home_dir, launcher)])
+    .filter(|path| path.exists())
+    .collect();
+    desktop_files.extend(prefix_menu_files(&app_paths.home_dir, &prefixes));
+    artifacts.extend(desktop_files.into_iter().map(Artifact::DesktopFile));
+
+    // Links inside the launcher directories go with them
+    artifacts.extend(recorded_links(&app_paths.home_dir).into_iter()
+    .filter(|(link, target)| fs::read_link(link).is_ok_and(|existing| existing == *target))
+    .filter(|(link, _)| !link.starts_with(&app_paths.games_root))
+    .map(|(link, _)| Artifact::Link(link)));
+
+    for launcher in registry.all() {
+        if let Ok(entries) = fs::read_dir(app_paths.snapshots_dir(launcher.dir_name)) {
+            let mut snapshots: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| btrfs::is_subvolume(path)).collect();
+            snapshots.sort();
+            artifacts.extend(snapshots.into_iter().map(Artifact::Snapshot));
+        }
+    }
+    artifacts.extend(registry.all().iter()
+    .map(|launcher| app_paths.launcher_dir(launcher.dir_name))
+    .filter(|dir| fs::symlink_metadata(dir).is_ok())
+    .map(Artifact::LauncherDir));
+    artifacts.extend(install_dirs.into_iter().filter(|(_, created)| *created).map(|(dir, _)| Artifact::InstallDir(dir)));
+    artifacts.extend(compat_data_dirs(app_paths, registry, &config).into_iter().filter(|(_, created)| *created).map(|(dir, _)| Artifact::CompatData(dir)));
+
+    artifacts.extend(TOOL_DIRS.iter()
+    .map(|dir| app_paths.home_dir.join(dir))
+    .chain([app_paths.games_root.join(".snapshots")])
+    .filter(|dir| dir.exists())
+    .map(Artifact::ToolDir));
+    artifacts
+}
+
+/// Installed launcher files in directories that existed before the install, e.g. one the user
+/// picked; `purge-all` lists them but leaves them alone.
+pub fn kept_install_dirs(app_paths: &AppPaths, registry: &LauncherRegistry) -> Vec<PathBuf> {
+    recorded_install_dirs(app_paths, registry).into_iter()
+    .filter(|(_, created)| !created)
+    .map(|(dir, _)| dir)
+    .collect()
+}
+
+/// Compatdata directories set for shortcuts that existed before `compatdata` or the install
+/// pointed a launcher there; `purge-all` lists them but leaves them alone.
+pub fn kept_compat_data_dirs(app_paths: &AppPaths, registry: &LauncherRegistry) -> Vec<PathBuf> {
+    compat_data_dirs(app_paths, registry, &Config::load(&app_paths.home_dir)).into_iter()
+    .filter(|(_, created)| !created)
+    .map(|(dir, _)| dir)
+    .collect()
+}
+
+// Existing compatdata directories of the config, with whether this tool created them
+fn compat_data_dirs(app_paths: &AppPaths, registry: &LauncherRegistry, config: &Config) -> Vec<(PathBuf, bool)> {
+    config.compat_data_paths.iter()
+    .filter(|(_, dir)| dir.exists())
+    .map(|(id, dir)| {
+        let created = registry.get(id)
+        .and_then(|launcher| LauncherState::load(&app_paths.state_path(launcher.dir_name)).compat_data_created)
+        .is_some_and(|created| created == *dir);
+        (dir.clone(), created)
+    })
+    .collect()
+}
+
+// Existing install directories outside the launcher directories, with whether the install created them
+fn recorded_install_dirs(app_paths: &AppPaths, registry: &LauncherRegistry) -> Vec<(PathBuf, bool)> {
+    registry.all().iter()
+    .filter_map(|launcher| {
+        let state = LauncherState::load(&app_paths.state_path(launcher.dir_name));
+        let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+        state.install_dir
+        .filter(|dir| dir.exists() && !dir.starts_with(&launcher_dir))
+        .map(|dir| (dir, state.install_dir_created))
+    })
+    .collect()
+}
+
+/// Remove the given artifacts in order, going on past failures. Returns how many were removed
+/// and the reason for each one that wasn't. Steam shortcuts are skipped while Steam is running,
+/// since it would write them back on exit.
+pub fn remove_artifacts(home_dir: &Path, artifacts: &[Artifact]) -> (usize, Vec<String>) {
+    let mut removed = 0;
+    let mut failures = Vec::new();
+    let mut shortcuts: HashMap<&Path, Vec<&str>> = HashMap::new();
+    let mut menu_files = Vec::new();
+
+    for artifact in artifacts {
+        let result = match artifact {
+            Artifact::SteamShortcut { file, exe, .. } => {
+                shortcuts.entry(file.as_path()).or_default().push(exe.as_str());
+                continue;
+            },
+            Artifact::SystemdTimer(name) => remove_timer(home_dir, name),
+            Artifact::DesktopFile(path) => {
+                menu_files.push(path.clone());
+                continue;
+            },
+            Artifact::Link(path) => fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e)),
+            Artifact::Snapshot(path) => btrfs::delete_subvolume(path),
+            Artifact::LauncherDir(path) | Artifact::InstallDir(path) | Artifact::CompatData(path) | Artifact::ToolDir(path) => remove_tree(path),
+        };
+        match result {
+            Ok(()) => removed += 1,
+            Err(e) => failures.push(e),
+        }
+    }
+
+    match remove_menu_files(home_dir, &menu_files) {
+        Ok(count) => removed += count,
+        Err(e) => failures.push(e),
+    }
+
+    if !shortcuts.is_empty() && steam::steam_running() {
+        failures.push("Steam is running, so its shortcuts were left alone. Close Steam and run purge-all again to remove them.".to_string());
+    } else {
+        for (file, exes) in shortcuts {
+            match steam::remove_shortcuts(file, |shortcut| exes.contains(&shortcut.exe.as_str())) {
+                Ok(count) => removed += count,
+                Err(e) => failures.push(e),
+            }
+        }
+    }
+
+    (removed, failures)
+}
+
+// Remove a directory, a btrfs subvolume or a symlink; a symlink's target is left alone
+fn remove_tree(path: &Path) -> Result<(), String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if metadata.file_type().is_symlink() || metadata.is_file() {
+        return fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e));
+    }
+    if btrfs::is_subvolume(path) && btrfs::delete_subvolume(path).is_ok() {
+        return Ok(());
+    }
+    fs::remove_dir_all(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_home(name: &str) -> AppPaths {
+        let home_dir = std::env::temp_dir().join(format!("gli-purge-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&home_dir);
+        fs::create_dir_all(&home_dir).unwrap();
+        AppPaths { games_root: home_dir.join("Games"), home_dir }
+    }
+
+    fn install(app_paths: &AppPaths, dir_name: &str, install_dir: &Path, created: bool) {
+        fs::create_dir_all(install_dir).unwrap();
+        let state = LauncherState { install_dir: Some(install_dir.to_path_buf()), install_dir_created: created, ..LauncherState::default() };
+        state.save(&app_paths.state_path(dir_name)).unwrap();
+    }
+
+    #[test]
+    fn only_created_install_dirs_are_removed() {
+        let app_paths = temp_home("install-dirs");
+        let registry = LauncherRegistry::builtin();
+        let created = app_paths.home_dir.join("Launchers/Battle.net");
+        let chosen = app_paths.home_dir.join("Games on SSD");
+        let inside = app_paths.launcher_dir("GOG Galaxy").join("files");
+        install(&app_paths, "Battle.net", &created, true);
+        install(&app_paths, "EA app", &chosen, false);
+        install(&app_paths, "GOG Galaxy", &inside, true);
+
+        let artifacts = find_artifacts(&app_paths, &registry, None);
+        let install_dirs: Vec<&PathBuf> = artifacts.iter()
+        .filter_map(|artifact| match artifact { Artifact::InstallDir(dir) => Some(dir), _ => None })
+        .collect();
+        assert_eq!(install_dirs, vec![&created]);
+        assert_eq!(kept_install_dirs(&app_paths, &registry), vec![chosen]);
+
+        // The launcher directories go as a whole, in registry order
+        let launcher_dirs: Vec<&PathBuf> = artifacts.iter()
+        .filter_map(|artifact| match artifact { Artifact::LauncherDir(dir) => Some(dir), _ => None })
+        .collect();
+        assert_eq!(launcher_dirs, vec![
+            &app_paths.launcher_dir("Battle.net"),
+            &app_paths.launcher_dir("EA app"),
+            &app_paths.launcher_dir("GOG Galaxy"),
+        ]);
+        fs::remove_dir_all(&app_paths.home_dir).unwrap();
+    }
+
+    #[test]
+    fn missing_install_dirs_are_skipped() {
+        let app_paths = temp_home("missing");
+        let registry = LauncherRegistry::builtin();
+        let gone = app_paths.home_dir.join("Launchers/Battle.net");
+        install(&app_paths, "Battle.net", &gone, true);
+        fs::remove_dir(&gone).unwrap();
+
+        let artifacts = find_artifacts(&app_paths, &registry, None);
+        assert!(!artifacts.iter().any(|artifact| matches!(artifact, Artifact::InstallDir(_))));
+        assert!(kept_install_dirs(&app_paths, &registry).is_empty());
+        fs::remove_dir_all(&app_paths.home_dir).unwrap();
+    }
+
+    #[test]
+    fn only_recorded_shortcuts_are_removed() {
+        let app_paths = temp_home("shortcuts");
+        let registry = LauncherRegistry::builtin();
+        let steam_root = app_paths.home_dir.join(".steam/steam");
+        let shortcuts = steam_root.join("userdata/1234/config/shortcuts.vdf");
+        fs::create_dir_all(shortcuts.parent().unwrap()).unwrap();
+        let added = app_paths.drive_dir("Battle.net").join("Battle.net Launcher.exe");
+        let users = app_paths.games_root.join("Some Game/game.exe");
+        steam::add_shortcut(&shortcuts, "Battle.net", &added, "", &[]).unwrap();
+        steam::add_shortcut(&shortcuts, "Some Game", &users, "", &[]).unwrap();
+        let state = LauncherState { steam_shortcut: Some(added.clone()), ..LauncherState::default() };
+        state.save(&app_paths.state_path("Battle.net")).unwrap();
+
+        let steam = SteamIntegration::from_libraries(vec![steam_root]);
+        let artifacts = find_artifacts(&app_paths, &registry, Some(&steam));
+        let exes: Vec<&str> = artifacts.iter()
+        .filter_map(|artifact| match artifact { Artifact::SteamShortcut { exe, .. } => Some(exe.as_str()), _ => None })
+        .collect();
+        assert_eq!(exes, vec![added.to_string_lossy()]);
+        fs::remove_dir_all(&app_paths.home_dir).unwrap();
+    }
+
+    #[test]
+    fn only_created_compat_data_dirs_are_removed() {
+        let app_paths = temp_home("compatdata");
+        let registry = LauncherRegistry::builtin();
+        let created = app_paths.home_dir.join("compat/battlenet");
+        let existing = app_paths.home_dir.join("compat/ea-app");
+        fs::create_dir_all(&created).unwrap();
+        fs::create_dir_all(&existing).unwrap();
+        let mut config = Config::load(&app_paths.home_dir);
+        config.set_compat_data_path("battlenet", Some(&created));
+        config.set_compat_data_path("ea-app", Some(&existing));
+        config.save(&app_paths.home_dir).unwrap();
+        let state = LauncherState { compat_data_created: Some(created.clone()), ..LauncherState::default() };
+        state.save(&app_paths.state_path("Battle.net")).unwrap();
+
+        let artifacts = find_artifacts(&app_paths, &registry, None);
+        let compat_data: Vec<&PathBuf> = artifacts.iter()
+        .filter_map(|artifact| match artifact { Artifact::CompatData(dir) => Some(dir), _ => None })
+        .collect();
+        assert_eq!(compat_data, vec![&created]);
+        assert_eq!(kept_compat_data_dirs(&app_paths, &registry), vec![existing]);
+        fs::remove_dir_all(&app_paths.home_dir).unwrap();
+    }
+
+    #[test]
+    fn tool_dirs_are_listed_last() {
+        let app_paths = temp_home("tool-dirs");
+        let registry = LauncherRegistry::builtin();
+        fs::create_dir_all(app_paths.home_dir.join(".cache/game-launcher-installer")).unwrap();
+        fs::create_dir_all(app_paths.games_root.join(".snapshots")).unwrap();
+        install(&app_paths, "Battle.net", &app_paths.home_dir.join("Launchers/Battle.net"), true);
+
+        let artifacts = find_artifacts(&app_paths, &registry, None);
+        let described: Vec<String> = artifacts.iter().map(Artifact::describe).collect();
+        assert_eq!(described[described.len() - 2..], [
+            format!("Tool data {}", app_paths.home_dir.join(".cache/game-launcher-installer").display()),
+            format!("Tool data {}", app_paths.games_root.join(".snapshots").display()),
+        ]);
+        fs::remove_dir_all(&app_paths.home_dir).unwrap();
+    }
+
+    #[test]
+    fn remove_tree_leaves_link_targets() {
+        let app_paths = temp_home("remove-tree");
+        let target = app_paths.home_dir.join("target");
+        let link = app_paths.home_dir.join("link");
+        fs::create_dir_all(target.join("inner")).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        remove_tree(&link).unwrap();
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(target.join("inner").is_dir());
+        remove_tree(&target).unwrap();
+        assert!(!target.exists());
+        fs::remove_dir_all(&app_paths.home_dir).unwrap();
+    }
+}
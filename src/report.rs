@@ -0,0 +1,212 @@
+//! Material for bug reports: the system the tool runs on, wine debug logs kept to a size that
+//! can be attached to an issue, a markdown summary to paste into one, and a `.tar.gz` bundling them.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::{Captures, Regex};
+
+use crate::journal::{journal_path, last_run};
+
+/// Debug channels turned on when a failing installer is run again with `--debug-wine`:
+/// exceptions, DLL loading, MSI and every error. `WINEDEBUG` from the environment replaces them.
+pub const DEBUG_CHANNELS: &str = "+seh,+loaddll,+msi,err+all,fixme-all";
+
+/// Largest debug log kept; longer logs keep their beginning and end.
+pub const DEBUG_LOG_LIMIT: u64 = 8 * 1024 * 1024;
+
+/// Where report archives are written.
+pub fn report_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join(".local/state/game-launcher-installer/reports")
+}
+
+/// The distribution, kernel and wine version, as name and value.
+pub fn system_info(wine_path: Option<&str>) -> Vec<(String, String)> {
+    let mut info = Vec::new();
+    let os_release = fs::read_to_string("/etc/os-release").unwrap_or_default();
+    let distro = os_release.lines()
+    .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+    .map(|name| name.trim_matches('"').to_string())
+    .unwrap_or_else(|| "unknown".to_string());
+    info.push(("Distribution".to_string(), distro));
+    let kernel = fs::read_to_string("/proc/sys/kernel/osrelease").map(|kernel| kernel.trim().to_string()).unwrap_or_else(|_| "unknown".to_string());
+    info.push(("Kernel".to_string(), kernel));
+    info.push(("Architecture".to_string(), std::env::consts::ARCH.to_string()));
+    if let Some(wine_path) = wine_path {
+        let version = Command::new(wine_path)
+        .arg("--version")
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+        info.push(("Wine".to_string(), format!("{} ({})", version, wine_path)));
+    }
+    info.push(("Tool".to_string(), format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))));
+    info
+}
+
+/// Cut a log longer than `limit` down to its first and last `limit / 2` bytes, with a line
+/// saying how much was left out in between. Returns whether the log was cut.
+pub fn bound_log(path: &Path, limit: u64) -> Result<bool, String> {
+    let len = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+    if len <= limit {
+        return Ok(false);
+    }
+    let half = limit / 2;
+    let mut file = File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut head = vec![0; half as usize];
+    let mut tail = vec![0; half as usize];
+    file.read_exact(&mut head)
+    .and_then(|_| file.seek(SeekFrom::End(-(half as i64))))
+    .and_then(|_| file.read_exact(&mut tail))
+    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut file = File::create(path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    file.write_all(&head)
+    .and_then(|_| write!(file, "\n[... {} bytes left out ...]\n", len - 2 * half))
+    .and_then(|_| file.write_all(&tail))
+    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+/// Bundle `info` (as `system-info.txt`), the journal of the current run and `files` into
+/// `<report_dir>/<name>-<time>.tar.gz`, returning its path.
+pub fn create_report(home_dir: &Path, name: &str, info: &[(String, String)], files: &[PathBuf]) -> Result<PathBuf, String> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let dir = report_dir(home_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let output = dir.join(format!("{}-{}.tar.gz", name, time));
+
+    // Staged in its own directory so tar stores every file at the archive root
+    let staging = std::env::temp_dir().join(format!("game-launcher-report-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    let result = (|| {
+        fs::create_dir_all(&staging).map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+        let info_text: String = info.iter().map(|(key, value)| format!("{}: {}\n", key, value)).collect();
+        fs::write(staging.join("system-info.txt"), info_text)
+        .map_err(|e| format!("Failed to write system info: {}", e))?;
+
+        if journal_path(home_dir).exists() {
+            let records: String = last_run(home_dir).iter()
+            .map(|record| format!("{}\t{}\t{}\n", record.time, record.event, record.describe()))
+            .collect();
+            fs::write(staging.join("journal.log"), records)
+            .map_err(|e| format!("Failed to write the journal: {}", e))?;
+        }
+        for file in files {
+            let file_name = file.file_name().ok_or_else(|| format!("{} is not a file", file.display()))?;
+            fs::copy(file, staging.join(file_name))
+            .map_err(|e| format!("Failed to copy {}: {}", file.display(), e))?;
+        }
+
+        let status = Command::new("tar")
+        .arg("-czf").arg(&output)
+        .arg("-C").arg(&staging).arg(".")
+        .status()
+        .map_err(|e| format!("Failed to execute tar: {}", e))?;
+        if !status.success() {
+            let _ = fs::remove_file(&output);
+            return Err(format!("Failed to create report {}", output.display()));
+        }
+        Ok(output.clone())
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// The GPUs the kernel drives, as `vendor:device name (driver version)`, and the OpenGL
+/// renderer and version `glxinfo` reports when it is installed.
+pub fn gpu_info() -> Vec<(String, String)> {
+    let mut info = Vec::new();
+    let mut cards: Vec<PathBuf> = fs::read_dir("/sys/class/drm").into_iter().flatten().flatten()
+    .map(|entry| entry.path())
+    // card0-DP-1 and the like are the card's connectors
+    .filter(|path| path.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        name.starts_with("card") && !name.contains('-')
+    }))
+    .collect();
+    cards.sort();
+
+    for card in cards {
+        let device = card.join("device");
+        let read = |name: &str| fs::read_to_string(device.join(name)).map(|value| value.trim().trim_start_matches("0x").to_string()).unwrap_or_default();
+        let ids = format!("{}:{}", read("vendor"), read("device"));
+        // lspci names the device from its PCI address, e.g. `0000:01:00.0`
+        let name = fs::canonicalize(&device).ok()
+        .and_then(|path| path.file_name().map(|slot| slot.to_string_lossy().to_string()))
+        .and_then(|slot| Command::new("lspci").arg("-s").arg(&slot).output().ok())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).lines().next().and_then(|line| line.split_once(": ").map(|(_, name)| name.to_string())))
+        .unwrap_or(ids);
+        let driver = fs::read_link(device.join("driver")).ok()
+        .and_then(|driver| driver.file_name().map(|driver| driver.to_string_lossy().to_string()));
+        let description = match driver {
+            Some(driver) => {
+                let version = if driver == "nvidia" {
+                    fs::read_to_string("/proc/driver/nvidia/version").ok()
+                    .and_then(|version| version.lines().next().map(str::to_string))
+                } else {
+                    fs::read_to_string(format!("/sys/module/{}/version", driver)).ok()
+                }
+                .map(|version| version.trim().to_string())
+                .filter(|version| !version.is_empty());
+                match version {
+                    Some(version) => format!("{} ({} driver, {})", name, driver, version),
+                    None => format!("{} ({} driver)", name, driver),
+                }
+            },
+            None => format!("{} (no driver)", name),
+        };
+        info.push(("GPU".to_string(), description));
+    }
+    if info.is_empty() {
+        info.push(("GPU".to_string(), "none found".to_string()));
+    }
+
+    if let Ok(output) = Command::new("glxinfo").arg("-B").output() {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(renderer) = line.trim().strip_prefix("OpenGL renderer string:") {
+                info.push(("OpenGL renderer".to_string(), renderer.trim().to_string()));
+            } else if let Some(version) = line.trim().strip_prefix("OpenGL core profile version string:") {
+                info.push(("OpenGL version".to_string(), version.trim().to_string()));
+            }
+        }
+    }
+    info
+}
+
+/// Config file or log text with the home directory shortened to `~` and the credentials and
+/// query strings of URLs (which can carry access tokens) replaced by `<redacted>`.
+pub fn redact(text: &str, home_dir: &Path) -> String {
+    let home = home_dir.to_string_lossy();
+    let text = if home.len() > 1 { text.replace(home.as_ref(), "~") } else { text.to_string() };
+    let url = Regex::new(r"(https?://)([^/\s@]+@)?([^\s?#]*)(\?[^\s#]*)?").unwrap();
+    url.replace_all(&text, |captures: &Captures| {
+        format!(
+            "{}{}{}{}",
+            &captures[1],
+            if captures.get(2).is_some() { "<redacted>@" } else { "" },
+            &captures[3],
+            if captures.get(4).is_some() { "?<redacted>" } else { "" }
+        )
+    })
+    .to_string()
+}
+
+/// A GitHub issue-ready markdown summary: `info` as a table, then each `(title, text)`
+/// section in a collapsed code block.
+pub fn markdown_report(info: &[(String, String)], sections: &[(String, String)]) -> String {
+    let mut markdown = String::from("## System\n\n| | |\n|---|---|\n");
+    for (key, value) in info {
+        markdown.push_str(&format!("| {} | {} |\n", key, value.replace('|', "\\|")));
+    }
+    for (title, text) in sections {
+        let text = if text.trim().is_empty() { "(empty)" } else { text.trim_end() };
+        markdown.push_str(&format!("\n<details><summary>{}</summary>\n\n```\n{}\n```\n\n</details>\n", title, text));
+    }
+    markdown
+}
@@ -0,0 +1,179 @@
+//! Finding launchers already installed in prefixes made by other tools (Lutris, Bottles,
+//! plain `~/.wine`, Steam) and adopting them.
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::config::AppPaths;
+use crate::launchers::{LauncherInfo, LauncherRegistry};
+use crate::links::record_link;
+use crate::pe::PeInfo;
+use crate::prefix::PrefixManager;
+use crate::state::LauncherState;
+use crate::steam::SteamIntegration;
+
+/// A launcher found in a prefix this tool did not create.
+#[derive(Clone, Debug)]
+pub struct FoundInstall {
+    pub launcher: LauncherInfo,
+    /// Tool the prefix belongs to, e.g. `Lutris`
+    pub source: &'static str,
+    pub prefix: PathBuf,
+    /// Directory holding the launcher's executable
+    pub install_dir: PathBuf,
+}
+
+/// Prefixes of other tools that may hold launchers, with the tool they belong to.
+pub fn candidate_prefixes(home_dir: &Path, steam: Option<&SteamIntegration>) -> Vec<(&'static str, PathBuf)> {
+    let mut prefixes = vec![("wine", home_dir.join(".wine"))];
+
+    // Lutris keeps each game's prefix in its YAML config; ~/Games is its default location
+    let prefix_line = Regex::new(r#"(?m)^\s*prefix:\s*['"]?([^'"\n]+?)['"]?\s*$"#).unwrap();
+    for config_dir in [home_dir.join(".config/lutris/games"), home_dir.join(".local/share/lutris/games")] {
+        for entry in read_dir_paths(&config_dir) {
+            let content = fs::read_to_string(&entry).unwrap_or_default();
+            for captures in prefix_line.captures_iter(&content) {
+                let prefix = match captures[1].strip_prefix("~/") {
+                    Some(relative) => home_dir.join(relative),
+                    None => PathBuf::from(&captures[1]),
+                };
+                prefixes.push(("Lutris", prefix));
+            }
+        }
+    }
+    prefixes.extend(read_dir_paths(&home_dir.join("Games")).into_iter().map(|path| ("Lutris", path)));
+
+    for bottles in [
+        home_dir.join(".local/share/bottles/bottles"),
+        home_dir.join(".var/app/com.usebottles.bottles/data/bottles/bottles"),
+    ] {
+        prefixes.extend(read_dir_paths(&bottles).into_iter().map(|path| ("Bottles", path)));
+    }
+
+    if let Some(steam) = steam {
+        for library in steam.libraries() {
+            prefixes.extend(read_dir_paths(&library.join("steamapps/compatdata")).into_iter().map(|path| ("Steam", path.join("pfx"))));
+        }
+    }
+
+    let mut seen = Vec::new();
+    prefixes.retain(|(_, prefix)| {
+        let is_prefix = prefix.join("system.reg").is_file() && prefix.join("drive_c").is_dir();
+        let key = fs::canonicalize(prefix).unwrap_or_else(|_| prefix.clone());
+        if !is_prefix || seen.contains(&key) {
+            return false;
+        }
+        seen.push(key);
+        true
+    });
+    prefixes
+}
+
+/// Look for known launchers in the prefixes of other tools. Prefixes under the Games root
+/// belong to this tool already and are skipped.
+pub fn scan(app_paths: &AppPaths, registry: &LauncherRegistry, steam: Option<&SteamIntegration>) -> Vec<FoundInstall> {
+    let games_root = fs::canonicalize(&app_paths.games_root).unwrap_or_else(|_| app_paths.games_root.clone());
+    let mut found = Vec::new();
+
+    for (source, prefix) in candidate_prefixes(&app_paths.home_dir, steam) {
+        if fs::canonicalize(&prefix).is_ok_and(|prefix| prefix.starts_with(&games_root)) {
+            continue;
+        }
+        for launcher in registry.all() {
+            let install_dir = launcher.install_dir_candidates(&prefix).into_iter()
+            .find(|dir| dir.join(launcher.exe_name).is_file());
+            if let Some(install_dir) = install_dir {
+                found.push(FoundInstall { launcher: launcher.clone(), source, prefix: prefix.clone(), install_dir });
+            }
+        }
+    }
+    found
+}
+
+/// Make a found install the launcher's prefix: `<root>/<Launcher>/prefix` becomes a link to it,
+/// so `update`, post-setup and the other commands manage it in place, and the other tool keeps working.
+pub fn adopt(app_paths: &AppPaths, install: &FoundInstall) -> Result<(), String> {
+    adopt_prefix(app_paths, &install.launcher, &install.prefix)
+}
+
+/// Make another tool's prefix the launcher's prefix, as `adopt` does, and record where it came
+/// from. Adopting the prefix the launcher already links to again is allowed; a prefix of the
+/// wrong architecture is refused.
+pub fn adopt_prefix(app_paths: &AppPaths, launcher: &LauncherInfo, prefix: &Path) -> Result<(), String> {
+    let prefix_dir = app_paths.prefix_dir(launcher.dir_name);
+    if fs::read_link(&prefix_dir).is_ok_and(|target| target == prefix) {
+        return Ok(());
+    }
+    if fs::symlink_metadata(&prefix_dir).is_ok() {
+        return Err(format!("{} already has a prefix at {}.", launcher.name, prefix_dir.display()));
+    }
+    if let Some(arch) = PrefixManager::new(prefix).arch().filter(|arch| *arch != launcher.arch) {
+        return Err(format!("{} is a {} ({}) prefix, but {} needs a {} one. Install it into a fresh prefix instead.",
+                           prefix.display(), arch.describe(), arch.name(), launcher.name, launcher.arch.describe()));
+    }
+
+    let launcher_dir = app_paths.launcher_dir(launcher.dir_name);
+    fs::create_dir_all(&launcher_dir)
+    .map_err(|e| format!("Failed to create {}: {}", launcher_dir.display(), e))?;
+    symlink(prefix, &prefix_dir)
+    .map_err(|e| format!("Failed to link {} to {}: {}", prefix_dir.display(), prefix.display(), e))?;
+    record_link(&app_paths.home_dir, &prefix_dir, prefix)?;
+
+    let state_path = app_paths.state_path(launcher.dir_name);
+    let mut state = LauncherState::load(&state_path);
+    state.adopted_from = Some(prefix.to_path_buf());
+    state.save(&state_path)
+}
+
+/// Executables of known launchers anywhere in a prefix's drive_c, for picking the one a
+/// shortcut or launch script should start. Executables in one of their launcher's usual install
+/// directories come first, since names like `launcher.exe` are also used by other programs.
+pub fn find_launcher_exes(prefix: &Path, registry: &LauncherRegistry) -> Vec<(LauncherInfo, PathBuf)> {
+    let mut exes = Vec::new();
+    collect_exes(&prefix.join("drive_c"), registry, &mut exes);
+    exes.sort_by_key(|(launcher, exe)| {
+        let in_install_dir = exe.ancestors().any(|dir| launcher.is_install_dir(dir));
+        (!in_install_dir, exe.clone())
+    });
+    exes
+}
+
+/// Version of the installed launcher client, from the version resource of its executable in
+/// `install_dir` or, failing that, anywhere in the prefix.
+pub fn client_version(launcher: &LauncherInfo, install_dir: &Path, prefix: &Path) -> Option<String> {
+    let registry = LauncherRegistry::builtin();
+    let installed = find_launcher_exes(prefix, &registry).into_iter()
+    .filter(|(found, _)| found.id == launcher.id)
+    .map(|(_, exe)| exe);
+    std::iter::once(install_dir.join(launcher.exe_name)).chain(installed)
+    .filter(|exe| exe.is_file())
+    .find_map(|exe| PeInfo::inspect(&exe).ok()?.version)
+}
+
+// Symlinks are not followed, since the prefix links out to the host filesystem
+fn collect_exes(dir: &Path, registry: &LauncherRegistry, exes: &mut Vec<(LauncherInfo, PathBuf)>) {
+    for path in read_dir_paths(dir) {
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+        if metadata.is_dir() {
+            // Wine's own programs
+            if name != "windows" {
+                collect_exes(&path, registry, exes);
+            }
+        } else if let Some(launcher) = registry.all().iter().find(|launcher| launcher.exe_name.to_lowercase() == name) {
+            exes.push((launcher.clone(), path));
+        }
+    }
+}
+
+fn read_dir_paths(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+    .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+    .unwrap_or_default()
+}
@@ -0,0 +1,278 @@
+//! One wineserver per prefix: starting it with the runner's own wineserver, waiting for it
+//! and shutting it down without touching the servers of other prefixes.
+
+use std::cell::Cell;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::wine::{find_in_path, wine_kind, WineKind};
+
+// How long a wineserver gets to exit after `wineserver -k`
+const SERVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The wineserver of one prefix, driven with the wineserver that belongs to the runner.
+///
+/// A session claims its prefix with a `<prefix>.lock` file while it is started, so a second
+/// process working on the same prefix is refused instead of racing it. Sessions on other
+/// prefixes are independent: every command is scoped with `WINEPREFIX`, and waiting and
+/// shutting down only look at this prefix's server.
+pub struct WineSession {
+    wine_path: String,
+    prefix: PathBuf,
+    wineserver: PathBuf,
+    // Whether this session created the lock file and has to remove it again
+    owns_lock: Cell<bool>,
+}
+
+impl WineSession {
+    pub fn new(wine_path: &str, prefix: impl Into<PathBuf>) -> WineSession {
+        WineSession {
+            wine_path: wine_path.to_string(),
+            prefix: prefix.into(),
+            wineserver: wineserver_for(wine_path),
+            owns_lock: Cell::new(false),
+        }
+    }
+
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    pub fn wine_path(&self) -> &str {
+        &self.wine_path
+    }
+
+    /// Command running a program in the prefix, with wine debug output off. The program's
+    /// wine connects to this session's server, or starts it with the runner's wineserver.
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(&self.wine_path);
+        for (name, value) in self.environment() {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+        command.arg(program);
+        command
+    }
+
+    /// The variables `command` sets, or removes where the value is None.
+    pub fn environment(&self) -> Vec<(&'static str, Option<OsString>)> {
+        let mut environment = vec![
+            ("WINEPREFIX", Some(self.prefix.clone().into_os_string())),
+            ("WINEDEBUG", Some("-all".into())),
+            // An exported WINEARCH=win32 would make a new prefix 32-bit, which no launcher runs in,
+            // and makes wine refuse an existing 64-bit one
+            ("WINEARCH", None),
+        ];
+        if self.wineserver.is_absolute() {
+            environment.push(("WINESERVER", Some(self.wineserver.clone().into_os_string())));
+        }
+        environment
+    }
+
+    /// Claim the prefix for this process. Fails if another running process holds it;
+    /// claiming it again from the same process is allowed.
+    pub fn claim(&self) -> Result<(), String> {
+        if self.owns_lock.get() {
+            return Ok(());
+        }
+        let lock_path = self.lock_path();
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    self.owns_lock.set(true);
+                    return Ok(());
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let holder = fs::read_to_string(&lock_path).ok().and_then(|pid| pid.trim().parse::<u32>().ok());
+                    match holder {
+                        Some(pid) if pid == std::process::id() => return Ok(()),
+                        Some(pid) if Path::new(&format!("/proc/{}", pid)).exists() => {
+                            return Err(format!("{} is in use by another process of this tool (PID {}).", self.prefix.display(), pid));
+                        },
+                        // Left behind by a process that is gone
+                        _ => {
+                            let _ = fs::remove_file(&lock_path);
+                        },
+                    }
+                },
+                Err(e) => return Err(format!("Failed to lock {}: {}", self.prefix.display(), e)),
+            }
+        }
+    }
+
+    /// Claim the prefix and start its wineserver if it isn't running yet.
+    pub fn start(&self) -> Result<(), String> {
+        self.claim()?;
+        // Without a wineserver of its own (e.g. Proton through umu-run) wine starts the one it ships
+        if self.server_pid().is_some() || !self.wineserver.is_absolute() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.prefix)
+        .map_err(|e| format!("Failed to create prefix directory: {}", e))?;
+
+        // wineserver detaches once it is ready and exits a few seconds after its last client
+        let status = Command::new(&self.wineserver)
+        .env("WINEPREFIX", &self.prefix)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to execute {}: {}", self.wineserver.display(), e))?;
+        if !status.success() {
+            return Err(format!("wineserver failed to start for {} ({}).", self.prefix.display(), status));
+        }
+        Ok(())
+    }
+
+    /// The PID of the prefix's wineserver, if one is running.
+    pub fn server_pid(&self) -> Option<u32> {
+        let server_dir = server_dir(&self.prefix)?;
+        fs::read_dir("/proc").ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        // wineserver runs in its server directory, and nothing else does
+        .find(|pid| fs::read_link(format!("/proc/{}/cwd", pid)).is_ok_and(|cwd| cwd == server_dir))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.server_pid().is_some()
+    }
+
+    /// Wait until every program in the prefix has exited and the wineserver with them
+    /// (`wineserver -w`). Without a timeout this waits as long as the programs run.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<(), String> {
+        if !self.is_running() {
+            return Ok(());
+        }
+        // Without a wineserver binary to ask, watch for the server process to go away
+        let mut child = Command::new(&self.wineserver)
+        .env("WINEPREFIX", &self.prefix)
+        .arg("-w")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok();
+
+        let started = Instant::now();
+        loop {
+            let done = match &mut child {
+                Some(child) => !matches!(child.try_wait(), Ok(None)),
+                None => !self.is_running(),
+            };
+            if done {
+                return Ok(());
+            }
+            if timeout.is_some_and(|timeout| started.elapsed() > timeout) {
+                if let Some(child) = &mut child {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                return Err(format!("Timed out waiting for the wineserver of {}.", self.prefix.display()));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Stop every program in the prefix with `wineserver -k`, wait for the server to exit
+    /// and release the prefix.
+    pub fn shutdown(&self) {
+        if let Some(pid) = self.server_pid() {
+            let _ = Command::new(&self.wineserver)
+            .env("WINEPREFIX", &self.prefix)
+            .arg("-k")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+            let stopping = Instant::now();
+            while Path::new(&format!("/proc/{}", pid)).exists() && stopping.elapsed() < SERVER_TIMEOUT {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+        self.release();
+    }
+
+    fn release(&self) {
+        if self.owns_lock.replace(false) {
+            let _ = fs::remove_file(self.lock_path());
+        }
+    }
+
+    // Next to the prefix rather than inside it, so the prefix can still be replaced or removed
+    fn lock_path(&self) -> PathBuf {
+        let name = self.prefix.file_name().unwrap_or_default().to_string_lossy();
+        self.prefix.with_file_name(format!("{}.lock", name))
+    }
+}
+
+impl Drop for WineSession {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// The wineserver of a wine build: the one next to its `wine` (after following symlinks),
+/// else the one on PATH. A wrapper script without one next to it gets the bare name, so wine
+/// starts the server it ships instead of a host one of another version.
+pub fn wineserver_for(wine_path: &str) -> PathBuf {
+    let wine = if wine_path.contains('/') {
+        Some(PathBuf::from(wine_path))
+    } else {
+        find_in_path(wine_path).map(PathBuf::from)
+    };
+    let beside = |wine: &Path| Some(wine.parent()?.join("wineserver")).filter(|wineserver| wineserver.is_file());
+    let found = wine.as_deref().and_then(|wine| beside(wine).or_else(|| beside(&fs::canonicalize(wine).ok()?)));
+    if found.is_none() && wine.as_deref().and_then(wine_kind) == Some(WineKind::Wrapper) {
+        return PathBuf::from("wineserver");
+    }
+    found
+    .or_else(|| find_in_path("wineserver").map(PathBuf::from))
+    .unwrap_or_else(|| PathBuf::from("wineserver"))
+}
+
+// wine keys each prefix's server by the device and inode of the prefix directory
+fn server_dir(prefix: &Path) -> Option<PathBuf> {
+    let prefix = fs::metadata(prefix).ok()?;
+    let uid = fs::metadata("/proc/self").ok()?.uid();
+    Some(PathBuf::from(format!("/tmp/.wine-{}/server-{:x}-{:x}", uid, prefix.dev(), prefix.ino())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn uses_the_wineserver_beside_the_runner() {
+        let dir = std::env::temp_dir().join(format!("gli-wineserver-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        for name in ["wine", "wineserver"] {
+            fs::write(dir.join("bin").join(name), "#!/bin/sh\n").unwrap();
+            fs::set_permissions(dir.join("bin").join(name), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        // A symlinked wine still finds the server next to the file it points at
+        std::os::unix::fs::symlink(dir.join("bin/wine"), dir.join("wine")).unwrap();
+
+        let wine = dir.join("bin/wine");
+        assert_eq!(wineserver_for(&wine.to_string_lossy()), dir.join("bin/wineserver"));
+        assert_eq!(wineserver_for(&dir.join("wine").to_string_lossy()), dir.join("bin/wineserver"));
+        assert_eq!(WineSession::new(&wine.to_string_lossy(), &dir).wineserver, dir.join("bin/wineserver"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+//! Shared installations: launcher files published once to a system-wide directory, with each
+//! user keeping their own prefix and Steam shortcut.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::launchers::LauncherInfo;
+use crate::output::*;
+
+// Copies the launcher files into place and lets the group update them (setgid keeps new files in the group).
+// Arguments: shared root, destination, source, group.
+const PUBLISH_SCRIPT: &str = r#"set -e
+mkdir -p "$1"
+rm -rf "$2.new"
+cp -a "$3/." "$2.new"
+chgrp -R "$4" "$2.new"
+chmod -R u+rwX,g+rwX,o+rX,o-w "$2.new"
+find "$2.new" -type d -exec chmod g+s {} +
+rm -rf "$2"
+mv "$2.new" "$2"
+"#;
+
+/// Where a launcher's files live under the shared root
+pub fn shared_launcher_dir(shared_root: &Path, launcher: &LauncherInfo) -> PathBuf {
+    shared_root.join(launcher.dir_name)
+}
+
+/// Copy an installed launcher to the shared root, replacing an earlier copy. Asks for
+/// administrator rights through polkit (`pkexec`) when the shared root isn't writable.
+pub fn publish(install_dir: &Path, shared_root: &Path, launcher: &LauncherInfo, group: &str) -> Result<PathBuf, String> {
+    if !install_dir.join(launcher.exe_name).exists() {
+        return Err(format!("{} not found in {}.", launcher.exe_name, install_dir.display()));
+    }
+    let destination = shared_launcher_dir(shared_root, launcher);
+
+    let mut command = if is_writable(shared_root) {
+        Command::new("sh")
+    } else {
+        println!("{}Administrator rights are needed to write to {}.{}", COLOR_YELLOW, shared_root.display(), COLOR_RESET);
+        let mut pkexec = Command::new("pkexec");
+        pkexec.arg("sh");
+        pkexec
+    };
+    let status = command
+    .args(["-c", PUBLISH_SCRIPT, "sh"])
+    .arg(shared_root)
+    .arg(&destination)
+    .arg(install_dir)
+    .arg(group)
+    .status()
+    .map_err(|e| format!("Failed to execute pkexec: {}", e))?;
+
+    if !status.success() {
+        return Err(format!("Publishing {} to {} failed with exit code: {}", launcher.name, destination.display(), status));
+    }
+    Ok(destination)
+}
+
+// Whether files can be created in `path`, or in the nearest existing directory above it
+fn is_writable(path: &Path) -> bool {
+    let existing = match path.ancestors().find(|ancestor| ancestor.exists()) {
+        Some(existing) => existing,
+        None => return false,
+    };
+    let probe = existing.join(format!(".game-launcher-installer-{}", std::process::id()));
+    let writable = fs::write(&probe, b"").is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
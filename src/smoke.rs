@@ -0,0 +1,87 @@
+//! Post-install smoke test: start the installed launcher for a short while, with its windows on
+//! a private Xvfb server when one can be started, and check that it wrote the files it writes
+//! on every start. This turns "the installer exited with 0" into "the launcher runs".
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::launchers::LauncherInfo;
+use crate::output::*;
+use crate::prefix::PrefixManager;
+use crate::xvfb::{display_available, VirtualDisplay};
+
+/// How long the launcher gets to write its files before the test counts as failed.
+pub const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Start `exe` in the prefix and wait until one of the launcher's startup files is created or
+/// changed, then stop everything in the prefix again. Returns the file that showed the launcher
+/// ran, or why the test failed.
+pub fn smoke_test(wine_path: &str, prefix: &PrefixManager, launcher: &LauncherInfo, exe: &Path) -> Result<PathBuf, String> {
+    if launcher.startup_files.is_empty() {
+        return Err(format!("There is no smoke test for {}.", launcher.name));
+    }
+    if !exe.is_file() {
+        return Err(format!("{} does not exist.", exe.display()));
+    }
+    let user_dir = prefix.user_dir().ok_or("Could not find the Windows user profile in the prefix.")?;
+    let files: Vec<PathBuf> = launcher.startup_files.iter().map(|file| user_dir.join(file)).collect();
+    // Left over from an earlier install, so only a change counts
+    let before: Vec<Option<SystemTime>> = files.iter().map(|file| modified(file)).collect();
+
+    // A window on the user's screen would be in the way; Xvfb keeps it out of sight
+    let virtual_display = VirtualDisplay::start().ok();
+    let display = match &virtual_display {
+        Some(virtual_display) => virtual_display.display().to_string(),
+        None if display_available() => std::env::var("DISPLAY").unwrap_or_default(),
+        None => return Err("There is no display to start the launcher on, and Xvfb is not installed.".to_string()),
+    };
+
+    println!("{}Smoke test: starting {} for up to {} seconds...{}", COLOR_BLUE, launcher.name, SMOKE_TEST_TIMEOUT.as_secs(), COLOR_RESET);
+    let session = prefix.session(wine_path);
+    session.start()?;
+    let mut command = session.command(exe);
+    command.env("DISPLAY", &display).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    if virtual_display.is_some() {
+        command.env_remove("WAYLAND_DISPLAY");
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            session.shutdown();
+            return Err(format!("Failed to start {}: {}", exe.display(), e));
+        },
+    };
+
+    let started = Instant::now();
+    let result = loop {
+        let written = files.iter().zip(&before).find(|(file, before)| modified(file).is_some_and(|time| Some(time) != **before));
+        if let Some((file, _)) = written {
+            break Ok(file.clone());
+        }
+        // Launchers often hand over to an updater and exit, so only a failure ends the wait early
+        if let Ok(Some(status)) = child.try_wait() {
+            if !status.success() {
+                break Err(format!("{} exited with {} before writing any of its files.", launcher.name, status));
+            }
+        }
+        if started.elapsed() > SMOKE_TEST_TIMEOUT {
+            break Err(format!("{} wrote none of {} within {} seconds.",
+                              launcher.name, launcher.startup_files.join(", "), SMOKE_TEST_TIMEOUT.as_secs()));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    session.shutdown();
+    let _ = child.kill();
+    let _ = child.wait();
+    result
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,134 @@
+//! Per-launcher state kept between runs, stored as `key = value` lines next to the prefix.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fsutil::write_atomic;
+use crate::prefix::Tweak;
+use crate::wine::WineKind;
+
+/// What this tool last installed for a launcher.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LauncherState {
+    /// Identity of the installer last served at the launcher's URL (see `remote_file_identity`)
+    pub installer_identity: Option<String>,
+    /// Version from the resources of the installer that was last run
+    pub installer_version: Option<String>,
+    /// Version of the installed launcher client, read from its executable after an install or update
+    pub client_version: Option<String>,
+    /// When the launcher was last installed or updated from the menu, in seconds since the epoch
+    pub last_used: Option<u64>,
+    /// Prefix of another tool this launcher was adopted from by `scan` or `--steam-game`
+    pub adopted_from: Option<PathBuf>,
+    /// App ID of the Steam game whose Proton prefix the launcher was installed into
+    pub steam_game: Option<String>,
+    /// Tweaks applied to the launcher's prefix, which `reapply` applies again
+    pub tweaks: Vec<Tweak>,
+    /// Wine binary `migrate-prefix` moved the prefix to, used instead of the configured runner
+    pub runner: Option<String>,
+    /// Whether the launcher's runner was last found to be wine's binary or a wrapper script
+    pub runner_kind: Option<WineKind>,
+    /// Directory the launcher's files were last installed to
+    pub install_dir: Option<PathBuf>,
+    /// Whether the install created `install_dir`, so `purge-all` may remove it; a directory
+    /// that already existed is the user's and is left alone
+    pub install_dir_created: bool,
+    /// Executable of the non-Steam shortcut this tool added for the launcher, which `purge-all`
+    /// removes; a shortcut the user made is left alone
+    pub steam_shortcut: Option<PathBuf>,
+    /// Compatdata directory this tool created for the launcher's Steam shortcut, which
+    /// `purge-all` removes; a directory that already existed is left alone
+    pub compat_data_created: Option<PathBuf>,
+}
+
+impl LauncherState {
+    /// Load the state, or an empty one if the file is missing or unreadable.
+    pub fn load(path: &Path) -> LauncherState {
+        let mut state = LauncherState::default();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return state,
+        };
+
+        for line in content.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                if value.is_empty() {
+                    continue;
+                }
+                match key.trim() {
+                    "installer_identity" => state.installer_identity = Some(value.to_string()),
+                    "installer_version" => state.installer_version = Some(value.to_string()),
+                    "client_version" => state.client_version = Some(value.to_string()),
+                    "last_used" => state.last_used = value.parse().ok(),
+                    "adopted_from" => state.adopted_from = Some(PathBuf::from(value)),
+                    "steam_game" => state.steam_game = Some(value.to_string()),
+                    "runner" => state.runner = Some(value.to_string()),
+                    "runner_kind" => state.runner_kind = WineKind::parse(value),
+                    "install_dir" => state.install_dir = Some(PathBuf::from(value)),
+                    "install_dir_created" => state.install_dir_created = value == "true",
+                    "steam_shortcut" => state.steam_shortcut = Some(PathBuf::from(value)),
+                    "compat_data_created" => state.compat_data_created = Some(PathBuf::from(value)),
+                    "tweaks" => state.tweaks = value.split(',').filter_map(Tweak::parse).collect(),
+                    _ => {}
+                }
+            }
+        }
+        state
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let mut content = String::new();
+        if let Some(identity) = &self.installer_identity {
+            content.push_str(&format!("installer_identity = {}\n", identity));
+        }
+        if let Some(version) = &self.installer_version {
+            content.push_str(&format!("installer_version = {}\n", version));
+        }
+        if let Some(version) = &self.client_version {
+            content.push_str(&format!("client_version = {}\n", version));
+        }
+        if let Some(last_used) = self.last_used {
+            content.push_str(&format!("last_used = {}\n", last_used));
+        }
+        if let Some(adopted_from) = &self.adopted_from {
+            content.push_str(&format!("adopted_from = {}\n", adopted_from.display()));
+        }
+        if let Some(steam_game) = &self.steam_game {
+            content.push_str(&format!("steam_game = {}\n", steam_game));
+        }
+        if let Some(runner) = &self.runner {
+            content.push_str(&format!("runner = {}\n", runner));
+        }
+        if let Some(kind) = self.runner_kind {
+            content.push_str(&format!("runner_kind = {}\n", kind.name()));
+        }
+        if let Some(install_dir) = &self.install_dir {
+            content.push_str(&format!("install_dir = {}\n", install_dir.display()));
+        }
+        if self.install_dir_created {
+            content.push_str("install_dir_created = true\n");
+        }
+        if let Some(exe) = &self.steam_shortcut {
+            content.push_str(&format!("steam_shortcut = {}\n", exe.display()));
+        }
+        if let Some(dir) = &self.compat_data_created {
+            content.push_str(&format!("compat_data_created = {}\n", dir.display()));
+        }
+        if !self.tweaks.is_empty() {
+            let tweaks: Vec<String> = self.tweaks.iter().map(Tweak::name).collect();
+            content.push_str(&format!("tweaks = {}\n", tweaks.join(", ")));
+        }
+        write_atomic(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Remember a tweak applied to the prefix, replacing an earlier value of the same setting.
+    pub fn record_tweak(&mut self, tweak: Tweak) {
+        self.tweaks.retain(|recorded| !recorded.same_setting(&tweak));
+        self.tweaks.push(tweak);
+    }
+}
@@ -0,0 +1,98 @@
+//! Disk usage of installed launchers, split by what the space is used for.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppPaths;
+use crate::fsutil::dir_size;
+use crate::launchers::LauncherInfo;
+
+// Driver and translation-layer shader caches, rebuilt on demand
+const SHADER_CACHE_DIRS: &[&str] = &["DXCache", "D3DSCache", "GLCache", "shadercache"];
+const SHADER_CACHE_SUFFIXES: &[&str] = &[".dxvk-cache", "vkd3d-proton.cache", "vkd3d-proton.cache.write"];
+
+/// Bytes a launcher uses in each part of its directory.
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsage {
+    /// `<root>/<Launcher>/drive`, without shader caches
+    pub install: u64,
+    /// The wine prefix, without shader caches
+    pub prefix: u64,
+    pub shader_caches: u64,
+    /// Downloaded installers in `<root>/<Launcher>/cache`
+    pub installers: u64,
+    /// Shader cache files and directories that were counted
+    pub shader_cache_paths: Vec<PathBuf>,
+}
+
+impl DiskUsage {
+    /// Measure a launcher, or `None` if it has no directory under the Games root.
+    pub fn measure(app_paths: &AppPaths, launcher: &LauncherInfo) -> Option<DiskUsage> {
+        if !app_paths.launcher_dir(launcher.dir_name).is_dir() {
+            return None;
+        }
+
+        let mut usage = DiskUsage::default();
+        for (root, total) in [
+            (app_paths.drive_dir(launcher.dir_name), &mut usage.install),
+            (app_paths.prefix_dir(launcher.dir_name), &mut usage.prefix),
+        ] {
+            let mut caches = Vec::new();
+            find_shader_caches(&root, &mut caches);
+            let cache_size: u64 = caches.iter().map(|path| dir_size(path)).sum();
+            *total = dir_size(&root).saturating_sub(cache_size);
+            usage.shader_caches += cache_size;
+            usage.shader_cache_paths.extend(caches);
+        }
+        usage.installers = dir_size(&app_paths.cache_dir(launcher.dir_name));
+        Some(usage)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.install + self.prefix + self.shader_caches + self.installers
+    }
+
+    /// Space that `clean` would free
+    pub fn reclaimable(&self) -> u64 {
+        self.shader_caches + self.installers
+    }
+
+    /// Delete the cached installers and shader caches. Both are recreated when needed.
+    pub fn clean(&self, app_paths: &AppPaths, launcher: &LauncherInfo) -> Result<(), String> {
+        let cache_dir = app_paths.cache_dir(launcher.dir_name);
+        let mut paths = self.shader_cache_paths.clone();
+        if let Ok(entries) = fs::read_dir(&cache_dir) {
+            paths.extend(entries.flatten().map(|entry| entry.path()));
+        }
+
+        for path in paths {
+            let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+            result.map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+// Collect shader cache directories and files under `path`, without descending into symlinks
+fn find_shader_caches(path: &Path, caches: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        if file_type.is_dir() {
+            if SHADER_CACHE_DIRS.contains(&name.as_str()) {
+                caches.push(entry.path());
+            } else {
+                find_shader_caches(&entry.path(), caches);
+            }
+        } else if file_type.is_file() && SHADER_CACHE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+            caches.push(entry.path());
+        }
+    }
+}
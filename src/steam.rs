@@ -0,0 +1,803 @@
+//! Steam libraries, compatdata prefixes and non-Steam shortcuts.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str;
+
+use regex::Regex;
+
+use crate::config::Config;
+use crate::fsutil::write_atomic;
+
+/// A Steam game installed in one of the libraries, from its app manifest.
+#[derive(Clone, Debug)]
+pub struct InstalledGame {
+    pub app_id: String,
+    pub name: String,
+    /// Its Proton prefix, once Steam created one by starting the game
+    pub prefix: Option<PathBuf>,
+}
+
+/// The Steam libraries found on this system. The first entry is always the main
+/// Steam install, which is where non-Steam shortcuts get their compatdata.
+pub struct SteamIntegration {
+    libraries: Vec<PathBuf>,
+}
+
+impl SteamIntegration {
+    /// Find Steam library folders from `~/.steam/steam/steamapps/libraryfolders.vdf`, the
+    /// `steam_libraries` in the config file, and libraries that app manifests or game
+    /// directories of the known libraries are symlinked to.
+    pub fn discover() -> Result<SteamIntegration, String> {
+        let home_dir = dirs::home_dir().ok_or_else(|| "Could not determine home directory".to_string())?;
+        let steam_root = home_dir.join(".steam/steam");
+        let library_vdf = steam_root.join("steamapps/libraryfolders.vdf");
+
+        if !library_vdf.exists() {
+            return Err(format!("Could not find libraryfolders.vdf at {}", library_vdf.display()));
+        }
+
+        let mut libraries = vec![steam_root];
+
+        // Parse libraryfolders.vdf to find additional library paths
+        let vdf_content = fs::read_to_string(&library_vdf)
+        .map_err(|e| format!("Failed to read libraryfolders.vdf: {}", e))?;
+
+        // Simple regex to extract paths from VDF, allowing escaped quotes inside the value
+        let re = Regex::new(r#""path"\s*"((?:[^"\\]|\\.)*)""#).unwrap();
+        for cap in re.captures_iter(&vdf_content) {
+            if let Some(path_match) = cap.get(1) {
+                libraries.push(PathBuf::from(unescape_vdf_string(path_match.as_str())));
+            }
+        }
+        libraries.extend(Config::load(&home_dir).steam_libraries.iter().map(|library| {
+            // Accept the steamapps directory itself as well
+            if library.ends_with("steamapps") { library.parent().unwrap_or(library).to_path_buf() } else { library.clone() }
+        }));
+        let linked: Vec<PathBuf> = libraries.iter().flat_map(|library| linked_libraries(library)).collect();
+        libraries.extend(linked);
+
+        let mut seen = Vec::new();
+        libraries.retain(|library| {
+            let key = fs::canonicalize(library).unwrap_or_else(|_| library.clone());
+            if seen.contains(&key) {
+                return false;
+            }
+            seen.push(key);
+            true
+        });
+        Ok(SteamIntegration { libraries })
+    }
+
+    /// Use an explicit list of libraries, main Steam install first.
+    pub fn from_libraries(libraries: Vec<PathBuf>) -> SteamIntegration {
+        SteamIntegration { libraries }
+    }
+
+    pub fn libraries(&self) -> &[PathBuf] {
+        &self.libraries
+    }
+
+    /// Find the existing compatdata prefix for an App ID in any library.
+    pub fn find_prefix(&self, app_id: &str) -> Option<PathBuf> {
+        for lib in &self.libraries {
+            let compatdata_path = lib.join(format!("steamapps/compatdata/{}/pfx", app_id));
+            if compatdata_path.exists() && compatdata_path.is_dir() {
+                return Some(compatdata_path);
+            }
+        }
+        None
+    }
+
+    /// Games installed in any library (`steamapps/appmanifest_*.acf`), sorted by name.
+    pub fn installed_games(&self) -> Vec<InstalledGame> {
+        let app_id = Regex::new(r#""appid"\s*"(\d+)""#).unwrap();
+        let name = Regex::new(r#""name"\s*"((?:[^"\\]|\\.)*)""#).unwrap();
+        let mut games: Vec<InstalledGame> = Vec::new();
+        for library in &self.libraries {
+            let manifests = fs::read_dir(library.join("steamapps")).into_iter().flatten().flatten()
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.starts_with("appmanifest_") && name.ends_with(".acf")
+            });
+            for manifest in manifests {
+                let content = fs::read_to_string(manifest.path()).unwrap_or_default();
+                let (id, title) = match (app_id.captures(&content), name.captures(&content)) {
+                    (Some(id), Some(title)) => (id, title),
+                    _ => continue,
+                };
+                if games.iter().any(|game| game.app_id == id[1]) {
+                    continue;
+                }
+                games.push(InstalledGame {
+                    app_id: id[1].to_string(),
+                    name: unescape_vdf_string(&title[1]),
+                    prefix: self.find_prefix(&id[1]),
+                });
+            }
+        }
+        games.sort_by_key(|game| game.name.to_lowercase());
+        games
+    }
+
+    /// `shortcuts.vdf` of every Steam user on this machine.
+    pub fn shortcut_files(&self) -> Vec<PathBuf> {
+        self.user_config_dirs().into_iter()
+        .map(|config| config.join("shortcuts.vdf"))
+        .filter(|path| path.is_file())
+        .collect()
+    }
+
+    /// Add a non-Steam shortcut for every Steam user, unless one for `exe` already exists, in
+    /// the collections named by `tags`. Returns its app ID, which also names its compatdata prefix.
+    pub fn add_shortcut(&self, app_name: &str, exe: &Path, launch_options: &str, tags: &[&str]) -> Result<u32, String> {
+        if steam_running() {
+            return Err("Steam is running. Close it so the shortcut isn't overwritten when it exits.".to_string());
+        }
+        let config_dirs = self.user_config_dirs();
+        if config_dirs.is_empty() {
+            return Err("No Steam user found. Log in to Steam once first.".to_string());
+        }
+
+        let mut app_id = shortcut_app_id(exe, app_name);
+        for config in config_dirs {
+            app_id = add_shortcut(&config.join("shortcuts.vdf"), app_name, exe, launch_options, tags)?;
+        }
+        Ok(app_id)
+    }
+
+    /// The non-Steam shortcut for `exe`, from the first Steam user that has one.
+    pub fn shortcut_for_exe(&self, exe: &Path) -> Option<Shortcut> {
+        self.shortcut_files().iter()
+        .flat_map(|file| read_shortcuts(file).unwrap_or_default())
+        .find(|shortcut| Path::new(&shortcut.exe) == exe)
+    }
+
+    /// Non-Steam shortcuts whose executable is named `exe_name` (compared case-insensitively,
+    /// as wine does), one per app ID across all Steam users.
+    pub fn shortcuts_named(&self, exe_name: &str) -> Vec<Shortcut> {
+        self.shortcuts().into_iter()
+        .filter(|shortcut| {
+            Path::new(&shortcut.exe.replace('\\', "/")).file_name()
+            .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(exe_name))
+        })
+        .collect()
+    }
+
+    /// Non-Steam shortcuts of all Steam users, one per app ID.
+    pub fn shortcuts(&self) -> Vec<Shortcut> {
+        let mut shortcuts: Vec<Shortcut> = Vec::new();
+        for shortcut in self.shortcut_files().iter().flat_map(|file| read_shortcuts(file).unwrap_or_default()) {
+            if shortcut.app_id.is_some() && !shortcuts.iter().any(|found| found.app_id == shortcut.app_id) {
+                shortcuts.push(shortcut);
+            }
+        }
+        shortcuts
+    }
+
+    /// The launch options of a non-Steam shortcut, from the first Steam user that has it.
+    pub fn shortcut_launch_options(&self, app_id: u32) -> Option<String> {
+        self.shortcut_files().iter()
+        .flat_map(|file| read_shortcuts(file).unwrap_or_default())
+        .find(|shortcut| shortcut.app_id == Some(app_id))
+        .map(|shortcut| shortcut.launch_options)
+    }
+
+    /// Set the launch options of a non-Steam shortcut for every Steam user that has it.
+    /// Returns how many shortcut files were changed.
+    pub fn set_shortcut_launch_options(&self, app_id: u32, launch_options: &str) -> Result<usize, String> {
+        if steam_running() {
+            return Err("Steam is running. Close it so the launch options aren't overwritten when it exits.".to_string());
+        }
+        let mut changed = 0;
+        for file in self.shortcut_files() {
+            if set_launch_options(&file, app_id, launch_options)? {
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    // `userdata/<account id>/config` of every Steam user
+    fn user_config_dirs(&self) -> Vec<PathBuf> {
+        let userdata = match self.libraries.first() {
+            Some(steam_root) => steam_root.join("userdata"),
+            None => return Vec::new(),
+        };
+        fs::read_dir(userdata)
+        .map(|entries| {
+            entries.flatten()
+            // Account ID 0 holds settings from before anyone logged in
+            .filter(|entry| entry.file_name() != "0")
+            .map(|entry| entry.path().join("config"))
+            .filter(|path| path.is_dir())
+            .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Proton builds Steam can run games with: the Proton tools installed in any library and
+    /// the custom ones in the main install's `compatibilitytools.d`, with the version they report.
+    pub fn proton_versions(&self) -> Vec<String> {
+        let mut versions = Vec::new();
+        for dir in self.proton_dirs() {
+            // `version` holds a build timestamp followed by the version name
+            let version = fs::read_to_string(dir.join("version")).ok()
+            .and_then(|version| version.split_whitespace().last().map(str::to_string));
+            let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let description = match version {
+                Some(version) if version != name => format!("{} ({})", name, version),
+                _ => name,
+            };
+            if !versions.contains(&description) {
+                versions.push(description);
+            }
+        }
+        versions.sort();
+        versions
+    }
+
+    /// The wine binary of the newest installed Proton build whose name has every word and number
+    /// of `name`, e.g. `proton-ge-9` finds `GE-Proton9-20` and `proton-experimental` finds
+    /// `Proton - Experimental`.
+    pub fn proton_wine(&self, name: &str) -> Option<PathBuf> {
+        let wanted = name_tokens(name);
+        self.proton_dirs().into_iter()
+        .filter(|dir| {
+            let tokens = name_tokens(&dir.file_name().unwrap_or_default().to_string_lossy());
+            wanted.iter().all(|token| tokens.contains(token))
+        })
+        .filter_map(|dir| {
+            // Proton 5 and later keep wine in `files`, older builds in `dist`
+            let wine = ["files/bin/wine", "dist/bin/wine"].iter().map(|bin| dir.join(bin)).find(|wine| wine.is_file())?;
+            let numbers: Vec<u64> = name_tokens(&dir.file_name().unwrap_or_default().to_string_lossy()).iter()
+            .filter_map(|token| token.parse().ok())
+            .collect();
+            Some((numbers, wine))
+        })
+        .max()
+        .map(|(_, wine)| wine)
+    }
+
+    // Directories of the Proton builds listed by `proton_versions`
+    fn proton_dirs(&self) -> Vec<PathBuf> {
+        let official = self.libraries.iter()
+        .flat_map(|library| fs::read_dir(library.join("steamapps/common")).into_iter().flatten().flatten())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("Proton"));
+        let custom = self.libraries.first()
+        .map(|steam_root| fs::read_dir(steam_root.join("compatibilitytools.d")).into_iter().flatten().flatten())
+        .into_iter()
+        .flatten();
+        official.chain(custom).map(|entry| entry.path()).filter(|path| path.is_dir()).collect()
+    }
+
+    /// The compatibility tool set for an app in Steam's `config/config.vdf`, e.g.
+    /// `proton_experimental`. Steam writes the file when it exits, so a tool picked while it
+    /// runs shows up only afterwards.
+    pub fn compat_tool(&self, app_id: u32) -> Option<String> {
+        let config = fs::read_to_string(self.libraries.first()?.join("config/config.vdf")).ok()?;
+        let mapping = &config[config.find("\"CompatToolMapping\"")?..];
+        let entry = Regex::new(&format!(r#""{}"\s*\{{[^}}]*?"name"\s*"((?:[^"\\]|\\.)*)""#, app_id)).unwrap();
+        entry.captures(mapping)
+        .map(|caps| unescape_vdf_string(&caps[1]))
+        .filter(|name| !name.is_empty())
+    }
+
+    /// Where Steam creates the prefix for a non-Steam shortcut: the main library's compatdata.
+    pub fn shortcut_prefix_path(&self, app_id: &str) -> Result<PathBuf, String> {
+        let steam_root = self.libraries.first()
+        .ok_or_else(|| "No Steam library found.".to_string())?;
+        Ok(steam_root.join(format!("steamapps/compatdata/{}/pfx", app_id)))
+    }
+}
+
+/// How much an install does in Steam once the launcher is in place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SteamIntegrationLevel {
+    /// Add a non-Steam shortcut for the launcher
+    Shortcut,
+    /// Print how to add the shortcut by hand
+    Instructions,
+    /// Leave Steam alone
+    Off,
+}
+
+impl SteamIntegrationLevel {
+    pub fn parse(value: &str) -> Option<SteamIntegrationLevel> {
+        match value.trim().to_lowercase().as_str() {
+            "shortcut" => Some(SteamIntegrationLevel::Shortcut),
+            "instructions" => Some(SteamIntegrationLevel::Instructions),
+            "none" => Some(SteamIntegrationLevel::Off),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SteamIntegrationLevel::Shortcut => "shortcut",
+            SteamIntegrationLevel::Instructions => "instructions",
+            SteamIntegrationLevel::Off => "none",
+        }
+    }
+}
+
+/// A non-Steam shortcut from a binary `shortcuts.vdf`.
+#[derive(Clone, Debug)]
+pub struct Shortcut {
+    pub app_name: String,
+    /// Target executable with Steam's surrounding quotes removed
+    pub exe: String,
+    pub app_id: Option<u32>,
+    pub launch_options: String,
+    // Byte range of the entry, and where its fields start (after the index key)
+    start: usize,
+    fields_start: usize,
+    end: usize,
+    // Byte range of the LaunchOptions value, without its terminator
+    launch_options_range: Option<(usize, usize)>,
+}
+
+// Lowercase runs of letters and of digits in a Proton build name: `GE-Proton9-20` gives
+// `ge`, `proton`, `9` and `20`
+fn name_tokens(name: &str) -> Vec<String> {
+    Regex::new(r"[a-z]+|[0-9]+").unwrap()
+    .find_iter(&name.to_lowercase())
+    .map(|token| token.as_str().to_string())
+    .collect()
+}
+
+// Libraries that a library's app manifests (`steamapps/*.acf`) or game directories
+// (`steamapps/common/*`) are symlinks into, e.g. on a drive mounted elsewhere
+fn linked_libraries(library: &Path) -> Vec<PathBuf> {
+    let steamapps = library.join("steamapps");
+    let manifests = fs::read_dir(&steamapps).into_iter().flatten().flatten()
+    .filter(|entry| entry.file_name().to_string_lossy().ends_with(".acf"));
+    let games = fs::read_dir(steamapps.join("common")).into_iter().flatten().flatten();
+
+    let mut linked = Vec::new();
+    for entry in manifests.chain(games) {
+        if !entry.file_type().is_ok_and(|file_type| file_type.is_symlink()) {
+            continue;
+        }
+        let target = match fs::canonicalize(entry.path()) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        let other = target.ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "steamapps"))
+        .and_then(Path::parent);
+        if let Some(other) = other {
+            if !linked.iter().any(|known: &PathBuf| known == other) {
+                linked.push(other.to_path_buf());
+            }
+        }
+    }
+    linked
+}
+
+/// Launch options that run the shortcut's Proton with its compatdata in `dir` instead of
+/// Steam's, or with Steam's again for `None`. Options without `%command%` are arguments to
+/// the game and are kept after it.
+pub fn with_compat_data_path(launch_options: &str, dir: Option<&Path>) -> String {
+    let existing = Regex::new(r#"STEAM_COMPAT_DATA_PATH=("[^"]*"|\S+)\s*"#).unwrap();
+    let launch_options = existing.replace_all(launch_options, "").trim().to_string();
+    let dir = match dir {
+        Some(dir) => dir,
+        None => return launch_options,
+    };
+    let env = format!("STEAM_COMPAT_DATA_PATH=\"{}\"", dir.display());
+    if launch_options.contains("%command%") {
+        format!("{} {}", env, launch_options)
+    } else if launch_options.is_empty() {
+        format!("{} %command%", env)
+    } else {
+        format!("{} %command% {}", env, launch_options)
+    }
+}
+
+/// Read the shortcuts from a binary `shortcuts.vdf`.
+pub fn read_shortcuts(path: &Path) -> Result<Vec<Shortcut>, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_shortcuts(&data)
+    .map(|(shortcuts, _)| shortcuts)
+    .ok_or_else(|| format!("{} is not a valid shortcuts file.", path.display()))
+}
+
+/// Append a shortcut to a binary `shortcuts.vdf`, creating the file if needed. `tags` are the
+/// collections it is listed in; Steam turns them into collections in the library and Big Picture.
+/// If a shortcut for `exe` is already there it is kept as it is. Returns the shortcut's app ID.
+pub fn add_shortcut(path: &Path, app_name: &str, exe: &Path, launch_options: &str, tags: &[&str]) -> Result<u32, String> {
+    let mut data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => b"\0shortcuts\0\x08\x08".to_vec(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let (shortcuts, list_end) = parse_shortcuts(&data)
+    .ok_or_else(|| format!("{} is not a valid shortcuts file.", path.display()))?;
+
+    let exe_string = exe.to_string_lossy().to_string();
+    if let Some(existing) = shortcuts.iter().find(|shortcut| shortcut.exe == exe_string) {
+        return Ok(existing.app_id.unwrap_or_else(|| shortcut_app_id(exe, &existing.app_name)));
+    }
+
+    let app_id = shortcut_app_id(exe, app_name);
+    let start_dir = exe.parent().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default();
+    let mut entry = Vec::new();
+    entry.push(VDF_MAP);
+    push_cstr(&mut entry, &shortcuts.len().to_string());
+    push_int(&mut entry, "appid", app_id);
+    push_string(&mut entry, "AppName", app_name);
+    push_string(&mut entry, "Exe", &format!("\"{}\"", exe_string));
+    push_string(&mut entry, "StartDir", &format!("\"{}\"", start_dir));
+    push_string(&mut entry, "icon", "");
+    push_string(&mut entry, "ShortcutPath", "");
+    push_string(&mut entry, "LaunchOptions", launch_options);
+    push_int(&mut entry, "IsHidden", 0);
+    // Launchers are used with a mouse: with a controller Steam Input gives them its desktop
+    // (mouse and keyboard) layout, and they stay out of the VR library
+    push_int(&mut entry, "AllowDesktopConfig", 1);
+    push_int(&mut entry, "AllowOverlay", 1);
+    push_int(&mut entry, "OpenVR", 0);
+    push_int(&mut entry, "Devkit", 0);
+    push_string(&mut entry, "DevkitGameID", "");
+    push_int(&mut entry, "DevkitOverrideAppID", 0);
+    push_int(&mut entry, "LastPlayTime", 0);
+    push_string(&mut entry, "FlatpakAppID", "");
+    entry.push(VDF_MAP);
+    push_cstr(&mut entry, "tags");
+    for (i, tag) in tags.iter().enumerate() {
+        push_string(&mut entry, &i.to_string(), tag);
+    }
+    entry.push(VDF_END);
+    entry.push(VDF_END);
+    data.splice(list_end..list_end, entry);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_atomic(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(app_id)
+}
+
+/// App ID Steam gives a non-Steam shortcut: the CRC-32 of its quoted exe and name, with the top bit set.
+pub fn shortcut_app_id(exe: &Path, app_name: &str) -> u32 {
+    crc32(format!("\"{}\"{}", exe.display(), app_name).as_bytes()) | 0x8000_0000
+}
+
+/// Rewrite `shortcuts.vdf` without the shortcuts for which `remove` returns true.
+/// Steam keeps the file in memory and overwrites it on exit, so it must not be running.
+pub fn remove_shortcuts(path: &Path, remove: impl Fn(&Shortcut) -> bool) -> Result<usize, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let (shortcuts, _) = parse_shortcuts(&data)
+    .ok_or_else(|| format!("{} is not a valid shortcuts file.", path.display()))?;
+    let (first, last) = match (shortcuts.first(), shortcuts.last()) {
+        (Some(first), Some(last)) => (first.start, last.end),
+        _ => return Ok(0),
+    };
+
+    let mut rewritten = data[..first].to_vec();
+    let mut kept = 0;
+    for shortcut in shortcuts.iter().filter(|shortcut| !remove(shortcut)) {
+        // Entries are keyed by their position, so renumber the ones that stay
+        rewritten.push(0x00);
+        rewritten.extend_from_slice(kept.to_string().as_bytes());
+        rewritten.push(0x00);
+        rewritten.extend_from_slice(&data[shortcut.fields_start..shortcut.end]);
+        kept += 1;
+    }
+    rewritten.extend_from_slice(&data[last..]);
+
+    write_atomic(path, rewritten).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(shortcuts.len() - kept)
+}
+
+/// Replace the launch options of the shortcut with the given app ID. Returns false if the file
+/// has no such shortcut. Like `remove_shortcuts`, Steam must not be running.
+pub fn set_launch_options(path: &Path, app_id: u32, launch_options: &str) -> Result<bool, String> {
+    let mut data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let (shortcuts, _) = parse_shortcuts(&data)
+    .ok_or_else(|| format!("{} is not a valid shortcuts file.", path.display()))?;
+    let shortcut = match shortcuts.iter().find(|shortcut| shortcut.app_id == Some(app_id)) {
+        Some(shortcut) => shortcut,
+        None => return Ok(false),
+    };
+
+    match shortcut.launch_options_range {
+        Some((start, end)) => {
+            data.splice(start..end, launch_options.bytes());
+        },
+        None => {
+            // Add the field before the entry's end marker
+            let mut field = Vec::new();
+            push_string(&mut field, "LaunchOptions", launch_options);
+            data.splice(shortcut.end - 1..shortcut.end - 1, field);
+        },
+    }
+    write_atomic(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+/// Steam's game ID for a non-Steam shortcut, as used in `steam://rungameid/` links.
+pub fn shortcut_game_id(app_id: u32) -> u64 {
+    (u64::from(app_id) << 32) | 0x0200_0000
+}
+
+/// Ask Steam to start a non-Steam shortcut, starting Steam minimized to the tray first if it
+/// isn't running. Returns once the request is handed to Steam, not when the shortcut runs.
+pub fn launch_shortcut(app_id: u32) -> Result<(), String> {
+    let url = format!("steam://rungameid/{}", shortcut_game_id(app_id));
+    let mut command = Command::new("steam");
+    if !steam_running() {
+        command.arg("-silent");
+    }
+    command.arg(&url)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map(|_| ())
+    .map_err(|e| format!("Failed to run steam {}: {}", url, e))
+}
+
+/// Whether the Steam client is running.
+pub fn steam_running() -> bool {
+    fs::read_dir("/proc")
+    .map(|entries| {
+        entries.flatten().any(|entry| {
+            fs::read_to_string(entry.path().join("comm"))
+            .map(|comm| comm.trim() == "steam")
+            .unwrap_or(false)
+        })
+    })
+    .unwrap_or(false)
+}
+
+/// Check if protontricks is installed.
+pub fn protontricks_available() -> bool {
+    Command::new("which")
+    .arg("protontricks")
+    .status()
+    .map(|status| status.success())
+    .unwrap_or(false)
+}
+
+/// List non-Steam shortcuts using `protontricks -l`, one line per shortcut.
+pub fn list_nonsteam_games() -> Result<Vec<String>, String> {
+    let output = Command::new("protontricks")
+    .arg("-l")
+    .output()
+    .map_err(|e| format!("Failed to execute protontricks: {}", e))?;
+
+    if !output.status.success() {
+        return Err("protontricks -l command failed".to_string());
+    }
+
+    let output_str = str::from_utf8(&output.stdout)
+    .map_err(|e| format!("Invalid UTF-8 in protontricks output: {}", e))?;
+
+    let mut games: Vec<String> = Vec::new();
+
+    for line in output_str.lines() {
+        if line.contains("Non-Steam shortcut:") {
+            games.push(line.to_string());
+        }
+    }
+
+    Ok(games)
+}
+
+/// Extract the App ID from a `protontricks -l` line.
+pub fn extract_appid(line: &str) -> Option<String> {
+    let re = Regex::new(r"\(([0-9]+)\)$").unwrap();
+    re.captures(line).and_then(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+}
+
+// Undo VDF string escaping (\\ and \")
+fn unescape_vdf_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                result.push(next);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+// Binary VDF field types
+const VDF_MAP: u8 = 0x00;
+const VDF_STRING: u8 = 0x01;
+const VDF_INT32: u8 = 0x02;
+const VDF_UINT64: u8 = 0x07;
+const VDF_END: u8 = 0x08;
+
+// The shortcuts, and the position of the end marker of the list
+fn parse_shortcuts(data: &[u8]) -> Option<(Vec<Shortcut>, usize)> {
+    if *data.first()? != VDF_MAP {
+        return None;
+    }
+    let (root_key, mut position) = read_cstr(data, 1)?;
+    if !root_key.eq_ignore_ascii_case("shortcuts") {
+        return None;
+    }
+
+    let mut shortcuts = Vec::new();
+    loop {
+        let start = position;
+        match *data.get(position)? {
+            VDF_END => return Some((shortcuts, position)),
+            VDF_MAP => {},
+            _ => return None,
+        }
+        let (_, fields_start) = read_cstr(data, position + 1)?;
+
+        let mut app_name = String::new();
+        let mut exe = String::new();
+        let mut app_id = None;
+        let mut launch_options = String::new();
+        let mut launch_options_range = None;
+        position = fields_start;
+        loop {
+            let kind = *data.get(position)?;
+            position += 1;
+            if kind == VDF_END {
+                break;
+            }
+            let (key, value_start) = read_cstr(data, position)?;
+            position = match kind {
+                VDF_MAP => skip_vdf_map(data, value_start)?,
+                VDF_STRING => {
+                    let (value, next) = read_cstr(data, value_start)?;
+                    match key.to_lowercase().as_str() {
+                        "appname" => app_name = value,
+                        "exe" => exe = value.trim_matches('"').to_string(),
+                        "launchoptions" => {
+                            launch_options = value;
+                            launch_options_range = Some((value_start, next - 1));
+                        },
+                        _ => {},
+                    }
+                    next
+                },
+                VDF_INT32 => {
+                    if key.eq_ignore_ascii_case("appid") {
+                        app_id = Some(u32::from_le_bytes(data.get(value_start..value_start + 4)?.try_into().ok()?));
+                    }
+                    value_start + 4
+                },
+                VDF_UINT64 => value_start + 8,
+                _ => return None,
+            };
+        }
+
+        shortcuts.push(Shortcut { app_name, exe, app_id, launch_options, start, fields_start, end: position, launch_options_range });
+    }
+}
+
+// Skip the fields of a nested map, returning the position after its end marker
+fn skip_vdf_map(data: &[u8], mut position: usize) -> Option<usize> {
+    loop {
+        let kind = *data.get(position)?;
+        position += 1;
+        if kind == VDF_END {
+            return Some(position);
+        }
+        let (_, value_start) = read_cstr(data, position)?;
+        position = match kind {
+            VDF_MAP => skip_vdf_map(data, value_start)?,
+            VDF_STRING => read_cstr(data, value_start)?.1,
+            VDF_INT32 => value_start + 4,
+            VDF_UINT64 => value_start + 8,
+            _ => return None,
+        };
+    }
+}
+
+// Read a NUL-terminated string, returning it and the position after the terminator
+fn read_cstr(data: &[u8], start: usize) -> Option<(String, usize)> {
+    let length = data.get(start..)?.iter().position(|b| *b == 0)?;
+    Some((String::from_utf8_lossy(&data[start..start + length]).to_string(), start + length + 1))
+}
+
+fn push_cstr(data: &mut Vec<u8>, value: &str) {
+    data.extend_from_slice(value.as_bytes());
+    data.push(0);
+}
+
+fn push_string(data: &mut Vec<u8>, key: &str, value: &str) {
+    data.push(VDF_STRING);
+    push_cstr(data, key);
+    push_cstr(data, value);
+}
+
+fn push_int(data: &mut Vec<u8>, key: &str, value: u32) {
+    data.push(VDF_INT32);
+    push_cstr(data, key);
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+// CRC-32 (IEEE), as used for shortcut app IDs
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bytes of a string field inside a shortcut entry
+    fn string_field(key: &str, value: &str) -> Vec<u8> {
+        let mut field = Vec::new();
+        push_string(&mut field, key, value);
+        field
+    }
+
+    #[test]
+    fn writes_tags_and_reads_shortcuts_back() {
+        let dir = std::env::temp_dir().join(format!("gli-shortcuts-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("config/shortcuts.vdf");
+        let battlenet = Path::new("/home/user/Games/battlenet/launch.sh");
+        let ea_app = Path::new("/home/user/Games/ea-app/launch.sh");
+
+        let first = add_shortcut(&path, "Battle.net", battlenet, "", &["Game Launchers", "Blizzard"]).unwrap();
+        let second = add_shortcut(&path, "EA App", ea_app, "-silent", &[]).unwrap();
+        assert_eq!(first, shortcut_app_id(battlenet, "Battle.net"));
+        assert_ne!(first, second);
+        assert!(first & 0x8000_0000 != 0);
+
+        // Tags are a nested map of index to collection name, empty when there are none
+        let data = fs::read(&path).unwrap();
+        let mut tags = vec![VDF_MAP];
+        tags.extend_from_slice(b"tags\0");
+        tags.extend(string_field("0", "Game Launchers"));
+        tags.extend(string_field("1", "Blizzard"));
+        tags.push(VDF_END);
+        assert!(data.windows(tags.len()).any(|window| window == tags.as_slice()));
+        assert!(data.windows(8).any(|window| window == b"\0tags\0\x08\x08"));
+
+        let shortcuts = read_shortcuts(&path).unwrap();
+        assert_eq!(shortcuts.len(), 2);
+        assert_eq!(shortcuts[0].app_name, "Battle.net");
+        assert_eq!(shortcuts[0].exe, battlenet.to_string_lossy());
+        assert_eq!(shortcuts[0].app_id, Some(first));
+        assert_eq!(shortcuts[1].launch_options, "-silent");
+
+        // Adding the same exe again keeps the existing shortcut
+        assert_eq!(add_shortcut(&path, "Renamed", battlenet, "", &["Other"]).unwrap(), first);
+        assert_eq!(read_shortcuts(&path).unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn edits_and_removes_shortcuts() {
+        let dir = std::env::temp_dir().join(format!("gli-shortcuts-edit-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("shortcuts.vdf");
+        let first = add_shortcut(&path, "Battle.net", Path::new("/games/battlenet.sh"), "", &["Game Launchers"]).unwrap();
+        let second = add_shortcut(&path, "EA App", Path::new("/games/ea-app.sh"), "", &[]).unwrap();
+
+        assert!(set_launch_options(&path, second, "PROTON_LOG=1 %command%").unwrap());
+        assert!(!set_launch_options(&path, 1, "unused").unwrap());
+        assert_eq!(read_shortcuts(&path).unwrap()[1].launch_options, "PROTON_LOG=1 %command%");
+
+        assert_eq!(remove_shortcuts(&path, |shortcut| shortcut.app_id == Some(first)).unwrap(), 1);
+        let shortcuts = read_shortcuts(&path).unwrap();
+        assert_eq!(shortcuts.len(), 1);
+        assert_eq!(shortcuts[0].app_id, Some(second));
+        assert_eq!(shortcuts[0].launch_options, "PROTON_LOG=1 %command%");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+//! systemd user units that run `update <launcher>`, `reapply <launcher>` or `healthcheck` on a timer.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::launchers::LauncherInfo;
+use crate::output::*;
+
+/// Directory systemd reads user units from
+pub fn user_unit_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join(".config/systemd/user")
+}
+
+/// Unit name without the `.service`/`.timer` suffix
+pub fn update_unit_name(launcher: &LauncherInfo) -> String {
+    format!("game-launcher-installer-update-{}", launcher.id)
+}
+
+/// Service that runs the update check once with `--headless`, since no terminal is attached.
+pub fn update_service(launcher: &LauncherInfo, exe: &Path) -> String {
+    format!(
+        "[Unit]\nDescription=Update {} (game-launcher-installer)\nAfter=network-online.target\n\n[Service]\nType=oneshot\nExecStart=\"{}\" --headless update {}\n",
+        launcher.name, exe.display(), launcher.id
+    )
+}
+
+/// Timer that starts the service daily, catching up on runs missed while the machine was off.
+pub fn update_timer(launcher: &LauncherInfo) -> String {
+    format!(
+        "[Unit]\nDescription=Check for {} updates daily\n\n[Timer]\nOnCalendar=daily\nRandomizedDelaySec=1h\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        launcher.name
+    )
+}
+
+/// Unit name of the timer that runs `reapply <launcher>`
+pub fn reapply_unit_name(launcher: &LauncherInfo) -> String {
+    format!("game-launcher-installer-reapply-{}", launcher.id)
+}
+
+/// Service that applies the launcher's recorded tweaks once, with no terminal attached.
+pub fn reapply_service(launcher: &LauncherInfo, exe: &Path) -> String {
+    format!(
+        "[Unit]\nDescription=Reapply prefix tweaks for {} (game-launcher-installer)\n\n[Service]\nType=oneshot\nExecStart=\"{}\" reapply {}\n",
+        launcher.name, exe.display(), launcher.id
+    )
+}
+
+/// Timer that starts the service hourly, since launchers update themselves whenever they run.
+pub fn reapply_timer(launcher: &LauncherInfo) -> String {
+    format!(
+        "[Unit]\nDescription=Reapply prefix tweaks for {} hourly\n\n[Timer]\nOnCalendar=hourly\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        launcher.name
+    )
+}
+
+/// Unit name of the timer that runs `healthcheck`
+pub const HEALTHCHECK_UNIT_NAME: &str = "game-launcher-installer-healthcheck";
+
+/// Service that runs the health checks once and shows a notification if anything is broken.
+pub fn healthcheck_service(exe: &Path) -> String {
+    format!(
+        "[Unit]\nDescription=Check installed launchers (game-launcher-installer)\n\n[Service]\nType=oneshot\nExecStart=\"{}\" healthcheck --notify\n",
+        exe.display()
+    )
+}
+
+/// Timer that starts the health checks shortly after each boot, when an upgrade has just been
+/// applied, and daily.
+pub fn healthcheck_timer() -> String {
+    "[Unit]\nDescription=Check installed launchers after boot and daily\n\n[Timer]\nOnBootSec=5min\nOnCalendar=daily\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n".to_string()
+}
+
+/// Write the service and timer for a launcher and start the timer.
+pub fn enable_auto_update(home_dir: &Path, launcher: &LauncherInfo, exe: &Path) -> Result<(), String> {
+    let name = update_unit_name(launcher);
+    install_timer(home_dir, &name, &update_service(launcher, exe), &update_timer(launcher))?;
+    println!("{}Daily update checks enabled for {} ({}.timer).{}", COLOR_GREEN, launcher.name, name, COLOR_RESET);
+    Ok(())
+}
+
+/// Stop the launcher's timer and remove its units.
+pub fn disable_auto_update(home_dir: &Path, launcher: &LauncherInfo) -> Result<(), String> {
+    remove_timer(home_dir, &update_unit_name(launcher))?;
+    println!("{}Automatic updates disabled for {}.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+    Ok(())
+}
+
+/// Write the service and timer that reapply the launcher's tweaks and start the timer.
+pub fn enable_reapply_timer(home_dir: &Path, launcher: &LauncherInfo, exe: &Path) -> Result<(), String> {
+    let name = reapply_unit_name(launcher);
+    install_timer(home_dir, &name, &reapply_service(launcher, exe), &reapply_timer(launcher))?;
+    println!("{}{}'s tweaks are reapplied hourly ({}.timer).{}", COLOR_GREEN, launcher.name, name, COLOR_RESET);
+    Ok(())
+}
+
+/// Stop the timer that reapplies the launcher's tweaks and remove its units.
+pub fn disable_reapply_timer(home_dir: &Path, launcher: &LauncherInfo) -> Result<(), String> {
+    remove_timer(home_dir, &reapply_unit_name(launcher))?;
+    println!("{}{}'s tweaks are no longer reapplied on a timer.{}", COLOR_GREEN, launcher.name, COLOR_RESET);
+    Ok(())
+}
+
+/// Write the service and timer that run the health checks and start the timer.
+pub fn enable_healthcheck_timer(home_dir: &Path, exe: &Path) -> Result<(), String> {
+    install_timer(home_dir, HEALTHCHECK_UNIT_NAME, &healthcheck_service(exe), &healthcheck_timer())?;
+    println!("{}Health checks run after boot and daily ({}.timer).{}", COLOR_GREEN, HEALTHCHECK_UNIT_NAME, COLOR_RESET);
+    Ok(())
+}
+
+/// Stop the health check timer and remove its units.
+pub fn disable_healthcheck_timer(home_dir: &Path) -> Result<(), String> {
+    remove_timer(home_dir, HEALTHCHECK_UNIT_NAME)?;
+    println!("{}Health checks no longer run on a timer.{}", COLOR_GREEN, COLOR_RESET);
+    Ok(())
+}
+
+// Write `<name>.service` and `<name>.timer` and start the timer
+fn install_timer(home_dir: &Path, name: &str, service: &str, timer: &str) -> Result<(), String> {
+    let unit_dir = user_unit_dir(home_dir);
+    fs::create_dir_all(&unit_dir)
+    .map_err(|e| format!("Failed to create {}: {}", unit_dir.display(), e))?;
+
+    fs::write(unit_dir.join(format!("{}.service", name)), service)
+    .map_err(|e| format!("Failed to write {}.service: {}", name, e))?;
+    fs::write(unit_dir.join(format!("{}.timer", name)), timer)
+    .map_err(|e| format!("Failed to write {}.timer: {}", name, e))?;
+
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", &format!("{}.timer", name)])
+}
+
+/// Stop `<name>.timer` and remove it and its service.
+pub fn remove_timer(home_dir: &Path, name: &str) -> Result<(), String> {
+    // Fails if the timer was never enabled, which is fine
+    let _ = systemctl(&["disable", "--now", &format!("{}.timer", name)]);
+
+    let unit_dir = user_unit_dir(home_dir);
+    for suffix in ["service", "timer"] {
+        let path = unit_dir.join(format!("{}.{}", name, suffix));
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+    systemctl(&["daemon-reload"])
+}
+
+/// Show a desktop notification; does nothing if `notify-send` is missing.
+pub fn notify(summary: &str, body: &str) {
+    let _ = Command::new("notify-send")
+    .args(["--app-name=Game Launcher Installer", summary, body])
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status();
+}
+
+// Run `systemctl --user <args>`
+fn systemctl(args: &[&str]) -> Result<(), String> {
+    let status = Command::new("systemctl")
+    .arg("--user")
+    .args(args)
+    .stdout(Stdio::null())
+    .status()
+    .map_err(|e| format!("Failed to execute systemctl: {}", e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("systemctl --user {} failed with exit code: {}", args.join(" "), status))
+    }
+}
@@ -0,0 +1,210 @@
+//! The prefix template: a prefix set up once with the runner, winetricks verbs (fonts, DXVK)
+//! and tweaks, stored as a `.slpfx` archive and cloned for every new launcher prefix.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::archive::{create_archive, extract_archive, read_manifest, ArchiveManifest, ARCHIVE_EXTENSION};
+use crate::download::download_file;
+use crate::manifest::{signing_key_file, verify_signature};
+use crate::output::*;
+use crate::policy::sha256_file;
+use crate::prefix::PrefixManager;
+use crate::session::wineserver_for;
+use crate::wine::find_in_path;
+
+/// Where the project publishes pre-baked templates unless `template_url` is set in the config:
+/// `wine-<major>.slpfx` per wine major version, next to a `.sha256` checksum in `sha256sum`'s
+/// format and its `.sha256.sig`, signed as described in `manifest`.
+pub const DEFAULT_TEMPLATE_URL: &str =
+    "https://github.com/SulfurNitride/Simple-Launcher-Installer/releases/download/prefix-templates";
+
+/// Verbs baked into the template unless others are given: Microsoft's core fonts and DXVK
+pub const DEFAULT_VERBS: &[&str] = &["corefonts", "dxvk"];
+
+// Launcher ID recorded in the template's manifest, so `import` doesn't take it for a launcher
+const TEMPLATE_LAUNCHER: &str = "template";
+
+// Prefix of the tweak names recording the verbs in the manifest
+const VERB_TWEAK: &str = "winetricks:";
+
+// How long winetricks may take to download and install the verbs
+const WINETRICKS_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Where the template archive is kept.
+pub fn template_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(".local/share/game-launcher-installer/templates").join(format!("default.{}", ARCHIVE_EXTENSION))
+}
+
+/// The winetricks verbs a template was baked with, from its manifest.
+pub fn template_verbs(manifest: &ArchiveManifest) -> Vec<&str> {
+    manifest.tweaks.iter().filter_map(|tweak| tweak.strip_prefix(VERB_TWEAK)).collect()
+}
+
+/// Bake the template: initialize a fresh prefix with `wine_path`, install `verbs` with
+/// winetricks, turn on font smoothing and store it compressed, replacing any older template.
+pub fn bake_template(home_dir: &Path, wine_path: &str, verbs: &[String]) -> Result<ArchiveManifest, String> {
+    if !verbs.is_empty() && find_in_path("winetricks").is_none() {
+        return Err("winetricks is not installed; it is needed to bake verbs into the template.".to_string());
+    }
+
+    let path = template_path(home_dir);
+    let work_dir = path.with_file_name(format!(".bake-{}", std::process::id()));
+    let prefix = PrefixManager::new(work_dir.join("prefix"));
+    let result = (|| {
+        prefix.initialize(wine_path)?;
+
+        if !verbs.is_empty() {
+            println!("{}Installing {} into the template...{}", COLOR_BLUE, verbs.join(", "), COLOR_RESET);
+            emit_progress("template", None, &format!("Installing {}", verbs.join(", ")));
+            let session = prefix.session(wine_path);
+            session.start()?;
+            let status = Command::new("winetricks")
+            .arg("-q")
+            .args(verbs)
+            .env("WINEPREFIX", prefix.path())
+            .env("WINE", wine_path)
+            .env("WINESERVER", wineserver_for(wine_path))
+            .env("WINEDEBUG", "-all")
+            .status()
+            .map_err(|e| format!("Failed to execute winetricks: {}", e))?;
+            session.wait(Some(WINETRICKS_TIMEOUT))?;
+            if !status.success() {
+                return Err(format!("winetricks failed to install {} ({}).", verbs.join(", "), status));
+            }
+        }
+
+        prefix.enable_font_smoothing()?;
+        // The registry is written out when the wineserver exits
+        prefix.kill_wineserver();
+
+        let mut tweaks = prefix.applied_tweaks();
+        tweaks.extend(verbs.iter().map(|verb| format!("{}{}", VERB_TWEAK, verb)));
+        let manifest = ArchiveManifest::for_prefix(prefix.path(), TEMPLATE_LAUNCHER, &prefix.runner().unwrap_or_default(), Vec::new(), tweaks)?;
+
+        // Written next to the old template first, so a failed bake keeps it
+        let partial = path.with_extension("partial");
+        create_archive(prefix.path(), &manifest, &partial)?;
+        fs::rename(&partial, &path).map_err(|e| format!("Failed to move the template into place: {}", e))?;
+        Ok(manifest)
+    })();
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+/// Download the pre-baked template for a wine major version and make it the template,
+/// replacing any older one. Its checksum has to be signed with `signing_key` or the project's
+/// key and match the archive, so nothing unverified is ever extracted.
+pub fn download_template(home_dir: &Path, base_url: &str, signing_key: Option<&Path>, wine_major: u32) -> Result<ArchiveManifest, String> {
+    let name = format!("wine-{}.{}", wine_major, ARCHIVE_EXTENSION);
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), name);
+    let staging = std::env::temp_dir().join(format!("game-launcher-template-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    let checksum = staging.join(format!("{}.sha256", name));
+    let signature = staging.join(format!("{}.sha256.sig", name));
+
+    let result = (|| {
+        let signing_key = signing_key_file(signing_key, &staging)?;
+        download_file(&format!("{}.sha256", url), &checksum)
+        .map_err(|e| format!("No pre-baked template for wine {} at {}: {}", wine_major, base_url, e))?;
+        download_file(&format!("{}.sha256.sig", url), &signature)?;
+        verify_signature(&checksum, &signature, &signing_key)
+        .map_err(|e| format!("The checksum of {} could not be verified: {}.", url, e))?;
+        println!("{}Template checksum signature verified.{}", COLOR_GREEN, COLOR_RESET);
+
+        let content = fs::read_to_string(&checksum)
+        .map_err(|e| format!("Failed to read {}: {}", checksum.display(), e))?;
+        let expected = signed_digest(&content, &name)?;
+
+        let path = template_path(home_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        // Downloaded next to the old template, which is only replaced once the hash matched
+        let partial = path.with_extension("partial");
+        let checked = download_file(&url, &partial).and_then(|()| {
+            if sha256_file(&partial)? != expected {
+                return Err(format!("{} does not match its signed checksum.", url));
+            }
+            let manifest = read_manifest(&partial)?;
+            if manifest.launcher != TEMPLATE_LAUNCHER {
+                return Err(format!("{} is not a prefix template.", url));
+            }
+            Ok(manifest)
+        });
+        let manifest = match checked {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(e);
+            },
+        };
+        fs::rename(&partial, &path).map_err(|e| format!("Failed to move the template into place: {}", e))?;
+        Ok(manifest)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+// The digest a signed `sha256sum` line gives for `name`. The file name is signed along with the
+// digest, so a checksum published for another template can't be passed off as this one's.
+fn signed_digest(content: &str, name: &str) -> Result<String, String> {
+    let mut fields = content.lines().next().unwrap_or_default().split_whitespace();
+    let digest = fields.next().unwrap_or_default().to_lowercase();
+    // `sha256sum -b` marks the name with `*`
+    let file_name = fields.next().map(|file| file.trim_start_matches('*'));
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("The signed checksum for {} is not a SHA-256 digest.", name));
+    }
+    match file_name {
+        Some(file_name) if file_name == name => Ok(digest),
+        Some(file_name) => Err(format!("The signed checksum is for {}, not {}.", file_name, name)),
+        None => Err(format!("The signed checksum does not name the file it is for ({}).", name)),
+    }
+}
+
+/// Create a new prefix at `prefix` from the template. Returns false, leaving the prefix alone,
+/// if there is no template or the prefix already has content.
+pub fn clone_template(home_dir: &Path, prefix: &Path) -> Result<bool, String> {
+    let template = template_path(home_dir);
+    if !template.is_file() || prefix.join("system.reg").exists() {
+        return Ok(false);
+    }
+    // An empty directory left by an earlier step can be replaced; anything else is kept
+    if prefix.exists() && fs::remove_dir(prefix).is_err() {
+        return Ok(false);
+    }
+
+    emit_progress("prefix", None, "Creating the prefix from the template");
+    extract_archive(&template, prefix)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[test]
+    fn takes_digest_for_the_named_file() {
+        assert_eq!(signed_digest(&format!("{}  wine-9.slpfx\n", DIGEST), "wine-9.slpfx"), Ok(DIGEST.to_string()));
+        assert_eq!(signed_digest(&format!("{} *wine-9.slpfx\n", DIGEST.to_uppercase()), "wine-9.slpfx"), Ok(DIGEST.to_string()));
+    }
+
+    #[test]
+    fn refuses_checksums_for_other_files() {
+        let other = signed_digest(&format!("{}  wine-8.slpfx\n", DIGEST), "wine-9.slpfx").unwrap_err();
+        assert_eq!(other, "The signed checksum is for wine-8.slpfx, not wine-9.slpfx.");
+        assert!(signed_digest(DIGEST, "wine-9.slpfx").is_err());
+    }
+
+    #[test]
+    fn refuses_malformed_digests() {
+        assert!(signed_digest("", "wine-9.slpfx").is_err());
+        assert!(signed_digest("abc123  wine-9.slpfx", "wine-9.slpfx").is_err());
+        assert!(signed_digest(&format!("{}  wine-9.slpfx", DIGEST.replace('9', "z")), "wine-9.slpfx").is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Checks run when an install step fails, picked by the step, and the fixes the troubleshooter
+//! can apply itself before the step is retried. The runner and disk space checks are public, so
+//! `doctor` runs the same ones.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fsutil::{available_space, format_size};
+use crate::wine::{find_in_path, wine_kind, wine_major_version, WineKind};
+use crate::xvfb::{display_available, VirtualDisplay};
+
+// Oldest wine the launchers are known to work with, as in their notes
+const MIN_WINE_VERSION: u32 = 9;
+
+// Where wine keeps its 32-bit Windows modules, relative to the runner binary's ancestors:
+// upstream and Proton builds, multilib distributions, and Debian's multiarch layout
+const I386_MODULE_DIRS: &[&str] = &[
+    "lib/wine/i386-windows",
+    "lib32/wine/i386-windows",
+    "lib64/wine/i386-windows",
+    "lib/i386-linux-gnu/wine/i386-windows",
+    "i386-linux-gnu/wine/i386-windows",
+    "wine/i386-windows",
+];
+
+/// An install step that can be retried on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Downloading the launcher's installer
+    Download,
+    /// Updating the prefix for the runner and installing winetricks verbs and runtimes into it
+    PrefixSetup,
+    /// Running the launcher's installer
+    Installer,
+}
+
+impl Step {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Step::Download => "Downloading the installer",
+            Step::PrefixSetup => "Setting up the prefix",
+            Step::Installer => "Running the installer",
+        }
+    }
+}
+
+/// What the checks need to know about the install.
+#[derive(Clone, Debug)]
+pub struct TroubleshootContext {
+    pub wine_path: String,
+    /// Where the launcher is installed to, checked for free space
+    pub games_root: PathBuf,
+    /// Space the install needs, in bytes
+    pub required_space: u64,
+    /// The downloaded installer, if it may be deleted and downloaded again; None for a pinned
+    /// local file
+    pub installer_path: Option<PathBuf>,
+    /// X display silent installs run on
+    pub installer_display: String,
+}
+
+/// A fix the troubleshooter can apply.
+#[derive(Clone, Debug)]
+pub enum Fix {
+    /// Start Xvfb on the display silent installs run on
+    StartXvfb,
+    /// Delete the downloaded installer, so the retry downloads it again
+    Redownload(PathBuf),
+}
+
+impl Fix {
+    pub fn describe(&self) -> String {
+        match self {
+            Fix::StartXvfb => "Start Xvfb for silent installs".to_string(),
+            Fix::Redownload(path) => format!("Delete {} and download it again", path.display()),
+        }
+    }
+
+    /// Apply the fix. Returns the Xvfb server it started, which has to be kept until the
+    /// install is finished.
+    pub fn apply(&self, context: &TroubleshootContext) -> Result<Option<VirtualDisplay>, String> {
+        match self {
+            Fix::StartXvfb => {
+                let display = VirtualDisplay::start()?;
+                if display.display() != context.installer_display {
+                    return Err(format!("Xvfb started on {}, but silent installs run on {}.", display.display(), context.installer_display));
+                }
+                Ok(Some(display))
+            },
+            Fix::Redownload(path) => {
+                let mut part = path.file_name().unwrap_or_default().to_os_string();
+                part.push(".part");
+                let _ = fs::remove_file(path.with_file_name(part));
+                match fs::remove_file(path) {
+                    Ok(()) => Ok(None),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(format!("Failed to delete {}: {}", path.display(), e)),
+                }
+            },
+        }
+    }
+}
+
+/// The result of one check.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    /// What was checked, e.g. `Disk space`
+    pub check: &'static str,
+    /// What was found, or what is wrong
+    pub message: String,
+    pub ok: bool,
+    /// A fix for the problem, or for a likely cause when `ok`
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, message: String) -> Finding {
+        Finding { check, message, ok: true, fix: None }
+    }
+
+    fn problem(check: &'static str, message: String, fix: Option<Fix>) -> Finding {
+        Finding { check, message, ok: false, fix }
+    }
+}
+
+/// Run the checks relevant to a failed step: the silent install display, the runner, free space
+/// and the downloaded installer.
+pub fn diagnose(step: Step, context: &TroubleshootContext) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if step == Step::Installer {
+        findings.extend(check_display(context));
+    }
+    if step != Step::Download {
+        findings.push(check_32bit(&context.wine_path));
+        findings.push(check_wine_version(&context.wine_path));
+    }
+    findings.push(check_free_space(&context.games_root, context.required_space));
+    // A download that failed its hash check stays in place; one the installer failed on only
+    // might be damaged
+    if let Some(path) = context.installer_path.as_ref().filter(|path| path.exists()) {
+        let fix = Some(Fix::Redownload(path.clone()));
+        match step {
+            Step::Download => findings.push(Finding::problem("Installer download", format!("{} is incomplete or damaged.", path.display()), fix)),
+            Step::Installer => findings.push(Finding { fix, ..Finding::ok("Installer download", format!("{} may be damaged.", path.display())) }),
+            Step::PrefixSetup => {},
+        }
+    }
+    findings
+}
+
+// Silent installs need an X server on their own display, and the interactive fallback a
+// display the user can see
+fn check_display(context: &TroubleshootContext) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let socket = context.installer_display.strip_prefix(':')
+    .map(|number| Path::new("/tmp/.X11-unix").join(format!("X{}", number.split('.').next().unwrap_or(""))));
+    match socket {
+        Some(socket) if !socket.exists() => {
+            let (message, fix) = if find_in_path("Xvfb").is_some() {
+                (format!("No X server is running on {}, where silent installs run. Some installers fail without one.", context.installer_display), Some(Fix::StartXvfb))
+            } else {
+                (format!("No X server is running on {}, where silent installs run, and Xvfb is not installed to start one.", context.installer_display), None)
+            };
+            findings.push(Finding::problem("Silent install display", message, fix));
+        },
+        _ => findings.push(Finding::ok("Silent install display", format!("{} is available.", context.installer_display))),
+    }
+    if display_available() {
+        findings.push(Finding::ok("Display", "The installer window can be shown if the silent install fails.".to_string()));
+    } else {
+        findings.push(Finding::problem(
+            "Display",
+            "No usable display was found, so the installer window can't be shown. Run the install from a desktop session or over `ssh -X`.".to_string(),
+            None,
+        ));
+    }
+    findings
+}
+
+/// Look for wine's 32-bit modules next to the runner, which most launcher installers need as
+/// 32-bit programs. Wrapper scripts aren't checked.
+pub fn check_32bit(wine_path: &str) -> Finding {
+    let check = "32-bit support";
+    if wine_kind(Path::new(wine_path)) != Some(WineKind::Binary) {
+        return Finding::ok(check, format!("Not checked: {} is a wrapper script.", wine_path));
+    }
+    let binary = fs::canonicalize(wine_path).unwrap_or_else(|_| PathBuf::from(wine_path));
+    let found = binary.ancestors().skip(1).take(3)
+    .flat_map(|ancestor| I386_MODULE_DIRS.iter().map(move |dir| ancestor.join(dir)))
+    .find(|dir| dir.is_dir());
+    match found {
+        Some(dir) => Finding::ok(check, format!("32-bit Windows modules found in {}.", dir.display())),
+        None => Finding::problem(
+            check,
+            format!("No 32-bit Windows modules were found next to {}, so 32-bit installers can't run. Install your distribution's 32-bit wine package (e.g. wine32 or lib32-wine) or use a WoW64 build.", binary.display()),
+            None,
+        ),
+    }
+}
+
+/// Check that the runner is at least the oldest wine the launchers are known to work with.
+pub fn check_wine_version(wine_path: &str) -> Finding {
+    let check = "Wine version";
+    match wine_major_version(wine_path) {
+        Some(version) if version < MIN_WINE_VERSION => Finding::problem(
+            check,
+            format!("wine {} is older than wine {}, which the launchers need. Update wine or set `runner` to a newer build.", version, MIN_WINE_VERSION),
+            None,
+        ),
+        Some(version) => Finding::ok(check, format!("wine {}", version)),
+        None => Finding::ok(check, format!("Not checked: `{} --version` did not print a wine version.", wine_path)),
+    }
+}
+
+/// Check that `dir` has `required` bytes free.
+pub fn check_free_space(dir: &Path, required: u64) -> Finding {
+    let check = "Disk space";
+    match available_space(dir) {
+        Some(available) if available < required => Finding::problem(
+            check,
+            format!("Only {} free in {}, about {} is needed. Free some space, e.g. with `gc` or `dedupe`.",
+                    format_size(available), dir.display(), format_size(required)),
+            None,
+        ),
+        Some(available) => Finding::ok(check, format!("{} free in {}", format_size(available), dir.display())),
+        None => Finding::ok(check, format!("Not checked: the free space in {} is unknown.", dir.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gli-troubleshoot-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // A wrapper script standing in for wine that prints `version` for --version
+    fn fake_wine(dir: &Path, name: &str, version: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\necho '{}'\n", version)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn context(dir: &Path, installer_path: Option<PathBuf>) -> TroubleshootContext {
+        TroubleshootContext {
+            wine_path: "/nonexistent/wine".to_string(),
+            games_root: dir.to_path_buf(),
+            required_space: 0,
+            installer_path,
+            installer_display: ":99".to_string(),
+        }
+    }
+
+    #[test]
+    fn checks_wine_version() {
+        let dir = temp_dir("version");
+        let old = check_wine_version(&fake_wine(&dir, "old", "wine-8.0"));
+        assert!(!old.ok);
+        assert!(old.message.starts_with("wine 8 is older than wine 9"), "{}", old.message);
+        let staging = check_wine_version(&fake_wine(&dir, "staging", "wine-10.2 (Staging)"));
+        assert!(staging.ok);
+        assert_eq!(staging.message, "wine 10");
+        let unknown = check_wine_version(&fake_wine(&dir, "unknown", "not wine at all"));
+        assert!(unknown.ok);
+        assert!(unknown.message.starts_with("Not checked"), "{}", unknown.message);
+        // Wrappers are started, but not searched for modules
+        assert!(check_32bit(&fake_wine(&dir, "wrapper", "wine-9.0")).message.contains("is a wrapper script"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checks_free_space() {
+        let dir = temp_dir("space");
+        assert!(check_free_space(&dir, 0).ok);
+        let full = check_free_space(&dir, u64::MAX);
+        assert!(!full.ok);
+        assert!(full.message.starts_with("Only "), "{}", full.message);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn picks_checks_by_step() {
+        let dir = temp_dir("steps");
+        let installer = dir.join("installer.exe");
+        fs::write(&installer, "MZ").unwrap();
+        let context = context(&dir, Some(installer.clone()));
+
+        let checks = |step| diagnose(step, &context).iter().map(|finding| finding.check).collect::<Vec<_>>();
+        assert_eq!(checks(Step::Download), ["Disk space", "Installer download"]);
+        assert_eq!(checks(Step::PrefixSetup), ["32-bit support", "Wine version", "Disk space"]);
+        assert_eq!(checks(Step::Installer), ["Silent install display", "Display", "32-bit support", "Wine version", "Disk space", "Installer download"]);
+
+        // A failed download is the problem; after a failed installer run it is only a suspect
+        let download = diagnose(Step::Download, &context).pop().unwrap();
+        assert!(!download.ok && matches!(download.fix, Some(Fix::Redownload(_))));
+        let installer_run = diagnose(Step::Installer, &context).pop().unwrap();
+        assert!(installer_run.ok && matches!(installer_run.fix, Some(Fix::Redownload(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn redownload_deletes_installer_and_partial_download() {
+        let dir = temp_dir("redownload");
+        let installer = dir.join("installer.exe");
+        fs::write(&installer, "MZ").unwrap();
+        fs::write(dir.join("installer.exe.part"), "M").unwrap();
+        let fix = Fix::Redownload(installer.clone());
+        assert!(fix.apply(&context(&dir, None)).unwrap().is_none());
+        assert!(!installer.exists() && !dir.join("installer.exe.part").exists());
+        // Already gone is fine
+        assert!(fix.apply(&context(&dir, None)).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+//! Noticing silent installers that hang: no CPU time or disk I/O in the prefix's processes
+//! and no file changes in the prefix for a while.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::prefix::PrefixManager;
+
+// CPU time (in clock ticks, usually 1/100 s) between two samples that still counts as idle.
+// Wine's own processes wake up now and then even when nothing happens.
+const IDLE_CPU_TICKS: u64 = 10;
+
+// What changes while an installer makes progress
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Activity {
+    cpu_ticks: u64,
+    io_bytes: u64,
+    files: u64,
+    file_bytes: u64,
+    newest_change: Option<SystemTime>,
+}
+
+/// Compares samples of a prefix's activity and reports when there was none for `timeout`.
+pub struct HangDetector<'a> {
+    prefix: &'a PrefixManager,
+    timeout: Duration,
+    last: Option<Activity>,
+    last_change: Instant,
+}
+
+impl<'a> HangDetector<'a> {
+    pub fn new(prefix: &'a PrefixManager, timeout: Duration) -> HangDetector<'a> {
+        HangDetector { prefix, timeout, last: None, last_change: Instant::now() }
+    }
+
+    /// Take a sample and return how long the prefix has been idle, once that reaches the timeout.
+    pub fn check(&mut self) -> Option<Duration> {
+        let activity = sample(self.prefix);
+        let active = match &self.last {
+            Some(last) => {
+                activity.cpu_ticks > last.cpu_ticks + IDLE_CPU_TICKS
+                    || activity.io_bytes != last.io_bytes
+                    || activity.files != last.files
+                    || activity.file_bytes != last.file_bytes
+                    || activity.newest_change != last.newest_change
+            },
+            None => true,
+        };
+        self.last = Some(activity);
+        if active {
+            self.last_change = Instant::now();
+        }
+
+        let idle = self.last_change.elapsed();
+        if idle >= self.timeout { Some(idle) } else { None }
+    }
+
+    /// Start counting idle time again, e.g. after the user chose to keep waiting.
+    pub fn reset(&mut self) {
+        self.last_change = Instant::now();
+    }
+}
+
+fn sample(prefix: &PrefixManager) -> Activity {
+    let mut activity = Activity { cpu_ticks: 0, io_bytes: 0, files: 0, file_bytes: 0, newest_change: None };
+
+    for pid in prefix.process_ids() {
+        // utime and stime are the 14th and 15th fields; the command name before them may contain spaces
+        if let Ok(stat) = fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            let fields: Vec<&str> = stat.rsplit_once(')').map(|(_, rest)| rest.split_whitespace().collect()).unwrap_or_default();
+            activity.cpu_ticks += fields.get(11..13).unwrap_or(&[]).iter().filter_map(|ticks| ticks.parse::<u64>().ok()).sum::<u64>();
+        }
+        if let Ok(io) = fs::read_to_string(format!("/proc/{}/io", pid)) {
+            activity.io_bytes += io.lines()
+            .filter(|line| line.starts_with("read_bytes:") || line.starts_with("write_bytes:"))
+            .filter_map(|line| line.split_once(':')?.1.trim().parse::<u64>().ok())
+            .sum::<u64>();
+        }
+    }
+
+    scan_files(prefix.path(), &mut activity);
+    activity
+}
+
+// Count files and bytes under `dir` and find the newest change. Symlinks are not followed,
+// since the prefix's dosdevices link to `/`.
+fn scan_files(dir: &Path, activity: &mut Activity) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let metadata = match fs::symlink_metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if let Ok(modified) = metadata.modified() {
+            activity.newest_change = activity.newest_change.max(Some(modified));
+        }
+        if metadata.is_dir() {
+            scan_files(&entry.path(), activity);
+        } else if metadata.is_file() {
+            activity.files += 1;
+            activity.file_bytes += metadata.len();
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Wayland sessions: finding Xwayland and wine's own Wayland driver for launch scripts.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::wine::{find_in_path, Runner};
+
+// Where distributions install Xwayland when it isn't on PATH
+const XWAYLAND_PATHS: &[&str] = &["/usr/bin/Xwayland", "/usr/libexec/Xwayland", "/usr/lib/xorg/Xwayland"];
+
+/// How launchers should show their windows in a Wayland session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaylandPreference {
+    /// wine's Wayland driver when the runner has one, Xwayland otherwise
+    Auto,
+    /// Always go through Xwayland
+    Xwayland,
+}
+
+impl WaylandPreference {
+    pub fn parse(value: &str) -> Option<WaylandPreference> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Some(WaylandPreference::Auto),
+            "xwayland" => Some(WaylandPreference::Xwayland),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WaylandPreference::Auto => "auto",
+            WaylandPreference::Xwayland => "xwayland",
+        }
+    }
+}
+
+/// Whether this process runs inside a Wayland session.
+pub fn is_wayland_session() -> bool {
+    let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    set("WAYLAND_DISPLAY") || std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+}
+
+/// The Xwayland server binary, if installed.
+pub fn find_xwayland() -> Option<PathBuf> {
+    find_in_path("Xwayland").map(PathBuf::from)
+    .or_else(|| XWAYLAND_PATHS.iter().map(PathBuf::from).find(|path| path.exists()))
+}
+
+/// Whether a wine build ships the Wayland graphics driver (`winewayland`, wine 9 and later).
+pub fn has_wayland_driver(wine: &Path) -> bool {
+    // bin/wine -> the install prefix, whose lib directories hold wine/<arch>-unix/
+    let wine = fs::canonicalize(wine).unwrap_or_else(|_| wine.to_path_buf());
+    let prefix = match wine.parent().and_then(Path::parent) {
+        Some(prefix) => prefix.to_path_buf(),
+        None => return false,
+    };
+    let lib_dirs = ["lib", "lib64", "lib/x86_64-linux-gnu", "lib/aarch64-linux-gnu"];
+    lib_dirs.iter().any(|lib| {
+        fs::read_dir(prefix.join(lib).join("wine")).map(|entries| {
+            entries.flatten().any(|entry| {
+                entry.file_name().to_string_lossy().ends_with("-unix") && entry.path().join("winewayland.so").exists()
+            })
+        }).unwrap_or(false)
+    })
+}
+
+/// Whether launch scripts should use wine's Wayland driver: the config allows it and the
+/// runner's wine has one. Proton through umu-run always goes through Xwayland.
+pub fn use_wayland_driver(config: &Config) -> bool {
+    if config.wayland == WaylandPreference::Xwayland {
+        return false;
+    }
+    let wine = match config.runner {
+        Runner::SystemWine => find_in_path("wine").map(PathBuf::from),
+        Runner::Fex | Runner::Box64 => config.x86_wine.clone().or_else(|| find_in_path("wine").map(PathBuf::from)),
+        Runner::Umu => None,
+    };
+    wine.is_some_and(|wine| has_wayland_driver(&wine))
+}
+
+/// Shell lines that set up the display for a launch script. They decide at launch time, so a
+/// script written in an X11 session still works under Wayland and the other way round.
+pub fn launch_display_setup(wayland_driver: bool) -> String {
+    let mut lines = String::new();
+    if wayland_driver {
+        // Without DISPLAY, wine falls back from its X11 driver to the Wayland one
+        lines.push_str("# Under Wayland, use wine's Wayland driver instead of Xwayland\n");
+        lines.push_str("if [ -n \"$WAYLAND_DISPLAY\" ]; then\n");
+        lines.push_str("    unset DISPLAY\n");
+        lines.push_str("fi\n");
+    } else {
+        lines.push_str("# Under Wayland, go through Xwayland; find its socket if DISPLAY was not passed on\n");
+        lines.push_str("if [ -n \"$WAYLAND_DISPLAY\" ] && [ -z \"$DISPLAY\" ]; then\n");
+        lines.push_str("    for socket in /tmp/.X11-unix/X*; do\n");
+        lines.push_str("        [ -S \"$socket\" ] && export DISPLAY=\":${socket#/tmp/.X11-unix/X}\" && break\n");
+        lines.push_str("    done\n");
+        lines.push_str("fi\n");
+    }
+    lines
+}
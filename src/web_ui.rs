@@ -0,0 +1,372 @@
+// Local web interface for starting installs and watching their progress from a browser.
+// Each install runs the installer binary as a child process that accepts every default,
+// and its --progress-events stream is served back to the page. Requests must name the
+// listening address as their Host, so a page on another site can't reach the server through
+// DNS rebinding, and installs need the token embedded in the served page.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use game_launcher_installer::LauncherRegistry;
+
+pub const DEFAULT_PORT: u16 = 8787;
+
+// How long a connection may take to send its request before it is dropped, so a client that
+// connects and sends nothing can't hold on to a thread
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Header the page sends its token in; a custom header also makes browsers send a CORS
+// preflight, which the server never answers, before any cross-origin POST
+const TOKEN_HEADER: &str = "x-install-token";
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Game Launcher Installer</title>
+<style>
+body { font-family: sans-serif; max-width: 48em; margin: 2em auto; background: #1b1d23; color: #ddd; }
+button { padding: 0.5em 1.5em; margin-right: 0.5em; }
+pre { background: #111; padding: 1em; min-height: 10em; white-space: pre-wrap; }
+</style>
+</head>
+<body>
+<h1>Game Launcher Installer</h1>
+<div id="launchers">{{LAUNCHERS}}</div>
+<h2>Progress <span id="status"></span></h2>
+<pre id="log"></pre>
+<script>
+const TOKEN = '{{TOKEN}}';
+function install(id) {
+  fetch('/install/' + id, { method: 'POST', headers: { 'X-Install-Token': TOKEN } }).then(r => r.text()).then(t => { if (t) alert(t); });
+}
+function poll() {
+  fetch('/status').then(r => r.json()).then(s => {
+    document.getElementById('status').textContent = s.running ? '(running)' : '';
+  });
+  fetch('/events').then(r => r.text()).then(t => {
+    const lines = t.split('\n').filter(l => l).map(l => {
+      const e = JSON.parse(l);
+      return (e.percent === null ? '' : e.percent + '% ') + '[' + e.stage + '] ' + e.message;
+    });
+    document.getElementById('log').textContent = lines.join('\n');
+  });
+}
+setInterval(poll, 1000);
+poll();
+</script>
+</body>
+</html>
+"#;
+
+// State of the install currently started from the page
+struct WebState {
+    port: u16,
+    // Secret embedded in the page and required on every POST
+    token: String,
+    events_path: PathBuf,
+    child: Option<Child>,
+}
+
+// The parts of a request the routes look at
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    host: Option<String>,
+    origin: Option<String>,
+    token: Option<String>,
+}
+
+impl WebState {
+    fn is_running(&mut self) -> bool {
+        match &mut self.child {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+}
+
+// Serve the web interface on localhost until the process is stopped
+pub fn serve(port: u16, home_dir: &Path) -> Result<(), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+    .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+
+    let events_dir = home_dir.join(".cache/game-launcher-installer");
+    fs::create_dir_all(&events_dir)
+    .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let state = Arc::new(Mutex::new(WebState {
+        port,
+        token: random_token()?,
+        events_path: events_dir.join("web-progress.jsonl"),
+        child: None,
+    }));
+
+    println!("Web interface running at http://127.0.0.1:{}/ (Ctrl+C to stop)", port);
+
+    // Each connection gets its own thread, so a slow client doesn't hold up the others
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = Arc::clone(&state);
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &state) {
+                        println!("Request failed: {}", e);
+                    }
+                });
+            },
+            Err(e) => println!("Connection failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<WebState>) -> Result<(), String> {
+    stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(|e| e.to_string())?;
+    let reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let request = read_request(reader)?;
+    let (status, content_type, body) = {
+        let mut state = state.lock().map_err(|_| "Server state is poisoned.".to_string())?;
+        route(&request, &mut state)
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).map_err(|e| e.to_string())
+}
+
+// Read the request line and the headers the routes need; none of the routes take a body
+fn read_request<R: BufRead>(mut reader: R) -> Result<Request, String> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let mut request = Request {
+        method: parts.next().unwrap_or("").to_string(),
+        path: parts.next().unwrap_or("").to_string(),
+        ..Request::default()
+    };
+
+    loop {
+        let mut header = String::new();
+        let read = reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        if read == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = Some(value.trim().to_string());
+            match name.trim().to_ascii_lowercase().as_str() {
+                "host" => request.host = value,
+                "origin" => request.origin = value,
+                TOKEN_HEADER => request.token = value,
+                _ => {},
+            }
+        }
+    }
+
+    Ok(request)
+}
+
+fn route(request: &Request, state: &mut WebState) -> (&'static str, &'static str, String) {
+    let local_hosts = [format!("127.0.0.1:{}", state.port), format!("localhost:{}", state.port)];
+    let is_local = |host: &str| local_hosts.iter().any(|local| local == host);
+    if !request.host.as_deref().is_some_and(is_local) {
+        return ("403 Forbidden", "text/plain; charset=utf-8", "Unknown host".to_string());
+    }
+    if let Some(origin) = &request.origin {
+        if !origin.strip_prefix("http://").is_some_and(is_local) {
+            return ("403 Forbidden", "text/plain; charset=utf-8", "Foreign origin".to_string());
+        }
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => ("200 OK", "text/html; charset=utf-8", render_index(&state.token)),
+        ("GET", "/events") => {
+            let events = fs::read_to_string(&state.events_path).unwrap_or_default();
+            ("200 OK", "application/x-ndjson", events)
+        },
+        ("GET", "/status") => {
+            let running = state.is_running();
+            ("200 OK", "application/json", format!("{{\"running\":{}}}", running))
+        },
+        ("POST", _) if request.token.as_deref() != Some(state.token.as_str()) => {
+            ("403 Forbidden", "text/plain; charset=utf-8", "Missing or wrong token".to_string())
+        },
+        ("POST", path) if path.starts_with("/install/") => {
+            let id = &path["/install/".len()..];
+            match start_install(state, id) {
+                Ok(()) => ("200 OK", "text/plain; charset=utf-8", String::new()),
+                Err(e) => ("409 Conflict", "text/plain; charset=utf-8", e),
+            }
+        },
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "Not found".to_string()),
+    }
+}
+
+// 128 random bits, hex encoded
+fn random_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    fs::File::open("/dev/urandom")
+    .and_then(|mut random| random.read_exact(&mut bytes))
+    .map_err(|e| format!("Failed to generate access token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn render_index(token: &str) -> String {
+    // Every known launcher, by the ID passed to --launcher
+    let buttons: Vec<String> = LauncherRegistry::builtin().all()
+    .iter()
+    .map(|launcher| format!("<button onclick=\"install('{}')\">Install {}</button>", launcher.id, launcher.name))
+    .collect();
+    INDEX_HTML.replace("{{LAUNCHERS}}", &buttons.join("\n")).replace("{{TOKEN}}", token)
+}
+
+// Start an install in a child installer process that takes the default answer at every prompt
+fn start_install(state: &mut WebState, id: &str) -> Result<(), String> {
+    if state.is_running() {
+        return Err("An install is already running.".to_string());
+    }
+
+    let registry = LauncherRegistry::builtin();
+    let launcher = registry.get(id).ok_or_else(|| format!("Unknown launcher: {}", id))?;
+
+    // Start each run with an empty event log
+    fs::write(&state.events_path, "")
+    .map_err(|e| format!("Failed to reset progress log: {}", e))?;
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate installer binary: {}", e))?;
+    // With no stdin every prompt reads an empty (default) answer
+    let child = Command::new(exe)
+    .arg("--progress-events")
+    .arg(&state.events_path)
+    .arg("--launcher")
+    .arg(launcher.id)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to start installer: {}", e))?;
+
+    println!("Started {} install.", launcher.name);
+    state.child = Some(child);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(events_path: PathBuf) -> WebState {
+        WebState { port: DEFAULT_PORT, token: "secret".to_string(), events_path, child: None }
+    }
+
+    fn request(raw: &str) -> Request {
+        read_request(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reads_request_line_and_headers() {
+        let request = request("POST /install/ea-app HTTP/1.1\r\nHost: 127.0.0.1:8787\r\nX-Install-Token: secret\r\nOrigin: http://localhost:8787\r\n\r\n");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/install/ea-app");
+        assert_eq!(request.host.as_deref(), Some("127.0.0.1:8787"));
+        assert_eq!(request.origin.as_deref(), Some("http://localhost:8787"));
+        assert_eq!(request.token.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn serves_page_with_token_to_local_hosts() {
+        let mut state = state(PathBuf::from("/nonexistent/events.jsonl"));
+        for host in ["127.0.0.1:8787", "localhost:8787"] {
+            let (status, _, body) = route(&request(&format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host)), &mut state);
+            assert_eq!(status, "200 OK");
+            assert!(body.contains("const TOKEN = 'secret';"));
+        }
+    }
+
+    #[test]
+    fn offers_every_launcher() {
+        let page = render_index("secret");
+        for launcher in LauncherRegistry::builtin().all() {
+            assert!(page.contains(&format!("<button onclick=\"install('{}')\">Install {}</button>", launcher.id, launcher.name)), "{}", launcher.id);
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_host_and_origin() {
+        let mut state = state(PathBuf::from("/nonexistent/events.jsonl"));
+        for raw in [
+            "GET /events HTTP/1.1\r\n\r\n",
+            "GET /events HTTP/1.1\r\nHost: attacker.example:8787\r\n\r\n",
+            "GET /events HTTP/1.1\r\nHost: 127.0.0.1:9999\r\n\r\n",
+            "GET /events HTTP/1.1\r\nHost: 127.0.0.1:8787\r\nOrigin: http://attacker.example\r\n\r\n",
+            "GET /events HTTP/1.1\r\nHost: 127.0.0.1:8787\r\nOrigin: null\r\n\r\n",
+        ] {
+            assert_eq!(route(&request(raw), &mut state).0, "403 Forbidden", "{}", raw);
+        }
+    }
+
+    #[test]
+    fn post_needs_token() {
+        let mut state = state(PathBuf::from("/nonexistent/events.jsonl"));
+        let missing = request("POST /install/ea-app HTTP/1.1\r\nHost: 127.0.0.1:8787\r\n\r\n");
+        assert_eq!(route(&missing, &mut state).0, "403 Forbidden");
+        let wrong = request("POST /install/ea-app HTTP/1.1\r\nHost: 127.0.0.1:8787\r\nX-Install-Token: guess\r\n\r\n");
+        assert_eq!(route(&wrong, &mut state).0, "403 Forbidden");
+        // With the token an unknown launcher gets as far as start_install, which starts nothing
+        let unknown = request("POST /install/nope HTTP/1.1\r\nHost: 127.0.0.1:8787\r\nX-Install-Token: secret\r\n\r\n");
+        let (status, _, body) = route(&unknown, &mut state);
+        assert_eq!(status, "409 Conflict");
+        assert_eq!(body, "Unknown launcher: nope");
+    }
+
+    #[test]
+    fn routes_status_and_unknown_paths() {
+        let mut state = state(PathBuf::from("/nonexistent/events.jsonl"));
+        let (status, _, body) = route(&request("GET /status HTTP/1.1\r\nHost: localhost:8787\r\n\r\n"), &mut state);
+        assert_eq!((status, body.as_str()), ("200 OK", "{\"running\":false}"));
+        let (status, _, body) = route(&request("GET /events HTTP/1.1\r\nHost: localhost:8787\r\n\r\n"), &mut state);
+        assert_eq!((status, body.as_str()), ("200 OK", ""));
+        assert_eq!(route(&request("GET /nope HTTP/1.1\r\nHost: localhost:8787\r\n\r\n"), &mut state).0, "404 Not Found");
+    }
+
+    #[test]
+    fn silent_connections_do_not_block_others() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(WebState { port, ..state(PathBuf::from("/nonexistent/events.jsonl")) }));
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let state = Arc::clone(&state);
+                thread::spawn(move || handle_connection(stream.unwrap(), &state));
+            }
+        });
+
+        // The first client connects and sends nothing
+        let _silent = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        write!(client, "GET /status HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", port).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("{\"running\":false}"), "{}", response);
+    }
+
+    #[test]
+    fn tokens_are_random_hex() {
+        let (first, second) = (random_token().unwrap(), random_token().unwrap());
+        assert_eq!(first.len(), 32);
+        assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,327 @@
+//! Locating wine and building the arguments passed to Windows programs.
+
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str;
+
+use crate::config::Config;
+use crate::output::*;
+
+/// What runs the launchers: the distribution's wine, Proton through umu-launcher, or on
+/// ARM64 hosts an x86-64 wine under an emulator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Runner {
+    SystemWine,
+    /// `umu-run`, which downloads and runs UMU-Proton (or `$PROTONPATH`) like wine
+    Umu,
+    /// x86-64 wine run through FEX-Emu's `FEXInterpreter`
+    Fex,
+    /// x86-64 wine run through Box64
+    Box64,
+}
+
+impl Runner {
+    pub fn parse(value: &str) -> Option<Runner> {
+        match value.trim().to_lowercase().as_str() {
+            "wine" => Some(Runner::SystemWine),
+            "umu" | "proton" => Some(Runner::Umu),
+            "fex" => Some(Runner::Fex),
+            "box64" => Some(Runner::Box64),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Runner::SystemWine => "wine",
+            Runner::Umu => "umu",
+            Runner::Fex => "fex",
+            Runner::Box64 => "box64",
+        }
+    }
+
+    /// Name shown when the runner is picked, e.g. "wine under FEX-Emu"
+    pub fn description(&self) -> &'static str {
+        match self {
+            Runner::SystemWine => "system wine",
+            Runner::Umu => "umu-run",
+            Runner::Fex => "wine under FEX-Emu",
+            Runner::Box64 => "wine under Box64",
+        }
+    }
+
+    // Emulator program that runs x86-64 binaries for this runner
+    fn emulator(&self) -> Option<&'static str> {
+        match self {
+            Runner::Fex => Some("FEXInterpreter"),
+            Runner::Box64 => Some("box64"),
+            _ => None,
+        }
+    }
+}
+
+// ELF e_machine values
+const ELF_X86_64: u16 = 0x3e;
+const ELF_AARCH64: u16 = 0xb7;
+
+/// Whether this machine is ARM64, where x86-64 wine builds need an emulator.
+pub fn host_is_arm64() -> bool {
+    std::env::consts::ARCH == "aarch64"
+}
+
+/// Architecture an ELF executable was built for, or None for scripts and other files.
+pub fn elf_machine(path: &Path) -> Option<&'static str> {
+    let data = fs::read(path).ok()?;
+    if data.get(0..4)? != b"\x7fELF" {
+        return None;
+    }
+    match u16::from_le_bytes([*data.get(18)?, *data.get(19)?]) {
+        ELF_X86_64 => Some("x86_64"),
+        ELF_AARCH64 => Some("aarch64"),
+        _ => Some("other"),
+    }
+}
+
+/// Find the program to run Windows executables with for the configured runner. For FEX and
+/// Box64 this is a script in `runner_dir` that starts `config.x86_wine` (or wine from PATH)
+/// under the emulator.
+pub fn find_runner(config: &Config, home_dir: &Path) -> Option<String> {
+    match config.runner {
+        Runner::SystemWine => {
+            let wine = find_system_wine();
+            if wine.is_none() && host_is_arm64() {
+                println!("Unless binfmt_misc hands x86-64 wine to an emulator, set `runner = fex` or `runner = box64` in the config file.");
+            }
+            wine
+        },
+        Runner::Umu => {
+            let umu_run = find_in_path("umu-run");
+            match &umu_run {
+                Some(path) => println!("{}Found umu-run at: {}{}", COLOR_GREEN, path, COLOR_RESET),
+                None => {
+                    println!("{}Error: umu-run not found.{}", COLOR_RED, COLOR_RESET);
+                    println!("Install umu-launcher, or set `runner = wine` in the config file to use system wine.");
+                },
+            }
+            umu_run
+        },
+        Runner::Fex | Runner::Box64 => match emulated_wine(config, home_dir) {
+            Ok(script) => Some(script.to_string_lossy().to_string()),
+            Err(e) => {
+                println!("{}Error: {}{}", COLOR_RED, e, COLOR_RESET);
+                None
+            },
+        },
+    }
+}
+
+/// Directory holding the generated FEX/Box64 wine scripts
+pub fn runner_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join(".local/share/game-launcher-installer/runners")
+}
+
+// Write the script that runs x86-64 wine under the runner's emulator
+fn emulated_wine(config: &Config, home_dir: &Path) -> Result<PathBuf, String> {
+    let emulator_name = config.runner.emulator().ok_or("Not an emulated runner")?;
+    let emulator = find_in_path(emulator_name)
+    .ok_or_else(|| format!("{} not found. Install {} or pick another runner.", emulator_name, config.runner.description()))?;
+
+    let wine = match &config.x86_wine {
+        Some(wine) => wine.clone(),
+        None => find_in_path("wine").map(PathBuf::from)
+        .ok_or("No x86-64 wine found. Set `x86_wine` in the config file to its `bin/wine`.")?,
+    };
+    match elf_machine(&wine) {
+        Some("x86_64") => {},
+        Some(machine) => return Err(format!("{} is a {} build, not x86-64. Set `x86_wine` to an x86-64 wine.", wine.display(), machine)),
+        None if !wine.exists() => return Err(format!("{} does not exist.", wine.display())),
+        // A launcher script; trust it
+        None => {},
+    }
+
+    let script = runner_dir(home_dir).join(format!("wine-{}", config.runner.name()));
+    let content = format!("#!/bin/sh\nexec '{}' '{}' \"$@\"\n",
+                          emulator.replace('\'', "'\\''"), wine.to_string_lossy().replace('\'', "'\\''"));
+    if fs::read_to_string(&script).ok().as_deref() != Some(content.as_str()) {
+        fs::create_dir_all(runner_dir(home_dir)).map_err(|e| format!("Failed to create runner directory: {}", e))?;
+        fs::write(&script, content).map_err(|e| format!("Failed to write {}: {}", script.display(), e))?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to make {} executable: {}", script.display(), e))?;
+    }
+    Ok(script)
+}
+
+/// Full path of a program found in PATH
+pub fn find_in_path(program: &str) -> Option<String> {
+    let output = Command::new("which").arg(program).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = str::from_utf8(&output.stdout).ok()?.trim();
+    if path.is_empty() { None } else { Some(path.to_string()) }
+}
+
+/// The major version of a wine build from `wine --version`, e.g. 9 for `wine-9.0 (Staging)`.
+pub fn wine_major_version(wine_path: &str) -> Option<u32> {
+    let output = Command::new(wine_path).arg("--version").output().ok()?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    let digits: String = version.trim().strip_prefix("wine-")?.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// What a wine path turned out to be once symlinks are followed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WineKind {
+    /// wine's own ELF binary
+    Binary,
+    /// A script that starts wine, e.g. a Flatpak export or a custom wrapper
+    Wrapper,
+}
+
+impl WineKind {
+    pub fn parse(value: &str) -> Option<WineKind> {
+        match value.trim() {
+            "binary" => Some(WineKind::Binary),
+            "wrapper" => Some(WineKind::Wrapper),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WineKind::Binary => "binary",
+            WineKind::Wrapper => "wrapper",
+        }
+    }
+}
+
+/// Whether `path` is wine's binary or a wrapper, following symlinks. None if it is not an
+/// executable file.
+pub fn wine_kind(path: &Path) -> Option<WineKind> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.permissions().mode() & 0o111 == 0 {
+        return None;
+    }
+    if elf_machine(path).is_some() {
+        return Some(WineKind::Binary);
+    }
+    let mut magic = [0; 2];
+    fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic)).ok()?;
+    (magic == *b"#!").then_some(WineKind::Wrapper)
+}
+
+/// A wine that answered a test run.
+#[derive(Clone, Debug)]
+pub struct ProbedWine {
+    /// The path as found, not resolved, so a wrapper's symlink keeps working when it is updated
+    pub path: String,
+    pub kind: WineKind,
+    /// What `wine --version` printed, e.g. `wine-9.0 (Staging)`
+    pub version: String,
+}
+
+/// Check that `path` runs as wine: an executable binary or wrapper script, reached through any
+/// symlinks, that answers `--version` like wine does. A build for another architecture fails
+/// the test run unless binfmt_misc hands it to an emulator.
+pub fn probe_wine(path: &str) -> Result<ProbedWine, String> {
+    let kind = wine_kind(Path::new(path)).ok_or_else(|| format!("{} is not an executable program.", path))?;
+    let output = Command::new(path)
+    .arg("--version")
+    .stdin(Stdio::null())
+    .stderr(Stdio::null())
+    .output()
+    .map_err(|e| format!("{} could not be started: {}", path, e))?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() {
+        let machine = elf_machine(Path::new(path)).filter(|machine| *machine != std::env::consts::ARCH);
+        return Err(match machine {
+            Some(machine) => format!("{} is a {} build and does not run on this {} machine.", path, machine, std::env::consts::ARCH),
+            None => format!("`{} --version` failed with {}.", path, output.status),
+        });
+    }
+    if !version.to_lowercase().contains("wine") {
+        return Err(format!("{} does not look like wine; `--version` printed `{}`.", path, version));
+    }
+    Ok(ProbedWine { path: path.to_string(), kind, version })
+}
+
+/// Find system wine installation
+pub fn find_system_wine() -> Option<String> {
+    println!("{}Searching for system wine installation...{}", COLOR_BLUE, COLOR_RESET);
+
+    // PATH first, then the usual install locations and the Flatpak export of WineHQ's build
+    let mut candidates: Vec<String> = find_in_path("wine").into_iter().collect();
+    candidates.extend(["/usr/bin/wine", "/usr/local/bin/wine"].map(String::from));
+    if let Some(home_dir) = dirs::home_dir() {
+        candidates.push(home_dir.join(".local/share/flatpak/exports/bin/org.winehq.Wine").to_string_lossy().to_string());
+    }
+    candidates.push("/var/lib/flatpak/exports/bin/org.winehq.Wine".to_string());
+
+    for candidate in candidates {
+        if fs::symlink_metadata(&candidate).is_err() {
+            continue;
+        }
+        match probe_wine(&candidate) {
+            Ok(wine) => {
+                let kind = if wine.kind == WineKind::Wrapper { " (wrapper script)" } else { "" };
+                println!("{}Found system wine at: {}{}{}", COLOR_GREEN, wine.path, kind, COLOR_RESET);
+                println!("{}Wine version: {}{}", COLOR_GREEN, wine.version, COLOR_RESET);
+                return Some(wine.path);
+            },
+            Err(e) => println!("{}Skipping {}: {}{}", COLOR_YELLOW, candidate, e, COLOR_RESET),
+        }
+    }
+
+    println!("{}Error: System wine installation not found.{}", COLOR_RED, COLOR_RESET);
+    println!("Please install wine using your distribution's package manager.");
+    println!("Example: sudo apt install wine    # For Debian/Ubuntu");
+    println!("         sudo dnf install wine    # For Fedora");
+    println!("         sudo pacman -S wine      # For Arch Linux");
+
+    None
+}
+
+/// Convert a Linux path to the Windows path wine exposes it under through the Z: drive
+pub fn unix_to_windows_path(path: &Path) -> String {
+    format!("Z:{}", path.to_string_lossy().replace('/', "\\"))
+}
+
+/// Builds the arguments passed to a Windows program run through wine.
+/// Wine quotes each argv entry itself when it assembles the Windows command line, so values
+/// containing spaces or non-ASCII characters must be passed raw. Wrapping them in literal
+/// quotes makes the program receive the quote characters as part of the value.
+#[derive(Default)]
+pub struct WineArgs {
+    args: Vec<String>,
+}
+
+impl WineArgs {
+    pub fn new() -> WineArgs {
+        WineArgs { args: Vec::new() }
+    }
+
+    /// Plain argument, passed through unchanged
+    pub fn arg(mut self, arg: &str) -> WineArgs {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    /// `--name=value` style option
+    pub fn option(mut self, name: &str, value: &str) -> WineArgs {
+        self.args.push(format!("{}={}", name, value));
+        self
+    }
+
+    /// Windows path argument for a file on the Linux side
+    pub fn path(mut self, path: &Path) -> WineArgs {
+        self.args.push(unix_to_windows_path(path));
+        self
+    }
+
+    pub fn build(self) -> Vec<String> {
+        self.args
+    }
+}
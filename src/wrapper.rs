@@ -0,0 +1,129 @@
+//! Sandboxed launch scripts (bubblewrap or firejail) and the `.desktop` entries that run them.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::launchers::LauncherInfo;
+use crate::wayland::launch_display_setup;
+
+// Host directories the wine runtime needs, bound read-only into the bubblewrap sandbox
+const BWRAP_SYSTEM_DIRS: &[&str] = &["/usr", "/etc", "/opt", "/bin", "/sbin", "/lib", "/lib32", "/lib64"];
+
+/// Program used to confine the launcher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SandboxTool {
+    Bubblewrap,
+    Firejail,
+}
+
+impl SandboxTool {
+    pub fn parse(name: &str) -> Option<SandboxTool> {
+        match name.trim().to_lowercase().as_str() {
+            "bwrap" | "bubblewrap" => Some(SandboxTool::Bubblewrap),
+            "firejail" => Some(SandboxTool::Firejail),
+            _ => None,
+        }
+    }
+
+    /// Executable name looked up on PATH.
+    pub fn command(&self) -> &'static str {
+        match self {
+            SandboxTool::Bubblewrap => "bwrap",
+            SandboxTool::Firejail => "firejail",
+        }
+    }
+}
+
+/// A launch script that runs one launcher's executable with only its prefix,
+/// its install directory and the GPU devices visible.
+pub struct LaunchWrapper {
+    pub tool: SandboxTool,
+    pub wine_path: String,
+    pub prefix: PathBuf,
+    pub install_dir: PathBuf,
+    pub exe: PathBuf,
+    /// Use wine's Wayland driver under Wayland instead of Xwayland, see `use_wayland_driver`
+    pub wayland_driver: bool,
+}
+
+impl LaunchWrapper {
+    /// The shell script that starts the launcher inside the sandbox.
+    pub fn script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\n# Generated by game-launcher-installer\n");
+        script.push_str(&launch_display_setup(self.wayland_driver));
+        match self.tool {
+            SandboxTool::Bubblewrap => {
+                script.push_str("set --\n");
+                // GPU device nodes differ per vendor, so collect the ones present at launch time
+                script.push_str("for dev in /dev/dri /dev/nvidia* /dev/kfd; do\n");
+                script.push_str("    [ -e \"$dev\" ] && set -- \"$@\" --dev-bind \"$dev\" \"$dev\"\n");
+                script.push_str("done\n");
+                // $HOME is replaced below, so an X authority file kept there has to be bound back in
+                script.push_str("[ -n \"$XAUTHORITY\" ] && [ -e \"$XAUTHORITY\" ] && set -- \"$@\" --ro-bind \"$XAUTHORITY\" \"$XAUTHORITY\"\n");
+                script.push_str("exec bwrap \\\n");
+                for dir in BWRAP_SYSTEM_DIRS {
+                    script.push_str(&format!("    --ro-bind-try {} {} \\\n", dir, dir));
+                }
+                script.push_str("    --proc /proc --dev /dev --ro-bind /sys /sys \\\n");
+                script.push_str("    --tmpfs /tmp --ro-bind-try /tmp/.X11-unix /tmp/.X11-unix \\\n");
+                script.push_str("    --tmpfs \"$HOME\" \\\n");
+                // Display and audio sockets live in the runtime directory
+                script.push_str("    --bind-try \"$XDG_RUNTIME_DIR\" \"$XDG_RUNTIME_DIR\" \\\n");
+                script.push_str(&format!("    --bind {} {} \\\n", sh_quote(&self.prefix), sh_quote(&self.prefix)));
+                script.push_str(&format!("    --bind {} {} \\\n", sh_quote(&self.install_dir), sh_quote(&self.install_dir)));
+                script.push_str("    --unshare-all --share-net --die-with-parent \\\n");
+                script.push_str(&format!("    --setenv WINEPREFIX {} \\\n", sh_quote(&self.prefix)));
+                script.push_str("    \"$@\" \\\n");
+                script.push_str(&format!("    {} {}\n", sh_quote(Path::new(&self.wine_path)), sh_quote(&self.exe)));
+            },
+            SandboxTool::Firejail => {
+                script.push_str("exec firejail --noprofile \\\n");
+                script.push_str(&format!("    --whitelist={} \\\n", sh_quote(&self.prefix)));
+                script.push_str(&format!("    --whitelist={} \\\n", sh_quote(&self.install_dir)));
+                script.push_str("    --private-tmp \\\n");
+                script.push_str("    ${XAUTHORITY:+\"--whitelist=$XAUTHORITY\"} \\\n");
+                script.push_str(&format!("    --env=WINEPREFIX={} \\\n", sh_quote(&self.prefix)));
+                script.push_str(&format!("    {} {}\n", sh_quote(Path::new(&self.wine_path)), sh_quote(&self.exe)));
+            },
+        }
+        script
+    }
+
+    /// Write the script to `path` and make it executable.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.script())
+        .map_err(|e| format!("Failed to write launch script {}: {}", path.display(), e))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))
+    }
+}
+
+/// Where the `.desktop` entry for a launcher's sandboxed script goes.
+pub fn desktop_entry_path(home_dir: &Path, launcher: &LauncherInfo) -> PathBuf {
+    home_dir.join(".local/share/applications").join(format!("game-launcher-installer-{}.desktop", launcher.id))
+}
+
+/// A `.desktop` entry that starts the launcher through its launch script.
+pub fn desktop_entry(launcher: &LauncherInfo, script: &Path) -> String {
+    format!(
+        "[Desktop Entry]\nType=Application\nName={} (sandboxed)\nExec={}\nCategories=Game;\nTerminal=false\n",
+        launcher.name, desktop_exec_quote(script)
+    )
+}
+
+// Quote a path for a POSIX shell
+fn sh_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+// Quote a path for a .desktop Exec key, which has its own escaping rules
+pub(crate) fn desktop_exec_quote(path: &Path) -> String {
+    let escaped: String = path.to_string_lossy().chars()
+    .flat_map(|c| match c {
+        '"' | '`' | '$' | '\\' => vec!['\\', c],
+        c => vec![c],
+    })
+    .collect();
+    format!("\"{}\"", escaped)
+}
@@ -0,0 +1,109 @@
+//! A private Xvfb server for running installers where no X or Wayland session exists.
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::wine::find_in_path;
+
+// Display number tried first; silent installs have always pointed at :99
+const FIRST_DISPLAY: u32 = 99;
+
+// How long Xvfb gets to create its socket, and to shut down
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An Xvfb server started by this process. It is stopped when dropped.
+pub struct VirtualDisplay {
+    child: Child,
+    display: String,
+}
+
+impl VirtualDisplay {
+    /// Start Xvfb on the first free display from :99 up and wait until it accepts connections.
+    pub fn start() -> Result<VirtualDisplay, String> {
+        if find_in_path("Xvfb").is_none() {
+            return Err("Xvfb is not installed; it is needed to run installers without a display.".to_string());
+        }
+
+        let number = (FIRST_DISPLAY..FIRST_DISPLAY + 100)
+        .find(|n| !display_in_use(*n))
+        .ok_or("No free X display number found.")?;
+        let display = format!(":{}", number);
+        let mut child = Command::new("Xvfb")
+        .arg(&display)
+        .args(["-nolisten", "tcp", "-screen", "0", "1280x720x24"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to execute Xvfb: {}", e))?;
+
+        let started = Instant::now();
+        let socket = format!("/tmp/.X11-unix/X{}", number);
+        while !Path::new(&socket).exists() {
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(format!("Xvfb exited on {} ({}).", display, status));
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Xvfb did not start on {}.", display));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(VirtualDisplay { child, display })
+    }
+
+    /// The `DISPLAY` value of the server, e.g. `:99`.
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+}
+
+impl Drop for VirtualDisplay {
+    fn drop(&mut self) {
+        // SIGTERM lets Xvfb remove its lock file and socket; SIGKILL would leave them behind
+        let _ = Command::new("kill")
+        .arg(self.child.id().to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+        let stopping = Instant::now();
+        while let Ok(None) = self.child.try_wait() {
+            if stopping.elapsed() > STARTUP_TIMEOUT {
+                let _ = self.child.kill();
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        let _ = self.child.wait();
+    }
+}
+
+/// Whether this process has an X or Wayland display it can show windows on: `DISPLAY` or
+/// `WAYLAND_DISPLAY` is set and, for local displays, the server's socket exists.
+pub fn display_available() -> bool {
+    let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+    let x11 = var("DISPLAY").is_some_and(|display| match display.strip_prefix(':') {
+        // `:0` or `:0.0`; displays on other hosts (ssh -X forwards to `localhost:10`) can't be checked here
+        Some(number) => Path::new(&format!("/tmp/.X11-unix/X{}", number.split('.').next().unwrap_or(""))).exists(),
+        None => true,
+    });
+    let wayland = var("WAYLAND_DISPLAY").is_some_and(|socket| {
+        if socket.starts_with('/') {
+            Path::new(&socket).exists()
+        } else {
+            var("XDG_RUNTIME_DIR").is_some_and(|runtime_dir| Path::new(&runtime_dir).join(&socket).exists())
+        }
+    });
+    x11 || wayland
+}
+
+// An X server holds a lock file and a socket for each display it serves
+fn display_in_use(number: u32) -> bool {
+    Path::new(&format!("/tmp/.X{}-lock", number)).exists()
+        || Path::new(&format!("/tmp/.X11-unix/X{}", number)).exists()
+}